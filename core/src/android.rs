@@ -3,10 +3,12 @@
 
 use crate::discovery::{DiscoveredPeer, DiscoveryManager};
 use crate::ffi;
-use jni::objects::{JClass, JObject, JString, JValue};
+use jni::objects::{GlobalRef, JClass, JMethodID, JObject, JString, JValue};
+use jni::signature::{Primitive, ReturnType};
 use jni::sys::{jboolean, jint, jlong, jobject, jobjectArray, jstring};
-use jni::JNIEnv;
+use jni::{JNIEnv, JavaVM};
 use std::ffi::{CStr, CString};
+use std::sync::{Mutex, OnceLock};
 
 /// Convert JString to CString
 fn get_string(env: &mut JNIEnv, string: JString) -> CString {
@@ -116,6 +118,7 @@ pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpAddM
     device_name: JString,
     ip_address: JString,
     port: jint,
+    scope_id: jint,
 ) -> jint {
     let handle_ptr = handle as *const ffi::VoidWarpHandle;
     if handle_ptr.is_null() {
@@ -131,12 +134,13 @@ pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpAddM
     let name = get_rust_string(&mut env, device_name);
     let ip_str = get_rust_string(&mut env, ip_address);
 
-    let ip: std::net::IpAddr = match ip_str.parse() {
-        Ok(ip) => ip,
-        Err(_) => return -2,
+    let (ip, zone_scope) = match crate::discovery::parse_zoned_ip(ip_str.to_str().unwrap_or("")) {
+        Some(parsed) => parsed,
+        None => return -2,
     };
+    let scope_id = zone_scope.or_else(|| (scope_id > 0).then_some(scope_id as u32));
 
-    discovery.add_manual_peer(id, name, ip, port as u16);
+    discovery.add_manual_peer(id, name, ip, port as u16, scope_id);
     0
 }
 
@@ -206,6 +210,63 @@ pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpGetP
     output_array.into_raw()
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpListInterfaces(
+    mut env: JNIEnv,
+    _class: JClass,
+) -> jobjectArray {
+    let list = ffi::voidwarp_list_interfaces();
+
+    let iface_class = env
+        .find_class("com/voidwarp/android/native/NativeLib$NetInterface")
+        .expect("Could not find NetInterface class");
+
+    let initial_element = JObject::null();
+    let output_array = env
+        .new_object_array(list.count as i32, &iface_class, &initial_element)
+        .expect("Could not create array");
+
+    if list.count > 0 && !list.interfaces.is_null() {
+        let ifaces_slice = std::slice::from_raw_parts(list.interfaces, list.count);
+        for (i, iface) in ifaces_slice.iter().enumerate() {
+            let name = if iface.name.is_null() {
+                std::borrow::Cow::from("")
+            } else {
+                CStr::from_ptr(iface.name).to_string_lossy()
+            };
+
+            let address = if iface.address.is_null() {
+                std::borrow::Cow::from("")
+            } else {
+                CStr::from_ptr(iface.address).to_string_lossy()
+            };
+
+            let j_name = env.new_string(&*name).unwrap();
+            let j_address = env.new_string(&*address).unwrap();
+
+            // Constructor: (String, String, boolean, boolean)
+            let obj = env
+                .new_object(
+                    &iface_class,
+                    "(Ljava/lang/String;Ljava/lang/String;ZZ)V",
+                    &[
+                        JValue::Object(&j_name),
+                        JValue::Object(&j_address),
+                        JValue::Bool(iface.is_ipv4 as jboolean),
+                        JValue::Bool(iface.is_loopback as jboolean),
+                    ],
+                )
+                .expect("Failed to create NetInterface object");
+
+            env.set_object_array_element(&output_array, i as i32, &obj)
+                .expect("Failed to set array element");
+        }
+    }
+
+    ffi::voidwarp_free_interface_list(list);
+    output_array.into_raw()
+}
+
 // File Transfer bindings
 #[no_mangle]
 pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpCreateSender(
@@ -328,6 +389,10 @@ pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpRece
     receiver: jlong,
 ) {
     ffi::voidwarp_receiver_stop(receiver as *mut ffi::FfiFileReceiver);
+    // Drop any open port mapping along with the receiver it was opened for,
+    // rather than leaving it held (and being renewed on a background
+    // thread) after there's nothing listening on the mapped port anymore.
+    ffi::voidwarp_natmap_stop();
 }
 
 #[no_mangle]
@@ -388,9 +453,35 @@ pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpRece
     _class: JClass,
     receiver: jlong,
     save_path: JString,
+    handle: jlong,
+    pairing_code: JString,
 ) -> jint {
     let path = get_string(&mut env, save_path);
-    ffi::voidwarp_receiver_accept(receiver as *mut ffi::FfiFileReceiver, path.as_ptr())
+    let code = get_string(&mut env, pairing_code);
+    ffi::voidwarp_receiver_accept(
+        receiver as *mut ffi::FfiFileReceiver,
+        path.as_ptr(),
+        handle as *const ffi::VoidWarpHandle,
+        code.as_ptr(),
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpReceiverAcceptFd(
+    mut env: JNIEnv,
+    _class: JClass,
+    receiver: jlong,
+    fd: jint,
+    handle: jlong,
+    pairing_code: JString,
+) -> jint {
+    let code = get_string(&mut env, pairing_code);
+    ffi::voidwarp_receiver_accept_fd(
+        receiver as *mut ffi::FfiFileReceiver,
+        fd,
+        handle as *const ffi::VoidWarpHandle,
+        code.as_ptr(),
+    )
 }
 
 #[no_mangle]
@@ -505,6 +596,28 @@ pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpTcpS
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpTcpSenderCreateFromFd(
+    mut env: JNIEnv,
+    _class: JClass,
+    fd: jint,
+    display_name: JString,
+    size: jlong,
+) -> jlong {
+    let name = get_string(&mut env, display_name);
+
+    match TcpFileSender::from_fd(fd, name.to_str().unwrap_or(""), size as u64) {
+        Ok(sender) => {
+            let boxed = Box::new(sender);
+            Box::into_raw(boxed) as jlong
+        }
+        Err(e) => {
+            tracing::error!("Failed to create fd-backed TcpFileSender: {}", e);
+            0
+        }
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpTcpSenderGetChecksum(
     mut env: JNIEnv,
@@ -579,13 +692,16 @@ pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpTcpS
     ip_address: JString,
     port: jint,
     sender_name: JString,
+    handle: jlong,
+    pairing_code: JString,
 ) -> jint {
-    if sender == 0 {
+    if sender == 0 || handle == 0 {
         return -1;
     }
 
     let ip_str = get_string(&mut env, ip_address);
     let name_str = get_string(&mut env, sender_name);
+    let code_str = get_string(&mut env, pairing_code);
 
     let ip: std::net::IpAddr = match ip_str.to_str().unwrap_or("").parse() {
         Ok(ip) => ip,
@@ -595,9 +711,41 @@ pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpTcpS
     let peer_addr = std::net::SocketAddr::new(ip, port as u16);
 
     let sender_ref = &*(sender as *const TcpFileSender);
+    let handle_ref = &*(handle as *const ffi::VoidWarpHandle);
+    let identity = &handle_ref.identity;
+    let relay_url = handle_ref.relay_url.lock().unwrap().clone();
+
+    // Sample bytes_sent() on a poller thread for the duration of the
+    // blocking send below and push it to the registered listener, so the
+    // caller doesn't have to poll voidwarpTcpSenderGetProgress on its own
+    // timer. `sender` outlives this call (the Java side owns and destroys
+    // it separately), so the poller can safely reconstruct a reference from
+    // the same raw pointer.
+    let sender_addr = sender as usize;
+    let file_size = sender_ref.file_size();
+    let stop = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let poller_stop = std::sync::Arc::clone(&stop);
+    let poller = std::thread::spawn(move || {
+        let sender_ref = unsafe { &*(sender_addr as *const TcpFileSender) };
+        while !poller_stop.load(std::sync::atomic::Ordering::Relaxed) {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            notify_progress(sender_ref.bytes_sent() as i64, file_size as i64, 0.0);
+        }
+    });
 
     // Blocking call! Should be called from background thread
-    match sender_ref.send_to(peer_addr, name_str.to_str().unwrap_or("Android Device")) {
+    let result = sender_ref.send_to_with_relay(
+        peer_addr,
+        name_str.to_str().unwrap_or("Android Device"),
+        identity,
+        code_str.to_str().unwrap_or(""),
+        relay_url.as_deref(),
+    );
+
+    stop.store(true, std::sync::atomic::Ordering::Relaxed);
+    let _ = poller.join();
+
+    let code = match result {
         crate::sender::TransferResult::Success => 0,
         crate::sender::TransferResult::Rejected => 1,
         crate::sender::TransferResult::ChecksumMismatch => 2,
@@ -605,7 +753,13 @@ pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpTcpS
         crate::sender::TransferResult::Timeout => 4,
         crate::sender::TransferResult::Cancelled => 5,
         crate::sender::TransferResult::IoError(_) => 6,
-    }
+        crate::sender::TransferResult::AuthenticationFailed => 7,
+        crate::sender::TransferResult::DecryptionFailed => 8,
+        crate::sender::TransferResult::QuicError(_) => 6,
+        crate::sender::TransferResult::SuccessViaRelay => 9,
+    };
+    notify_complete(code);
+    code
 }
 
 #[no_mangle]
@@ -646,3 +800,318 @@ pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpTran
         0
     }
 }
+
+// ============================================================================
+// NAT Port Mapping JNI Bindings
+// ============================================================================
+
+/// Open a UPnP-IGD (falling back to NAT-PMP/PCP) port mapping for
+/// `internal_port` and return the external `ip:port` the gateway is now
+/// forwarding to it, or `null` if neither mechanism reached a gateway.
+#[no_mangle]
+pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpOpenPortMapping(
+    mut env: JNIEnv,
+    _class: JClass,
+    internal_port: jint,
+    is_tcp: jboolean,
+    lease_seconds: jint,
+) -> jstring {
+    let result = ffi::voidwarp_natmap_start(
+        internal_port as u16,
+        is_tcp != 0,
+        lease_seconds.max(0) as u32,
+    );
+    if result != 0 {
+        return JObject::null().into_raw();
+    }
+
+    let ptr = ffi::voidwarp_natmap_external_addr();
+    let jstr = from_c_string(&mut env, ptr);
+    ffi::voidwarp_free_string(ptr);
+    jstr
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpClosePortMapping(
+    _env: JNIEnv,
+    _class: JClass,
+) {
+    ffi::voidwarp_natmap_stop();
+}
+
+// ============================================================================
+// Event Listener JNI Bindings
+// ============================================================================
+//
+// Lets the app register one listener object instead of busy-polling
+// voidwarpReceiverGetPending/voidwarpReceiverGetProgress/
+// voidwarpTcpSenderGetProgress on a timer: once registered,
+// voidwarpTcpSenderStart and voidwarpReceiverListen (below) push
+// onProgress/onPendingTransfer/onComplete calls from their own background
+// threads instead.
+
+/// A registered Java listener plus its resolved method IDs - resolved once
+/// at registration time rather than by name on every event, since a method
+/// ID stays valid for as long as the listener's class does.
+struct RegisteredListener {
+    listener: GlobalRef,
+    on_progress: JMethodID,
+    on_pending_transfer: JMethodID,
+    on_complete: JMethodID,
+}
+
+// GlobalRef and JMethodID are just a global JNI reference and a raw method
+// id - safe to hand to the background threads that deliver events, same
+// justification as ffi.rs's ProgressCallbackHandle.
+unsafe impl Send for RegisteredListener {}
+
+static JVM: OnceLock<JavaVM> = OnceLock::new();
+static LISTENER: Mutex<Option<RegisteredListener>> = Mutex::new(None);
+
+/// Cache the process-wide `JavaVM*` so the background threads spawned by
+/// `voidwarpTcpSenderStart`/`voidwarpReceiverListen` can attach themselves to
+/// deliver listener callbacks - there's no other way to get a `JNIEnv` on a
+/// thread the JVM didn't create.
+#[no_mangle]
+pub extern "system" fn JNI_OnLoad(vm: JavaVM, _reserved: *mut std::ffi::c_void) -> jint {
+    let _ = JVM.set(vm);
+    jni::sys::JNI_VERSION_1_6
+}
+
+/// Register `listener` to receive push events in place of polling. Only one
+/// listener can be registered at a time - a second call replaces the first.
+/// `handle` is accepted for symmetry with the other per-engine calls but
+/// isn't used: there's one listener for the whole process, not one per
+/// handle.
+#[no_mangle]
+pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpRegisterListener(
+    mut env: JNIEnv,
+    _class: JClass,
+    _handle: jlong,
+    listener: JObject,
+) {
+    let class = match env.get_object_class(&listener) {
+        Ok(class) => class,
+        Err(e) => {
+            tracing::error!(
+                "voidwarpRegisterListener: couldn't resolve listener class: {}",
+                e
+            );
+            return;
+        }
+    };
+
+    let on_progress = match env.get_method_id(&class, "onProgress", "(JJF)V") {
+        Ok(id) => id,
+        Err(e) => {
+            tracing::error!(
+                "voidwarpRegisterListener: missing onProgress(long,long,float): {}",
+                e
+            );
+            return;
+        }
+    };
+    let on_pending_transfer = match env.get_method_id(
+        &class,
+        "onPendingTransfer",
+        "(Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;J)V",
+    ) {
+        Ok(id) => id,
+        Err(e) => {
+            tracing::error!(
+                "voidwarpRegisterListener: missing onPendingTransfer(String,String,String,long): {}",
+                e
+            );
+            return;
+        }
+    };
+    let on_complete = match env.get_method_id(&class, "onComplete", "(I)V") {
+        Ok(id) => id,
+        Err(e) => {
+            tracing::error!("voidwarpRegisterListener: missing onComplete(int): {}", e);
+            return;
+        }
+    };
+
+    let global = match env.new_global_ref(&listener) {
+        Ok(global) => global,
+        Err(e) => {
+            tracing::error!(
+                "voidwarpRegisterListener: failed to create global ref: {}",
+                e
+            );
+            return;
+        }
+    };
+
+    *LISTENER.lock().unwrap() = Some(RegisteredListener {
+        listener: global,
+        on_progress,
+        on_pending_transfer,
+        on_complete,
+    });
+}
+
+/// Drop the registered listener's global ref - events fire nowhere until
+/// another one is registered.
+#[no_mangle]
+pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpUnregisterListener(
+    _env: JNIEnv,
+    _class: JClass,
+) {
+    *LISTENER.lock().unwrap() = None;
+}
+
+/// Attach the calling thread to the JVM just long enough to deliver one
+/// event to the registered listener (if any), then detach. `attach_current_thread`
+/// returns a guard that detaches on drop unless the thread was already
+/// attached, so callers here don't need to track attach state themselves.
+fn with_listener(f: impl FnOnce(&mut JNIEnv, &RegisteredListener)) {
+    let guard = LISTENER.lock().unwrap();
+    let Some(registered) = guard.as_ref() else {
+        return;
+    };
+    let Some(vm) = JVM.get() else {
+        tracing::warn!("event fired before JNI_OnLoad cached the JavaVM, dropping it");
+        return;
+    };
+    match vm.attach_current_thread() {
+        Ok(mut env) => f(&mut env, registered),
+        Err(e) => tracing::error!("failed to attach thread to deliver listener event: {}", e),
+    }
+}
+
+fn notify_progress(bytes_transferred: i64, total_bytes: i64, speed_mbps: f32) {
+    with_listener(|env, registered| {
+        let args = [
+            JValue::Long(bytes_transferred).as_jni(),
+            JValue::Long(total_bytes).as_jni(),
+            JValue::Float(speed_mbps).as_jni(),
+        ];
+        let result = unsafe {
+            env.call_method_unchecked(
+                registered.listener.as_obj(),
+                registered.on_progress,
+                ReturnType::Primitive(Primitive::Void),
+                &args,
+            )
+        };
+        if let Err(e) = result {
+            tracing::error!("onProgress callback failed: {}", e);
+        }
+    });
+}
+
+fn notify_pending_transfer(sender_name: &str, sender_addr: &str, file_name: &str, file_size: i64) {
+    with_listener(|env, registered| {
+        let (j_sender_name, j_sender_addr, j_file_name) = match (
+            env.new_string(sender_name),
+            env.new_string(sender_addr),
+            env.new_string(file_name),
+        ) {
+            (Ok(a), Ok(b), Ok(c)) => (a, b, c),
+            _ => {
+                tracing::error!("onPendingTransfer: failed to allocate argument strings");
+                return;
+            }
+        };
+
+        let args = [
+            JValue::Object(&j_sender_name).as_jni(),
+            JValue::Object(&j_sender_addr).as_jni(),
+            JValue::Object(&j_file_name).as_jni(),
+            JValue::Long(file_size).as_jni(),
+        ];
+        let result = unsafe {
+            env.call_method_unchecked(
+                registered.listener.as_obj(),
+                registered.on_pending_transfer,
+                ReturnType::Primitive(Primitive::Void),
+                &args,
+            )
+        };
+        if let Err(e) = result {
+            tracing::error!("onPendingTransfer callback failed: {}", e);
+        }
+    });
+}
+
+fn notify_complete(result_code: i32) {
+    with_listener(|env, registered| {
+        let args = [JValue::Int(result_code).as_jni()];
+        let result = unsafe {
+            env.call_method_unchecked(
+                registered.listener.as_obj(),
+                registered.on_complete,
+                ReturnType::Primitive(Primitive::Void),
+                &args,
+            )
+        };
+        if let Err(e) = result {
+            tracing::error!("onComplete callback failed: {}", e);
+        }
+    });
+}
+
+/// Watch `receiver` on a background thread and push `onPendingTransfer`/
+/// `onProgress`/`onComplete` to the registered listener as its state
+/// changes, instead of making the caller poll
+/// `voidwarpReceiverGetPending`/`voidwarpReceiverGetProgress` on a timer.
+/// Call this once after `voidwarpReceiverStart`; the thread exits on its own
+/// once the receiver reaches a terminal state (`Completed` or `Error`).
+#[no_mangle]
+pub unsafe extern "C" fn Java_com_voidwarp_android_native_NativeLib_voidwarpReceiverListen(
+    _env: JNIEnv,
+    _class: JClass,
+    receiver: jlong,
+) {
+    if receiver == 0 {
+        return;
+    }
+    // jlong (an i64) rather than the raw pointer crosses the thread::spawn
+    // boundary, sidestepping the raw pointer's missing Send impl - the same
+    // trick voidwarpTcpSenderStart uses below.
+    let receiver_addr = receiver as usize;
+
+    std::thread::spawn(move || {
+        let receiver = receiver_addr as *const ffi::FfiFileReceiver;
+        let mut announced_pending = false;
+        let mut total_bytes = 0i64;
+        loop {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+
+            let state = ffi::voidwarp_receiver_get_state(receiver);
+            match state {
+                2 /* AwaitingAccept */ => {
+                    if !announced_pending {
+                        let pending = ffi::voidwarp_receiver_get_pending(receiver);
+                        if pending.is_valid {
+                            let sender_name = unsafe { CStr::from_ptr(pending.sender_name) }.to_string_lossy().into_owned();
+                            let sender_addr = unsafe { CStr::from_ptr(pending.sender_addr) }.to_string_lossy().into_owned();
+                            let file_name = unsafe { CStr::from_ptr(pending.file_name) }.to_string_lossy().into_owned();
+                            total_bytes = pending.file_size as i64;
+                            ffi::voidwarp_free_pending_transfer(pending);
+                            notify_pending_transfer(&sender_name, &sender_addr, &file_name, total_bytes);
+                            announced_pending = true;
+                        } else {
+                            ffi::voidwarp_free_pending_transfer(pending);
+                        }
+                    }
+                }
+                3 /* Receiving */ => {
+                    let bytes_received = ffi::voidwarp_receiver_get_bytes_received(receiver) as i64;
+                    notify_progress(bytes_received, total_bytes, 0.0);
+                }
+                4 /* Completed */ => {
+                    notify_complete(0);
+                    break;
+                }
+                5 /* Error */ => {
+                    notify_complete(6); // IoError
+                    break;
+                }
+                _ => {}
+            }
+        }
+    });
+}
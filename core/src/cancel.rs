@@ -0,0 +1,143 @@
+//! Interruptible blocking socket calls for in-flight transfers, modeled on
+//! Android's `AsynchronousSocketCloseMonitor`.
+//!
+//! `TcpFileSender::cancel()` already flips an `AtomicBool` that the chunk
+//! loop in `sender.rs` checks between chunks, but a thread already parked
+//! in a blocking `recv` won't see that flag until its read timeout next
+//! fires - up to `ACK_TIMEOUT` later. This module closes that gap: install
+//! a handler for a dedicated realtime signal whose only effect is to make
+//! an in-progress blocking syscall return `EINTR`, keep a registry of which
+//! thread is currently blocked on which fd, and let `cancel()` look the
+//! thread up and `pthread_kill` it.
+//!
+//! Unlike `std::net::TcpStream::read`/`write`, which retry transparently on
+//! `EINTR` (see libstd's `cvt_r`), [`interruptible_recv_exact`] treats
+//! `EINTR` as "stop and let the caller decide" rather than "try the syscall
+//! again" - that distinction is the entire point, since a transparent retry
+//! would just block again and the signal would have done nothing.
+//!
+//! Android-only: this leans on bionic's fixed `SIGRTMIN` constant and
+//! `pthread_kill`, neither of which this crate needs outside the mobile
+//! build (desktop builds rely on the existing flag-plus-timeout path).
+
+#![cfg(target_os = "android")]
+
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+/// Realtime signal used to wake a blocked transfer thread. One past
+/// `SIGRTMIN` itself, since some runtimes reserve the first few realtime
+/// signals for their own use.
+const CANCEL_SIGNAL: libc::c_int = libc::SIGRTMIN + 1;
+
+static HANDLER_INSTALLED: AtomicBool = AtomicBool::new(false);
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(1);
+
+struct Blocked {
+    token: u64,
+    thread: libc::pthread_t,
+}
+
+fn registry() -> &'static Mutex<Vec<Blocked>> {
+    static REGISTRY: OnceLock<Mutex<Vec<Blocked>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+extern "C" fn handle_cancel_signal(_: libc::c_int) {
+    // Intentionally empty - the only effect we want from delivery is
+    // `EINTR` on whatever syscall the target thread is blocked in.
+}
+
+/// Install the no-op handler for [`CANCEL_SIGNAL`]. Idempotent, so it's
+/// safe to call from `init()` on every launch. Must run before any transfer
+/// thread can block, or a signal sent to it before the handler is in place
+/// would fall back to the default action (terminating the process).
+pub fn install_handler() {
+    if HANDLER_INSTALLED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handle_cancel_signal as usize;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(CANCEL_SIGNAL, &action, std::ptr::null_mut());
+    }
+}
+
+/// Reserve a fresh token identifying one sender/receiver's blocking calls
+/// for its lifetime, so `cancel(token)` can find whichever thread is
+/// currently registered under it without the caller tracking a raw
+/// `pthread_t` itself. Call once per `TcpFileSender` at construction.
+pub fn new_token() -> u64 {
+    NEXT_TOKEN.fetch_add(1, Ordering::Relaxed)
+}
+
+/// RAII guard marking the current thread as blocked under `token` for as
+/// long as it's held, so `cancel(token)` has somewhere to deliver the
+/// signal. Drop it (including via early return, since it's a guard) as
+/// soon as the blocking call finishes - otherwise a later `cancel()` could
+/// find and kill a thread that's since moved on to unrelated work.
+struct BlockedGuard {
+    token: u64,
+}
+
+impl BlockedGuard {
+    fn new(token: u64) -> Self {
+        let thread = unsafe { libc::pthread_self() };
+        registry().lock().unwrap().push(Blocked { token, thread });
+        BlockedGuard { token }
+    }
+}
+
+impl Drop for BlockedGuard {
+    fn drop(&mut self) {
+        registry().lock().unwrap().retain(|b| b.token != self.token);
+    }
+}
+
+/// Signal whichever thread is currently registered under `token`, if any.
+/// A no-op if that transfer isn't parked in a blocking call right now - the
+/// existing `AtomicBool` flag still covers that case on its own once the
+/// loop comes back around.
+pub fn cancel(token: u64) {
+    let guard = registry().lock().unwrap();
+    if let Some(blocked) = guard.iter().find(|b| b.token == token) {
+        unsafe {
+            libc::pthread_kill(blocked.thread, CANCEL_SIGNAL);
+        }
+    }
+}
+
+/// Like `Read::read_exact`, but via a raw blocking `recv` loop that treats
+/// `EINTR` as "stop now" instead of retrying the syscall. Registers the
+/// current thread under `token` for the duration of each individual `recv`
+/// call (not the whole read), so a `cancel(token)` sent while several short
+/// reads are in flight back-to-back still has a registration to find.
+pub fn interruptible_recv_exact(fd: RawFd, token: u64, buf: &mut [u8]) -> std::io::Result<()> {
+    let mut read = 0;
+    while read < buf.len() {
+        let guard = BlockedGuard::new(token);
+        let n = unsafe {
+            libc::recv(
+                fd,
+                buf[read..].as_mut_ptr() as *mut libc::c_void,
+                buf.len() - read,
+                0,
+            )
+        };
+        drop(guard);
+
+        if n < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        if n == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "connection closed while waiting for ACK",
+            ));
+        }
+        read += n as usize;
+    }
+    Ok(())
+}
@@ -1,37 +1,145 @@
 //! Checksum Module
 //!
-//! Provides MD5 checksum calculation for file integrity verification.
+//! Provides checksum calculation for file integrity verification. MD5 stays
+//! the default for every plain `calculate_*`/`verify_*` call so existing
+//! callers are unaffected; a transfer that negotiates a different
+//! [`HashMethod`] up front (see `protocol::HandshakeRequest::hash_method`)
+//! uses the matching `_with_method` variant instead.
 
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::Path;
 
+use sha2::{Digest, Sha256};
+
 /// Default buffer size for file reading (4MB)
 const BUFFER_SIZE: usize = 4 * 1024 * 1024;
 
+/// Which hash algorithm a transfer uses for file/chunk integrity and its
+/// Merkle tree (see `merkle::MerkleAccumulator`), agreed up front in
+/// `protocol::HandshakeRequest::hash_method`. Doesn't affect
+/// `calculate_chunk_checksum_raw`, which stays MD5-only: it sizes
+/// `protocol::ChunkFrame`'s checksum field at a fixed 16 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashMethod {
+    Md5,
+    Sha256,
+    Blake3,
+}
+
+impl HashMethod {
+    pub fn to_byte(self) -> u8 {
+        match self {
+            HashMethod::Md5 => 0,
+            HashMethod::Sha256 => 1,
+            HashMethod::Blake3 => 2,
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> std::io::Result<Self> {
+        match byte {
+            0 => Ok(HashMethod::Md5),
+            1 => Ok(HashMethod::Sha256),
+            2 => Ok(HashMethod::Blake3),
+            other => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Unknown hash method byte: {}", other),
+            )),
+        }
+    }
+}
+
+/// Hash `data` with `method`, returning the raw digest (16 bytes for MD5,
+/// 32 for SHA-256/BLAKE3). Used directly by `merkle::MerkleAccumulator` to
+/// hash leaves and combine nodes with whichever method a transfer
+/// negotiated.
+pub fn hash_bytes(method: HashMethod, data: &[u8]) -> Vec<u8> {
+    match method {
+        HashMethod::Md5 => md5::compute(data).to_vec(),
+        HashMethod::Sha256 => Sha256::digest(data).to_vec(),
+        HashMethod::Blake3 => blake3::hash(data).as_bytes().to_vec(),
+    }
+}
+
 /// Calculate MD5 checksum of a file
 pub fn calculate_file_checksum(path: &Path) -> std::io::Result<String> {
+    calculate_file_checksum_with_method(path, HashMethod::Md5)
+}
+
+/// Like `calculate_file_checksum`, but with an explicitly negotiated
+/// `HashMethod` instead of the MD5 default.
+pub fn calculate_file_checksum_with_method(
+    path: &Path,
+    method: HashMethod,
+) -> std::io::Result<String> {
     let file = File::open(path)?;
-    let mut reader = BufReader::with_capacity(BUFFER_SIZE, file);
-    let mut context = md5::Context::new();
+    calculate_reader_checksum_with_method(file, method)
+}
+
+/// Calculate MD5 checksum of anything `Read`, without requiring a `Path` to
+/// (re)open - used for an already-open handle a caller can't reopen by path,
+/// such as a `TcpFileSender::from_fd`-backed file descriptor.
+pub fn calculate_reader_checksum<R: Read>(reader: R) -> std::io::Result<String> {
+    calculate_reader_checksum_with_method(reader, HashMethod::Md5)
+}
+
+/// Like `calculate_reader_checksum`, but with an explicitly negotiated
+/// `HashMethod` instead of the MD5 default.
+pub fn calculate_reader_checksum_with_method<R: Read>(
+    reader: R,
+    method: HashMethod,
+) -> std::io::Result<String> {
+    let mut reader = BufReader::with_capacity(BUFFER_SIZE, reader);
     let mut buffer = vec![0u8; BUFFER_SIZE];
 
-    loop {
-        let bytes_read = reader.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
+    let digest = match method {
+        HashMethod::Md5 => {
+            let mut context = md5::Context::new();
+            loop {
+                let bytes_read = reader.read(&mut buffer)?;
+                if bytes_read == 0 {
+                    break;
+                }
+                context.consume(&buffer[..bytes_read]);
+            }
+            context.compute().to_vec()
         }
-        context.consume(&buffer[..bytes_read]);
-    }
+        HashMethod::Sha256 => {
+            let mut hasher = Sha256::new();
+            loop {
+                let bytes_read = reader.read(&mut buffer)?;
+                if bytes_read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..bytes_read]);
+            }
+            hasher.finalize().to_vec()
+        }
+        HashMethod::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            loop {
+                let bytes_read = reader.read(&mut buffer)?;
+                if bytes_read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..bytes_read]);
+            }
+            hasher.finalize().as_bytes().to_vec()
+        }
+    };
 
-    let digest = context.compute();
-    Ok(format!("{:x}", digest))
+    Ok(hex_encode(&digest))
 }
 
 /// Calculate MD5 checksum of a byte slice (for chunks)
 pub fn calculate_chunk_checksum(data: &[u8]) -> String {
-    let digest = md5::compute(data);
-    format!("{:x}", digest)
+    calculate_chunk_checksum_with_method(data, HashMethod::Md5)
+}
+
+/// Like `calculate_chunk_checksum`, but with an explicitly negotiated
+/// `HashMethod` instead of the MD5 default.
+pub fn calculate_chunk_checksum_with_method(data: &[u8], method: HashMethod) -> String {
+    hex_encode(&hash_bytes(method, data))
 }
 
 /// Calculate MD5 checksum of a byte slice and return raw bytes
@@ -42,10 +150,29 @@ pub fn calculate_chunk_checksum_raw(data: &[u8]) -> [u8; 16] {
 
 /// Verify file checksum matches expected value
 pub fn verify_file_checksum(path: &Path, expected: &str) -> std::io::Result<bool> {
-    let actual = calculate_file_checksum(path)?;
+    verify_file_checksum_with_method(path, expected, HashMethod::Md5)
+}
+
+/// Like `verify_file_checksum`, but with an explicitly negotiated
+/// `HashMethod` instead of the MD5 default.
+pub fn verify_file_checksum_with_method(
+    path: &Path,
+    expected: &str,
+    method: HashMethod,
+) -> std::io::Result<bool> {
+    let actual = calculate_file_checksum_with_method(path, method)?;
     Ok(actual.eq_ignore_ascii_case(expected))
 }
 
+fn hex_encode(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        write!(out, "{:02x}", byte).expect("writing to a String never fails");
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -84,4 +211,31 @@ mod tests {
         assert!(verify_file_checksum(temp.path(), &checksum).unwrap());
         assert!(!verify_file_checksum(temp.path(), "wrong_hash").unwrap());
     }
+
+    #[test]
+    fn sha256_and_blake3_checksums_differ_from_md5_and_from_each_other() {
+        let data = b"Hello, VoidWarp!";
+        let md5 = calculate_chunk_checksum_with_method(data, HashMethod::Md5);
+        let sha256 = calculate_chunk_checksum_with_method(data, HashMethod::Sha256);
+        let blake3 = calculate_chunk_checksum_with_method(data, HashMethod::Blake3);
+
+        assert_eq!(md5.len(), 32);
+        assert_eq!(sha256.len(), 64);
+        assert_eq!(blake3.len(), 64);
+        assert_ne!(sha256, blake3);
+    }
+
+    #[test]
+    fn file_and_chunk_checksums_agree_for_every_hash_method() {
+        let mut temp = NamedTempFile::new().unwrap();
+        let data = b"Consistent across file and chunk paths";
+        temp.write_all(data).unwrap();
+        temp.flush().unwrap();
+
+        for method in [HashMethod::Md5, HashMethod::Sha256, HashMethod::Blake3] {
+            let file_checksum = calculate_file_checksum_with_method(temp.path(), method).unwrap();
+            let chunk_checksum = calculate_chunk_checksum_with_method(data, method);
+            assert_eq!(file_checksum, chunk_checksum);
+        }
+    }
 }
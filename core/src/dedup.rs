@@ -0,0 +1,353 @@
+//! Content-defined chunking (CDC) for deduplicated transfers.
+//!
+//! `sender::TcpFileSender::send_deduplicated` replaces fixed
+//! `DEFAULT_CHUNK_SIZE` slicing with boundaries chosen by content rather
+//! than position - the same idea rsync/restic/Borg use. A small edit only
+//! shifts the chunk(s) straddling it instead of re-chunking everything
+//! after the edit point the way fixed-size slicing would, so resending a
+//! folder that changed only slightly, or resuming against a receiver that
+//! already holds most of the data, only has to transmit the handful of
+//! chunks that actually differ. Chunks are identified by a BLAKE3 hash of
+//! their plaintext bytes (see `protocol::ContentChunkId`) rather than by
+//! position, so both sides agree on identity without agreeing on offsets.
+
+/// Target average chunk size; see `CDC_MASK`.
+pub const CDC_TARGET_CHUNK: usize = 1024 * 1024;
+/// Never cut a chunk shorter than this - avoids pathologically small
+/// chunks (and their 32-byte id overhead) on data with long hash-matching
+/// runs.
+pub const CDC_MIN_CHUNK: usize = 256 * 1024;
+/// Force a cut if no natural boundary shows up by this size, bounding the
+/// worst-case chunk size variance.
+pub const CDC_MAX_CHUNK: usize = 4 * 1024 * 1024;
+
+/// Width of the rolling buzhash window.
+const CDC_WINDOW: usize = 64;
+
+/// Cut whenever the rolling hash's low bits are all zero; with a roughly
+/// uniform hash that happens on average every `CDC_MASK + 1` bytes.
+const CDC_MASK: u64 = (CDC_TARGET_CHUNK as u64) - 1;
+
+/// A single content-defined chunk of some larger byte stream: its stable
+/// content id plus where it sits in the stream it was cut from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentChunk {
+    pub id: [u8; 32],
+    pub offset: u64,
+    pub len: u64,
+}
+
+/// Cut `data` into content-defined chunks with a buzhash rolling hash over
+/// a `CDC_WINDOW`-byte window, bounded to `[CDC_MIN_CHUNK, CDC_MAX_CHUNK]`.
+pub fn cut_content_chunks(data: &[u8]) -> Vec<ContentChunk> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+
+    let mut chunks = Vec::new();
+    let mut start = 0usize;
+    let mut hash: u64 = 0;
+
+    for i in 0..data.len() {
+        let chunk_len = i - start + 1;
+        hash = hash.rotate_left(1) ^ BUZHASH_TABLE[data[i] as usize];
+        if chunk_len > CDC_WINDOW {
+            // Undo the byte that's now fallen out of the trailing window -
+            // the usual trick that keeps a rolling buzhash O(1) per byte.
+            let dropped = data[i - CDC_WINDOW];
+            hash ^= BUZHASH_TABLE[dropped as usize].rotate_left((CDC_WINDOW % 64) as u32);
+        }
+
+        let hit_boundary = chunk_len >= CDC_MIN_CHUNK && (hash & CDC_MASK) == 0;
+        let forced_boundary = chunk_len >= CDC_MAX_CHUNK;
+        if hit_boundary || forced_boundary {
+            let end = i + 1;
+            chunks.push(ContentChunk {
+                id: *blake3::hash(&data[start..end]).as_bytes(),
+                offset: start as u64,
+                len: (end - start) as u64,
+            });
+            start = end;
+            hash = 0;
+        }
+    }
+
+    if start < data.len() {
+        chunks.push(ContentChunk {
+            id: *blake3::hash(&data[start..]).as_bytes(),
+            offset: start as u64,
+            len: (data.len() - start) as u64,
+        });
+    }
+
+    chunks
+}
+
+/// Index of content ids found in an existing local file, keyed to the byte
+/// range that produced them - what `receiver::FileReceiverServer` uses to
+/// answer a "have-it" reference with bytes read back off disk instead of
+/// over the network.
+pub fn index_known_chunks(data: &[u8]) -> std::collections::HashMap<[u8; 32], (u64, u64)> {
+    cut_content_chunks(data)
+        .into_iter()
+        .map(|c| (c.id, (c.offset, c.len)))
+        .collect()
+}
+
+/// FastCDC-style content-defined chunker: a gear-hash rolling hash with
+/// dual-mask normalization (Xia et al., "FastCDC: a Fast and Efficient
+/// Content-Defined Chunking Approach for Data Deduplication"). `transfer`'s
+/// `FileSender` uses this to emit variable-sized, content-aligned chunks
+/// instead of slicing at fixed byte offsets - unlike `cut_content_chunks`
+/// above, which cuts a whole in-memory buffer in one pass with a single
+/// mask, this reads incrementally from any `Read` and hands back one chunk
+/// at a time, the shape a streaming sender needs.
+pub struct ContentDefinedChunker<R> {
+    reader: std::io::BufReader<R>,
+    min_size: usize,
+    avg_size: usize,
+    max_size: usize,
+    mask_s: u64,
+    mask_l: u64,
+    offset: u64,
+}
+
+impl<R: std::io::Read> ContentDefinedChunker<R> {
+    /// Build a chunker using this module's own CDC size bounds.
+    pub fn new(reader: R) -> Self {
+        Self::with_sizes(reader, CDC_MIN_CHUNK, CDC_TARGET_CHUNK, CDC_MAX_CHUNK)
+    }
+
+    /// Build a chunker with explicit `[min_size, avg_size, max_size]`
+    /// bounds. `avg_size` sets the normalization masks: `mask_s` (more
+    /// required zero bits, so harder to satisfy) applies below `avg_size`
+    /// to discourage premature cuts, `mask_l` (fewer bits, easier to
+    /// satisfy) applies from `avg_size` up to `max_size` to pull the cut
+    /// back toward the target instead of drifting all the way to the hard
+    /// cutoff.
+    pub fn with_sizes(reader: R, min_size: usize, avg_size: usize, max_size: usize) -> Self {
+        let avg_bits = (avg_size.max(1) as f64).log2().round() as u32;
+        ContentDefinedChunker {
+            reader: std::io::BufReader::new(reader),
+            min_size,
+            avg_size,
+            max_size,
+            mask_s: mask_with_bits(avg_bits + 1),
+            mask_l: mask_with_bits(avg_bits.saturating_sub(1)),
+            offset: 0,
+        }
+    }
+
+    pub fn min_size(&self) -> usize {
+        self.min_size
+    }
+
+    pub fn avg_size(&self) -> usize {
+        self.avg_size
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Read the next content-defined chunk as `(offset, data)`, or `None`
+    /// once the underlying reader is exhausted.
+    pub fn read_chunk(&mut self) -> std::io::Result<Option<(u64, Vec<u8>)>> {
+        let mut buf = Vec::with_capacity(self.avg_size);
+        let mut byte = [0u8; 1];
+        let mut hash: u64 = 0;
+
+        loop {
+            if self.reader.read(&mut byte)? == 0 {
+                break;
+            }
+            buf.push(byte[0]);
+            hash = (hash << 1).wrapping_add(GEAR_TABLE[byte[0] as usize]);
+
+            if buf.len() >= self.max_size {
+                break;
+            }
+            if buf.len() < self.min_size {
+                continue;
+            }
+            let mask = if buf.len() < self.avg_size {
+                self.mask_s
+            } else {
+                self.mask_l
+            };
+            if hash & mask == 0 {
+                break;
+            }
+        }
+
+        if buf.is_empty() {
+            return Ok(None);
+        }
+
+        let offset = self.offset;
+        self.offset += buf.len() as u64;
+        Ok(Some((offset, buf)))
+    }
+}
+
+fn mask_with_bits(bits: u32) -> u64 {
+    (1u64 << bits.min(63)) - 1
+}
+
+// Fixed, arbitrary 256-entry table - it doesn't need to be
+// cryptographically random, just well-distributed enough that the rolling
+// hash's low bits land on content boundaries roughly every
+// `CDC_TARGET_CHUNK` bytes.
+static BUZHASH_TABLE: [u64; 256] = build_buzhash_table();
+
+const fn build_buzhash_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x9E3779B97F4A7C15;
+    let mut i = 0;
+    while i < 256 {
+        // splitmix64
+        state = state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+        table[i] = z;
+        i += 1;
+    }
+    table
+}
+
+/// `ContentDefinedChunker`'s gear-hash table - same splitmix64 construction
+/// as `BUZHASH_TABLE` above, just seeded differently so the two rolling
+/// hashes don't land on correlated boundaries.
+static GEAR_TABLE: [u64; 256] = build_gear_table();
+
+const fn build_gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x2545F4914F6CDD1D;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+        table[i] = z;
+        i += 1;
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cuts_cover_the_whole_input_with_no_gaps_or_overlap() {
+        let data = vec![0u8; CDC_MIN_CHUNK * 3];
+        let chunks = cut_content_chunks(&data);
+        assert!(!chunks.is_empty());
+
+        let mut expected_offset = 0u64;
+        for chunk in &chunks {
+            assert_eq!(chunk.offset, expected_offset);
+            expected_offset += chunk.len;
+        }
+        assert_eq!(expected_offset, data.len() as u64);
+    }
+
+    #[test]
+    fn chunk_ids_are_stable_for_identical_content() {
+        let data = b"the quick brown fox jumps over the lazy dog".repeat(20_000);
+        assert_eq!(cut_content_chunks(&data), cut_content_chunks(&data));
+    }
+
+    #[test]
+    fn no_chunk_exceeds_the_max_bound() {
+        let mut data = vec![0u8; CDC_MAX_CHUNK * 4];
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = (i % 7) as u8; // avoid runs the hash would never cut
+        }
+        for chunk in cut_content_chunks(&data) {
+            assert!(chunk.len <= CDC_MAX_CHUNK as u64);
+        }
+    }
+
+    #[test]
+    fn an_insertion_only_changes_nearby_chunks() {
+        let mut original = vec![0u8; CDC_MIN_CHUNK * 8];
+        for (i, b) in original.iter_mut().enumerate() {
+            *b = (i % 251) as u8;
+        }
+        let mut edited = original.clone();
+        edited.splice(CDC_MIN_CHUNK * 4..CDC_MIN_CHUNK * 4, vec![0xAB; 37]);
+
+        let original_ids: std::collections::HashSet<_> =
+            cut_content_chunks(&original).into_iter().map(|c| c.id).collect();
+        let edited_ids: std::collections::HashSet<_> =
+            cut_content_chunks(&edited).into_iter().map(|c| c.id).collect();
+
+        let unchanged = original_ids.intersection(&edited_ids).count();
+        assert!(
+            unchanged > 0,
+            "content-defined cuts should preserve most chunk ids around an unrelated edit"
+        );
+    }
+
+    #[test]
+    fn gear_chunker_covers_the_whole_input_with_no_gaps_or_overlap() {
+        let data = vec![0u8; CDC_MIN_CHUNK * 5];
+        let mut chunker = ContentDefinedChunker::new(std::io::Cursor::new(&data));
+
+        let mut expected_offset = 0u64;
+        while let Some((offset, chunk)) = chunker.read_chunk().unwrap() {
+            assert_eq!(offset, expected_offset);
+            expected_offset += chunk.len() as u64;
+        }
+        assert_eq!(expected_offset, data.len() as u64);
+    }
+
+    #[test]
+    fn gear_chunker_respects_the_max_bound() {
+        let mut data = vec![0u8; CDC_MAX_CHUNK * 4];
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = (i % 7) as u8; // avoid runs the hash would never cut
+        }
+        let mut chunker = ContentDefinedChunker::new(std::io::Cursor::new(&data));
+        while let Some((_, chunk)) = chunker.read_chunk().unwrap() {
+            assert!(chunk.len() <= CDC_MAX_CHUNK);
+        }
+    }
+
+    #[test]
+    fn gear_chunker_cuts_are_stable_for_identical_content() {
+        let data = b"the quick brown fox jumps over the lazy dog".repeat(20_000);
+
+        let mut a = ContentDefinedChunker::new(std::io::Cursor::new(&data));
+        let mut b = ContentDefinedChunker::new(std::io::Cursor::new(&data));
+        loop {
+            let next_a = a.read_chunk().unwrap();
+            let next_b = b.read_chunk().unwrap();
+            assert_eq!(next_a, next_b);
+            if next_a.is_none() {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn gear_chunker_honors_custom_size_bounds() {
+        let mut data = vec![0u8; 64 * 1024];
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = (i % 251) as u8;
+        }
+        let mut chunker = ContentDefinedChunker::with_sizes(std::io::Cursor::new(&data), 1024, 8192, 16384);
+        assert_eq!(chunker.min_size(), 1024);
+        assert_eq!(chunker.avg_size(), 8192);
+        assert_eq!(chunker.max_size(), 16384);
+
+        while let Some((_, chunk)) = chunker.read_chunk().unwrap() {
+            assert!(chunk.len() <= 16384);
+        }
+    }
+}
@@ -0,0 +1,314 @@
+//! Out-of-band "beacon" peer exchange for networks where mDNS is blocked.
+//!
+//! `DiscoveryManager` already has a [`super::DiscoveryManager::new_fallback`]
+//! mode for when mDNS is unavailable, but that mode only helps once you
+//! already know a peer's address. A beacon closes that gap: it's a short,
+//! shareable token (pasteable into chat, or embeddable in a QR code) that
+//! carries this device's reachable [`SocketAddr`]s. One side calls
+//! [`generate_beacon`], the other pastes/scans the token into
+//! [`super::DiscoveryManager::ingest_beacon`], and the addresses land in
+//! the normal peer table via [`super::DiscoveryManager::add_manual_peer`] -
+//! no multicast involved.
+//!
+//! The payload is encrypted with a key derived from the pairing code, so
+//! the token is useless to anyone who hasn't also seen that code, and it
+//! embeds a timestamp so a captured beacon can't be replayed once the
+//! advertised addresses have moved on - see [`BEACON_VALIDITY_WINDOW`].
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use data_encoding::BASE32_NOPAD;
+use hkdf::Hkdf;
+use ring::aead::{self, Aad, LessSafeKey, UnboundKey, CHACHA20_POLY1305};
+use ring::rand::{SecureRandom, SystemRandom};
+use sha2::Sha256;
+use thiserror::Error;
+
+/// Wraps every generated beacon, so it's recognizable when pasted into
+/// arbitrary surrounding text (chat messages, notes, etc).
+const BEGIN_MARKER: &str = "-----BEGIN VOIDWARP BEACON-----";
+const END_MARKER: &str = "-----END VOIDWARP BEACON-----";
+
+/// Default tolerance for how far a beacon's embedded timestamp may drift
+/// from "now" (either direction, to allow for clock skew) before
+/// [`parse_beacon`] rejects it as stale.
+pub const BEACON_VALIDITY_WINDOW: Duration = Duration::from_secs(30 * 60);
+
+/// Errors that can occur while generating or parsing a beacon token.
+#[derive(Error, Debug)]
+pub enum BeaconError {
+    #[error("beacon is missing the expected begin/end markers")]
+    MalformedMarkers,
+    #[error("beacon body is not valid base32")]
+    InvalidEncoding,
+    #[error("beacon could not be decrypted (wrong pairing code, or corrupted)")]
+    DecryptionFailed,
+    #[error("beacon payload is truncated or malformed")]
+    Truncated,
+    #[error("beacon timestamp is outside the validity window")]
+    Expired,
+}
+
+/// A decoded beacon: the advertising device's id and its reachable addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BeaconPayload {
+    pub device_id: String,
+    pub addresses: Vec<SocketAddr>,
+}
+
+/// Encode `device_id` and `addresses` into a shareable, encrypted beacon
+/// token, keyed by `pairing_code`.
+pub fn generate_beacon(device_id: &str, addresses: &[SocketAddr], pairing_code: &str) -> String {
+    let timestamp = unix_timestamp_now();
+    let plaintext = encode_payload(timestamp, device_id, addresses);
+    let key = derive_beacon_key(pairing_code);
+    let sealed = seal(&key, &plaintext);
+
+    format!(
+        "{}\n{}\n{}",
+        BEGIN_MARKER,
+        BASE32_NOPAD.encode(&sealed),
+        END_MARKER
+    )
+}
+
+/// Decode a beacon produced by [`generate_beacon`], rejecting it if
+/// `pairing_code` doesn't match or the embedded timestamp is more than
+/// `validity_window` away from now.
+pub fn parse_beacon(
+    beacon: &str,
+    pairing_code: &str,
+    validity_window: Duration,
+) -> Result<BeaconPayload, BeaconError> {
+    let body = strip_markers(beacon)?;
+    let sealed = BASE32_NOPAD
+        .decode(body.as_bytes())
+        .map_err(|_| BeaconError::InvalidEncoding)?;
+
+    let key = derive_beacon_key(pairing_code);
+    let plaintext = open(&key, &sealed)?;
+    let (timestamp, device_id, addresses) = decode_payload(&plaintext)?;
+
+    let age = unix_timestamp_now().abs_diff(timestamp);
+    if age > validity_window.as_secs() {
+        return Err(BeaconError::Expired);
+    }
+
+    Ok(BeaconPayload {
+        device_id,
+        addresses,
+    })
+}
+
+fn unix_timestamp_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn derive_beacon_key(pairing_code: &str) -> [u8; 32] {
+    let hk = Hkdf::<Sha256>::new(Some(b"voidwarp-beacon"), pairing_code.as_bytes());
+    let mut key = [0u8; 32];
+    hk.expand(b"voidwarp beacon key", &mut key)
+        .expect("32 bytes is a valid HKDF output length");
+    key
+}
+
+/// Encrypt `plaintext` under `key`, returning `12-byte random nonce ||
+/// ciphertext || 16-byte auth tag`. Unlike [`crate::security::channel`]'s
+/// counter-based nonces, a beacon is a single stateless message, so a
+/// fresh random nonce per call is simplest.
+fn seal(key: &[u8; 32], plaintext: &[u8]) -> Vec<u8> {
+    let unbound = UnboundKey::new(&CHACHA20_POLY1305, key).expect("32-byte key is valid");
+    let sealing_key = LessSafeKey::new(unbound);
+
+    let rng = SystemRandom::new();
+    let mut nonce_bytes = [0u8; 12];
+    rng.fill(&mut nonce_bytes).expect("system RNG is available");
+    let nonce = aead::Nonce::assume_unique_for_key(nonce_bytes);
+
+    let mut in_out = plaintext.to_vec();
+    sealing_key
+        .seal_in_place_append_tag(nonce, Aad::empty(), &mut in_out)
+        .expect("sealing with a fresh nonce and valid key cannot fail");
+
+    let mut packet = Vec::with_capacity(12 + in_out.len());
+    packet.extend_from_slice(&nonce_bytes);
+    packet.extend_from_slice(&in_out);
+    packet
+}
+
+fn open(key: &[u8; 32], packet: &[u8]) -> Result<Vec<u8>, BeaconError> {
+    if packet.len() < 12 {
+        return Err(BeaconError::Truncated);
+    }
+    let unbound = UnboundKey::new(&CHACHA20_POLY1305, key).expect("32-byte key is valid");
+    let opening_key = LessSafeKey::new(unbound);
+
+    let mut nonce_bytes = [0u8; 12];
+    nonce_bytes.copy_from_slice(&packet[0..12]);
+    let nonce = aead::Nonce::assume_unique_for_key(nonce_bytes);
+
+    let mut in_out = packet[12..].to_vec();
+    let plaintext = opening_key
+        .open_in_place(nonce, Aad::empty(), &mut in_out)
+        .map_err(|_| BeaconError::DecryptionFailed)?;
+    Ok(plaintext.to_vec())
+}
+
+fn strip_markers(beacon: &str) -> Result<String, BeaconError> {
+    let begin = beacon
+        .find(BEGIN_MARKER)
+        .ok_or(BeaconError::MalformedMarkers)?;
+    let end = beacon
+        .find(END_MARKER)
+        .ok_or(BeaconError::MalformedMarkers)?;
+    let start = begin + BEGIN_MARKER.len();
+    if end < start {
+        return Err(BeaconError::MalformedMarkers);
+    }
+
+    Ok(beacon[start..end].chars().filter(|c| !c.is_whitespace()).collect())
+}
+
+/// `timestamp(4) || device_id_len(1) || device_id || addr_count(1) ||
+/// addr*`, where each `addr` is `tag(1, 4=v4/6=v6) || ip_bytes || port(2,
+/// big-endian)`.
+fn encode_payload(timestamp: u64, device_id: &str, addresses: &[SocketAddr]) -> Vec<u8> {
+    let id_bytes = device_id.as_bytes();
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&(timestamp as u32).to_le_bytes());
+    buf.push(id_bytes.len() as u8);
+    buf.extend_from_slice(id_bytes);
+    buf.push(addresses.len() as u8);
+    for addr in addresses {
+        match addr.ip() {
+            IpAddr::V4(v4) => {
+                buf.push(4);
+                buf.extend_from_slice(&v4.octets());
+            }
+            IpAddr::V6(v6) => {
+                buf.push(6);
+                buf.extend_from_slice(&v6.octets());
+            }
+        }
+        buf.extend_from_slice(&addr.port().to_be_bytes());
+    }
+    buf
+}
+
+fn decode_payload(buf: &[u8]) -> Result<(u64, String, Vec<SocketAddr>), BeaconError> {
+    let mut cursor = 0usize;
+
+    let ts_bytes: [u8; 4] = buf
+        .get(0..4)
+        .and_then(|s| s.try_into().ok())
+        .ok_or(BeaconError::Truncated)?;
+    let timestamp = u32::from_le_bytes(ts_bytes) as u64;
+    cursor += 4;
+
+    let id_len = *buf.get(cursor).ok_or(BeaconError::Truncated)? as usize;
+    cursor += 1;
+    let id_bytes = buf
+        .get(cursor..cursor + id_len)
+        .ok_or(BeaconError::Truncated)?;
+    let device_id = String::from_utf8(id_bytes.to_vec()).map_err(|_| BeaconError::Truncated)?;
+    cursor += id_len;
+
+    let addr_count = *buf.get(cursor).ok_or(BeaconError::Truncated)? as usize;
+    cursor += 1;
+
+    let mut addresses = Vec::with_capacity(addr_count);
+    for _ in 0..addr_count {
+        let tag = *buf.get(cursor).ok_or(BeaconError::Truncated)?;
+        cursor += 1;
+        let ip = match tag {
+            4 => {
+                let octets: [u8; 4] = buf
+                    .get(cursor..cursor + 4)
+                    .and_then(|s| s.try_into().ok())
+                    .ok_or(BeaconError::Truncated)?;
+                cursor += 4;
+                IpAddr::V4(Ipv4Addr::from(octets))
+            }
+            6 => {
+                let octets: [u8; 16] = buf
+                    .get(cursor..cursor + 16)
+                    .and_then(|s| s.try_into().ok())
+                    .ok_or(BeaconError::Truncated)?;
+                cursor += 16;
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+            _ => return Err(BeaconError::Truncated),
+        };
+        let port_bytes: [u8; 2] = buf
+            .get(cursor..cursor + 2)
+            .and_then(|s| s.try_into().ok())
+            .ok_or(BeaconError::Truncated)?;
+        cursor += 2;
+        addresses.push(SocketAddr::new(ip, u16::from_be_bytes(port_bytes)));
+    }
+
+    Ok((timestamp, device_id, addresses))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_addresses() -> Vec<SocketAddr> {
+        vec![
+            "192.168.1.42:9876".parse().unwrap(),
+            "[fe80::1]:9876".parse().unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_beacon_roundtrip() {
+        let beacon = generate_beacon("deadbeef", &sample_addresses(), "123456");
+        assert!(beacon.starts_with(BEGIN_MARKER));
+        assert!(beacon.trim_end().ends_with(END_MARKER));
+
+        let decoded = parse_beacon(&beacon, "123456", BEACON_VALIDITY_WINDOW).unwrap();
+        assert_eq!(decoded.device_id, "deadbeef");
+        assert_eq!(decoded.addresses, sample_addresses());
+    }
+
+    #[test]
+    fn test_wrong_pairing_code_rejected() {
+        let beacon = generate_beacon("deadbeef", &sample_addresses(), "123456");
+        assert!(matches!(
+            parse_beacon(&beacon, "654321", BEACON_VALIDITY_WINDOW),
+            Err(BeaconError::DecryptionFailed)
+        ));
+    }
+
+    #[test]
+    fn test_malformed_markers_rejected() {
+        assert!(matches!(
+            parse_beacon("not a beacon at all", "123456", BEACON_VALIDITY_WINDOW),
+            Err(BeaconError::MalformedMarkers)
+        ));
+    }
+
+    #[test]
+    fn test_expired_beacon_rejected() {
+        let key = derive_beacon_key("123456");
+        let stale_timestamp = unix_timestamp_now().saturating_sub(3600);
+        let plaintext = encode_payload(stale_timestamp, "deadbeef", &sample_addresses());
+        let sealed = seal(&key, &plaintext);
+        let beacon = format!(
+            "{}\n{}\n{}",
+            BEGIN_MARKER,
+            BASE32_NOPAD.encode(&sealed),
+            END_MARKER
+        );
+
+        assert!(matches!(
+            parse_beacon(&beacon, "123456", Duration::from_secs(30 * 60)),
+            Err(BeaconError::Expired)
+        ));
+    }
+}
@@ -0,0 +1,353 @@
+//! A generic, marker-configurable beacon token: encodes a bare list of
+//! [`SocketAddr`]s into an opaque base32 blob wrapped in caller-supplied
+//! begin/end markers, for publish channels whose own format or size budget
+//! doesn't fit [`super::beacon`]'s fixed `-----BEGIN VOIDWARP
+//! BEACON-----` markers or [`super::rendezvous`]'s richer
+//! `RendezvousBeacon` (device id, public key, name) - a DNS TXT record's
+//! character limit, for instance, or a paste service with its own
+//! delimiter conventions the caller needs the token to avoid colliding
+//! with.
+//!
+//! Like [`super::rendezvous`], there's no pairing-code encryption here: the
+//! publish channel itself (a private paste, a DM) is the trust boundary,
+//! not a shared secret.
+
+use std::fs;
+use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use data_encoding::BASE32_NOPAD;
+use thiserror::Error;
+
+/// Errors that can occur while encoding, decoding, publishing, or fetching
+/// a [`BeaconSerializer`] token.
+#[derive(Error, Debug)]
+pub enum BeaconSerializerError {
+    #[error("beacon is missing the expected begin/end markers")]
+    MalformedMarkers,
+    #[error("beacon body is not valid base32")]
+    InvalidEncoding,
+    #[error("beacon payload is truncated or malformed")]
+    Truncated,
+    #[error("I/O error publishing/fetching beacon: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("beacon command exited with status {0}")]
+    CommandFailed(std::process::ExitStatus),
+}
+
+/// Encodes/decodes address lists framed by markers supplied at construction
+/// time, rather than the fixed markers `beacon`/`rendezvous` hardcode, plus
+/// two publish/fetch backends (a file, or a user-supplied shell command) to
+/// move the resulting token across whatever channel actually bridges two
+/// networks.
+pub struct BeaconSerializer {
+    begin: String,
+    end: String,
+}
+
+impl BeaconSerializer {
+    pub fn new(begin: impl Into<String>, end: impl Into<String>) -> Self {
+        Self {
+            begin: begin.into(),
+            end: end.into(),
+        }
+    }
+
+    /// Encode `addresses` into a marker-wrapped, base32 text token.
+    pub fn encode(&self, addresses: &[SocketAddr]) -> String {
+        let body = encode_addresses(addresses);
+        format!(
+            "{}\n{}\n{}",
+            self.begin,
+            BASE32_NOPAD.encode(&body),
+            self.end
+        )
+    }
+
+    /// Scan `text` for this serializer's markers and decode whatever's
+    /// between them, ignoring everything outside - so the token can be
+    /// embedded inside a larger document (an email signature, a DNS TXT
+    /// record alongside other key=value pairs, etc).
+    pub fn decode(&self, text: &str) -> Result<Vec<SocketAddr>, BeaconSerializerError> {
+        let body = self.strip_markers(text)?;
+        let bytes = BASE32_NOPAD
+            .decode(body.as_bytes())
+            .map_err(|_| BeaconSerializerError::InvalidEncoding)?;
+        decode_addresses(&bytes)
+    }
+
+    fn strip_markers(&self, text: &str) -> Result<String, BeaconSerializerError> {
+        let begin = text
+            .find(&self.begin)
+            .ok_or(BeaconSerializerError::MalformedMarkers)?;
+        let end = text
+            .find(&self.end)
+            .ok_or(BeaconSerializerError::MalformedMarkers)?;
+        let start = begin + self.begin.len();
+        if end < start {
+            return Err(BeaconSerializerError::MalformedMarkers);
+        }
+        Ok(text[start..end]
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect())
+    }
+
+    /// Write the encoded token to `path`, restricted to owner read/write
+    /// (unix mode 0600) so only this user can read the addresses back off
+    /// shared storage.
+    pub fn publish_to_file(
+        &self,
+        path: &Path,
+        addresses: &[SocketAddr],
+    ) -> Result<(), BeaconSerializerError> {
+        let token = self.encode(addresses);
+        fs::write(path, token)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(())
+    }
+
+    /// Read and decode a token previously written by
+    /// [`Self::publish_to_file`].
+    pub fn fetch_from_file(&self, path: &Path) -> Result<Vec<SocketAddr>, BeaconSerializerError> {
+        let text = fs::read_to_string(path)?;
+        self.decode(&text)
+    }
+
+    /// Publish by invoking a user-supplied shell command, passing the
+    /// token's parts as environment variables (`begin`/`data`/`end` for the
+    /// individual pieces, `beacon` for the whole marked token) so the
+    /// command can build whatever request its channel needs - e.g. `curl`
+    /// a paste service, or set a DNS TXT record via a provider's CLI. The
+    /// whole token is also piped to the command's stdin, for commands that
+    /// would rather read it than assemble it from the env vars.
+    pub fn publish_via_command(
+        &self,
+        command: &str,
+        addresses: &[SocketAddr],
+    ) -> Result<(), BeaconSerializerError> {
+        let token = self.encode(addresses);
+        let data = self.strip_markers(&token)?;
+
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("begin", &self.begin)
+            .env("data", &data)
+            .env("end", &self.end)
+            .env("beacon", &token)
+            .stdin(Stdio::piped())
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(token.as_bytes());
+        }
+
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(BeaconSerializerError::CommandFailed(status));
+        }
+        Ok(())
+    }
+
+    /// Fetch a token by invoking a user-supplied shell command (e.g.
+    /// `curl`ing a URL where a peer published its token) and decoding its
+    /// stdout.
+    pub fn fetch_via_command(&self, command: &str) -> Result<Vec<SocketAddr>, BeaconSerializerError> {
+        let output = Command::new("sh").arg("-c").arg(command).output()?;
+        if !output.status.success() {
+            return Err(BeaconSerializerError::CommandFailed(output.status));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        self.decode(stdout.trim())
+    }
+}
+
+/// `addr_count(1) || addr*`, where each `addr` is `tag(1, 4=v4/6=v6) ||
+/// ip_bytes || port(2, big-endian)` - the same per-address layout
+/// [`super::beacon`] and [`super::rendezvous`] use.
+fn encode_addresses(addresses: &[SocketAddr]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.push(addresses.len() as u8);
+    for addr in addresses {
+        match addr.ip() {
+            IpAddr::V4(v4) => {
+                buf.push(4);
+                buf.extend_from_slice(&v4.octets());
+            }
+            IpAddr::V6(v6) => {
+                buf.push(6);
+                buf.extend_from_slice(&v6.octets());
+            }
+        }
+        buf.extend_from_slice(&addr.port().to_be_bytes());
+    }
+    buf
+}
+
+fn decode_addresses(buf: &[u8]) -> Result<Vec<SocketAddr>, BeaconSerializerError> {
+    let mut cursor = 0usize;
+    let addr_count = *buf.get(cursor).ok_or(BeaconSerializerError::Truncated)? as usize;
+    cursor += 1;
+
+    let mut addresses = Vec::with_capacity(addr_count);
+    for _ in 0..addr_count {
+        let tag = *buf.get(cursor).ok_or(BeaconSerializerError::Truncated)?;
+        cursor += 1;
+        let ip = match tag {
+            4 => {
+                let octets: [u8; 4] = buf
+                    .get(cursor..cursor + 4)
+                    .and_then(|s| s.try_into().ok())
+                    .ok_or(BeaconSerializerError::Truncated)?;
+                cursor += 4;
+                IpAddr::V4(Ipv4Addr::from(octets))
+            }
+            6 => {
+                let octets: [u8; 16] = buf
+                    .get(cursor..cursor + 16)
+                    .and_then(|s| s.try_into().ok())
+                    .ok_or(BeaconSerializerError::Truncated)?;
+                cursor += 16;
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+            _ => return Err(BeaconSerializerError::Truncated),
+        };
+        let port_bytes: [u8; 2] = buf
+            .get(cursor..cursor + 2)
+            .and_then(|s| s.try_into().ok())
+            .ok_or(BeaconSerializerError::Truncated)?;
+        cursor += 2;
+        addresses.push(SocketAddr::new(ip, u16::from_be_bytes(port_bytes)));
+    }
+    Ok(addresses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_addresses() -> Vec<SocketAddr> {
+        vec![
+            "192.168.1.42:9876".parse().unwrap(),
+            "[fe80::1]:9876".parse().unwrap(),
+        ]
+    }
+
+    fn serializer() -> BeaconSerializer {
+        BeaconSerializer::new("<<VW-BEGIN>>", "<<VW-END>>")
+    }
+
+    #[test]
+    fn roundtrips_with_caller_supplied_markers() {
+        let s = serializer();
+        let token = s.encode(&sample_addresses());
+        assert!(token.contains("<<VW-BEGIN>>"));
+        assert!(token.contains("<<VW-END>>"));
+
+        let decoded = s.decode(&token).unwrap();
+        assert_eq!(decoded, sample_addresses());
+    }
+
+    #[test]
+    fn decode_ignores_surrounding_text() {
+        let s = serializer();
+        let token = s.encode(&sample_addresses());
+        let embedded = format!("Hey, here's my address:\n{}\nTalk soon!", token);
+
+        let decoded = s.decode(&embedded).unwrap();
+        assert_eq!(decoded, sample_addresses());
+    }
+
+    #[test]
+    fn different_marker_pairs_do_not_cross_decode() {
+        let a = BeaconSerializer::new("A-BEGIN", "A-END");
+        let b = BeaconSerializer::new("B-BEGIN", "B-END");
+        let token = a.encode(&sample_addresses());
+
+        assert!(matches!(
+            b.decode(&token),
+            Err(BeaconSerializerError::MalformedMarkers)
+        ));
+    }
+
+    #[test]
+    fn malformed_text_is_rejected() {
+        assert!(matches!(
+            serializer().decode("nothing to see here"),
+            Err(BeaconSerializerError::MalformedMarkers)
+        ));
+    }
+
+    #[test]
+    fn truncated_payload_is_rejected() {
+        let s = serializer();
+        let body = BASE32_NOPAD.encode(&[0xff]);
+        let token = format!("<<VW-BEGIN>>\n{}\n<<VW-END>>", body);
+        assert!(matches!(
+            s.decode(&token),
+            Err(BeaconSerializerError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn file_roundtrip_preserves_addresses() {
+        let s = serializer();
+        let path = std::env::temp_dir().join(format!(
+            "voidwarp-beacon-serializer-test-{}",
+            std::process::id()
+        ));
+
+        s.publish_to_file(&path, &sample_addresses()).unwrap();
+        let decoded = s.fetch_from_file(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(decoded, sample_addresses());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_is_published_with_restrictive_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let s = serializer();
+        let path = std::env::temp_dir().join(format!(
+            "voidwarp-beacon-serializer-perm-test-{}",
+            std::process::id()
+        ));
+
+        s.publish_to_file(&path, &sample_addresses()).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(mode, 0o600);
+    }
+
+    #[test]
+    fn command_roundtrip_preserves_addresses() {
+        let s = serializer();
+        let path = std::env::temp_dir().join(format!(
+            "voidwarp-beacon-serializer-cmd-test-{}",
+            std::process::id()
+        ));
+
+        // `publish_via_command` feeds the token to the command's stdin;
+        // redirect to a file so `fetch_via_command` (which reads stdout)
+        // can read it back with a plain `cat`, avoiding shell quoting of
+        // the token's embedded newlines.
+        s.publish_via_command(&format!("cat > {}", path.display()), &sample_addresses())
+            .unwrap();
+        let decoded = s
+            .fetch_via_command(&format!("cat {}", path.display()))
+            .unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(decoded, sample_addresses());
+    }
+}
@@ -4,47 +4,125 @@
 //! This module sends a "Hello" UDP packet to the broadcast address of **every** IPv4 interface,
 //! so that at least one copy reaches the same LAN as the discovering device (e.g. Android).
 //! On Android we only use the UDP listener (BeaconListener); the beacon is Windows-only.
+//!
+//! Every Hello is signed with the sender's [`DeviceIdentity`], and
+//! [`parse_hello_packet`] verifies that signature before anyone gets to look
+//! at the claimed `device_id`/`device_name` - this is what keeps an
+//! unauthenticated host on the LAN from poisoning [`BeaconListener`]'s peer
+//! map with a forged identity.
+//!
+//! IPv4 broadcast silently goes nowhere on IPv6-only or broadcast-suppressed
+//! networks, so [`DiscoveryMode`] additionally gates an IPv6 link-local
+//! multicast path that uses the same signed Hello payload - `parse_hello_packet`
+//! doesn't care which transport a packet arrived over.
 
 #[cfg(not(target_os = "android"))]
 use local_ip_address::list_afinet_netifas;
 use std::collections::HashMap;
-use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6, UdpSocket};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 use std::thread;
 use std::time::Duration;
 
+use crate::security::crypto::DeviceIdentity;
+use crate::security::trust::TrustStore;
+
 /// Discovery beacon magic and packet type
 const BEACON_MAGIC: [u8; 2] = [0x56, 0x57]; // "VW"
 const PACKET_TYPE_HELLO: u8 = 0x03;
 
+/// Length of a detached Ed25519 signature.
+const SIGNATURE_LEN: usize = 64;
+
+/// VoidWarp's link-local (`ff02::/16`) Hello multicast group. The low 16
+/// bits spell out the "VW" magic for easy recognition in a packet capture.
+const MULTICAST_GROUP_V6: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0x5657);
+
+/// Which transport(s) a [`BroadcastBeacon`]/[`BeaconListener`] pair uses.
+/// Both can run at once so a host reaches peers over whichever path their
+/// network actually allows, deduping by `device_id` once Hellos land in the
+/// shared peer map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiscoveryMode {
+    /// IPv4 broadcast only (the original behavior).
+    BroadcastV4,
+    /// IPv6 link-local multicast only.
+    MulticastV6,
+    /// Both transports simultaneously.
+    Both,
+}
+
+impl DiscoveryMode {
+    fn wants_broadcast(self) -> bool {
+        matches!(self, DiscoveryMode::BroadcastV4 | DiscoveryMode::Both)
+    }
+
+    fn wants_multicast(self) -> bool {
+        matches!(self, DiscoveryMode::MulticastV6 | DiscoveryMode::Both)
+    }
+}
+
 /// Multi-interface broadcast beacon: sends Hello packets on every IPv4 interface
-/// so that discovery works on Windows with multiple adapters (WiFi, WSL, Docker, etc.).
+/// so that discovery works on Windows with multiple adapters (WiFi, WSL, Docker, etc.),
+/// plus (per [`DiscoveryMode`]) an IPv6 multicast path for networks that drop broadcast.
 /// On Android this is a no-op (we only run the UDP listener).
 pub struct BroadcastBeacon {
     stop: Arc<AtomicBool>,
-    handle: Option<thread::JoinHandle<()>>,
+    handles: Vec<thread::JoinHandle<()>>,
 }
 
 #[cfg(not(target_os = "android"))]
 impl BroadcastBeacon {
-    /// Start the beacon. Sends Hello to 255.255.255.255:port via every non-loopback IPv4 interface.
-    pub fn start(device_id: String, device_name: String, port: u16) -> Self {
+    /// Start the beacon. Sends a Hello, signed with `identity`, to
+    /// 255.255.255.255:port via every non-loopback IPv4 interface and/or
+    /// to the [`MULTICAST_GROUP_V6`] group, depending on `mode`.
+    pub fn start(identity: &DeviceIdentity, port: u16, mode: DiscoveryMode) -> Self {
         let stop = Arc::new(AtomicBool::new(false));
-        let stop_clone = stop.clone();
 
-        let handle = thread::spawn(move || {
-            Self::run_beacon_loop(device_id, device_name, port, stop_clone);
-        });
+        // `DeviceIdentity` isn't `Clone` or `Send` (it wraps key material
+        // that's meant to stay put); each beacon thread re-derives its own
+        // copy from the exported PKCS#8 document instead of moving
+        // `identity` in, matching how `sender`/`ffi` hand identities to
+        // background threads elsewhere in this crate.
+        let pkcs8 = identity.export();
+        let device_name = identity.device_name.clone();
 
-        Self {
-            stop,
-            handle: Some(handle),
+        let mut handles = Vec::new();
+        if mode.wants_broadcast() {
+            let stop = stop.clone();
+            let pkcs8 = pkcs8.clone();
+            let device_name = device_name.clone();
+            handles.push(thread::spawn(move || {
+                let identity = match DeviceIdentity::import(&device_name, &pkcs8) {
+                    Ok(identity) => identity,
+                    Err(e) => {
+                        tracing::error!("broadcast beacon: failed to re-import identity: {}", e);
+                        return;
+                    }
+                };
+                Self::run_broadcast_loop(&identity, port, stop);
+            }));
         }
+        if mode.wants_multicast() {
+            let stop = stop.clone();
+            handles.push(thread::spawn(move || {
+                let identity = match DeviceIdentity::import(&device_name, &pkcs8) {
+                    Ok(identity) => identity,
+                    Err(e) => {
+                        tracing::error!("multicast beacon: failed to re-import identity: {}", e);
+                        return;
+                    }
+                };
+                Self::run_multicast_loop(&identity, port, stop);
+            }));
+        }
+
+        Self { stop, handles }
     }
 
-    fn run_beacon_loop(device_id: String, device_name: String, port: u16, stop: Arc<AtomicBool>) {
-        let payload = build_hello_packet(&device_id, &device_name, port);
+    fn run_broadcast_loop(identity: &DeviceIdentity, port: u16, stop: Arc<AtomicBool>) {
+        let payload = build_hello_packet(identity, port);
         let broadcast_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::BROADCAST), port);
 
         while !stop.load(Ordering::SeqCst) {
@@ -71,19 +149,37 @@ impl BroadcastBeacon {
                     tracing::warn!("Failed to list interfaces for beacon: {}", e);
                 }
             }
-            for _ in 0..20 {
-                if stop.load(Ordering::SeqCst) {
-                    return;
+            sleep_in_ticks(&stop);
+        }
+    }
+
+    fn run_multicast_loop(identity: &DeviceIdentity, port: u16, stop: Arc<AtomicBool>) {
+        let payload = build_hello_packet(identity, port);
+        let dest = SocketAddr::V6(SocketAddrV6::new(MULTICAST_GROUP_V6, port, 0, 0));
+
+        while !stop.load(Ordering::SeqCst) {
+            match non_loopback_interface_indices() {
+                Ok(indices) => {
+                    for (name, index) in &indices {
+                        if let Err(e) = send_via_interface_v6(*index, &dest, &payload) {
+                            tracing::debug!("Multicast beacon send via {}: {}", name, e);
+                        } else {
+                            tracing::info!("Multicasting to {} via {}", dest, name);
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to enumerate interfaces for multicast beacon: {}", e);
                 }
-                thread::sleep(Duration::from_millis(100));
             }
+            sleep_in_ticks(&stop);
         }
     }
 
     /// Stop the beacon.
     pub fn stop(mut self) {
         self.stop.store(true, Ordering::SeqCst);
-        if let Some(h) = self.handle.take() {
+        for h in self.handles.drain(..) {
             let _ = h.join();
         }
     }
@@ -92,17 +188,31 @@ impl BroadcastBeacon {
 #[cfg(target_os = "android")]
 impl BroadcastBeacon {
     /// No-op on Android (beacon is Windows-only; Android only runs the UDP listener).
-    pub fn start(_device_id: String, _device_name: String, _port: u16) -> Self {
+    pub fn start(_identity: &DeviceIdentity, _port: u16, _mode: DiscoveryMode) -> Self {
         Self {
             stop: Arc::new(AtomicBool::new(true)),
-            handle: None,
+            handles: Vec::new(),
         }
     }
 
     pub fn stop(self) {}
 }
 
-fn build_hello_packet(device_id: &str, device_name: &str, port: u16) -> Vec<u8> {
+/// Sleep for ~2s in 100ms ticks so `stop` is noticed promptly.
+fn sleep_in_ticks(stop: &AtomicBool) {
+    for _ in 0..20 {
+        if stop.load(Ordering::SeqCst) {
+            return;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// The portion of a Hello packet that gets signed: `magic || type || port ||
+/// id || name`. Factored out so `parse_hello_packet` can reconstruct the
+/// exact bytes `build_hello_packet` signed, from the same field layout,
+/// without needing to special-case the trailing signature.
+fn signed_hello_prefix(device_id: &str, device_name: &str, port: u16) -> Vec<u8> {
     let id_bytes = device_id.as_bytes();
     let name_bytes = device_name.as_bytes();
     let id_len = id_bytes.len().min(255) as u8;
@@ -118,6 +228,18 @@ fn build_hello_packet(device_id: &str, device_name: &str, port: u16) -> Vec<u8>
     buf
 }
 
+/// Build a Hello packet, signed with `identity`'s private key.
+///
+/// There's no separate "public key" field: `identity.device_id` already *is*
+/// the hex-encoded public key (see [`DeviceIdentity`]), so carrying it once
+/// is enough for `parse_hello_packet` to verify the signature below.
+fn build_hello_packet(identity: &DeviceIdentity, port: u16) -> Vec<u8> {
+    let mut buf = signed_hello_prefix(&identity.device_id, &identity.device_name, port);
+    let sig = identity.sign(&buf);
+    buf.extend_from_slice(sig.as_ref());
+    buf
+}
+
 #[cfg(not(target_os = "android"))]
 fn send_via_interface(
     interface_ip: Ipv4Addr,
@@ -131,9 +253,70 @@ fn send_via_interface(
     Ok(())
 }
 
+/// Non-loopback interface names paired with their OS interface index, which
+/// `IPV6_MULTICAST_IF`/`join_multicast_v6` key on instead of an address.
+/// `list_afinet_netifas` only gives us names and addresses, so the index is
+/// resolved separately via `if_nametoindex` - the same raw-libc-FFI approach
+/// `io_utils` already uses where std has no portable equivalent.
+#[cfg(all(not(target_os = "android"), unix))]
+fn non_loopback_interface_indices() -> std::io::Result<Vec<(String, u32)>> {
+    let interfaces = list_afinet_netifas()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    let mut seen = std::collections::HashSet::new();
+    let mut out = Vec::new();
+    for (name, ip) in interfaces {
+        if ip.is_loopback() || !seen.insert(name.clone()) {
+            continue;
+        }
+        let index = interface_index(&name)?;
+        if index != 0 {
+            out.push((name, index));
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(all(not(target_os = "android"), unix))]
+fn interface_index(name: &str) -> std::io::Result<u32> {
+    let c_name = std::ffi::CString::new(name)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    // SAFETY: `c_name` is a valid, NUL-terminated C string for the duration
+    // of this call; `if_nametoindex` reads it and returns 0 on no match.
+    let index = unsafe { libc::if_nametoindex(c_name.as_ptr()) };
+    Ok(index)
+}
+
+/// No portable (std-only) way to resolve an interface index on non-unix
+/// targets without adding a platform-specific dependency, so IPv6 multicast
+/// simply finds no interfaces to join/send on there; IPv4 broadcast still
+/// works unaffected.
+#[cfg(all(not(target_os = "android"), not(unix)))]
+fn non_loopback_interface_indices() -> std::io::Result<Vec<(String, u32)>> {
+    Ok(Vec::new())
+}
+
+#[cfg(not(target_os = "android"))]
+fn send_via_interface_v6(
+    interface_index: u32,
+    dest: &SocketAddr,
+    payload: &[u8],
+) -> std::io::Result<()> {
+    // `IPV6_MULTICAST_IF` isn't exposed by `std::net::UdpSocket`, only by
+    // `socket2` - already a dependency of this crate (see
+    // `transport/udp.rs`), so it's reused here rather than reaching for
+    // another one.
+    use socket2::{Domain, Protocol, Socket, Type};
+    let socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.bind(&SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0).into())?;
+    socket.set_multicast_if_v6(interface_index)?;
+    let std_socket: UdpSocket = socket.into();
+    std_socket.send_to(payload, dest)?;
+    Ok(())
+}
+
 // --- UDP listener: receive Hello beacons and add peers ---
 
-/// Parsed Hello beacon from the network
+/// Parsed, signature-verified Hello beacon from the network.
 #[derive(Debug)]
 pub struct HelloPeer {
     pub device_id: String,
@@ -141,7 +324,13 @@ pub struct HelloPeer {
     pub port: u16,
 }
 
-/// Parse a received UDP packet; returns None if not a valid Hello.
+/// Parse a received UDP packet and verify its signature; returns `None` if
+/// it isn't a valid, authentically-signed Hello.
+///
+/// The signature is checked against `device_id` itself, since `device_id`
+/// *is* the claimed signer's public key - there's no separate "forged
+/// device_id with someone else's key" case to reject, only "no valid
+/// signature at all".
 pub fn parse_hello_packet(buf: &[u8]) -> Option<HelloPeer> {
     if buf.len() < 2 + 1 + 2 + 1 {
         return None;
@@ -151,21 +340,29 @@ pub fn parse_hello_packet(buf: &[u8]) -> Option<HelloPeer> {
     }
     let port = u16::from_be_bytes([buf[3], buf[4]]);
     let mut i = 5;
-    let id_len = buf.get(i)?;
+    let id_len = *buf.get(i)?;
     i += 1;
-    let id_end = i + (*id_len as usize);
+    let id_end = i + id_len as usize;
     if buf.len() < id_end + 1 {
         return None;
     }
     let device_id = String::from_utf8_lossy(&buf[i..id_end]).into_owned();
     i = id_end;
-    let name_len = buf.get(i)?;
+    let name_len = *buf.get(i)?;
     i += 1;
-    let name_end = i + (*name_len as usize);
-    if buf.len() < name_end {
+    let name_end = i + name_len as usize;
+    if buf.len() < name_end + SIGNATURE_LEN {
         return None;
     }
     let device_name = String::from_utf8_lossy(&buf[i..name_end]).into_owned();
+
+    let signed = &buf[..name_end];
+    let sig = &buf[name_end..name_end + SIGNATURE_LEN];
+    if DeviceIdentity::verify(&device_id, signed, sig).is_err() {
+        tracing::debug!("Dropping Hello claiming {}: invalid signature", device_id);
+        return None;
+    }
+
     Some(HelloPeer {
         device_id,
         device_name,
@@ -173,72 +370,327 @@ pub fn parse_hello_packet(buf: &[u8]) -> Option<HelloPeer> {
     })
 }
 
-/// Listener that receives Hello beacons and inserts peers into the map.
+/// Default peer TTL: ~3x the beacon interval (beacons repeat roughly every
+/// 2s, see [`sleep_in_ticks`]), so a peer survives a couple of missed
+/// Hellos before being evicted as stale.
+pub const DEFAULT_PEER_TTL: Duration = Duration::from_secs(6);
+
+/// How often the housekeeping sweep checks for stale peers.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Returns `true` if `device_id` should be kept around even past its TTL
+/// (e.g. because it has a live [`crate::heartbeat::HeartbeatManager`]
+/// session, which is a stronger liveness signal than a missed Hello).
+pub type KeepAlivePredicate = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// Listener that receives Hello beacons, inserts peers into the map, and
+/// evicts ones that haven't been seen within `ttl`.
 pub struct BeaconListener {
     stop: Arc<AtomicBool>,
-    handle: Option<thread::JoinHandle<()>>,
+    handles: Vec<thread::JoinHandle<()>>,
 }
 
 impl BeaconListener {
-    /// Start listening on 0.0.0.0:port and add received peers to the map.
+    /// Start listening for Hello beacons per `mode` (IPv4 broadcast on
+    /// 0.0.0.0:port, IPv6 multicast on [`MULTICAST_GROUP_V6`], or both),
+    /// add received peers to the map, and evict any peer not re-seen within
+    /// `ttl` unless `keep_alive` (if given) says otherwise.
+    ///
+    /// `trust` pins each `device_id` to the key that signed its first Hello.
+    /// Since `device_id` already *is* that key, the pin can only ever reject
+    /// a packet that `parse_hello_packet`'s signature check would also have
+    /// rejected - it's kept as defense-in-depth (see [`TrustStore`]) rather
+    /// than load-bearing on its own.
+    #[allow(clippy::too_many_arguments)]
     pub fn start(
         port: u16,
         our_device_id: Option<String>,
         peers: Arc<RwLock<HashMap<String, super::DiscoveredPeer>>>,
+        trust: Arc<TrustStore>,
+        mode: DiscoveryMode,
+        ttl: Duration,
+        keep_alive: Option<KeepAlivePredicate>,
     ) -> std::io::Result<Self> {
-        let socket = UdpSocket::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port))?;
-        socket.set_broadcast(true)?;
-        socket
-            .set_read_timeout(Some(Duration::from_millis(500)))
-            .ok();
         let stop = Arc::new(AtomicBool::new(false));
-        let stop_clone = stop.clone();
-        let handle = thread::spawn(move || {
-            let mut buf = [0u8; 512];
-            while !stop_clone.load(Ordering::SeqCst) {
-                match socket.recv_from(&mut buf) {
-                    Ok((len, from)) => {
-                        let packet = &buf[..len];
-                        if let Some(hello) = parse_hello_packet(packet) {
-                            if let Some(ref our) = our_device_id {
-                                if hello.device_id == *our {
-                                    continue;
-                                }
-                            }
-                            let peer = super::DiscoveredPeer {
-                                device_id: hello.device_id.clone(),
-                                device_name: hello.device_name,
-                                addresses: vec![from.ip()],
-                                port: hello.port,
-                            };
-                            tracing::info!(
-                                "Discovered peer via UDP beacon: {} ({}) from {}",
-                                peer.device_name,
-                                peer.device_id,
-                                from
-                            );
-                            let mut guard = peers.write().unwrap();
-                            guard.insert(hello.device_id, peer);
-                        }
-                    }
-                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
-                    Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {}
-                    Err(e) => {
-                        tracing::debug!("Beacon listener recv: {}", e);
-                    }
+        let mut handles = Vec::new();
+        let last_seen: Arc<RwLock<HashMap<String, std::time::Instant>>> =
+            Arc::new(RwLock::new(HashMap::new()));
+
+        if mode.wants_broadcast() {
+            let socket =
+                UdpSocket::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port))?;
+            socket.set_broadcast(true)?;
+            socket
+                .set_read_timeout(Some(Duration::from_millis(500)))
+                .ok();
+            handles.push(spawn_hello_receiver(
+                socket,
+                our_device_id.clone(),
+                peers.clone(),
+                trust.clone(),
+                last_seen.clone(),
+                stop.clone(),
+            ));
+        }
+
+        if mode.wants_multicast() {
+            match bind_multicast_v6_socket(port) {
+                Ok(socket) => handles.push(spawn_hello_receiver(
+                    socket,
+                    our_device_id,
+                    peers.clone(),
+                    trust,
+                    last_seen.clone(),
+                    stop.clone(),
+                )),
+                Err(e) => {
+                    tracing::warn!("Failed to join IPv6 multicast group for beacon: {}", e);
                 }
             }
-        });
-        Ok(Self {
-            stop,
-            handle: Some(handle),
-        })
+        }
+
+        handles.push(spawn_housekeeping_sweep(
+            peers,
+            last_seen,
+            ttl,
+            keep_alive,
+            stop.clone(),
+        ));
+
+        Ok(Self { stop, handles })
     }
 
     pub fn stop(mut self) {
         self.stop.store(true, Ordering::SeqCst);
-        if let Some(h) = self.handle.take() {
+        for h in self.handles.drain(..) {
             let _ = h.join();
         }
     }
 }
+
+/// Device ids in `last_seen` not refreshed within `ttl` of `now`, excluding
+/// any for which `keep_alive` returns `true`. Factored out of
+/// [`spawn_housekeeping_sweep`] so the eviction rule can be unit-tested
+/// without waiting on a real thread/sleep.
+fn stale_peer_ids(
+    last_seen: &HashMap<String, std::time::Instant>,
+    now: std::time::Instant,
+    ttl: Duration,
+    keep_alive: Option<&KeepAlivePredicate>,
+) -> Vec<String> {
+    last_seen
+        .iter()
+        .filter(|(_, seen)| now.duration_since(**seen) > ttl)
+        .map(|(device_id, _)| device_id.clone())
+        .filter(|device_id| !keep_alive.is_some_and(|alive| alive(device_id)))
+        .collect()
+}
+
+/// Periodically evict peers not re-seen within `ttl`, skipping any for
+/// which `keep_alive` returns `true` (e.g. a live heartbeat session).
+fn spawn_housekeeping_sweep(
+    peers: Arc<RwLock<HashMap<String, super::DiscoveredPeer>>>,
+    last_seen: Arc<RwLock<HashMap<String, std::time::Instant>>>,
+    ttl: Duration,
+    keep_alive: Option<KeepAlivePredicate>,
+    stop: Arc<AtomicBool>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        while !stop.load(Ordering::SeqCst) {
+            thread::sleep(SWEEP_INTERVAL);
+            if stop.load(Ordering::SeqCst) {
+                return;
+            }
+            let now = std::time::Instant::now();
+            let stale = stale_peer_ids(&last_seen.read().unwrap(), now, ttl, keep_alive.as_ref());
+            if stale.is_empty() {
+                continue;
+            }
+            let mut peers_guard = peers.write().unwrap();
+            let mut last_seen_guard = last_seen.write().unwrap();
+            for device_id in stale {
+                if let Some(peer) = peers_guard.remove(&device_id) {
+                    tracing::info!(
+                        "Evicting stale peer {} ({}): not seen within {:?}",
+                        peer.device_name,
+                        device_id,
+                        ttl
+                    );
+                }
+                last_seen_guard.remove(&device_id);
+            }
+        }
+    })
+}
+
+/// Bind a socket to the Hello port and join [`MULTICAST_GROUP_V6`] on every
+/// non-loopback interface, so a Hello multicast from any adapter is heard
+/// regardless of which interface the OS would otherwise route replies through.
+#[cfg(not(target_os = "android"))]
+fn bind_multicast_v6_socket(port: u16) -> std::io::Result<UdpSocket> {
+    let socket = UdpSocket::bind(SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), port))?;
+    socket
+        .set_read_timeout(Some(Duration::from_millis(500)))
+        .ok();
+    let mut joined_any = false;
+    for (name, index) in non_loopback_interface_indices()? {
+        match socket.join_multicast_v6(&MULTICAST_GROUP_V6, index) {
+            Ok(()) => joined_any = true,
+            Err(e) => tracing::debug!("Failed to join multicast group via {}: {}", name, e),
+        }
+    }
+    if !joined_any {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "no interface could join the IPv6 multicast group",
+        ));
+    }
+    Ok(socket)
+}
+
+#[cfg(target_os = "android")]
+fn bind_multicast_v6_socket(_port: u16) -> std::io::Result<UdpSocket> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "IPv6 multicast discovery is not used on Android",
+    ))
+}
+
+/// Spawn the shared "receive signed Hello, verify, pin, insert into peer
+/// map" loop against an already-bound/joined socket, whether it's the plain
+/// broadcast socket or the IPv6 multicast socket.
+fn spawn_hello_receiver(
+    socket: UdpSocket,
+    our_device_id: Option<String>,
+    peers: Arc<RwLock<HashMap<String, super::DiscoveredPeer>>>,
+    trust: Arc<TrustStore>,
+    last_seen: Arc<RwLock<HashMap<String, std::time::Instant>>>,
+    stop: Arc<AtomicBool>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut buf = [0u8; 512];
+        while !stop.load(Ordering::SeqCst) {
+            match socket.recv_from(&mut buf) {
+                Ok((len, from)) => {
+                    let packet = &buf[..len];
+                    if let Some(hello) = parse_hello_packet(packet) {
+                        if let Some(ref our) = our_device_id {
+                            if hello.device_id == *our {
+                                continue;
+                            }
+                        }
+                        if !trust.pin(&hello.device_id, &hello.device_id) {
+                            continue;
+                        }
+                        let scope_id = match from {
+                            SocketAddr::V6(v6) if v6.scope_id() != 0 => Some(v6.scope_id()),
+                            _ => None,
+                        };
+                        let peer = super::DiscoveredPeer {
+                            device_id: hello.device_id.clone(),
+                            device_name: hello.device_name,
+                            addresses: vec![from.ip()],
+                            port: hello.port,
+                            scope_id,
+                            last_seen: std::time::Instant::now(),
+                            manual: false,
+                            identity: None,
+                        };
+                        tracing::info!(
+                            "Discovered peer via UDP beacon: {} ({}) from {}",
+                            peer.device_name,
+                            peer.device_id,
+                            from
+                        );
+                        last_seen
+                            .write()
+                            .unwrap()
+                            .insert(hello.device_id.clone(), std::time::Instant::now());
+                        let mut guard = peers.write().unwrap();
+                        guard.insert(hello.device_id, peer);
+                    }
+                }
+                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+                Err(e) => {
+                    tracing::debug!("Beacon listener recv: {}", e);
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hello_roundtrip_verifies_and_preserves_fields() {
+        let identity = DeviceIdentity::generate("alice's phone");
+        let packet = build_hello_packet(&identity, 4242);
+
+        let hello = parse_hello_packet(&packet).expect("valid signed Hello should parse");
+        assert_eq!(hello.device_id, identity.device_id);
+        assert_eq!(hello.device_name, "alice's phone");
+        assert_eq!(hello.port, 4242);
+    }
+
+    #[test]
+    fn tampered_hello_is_rejected() {
+        let identity = DeviceIdentity::generate("bob's laptop");
+        let mut packet = build_hello_packet(&identity, 4242);
+
+        // Flip a byte in the claimed device name, after signing.
+        let tamper_at = packet.len() - SIGNATURE_LEN - 1;
+        packet[tamper_at] ^= 0xff;
+
+        assert!(parse_hello_packet(&packet).is_none());
+    }
+
+    #[test]
+    fn hello_signed_by_a_different_key_is_rejected() {
+        let identity = DeviceIdentity::generate("carol's tablet");
+        let impostor = DeviceIdentity::generate("mallory");
+        let mut packet = signed_hello_prefix(&identity.device_id, &identity.device_name, 4242);
+        let forged_sig = impostor.sign(&packet);
+        packet.extend_from_slice(forged_sig.as_ref());
+
+        assert!(parse_hello_packet(&packet).is_none());
+    }
+
+    #[test]
+    fn discovery_mode_gates_transports_correctly() {
+        assert!(DiscoveryMode::BroadcastV4.wants_broadcast());
+        assert!(!DiscoveryMode::BroadcastV4.wants_multicast());
+
+        assert!(!DiscoveryMode::MulticastV6.wants_broadcast());
+        assert!(DiscoveryMode::MulticastV6.wants_multicast());
+
+        assert!(DiscoveryMode::Both.wants_broadcast());
+        assert!(DiscoveryMode::Both.wants_multicast());
+    }
+
+    #[test]
+    fn stale_peer_ids_evicts_only_expired_entries() {
+        let now = std::time::Instant::now();
+        let ttl = Duration::from_secs(10);
+        let mut last_seen = HashMap::new();
+        last_seen.insert("fresh".to_string(), now - Duration::from_secs(1));
+        last_seen.insert("stale".to_string(), now - Duration::from_secs(20));
+
+        let stale = stale_peer_ids(&last_seen, now, ttl, None);
+        assert_eq!(stale, vec!["stale".to_string()]);
+    }
+
+    #[test]
+    fn stale_peer_ids_spares_entries_kept_alive() {
+        let now = std::time::Instant::now();
+        let ttl = Duration::from_secs(10);
+        let mut last_seen = HashMap::new();
+        last_seen.insert("stale-but-alive".to_string(), now - Duration::from_secs(20));
+
+        let keep_alive: KeepAlivePredicate = Arc::new(|id: &str| id == "stale-but-alive");
+        let stale = stale_peer_ids(&last_seen, now, ttl, Some(&keep_alive));
+        assert!(stale.is_empty());
+    }
+}
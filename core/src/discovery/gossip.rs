@@ -0,0 +1,374 @@
+//! Gossip-based peer discovery: a small JSON-framed message protocol
+//! carried over a plain TCP connection, conceptually sitting alongside
+//! `TransportServer`'s Ping/Pong keep-alive but with its own wire format -
+//! `TransportServer`'s `Packet` is a fixed binary header built for the
+//! file-transfer keep-alive, not for the variable-shaped messages below.
+//!
+//! On connect, a node sends [`GossipMessage::Hand`]; the peer checks the
+//! protocol version and replies [`GossipMessage::Shake`]. From there, a
+//! [`GossipMessage::Ping`]/[`GossipMessage::Pong`] round checks liveness
+//! (replacing the one-shot `voidwarp_transport_ping` with something that
+//! can run periodically), and [`GossipMessage::GetPeers`] pulls the peer's
+//! known-peer list, merging it into a shared, size-bounded [`PeerTable`] -
+//! so a device can learn about peers beyond a manually supplied IP without
+//! a central directory server.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Gossip protocol version. `Shake.ok` is false when a peer reports a
+/// different version, so both sides stop talking rather than misparse
+/// each other's frames.
+pub const GOSSIP_VERSION: u8 = 1;
+
+/// How long a single dial/handshake/exchange round waits before giving up.
+const GOSSIP_ROUND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often a gossip round re-runs against each known peer.
+const GOSSIP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Upper bound on frame size, so a malformed or hostile peer can't make us
+/// allocate an unbounded buffer from a forged length prefix.
+const MAX_FRAME_LEN: u32 = 64 * 1024;
+
+/// Upper bound on the peer table, so repeated `Peers` merges from a
+/// misbehaving peer can't grow it without limit.
+const MAX_KNOWN_PEERS: usize = 512;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GossipMessage {
+    Hand { device_name: String, version: u8 },
+    Shake { ok: bool, device_id: String },
+    Ping,
+    Pong,
+    GetPeers,
+    Peers { addrs: Vec<SocketAddr> },
+}
+
+fn write_frame(stream: &mut TcpStream, message: &GossipMessage) -> io::Result<()> {
+    let json = serde_json::to_vec(message)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    stream.write_all(&(json.len() as u32).to_be_bytes())?;
+    stream.write_all(&json)?;
+    stream.flush()
+}
+
+fn read_frame(stream: &mut TcpStream) -> io::Result<GossipMessage> {
+    let mut len_buf = [0u8; 4];
+    stream.read_exact(&mut len_buf)?;
+    let len = u32::from_be_bytes(len_buf);
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "gossip frame too large",
+        ));
+    }
+    let mut buf = vec![0u8; len as usize];
+    stream.read_exact(&mut buf)?;
+    serde_json::from_slice(&buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// A device as known to the gossip table: the address it's reachable at,
+/// and the id it presented during its `Hand` (or, for peers only learned
+/// second-hand via `GetPeers` and not yet gossiped with directly, a
+/// placeholder key derived from the address - replaced once we do).
+#[derive(Debug, Clone)]
+pub struct GossipPeer {
+    pub device_id: String,
+    pub addr: SocketAddr,
+}
+
+/// Shared, bounded table of known peers, merged from every handshake and
+/// `GetPeers` exchange. Keyed by device_id so re-learning the same peer
+/// from multiple directions doesn't grow the table.
+#[derive(Clone, Default)]
+pub struct PeerTable(Arc<Mutex<HashMap<String, GossipPeer>>>);
+
+impl PeerTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, peer: GossipPeer) {
+        let mut table = self.0.lock().unwrap();
+        if table.len() >= MAX_KNOWN_PEERS && !table.contains_key(&peer.device_id) {
+            tracing::warn!(
+                "Gossip peer table full ({} entries), dropping {}",
+                MAX_KNOWN_PEERS,
+                peer.device_id
+            );
+            return;
+        }
+        table.insert(peer.device_id.clone(), peer);
+    }
+
+    /// Current table contents, for `voidwarp_discovery_known_peers`.
+    pub fn snapshot(&self) -> Vec<GossipPeer> {
+        self.0.lock().unwrap().values().cloned().collect()
+    }
+
+    fn addrs(&self) -> Vec<SocketAddr> {
+        self.0.lock().unwrap().values().map(|p| p.addr).collect()
+    }
+}
+
+/// One connect-handshake-exchange round against `addr`: send `Hand`, wait
+/// for `Shake`, then one `Ping`/`Pong` liveness check and one
+/// `GetPeers`/`Peers` exchange, merging everything learned into `table`.
+/// Returns `Ok(false)` (not an error) when the peer rejected our
+/// handshake, typically a protocol version mismatch.
+pub fn gossip_once(
+    addr: SocketAddr,
+    our_device_id: &str,
+    our_device_name: &str,
+    table: &PeerTable,
+) -> io::Result<bool> {
+    let mut stream = TcpStream::connect_timeout(&addr, GOSSIP_ROUND_TIMEOUT)?;
+    stream.set_read_timeout(Some(GOSSIP_ROUND_TIMEOUT))?;
+    stream.set_write_timeout(Some(GOSSIP_ROUND_TIMEOUT))?;
+
+    write_frame(
+        &mut stream,
+        &GossipMessage::Hand {
+            device_name: our_device_name.to_string(),
+            version: GOSSIP_VERSION,
+        },
+    )?;
+
+    let peer_id = match read_frame(&mut stream)? {
+        GossipMessage::Shake { ok: true, device_id } => device_id,
+        GossipMessage::Shake { ok: false, device_id } => {
+            tracing::warn!("Gossip handshake rejected by {} ({})", addr, device_id);
+            return Ok(false);
+        }
+        other => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected Shake, got {:?}", other),
+            ))
+        }
+    };
+    if peer_id == our_device_id {
+        return Ok(true); // talked to ourselves via a stale/looped-back address
+    }
+    table.insert(GossipPeer {
+        device_id: peer_id,
+        addr,
+    });
+
+    write_frame(&mut stream, &GossipMessage::Ping)?;
+    match read_frame(&mut stream)? {
+        GossipMessage::Pong => {}
+        other => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected Pong, got {:?}", other),
+            ))
+        }
+    }
+
+    write_frame(&mut stream, &GossipMessage::GetPeers)?;
+    match read_frame(&mut stream)? {
+        GossipMessage::Peers { addrs } => {
+            for learned in addrs {
+                if learned != addr {
+                    table.insert(GossipPeer {
+                        device_id: format!("addr:{}", learned),
+                        addr: learned,
+                    });
+                }
+            }
+        }
+        other => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected Peers, got {:?}", other),
+            ))
+        }
+    }
+
+    Ok(true)
+}
+
+fn handle_inbound(
+    mut stream: TcpStream,
+    our_device_id: &str,
+    our_device_name: &str,
+    table: &PeerTable,
+) -> io::Result<()> {
+    stream.set_read_timeout(Some(GOSSIP_ROUND_TIMEOUT))?;
+    stream.set_write_timeout(Some(GOSSIP_ROUND_TIMEOUT))?;
+    let peer_addr = stream.peer_addr()?;
+
+    loop {
+        match read_frame(&mut stream)? {
+            GossipMessage::Hand { device_name, version } => {
+                let ok = version == GOSSIP_VERSION;
+                write_frame(
+                    &mut stream,
+                    &GossipMessage::Shake {
+                        ok,
+                        device_id: our_device_id.to_string(),
+                    },
+                )?;
+                if !ok {
+                    tracing::warn!(
+                        "Gossip peer {} ({}) speaks version {}, we speak {}",
+                        device_name,
+                        peer_addr,
+                        version,
+                        GOSSIP_VERSION
+                    );
+                    return Ok(());
+                }
+                table.insert(GossipPeer {
+                    device_id: format!("addr:{}", peer_addr),
+                    addr: peer_addr,
+                });
+            }
+            GossipMessage::Ping => write_frame(&mut stream, &GossipMessage::Pong)?,
+            GossipMessage::GetPeers => {
+                write_frame(
+                    &mut stream,
+                    &GossipMessage::Peers {
+                        addrs: table.addrs(),
+                    },
+                )?;
+            }
+            other => {
+                tracing::debug!("Unexpected gossip message from {}: {:?}", peer_addr, other);
+            }
+        }
+    }
+}
+
+/// Gossip listener: accepts inbound gossip sessions and answers
+/// `Hand`/`Ping`/`GetPeers` from whoever connects, mirroring
+/// `TransportServer`'s accept-loop-per-connection shape.
+pub struct GossipServer {
+    table: PeerTable,
+    _accept_thread: thread::JoinHandle<()>,
+}
+
+impl GossipServer {
+    pub fn bind(addr: SocketAddr, device_id: String, device_name: String) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let table = PeerTable::new();
+        let accept_table = table.clone();
+
+        let _accept_thread = thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let table = accept_table.clone();
+                        let device_id = device_id.clone();
+                        let device_name = device_name.clone();
+                        thread::spawn(move || {
+                            if let Err(e) = handle_inbound(stream, &device_id, &device_name, &table)
+                            {
+                                tracing::debug!("Gossip inbound session ended: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => tracing::debug!("Gossip accept error: {}", e),
+                }
+            }
+        });
+
+        Ok(GossipServer {
+            table,
+            _accept_thread,
+        })
+    }
+
+    pub fn table(&self) -> PeerTable {
+        self.table.clone()
+    }
+}
+
+/// Periodically re-runs `gossip_once` against every currently-known peer
+/// (plus any `seed_addrs` given up front), until `stop` is set. This is
+/// what turns one-off `gossip_once` calls into an actual gossip network:
+/// each round's `GetPeers` can surface new addresses for the next round to
+/// dial.
+pub fn start_gossip_loop(
+    seed_addrs: Vec<SocketAddr>,
+    our_device_id: String,
+    our_device_name: String,
+    table: PeerTable,
+    stop: Arc<AtomicBool>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        for addr in &seed_addrs {
+            table.insert(GossipPeer {
+                device_id: format!("addr:{}", addr),
+                addr: *addr,
+            });
+        }
+
+        while !stop.load(Ordering::SeqCst) {
+            for addr in table.addrs() {
+                if stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Err(e) = gossip_once(addr, &our_device_id, &our_device_name, &table) {
+                    tracing::debug!("Gossip round with {} failed: {}", addr, e);
+                }
+            }
+
+            for _ in 0..(GOSSIP_INTERVAL.as_millis() / 100) {
+                if stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_roundtrips_through_serde_json() {
+        let message = GossipMessage::Peers {
+            addrs: vec!["127.0.0.1:9000".parse().unwrap()],
+        };
+        let json = serde_json::to_vec(&message).unwrap();
+        let decoded: GossipMessage = serde_json::from_slice(&json).unwrap();
+        match decoded {
+            GossipMessage::Peers { addrs } => assert_eq!(addrs.len(), 1),
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn peer_table_dedupes_by_device_id_and_bounds_size() {
+        let table = PeerTable::new();
+        let addr: SocketAddr = "127.0.0.1:1".parse().unwrap();
+        table.insert(GossipPeer {
+            device_id: "a".to_string(),
+            addr,
+        });
+        table.insert(GossipPeer {
+            device_id: "a".to_string(),
+            addr,
+        });
+        assert_eq!(table.snapshot().len(), 1);
+
+        for i in 0..(MAX_KNOWN_PEERS + 10) {
+            table.insert(GossipPeer {
+                device_id: format!("peer-{}", i),
+                addr,
+            });
+        }
+        assert!(table.snapshot().len() <= MAX_KNOWN_PEERS);
+    }
+}
@@ -1,13 +1,33 @@
 //! Discovery Module
 //!
-//! mDNS-based service discovery for finding VoidWarp peers on LAN.
+//! mDNS-based service discovery for finding VoidWarp peers on LAN, plus a
+//! [`beacon`] subsystem for pairing over out-of-band channels (paste/QR)
+//! when mDNS is blocked entirely, a [`broadcast`] subsystem for
+//! Windows/multi-interface UDP "Hello" discovery when mDNS routes to the
+//! wrong adapter, a [`rendezvous`] subsystem for bootstrapping across
+//! NATs/WANs where no local-network discovery reaches at all, a
+//! [`beacon_serializer`] subsystem for channels whose own framing or size
+//! budget doesn't fit `beacon` or `rendezvous`'s fixed markers, and a
+//! [`relay_fallback`] subsystem for the same NAT/WAN case as `rendezvous`
+//! but automatic: it heartbeats a directory server instead of requiring a
+//! manual publish/fetch step.
+
+pub mod beacon;
+pub mod beacon_serializer;
+pub mod broadcast;
+pub mod gossip;
+pub mod relay_fallback;
+pub mod rendezvous;
 
 use mdns_sd::{Receiver as MdnsReceiver, ServiceDaemon, ServiceEvent, ServiceInfo};
 use std::collections::HashMap;
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
+use self::beacon::{BeaconError, BeaconPayload};
+
 /// Service type for VoidWarp
 pub const SERVICE_TYPE: &str = "_voidwarp._udp.local.";
 
@@ -18,6 +38,30 @@ pub struct DiscoveredPeer {
     pub device_name: String,
     pub addresses: Vec<IpAddr>,
     pub port: u16,
+    /// IPv6 zone/scope id (the `5` in `fe80::1%5`), needed to actually
+    /// dial a link-local address. `None` for peers with no link-local
+    /// address, or discovered via mDNS before a scope has been resolved.
+    pub scope_id: Option<u32>,
+    /// When this entry was last (re)confirmed - bumped on every
+    /// `ServiceResolved`/roster refresh. Used by the expiry sweep in
+    /// [`DiscoveryManager::start_expiry_sweep`] to drop peers we haven't
+    /// heard from in a while instead of relying solely on `ServiceRemoved`,
+    /// which mDNS doesn't always deliver promptly (or at all, e.g. across
+    /// a sleep/wake cycle).
+    pub last_seen: Instant,
+    /// True for peers added via [`DiscoveryManager::add_manual_peer`] or a
+    /// decoded [`beacon`]/[`rendezvous`] token - these have no ongoing
+    /// liveness signal to refresh `last_seen`, so the expiry sweep leaves
+    /// them alone rather than flushing them out after one TTL window.
+    pub manual: bool,
+    /// The peer's long-term X25519 static public key, as advertised in its
+    /// `pubkey` TXT property - `None` if the peer didn't advertise one (an
+    /// older build) or we haven't resolved far enough to see TXT records
+    /// yet. Compared against the key actually used in
+    /// [`crate::security::noise::run_handshake`] so a mismatch (a
+    /// different device answering for an mDNS-advertised `device_id`) is
+    /// caught before any data is sent - see [`DiscoveryEvent::PeerIdentityMismatch`].
+    pub identity: Option<[u8; 32]>,
 }
 
 /// Events from the discovery system
@@ -25,8 +69,23 @@ pub struct DiscoveredPeer {
 pub enum DiscoveryEvent {
     PeerFound(DiscoveredPeer),
     PeerLost(String), // device_id
+    /// The X25519 static key seen during [`crate::security::noise::run_handshake`]
+    /// with `device_id` didn't match the `pubkey` it advertised over
+    /// discovery - raised by the caller performing the handshake via
+    /// [`DiscoveryManager::check_identity`], not by this module itself
+    /// (discovery has no visibility into an in-progress handshake).
+    PeerIdentityMismatch(String), // device_id
 }
 
+/// Default staleness window for the expiry sweep - a peer that hasn't
+/// been reconfirmed in this long is dropped even without a `ServiceRemoved`
+/// event. Comfortably longer than mDNS's typical re-announce interval so a
+/// single missed resolution doesn't cause a spurious `PeerLost`.
+pub const DEFAULT_PEER_TTL: Duration = Duration::from_secs(30);
+
+/// How often the expiry sweep re-scans the peer map.
+pub const DEFAULT_SWEEP_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Discovery Manager for mDNS operations
 pub struct DiscoveryManager {
     daemon: Option<ServiceDaemon>,
@@ -66,12 +125,52 @@ impl DiscoveryManager {
         self.fallback_mode
     }
 
-    /// Register our service for others to discover
+    /// Whether mDNS is currently active, regardless of how this manager was
+    /// constructed - distinct from [`Self::is_fallback`], which only
+    /// reflects the *original* construction path and never changes.
+    pub fn discovery_enabled(&self) -> bool {
+        self.daemon.is_some()
+    }
+
+    /// Lazily bring mDNS up if it isn't already running, without
+    /// recreating the manager (and so without losing manually-added
+    /// peers or our own service registration). No-op if already enabled.
+    pub fn enable_mdns(&mut self) -> Result<(), String> {
+        if self.daemon.is_some() {
+            return Ok(());
+        }
+        let daemon =
+            ServiceDaemon::new().map_err(|e| format!("Failed to create mDNS daemon: {}", e))?;
+        self.daemon = Some(daemon);
+        tracing::info!("mDNS discovery enabled");
+        Ok(())
+    }
+
+    /// Tear mDNS down (unregistering our own service first, if any) while
+    /// keeping the manager itself - and its manually-added peers - alive.
+    /// No-op if already disabled.
+    pub fn disable_mdns(&mut self) {
+        if self.daemon.is_none() {
+            return;
+        }
+        self.unregister();
+        if let Some(daemon) = self.daemon.take() {
+            let _ = daemon.shutdown();
+        }
+        tracing::info!("mDNS discovery disabled");
+    }
+
+    /// Register our service for others to discover. `x25519_pubkey_hex` is
+    /// advertised in a `pubkey` TXT property so peers can cross-check it
+    /// against the key we actually present during
+    /// [`crate::security::noise::run_handshake`] (see
+    /// [`DiscoveredPeer::identity`]).
     pub fn register_service(
         &mut self,
         device_id: &str,
         device_name: &str,
         port: u16,
+        x25519_pubkey_hex: &str,
     ) -> Result<(), String> {
         // Include platform identifier for debugging cross-platform issues
         #[cfg(target_os = "windows")]
@@ -89,6 +188,7 @@ impl DiscoveryManager {
             ("id", device_id),
             ("name", device_name),
             ("platform", platform),
+            ("pubkey", x25519_pubkey_hex),
         ];
 
         // Generate a unique instance name using device_id
@@ -179,20 +279,17 @@ impl DiscoveryManager {
                             .unwrap_or("unknown")
                             .to_string();
 
+                        let identity = info
+                            .get_property_val_str("pubkey")
+                            .and_then(|hex| crate::security::crypto::hex_decode(hex))
+                            .and_then(|bytes| bytes.try_into().ok());
+
                         // Collect IPs from mDNS response.
                         // Android environments often return v6-only entries depending on network,
                         // so we keep both v4 and v6.
                         // For resolved services mdns-sd returns `ScopedIp` (may include v6 + scope).
                         // Convert to plain `IpAddr` for FFI/UI.
-                        let addresses: Vec<IpAddr> = info
-                            .get_addresses()
-                            .iter()
-                            .filter_map(|ip| match ip {
-                                mdns_sd::ScopedIp::V4(v4) => Some(IpAddr::V4(*v4.addr())),
-                                mdns_sd::ScopedIp::V6(v6) => Some(IpAddr::V6(*v6.addr())),
-                                _ => None,
-                            })
-                            .collect();
+                        let (addresses, scope_id) = collect_addresses(&info);
 
                         tracing::info!(
                             "Peer discovered: name='{}', id='{}', platform='{}', addresses={:?}, port={}",
@@ -204,6 +301,10 @@ impl DiscoveryManager {
                             device_name,
                             addresses,
                             port: info.get_port(),
+                            scope_id,
+                            last_seen: Instant::now(),
+                            manual: false,
+                            identity,
                         };
 
                         {
@@ -239,12 +340,116 @@ impl DiscoveryManager {
         Ok(())
     }
 
+    /// One-shot discovery: browse for up to `timeout`, collecting every
+    /// peer resolved in that window, then stop and return the roster -
+    /// unlike [`Self::run_discovery`]/[`Self::start_background_browsing`],
+    /// this doesn't leave a background thread running afterwards. Meant
+    /// for CLI/FFI callers that want a single up-to-date peer list rather
+    /// than an ongoing event stream.
+    pub async fn discover_once(&self, timeout: Duration) -> Result<Vec<DiscoveredPeer>, String> {
+        let receiver = self.browse()?;
+        let our_id = self.our_service.clone();
+
+        let peers = tokio::task::spawn_blocking(move || {
+            let deadline = Instant::now() + timeout;
+            let mut found: HashMap<String, DiscoveredPeer> = HashMap::new();
+
+            loop {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    break;
+                }
+
+                let event = match receiver.recv_timeout(remaining) {
+                    Ok(event) => event,
+                    Err(_) => break, // timed out or channel closed
+                };
+
+                if let ServiceEvent::ServiceResolved(info) = event {
+                    let device_id = info
+                        .get_property_val_str("id")
+                        .unwrap_or_default()
+                        .to_string();
+                    if let Some(ref our) = our_id {
+                        if &device_id == our {
+                            continue;
+                        }
+                    }
+
+                    let device_name = info
+                        .get_property_val_str("name")
+                        .unwrap_or_else(|| info.get_fullname())
+                        .to_string();
+
+                    let (addresses, scope_id) = collect_addresses(&info);
+
+                    let identity = info
+                        .get_property_val_str("pubkey")
+                        .and_then(|hex| crate::security::crypto::hex_decode(hex))
+                        .and_then(|bytes| bytes.try_into().ok());
+
+                    found.insert(
+                        device_id.clone(),
+                        DiscoveredPeer {
+                            device_id,
+                            device_name,
+                            addresses,
+                            port: info.get_port(),
+                            scope_id,
+                            last_seen: Instant::now(),
+                            manual: false,
+                            identity,
+                        },
+                    );
+                }
+            }
+
+            found.into_values().collect::<Vec<_>>()
+        })
+        .await
+        .map_err(|e| format!("discovery task panicked: {}", e))?;
+
+        Ok(peers)
+    }
+
     /// Get currently known peers
     pub fn get_peers(&self) -> Vec<DiscoveredPeer> {
         let peers_guard = self.peers.read().unwrap();
         peers_guard.values().cloned().collect()
     }
 
+    /// Verify that the X25519 static key seen in
+    /// [`crate::security::noise::run_handshake`] with `device_id` matches
+    /// what discovery previously advertised for it. If we never captured
+    /// an advertised key (no TXT `pubkey`, or this peer was never seen via
+    /// discovery at all), this trusts on first use and returns `Ok`. On a
+    /// mismatch, pushes [`DiscoveryEvent::PeerIdentityMismatch`] to
+    /// `event_tx` and returns `Err` so the caller can abort the handshake.
+    pub fn check_identity(
+        &self,
+        device_id: &str,
+        actual_x25519_pubkey: &[u8; 32],
+        event_tx: &mpsc::Sender<DiscoveryEvent>,
+    ) -> Result<(), String> {
+        let expected = {
+            let peers_guard = self.peers.read().unwrap();
+            peers_guard.get(device_id).and_then(|peer| peer.identity)
+        };
+
+        match expected {
+            Some(expected) if &expected != actual_x25519_pubkey => {
+                let _ = event_tx.try_send(DiscoveryEvent::PeerIdentityMismatch(
+                    device_id.to_string(),
+                ));
+                Err(format!(
+                    "pubkey advertised by {} over discovery doesn't match the one used in the handshake",
+                    device_id
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Start background browsing thread for FFI usage (no channel, just updates map)
     pub fn start_background_browsing(&self) -> Result<(), String> {
         let receiver = self.browse()?;
@@ -278,16 +483,13 @@ impl DiscoveryManager {
                             .unwrap_or("unknown")
                             .to_string();
 
+                        let identity = info
+                            .get_property_val_str("pubkey")
+                            .and_then(|hex| crate::security::crypto::hex_decode(hex))
+                            .and_then(|bytes| bytes.try_into().ok());
+
                         // Collect both v4 and v6 (see comment in async loop above).
-                        let addresses: Vec<IpAddr> = info
-                            .get_addresses()
-                            .iter()
-                            .filter_map(|ip| match ip {
-                                mdns_sd::ScopedIp::V4(v4) => Some(IpAddr::V4(*v4.addr())),
-                                mdns_sd::ScopedIp::V6(v6) => Some(IpAddr::V6(*v6.addr())),
-                                _ => None,
-                            })
-                            .collect();
+                        let (addresses, scope_id) = collect_addresses(&info);
 
                         tracing::info!(
                             "FFI Peer discovered: name='{}', id='{}', platform='{}', addresses={:?}, port={}",
@@ -299,6 +501,10 @@ impl DiscoveryManager {
                             device_name,
                             addresses,
                             port: info.get_port(),
+                            scope_id,
+                            last_seen: Instant::now(),
+                            manual: false,
+                            identity,
                         };
 
                         {
@@ -323,25 +529,154 @@ impl DiscoveryManager {
         Ok(())
     }
 
-    /// Manually add a peer (e.g. for direct USB connection)
+    /// Manually add a peer (e.g. for direct USB connection). `scope_id` is
+    /// the IPv6 zone index for `ip` when it's link-local (see
+    /// [`parse_zoned_ip`]); `None` for global/ULA addresses and all IPv4.
     pub fn add_manual_peer(
         &self,
         device_id: String,
         device_name: String,
         ip: IpAddr,
         port: u16,
+        scope_id: Option<u32>,
     ) {
         let peer = DiscoveredPeer {
             device_id: device_id.clone(),
             device_name,
             addresses: vec![ip],
             port,
+            scope_id,
+            last_seen: Instant::now(),
+            manual: true,
+            identity: None,
         };
         tracing::info!("Manually adding peer: {:?}", peer);
         let mut peers_guard = self.peers.write().unwrap();
         peers_guard.insert(device_id, peer);
     }
 
+    /// Build a shareable beacon token advertising `device_id` at
+    /// `addresses`, encrypted with `pairing_code`. See [`beacon`] for the
+    /// token format.
+    pub fn generate_beacon(
+        &self,
+        device_id: &str,
+        addresses: &[SocketAddr],
+        pairing_code: &str,
+    ) -> String {
+        beacon::generate_beacon(device_id, addresses, pairing_code)
+    }
+
+    /// Decode a beacon produced by [`Self::generate_beacon`] and add every
+    /// advertised address as a manual peer. Returns the advertising
+    /// device's id on success.
+    pub fn ingest_beacon(
+        &self,
+        token: &str,
+        pairing_code: &str,
+    ) -> Result<String, BeaconError> {
+        let BeaconPayload {
+            device_id,
+            addresses,
+        } = beacon::parse_beacon(token, pairing_code, beacon::BEACON_VALIDITY_WINDOW)?;
+
+        for addr in &addresses {
+            // Beacon tokens carry plain socket addresses, no zone info.
+            self.add_manual_peer(
+                device_id.clone(),
+                device_id.clone(),
+                addr.ip(),
+                addr.port(),
+                None,
+            );
+        }
+
+        Ok(device_id)
+    }
+
+    /// Start a background thread that registers with `relay_addr` and
+    /// heartbeats every `interval`, merging the returned roster into our
+    /// peer map and emitting [`DiscoveryEvent::PeerFound`]/`PeerLost` the
+    /// same way mDNS resolution does. Meant for peers off our local
+    /// segment entirely, as a lower-effort alternative to
+    /// [`rendezvous`]'s manual publish/fetch flow.
+    pub fn start_relay_discovery(
+        &self,
+        relay_addr: String,
+        registration: relay_fallback::RelayRegistration,
+        interval: Duration,
+        event_tx: mpsc::Sender<DiscoveryEvent>,
+    ) {
+        let peers = self.peers.clone();
+        std::thread::spawn(move || {
+            let mut last_roster: std::collections::HashSet<String> = std::collections::HashSet::new();
+            loop {
+                match relay_fallback::heartbeat(&relay_addr, &registration, Duration::from_secs(10))
+                {
+                    Ok(roster) => {
+                        let mut current_roster = std::collections::HashSet::new();
+                        for peer in roster {
+                            current_roster.insert(peer.device_id.clone());
+                            let is_new = !last_roster.contains(&peer.device_id);
+                            peers
+                                .write()
+                                .unwrap()
+                                .insert(peer.device_id.clone(), peer.clone());
+                            if is_new {
+                                let _ = event_tx.blocking_send(DiscoveryEvent::PeerFound(peer));
+                            }
+                        }
+
+                        for stale_id in last_roster.difference(&current_roster) {
+                            peers.write().unwrap().remove(stale_id);
+                            let _ = event_tx.blocking_send(DiscoveryEvent::PeerLost(stale_id.clone()));
+                        }
+
+                        last_roster = current_roster;
+                    }
+                    Err(e) => tracing::warn!("relay discovery heartbeat failed: {}", e),
+                }
+                std::thread::sleep(interval);
+            }
+        });
+    }
+
+    /// Start a background thread that re-scans the peer map every
+    /// `sweep_interval` and drops any non-[`manual`](DiscoveredPeer::manual)
+    /// peer whose `last_seen` is older than `ttl`, emitting
+    /// [`DiscoveryEvent::PeerLost`] for each one. Covers the case where
+    /// mDNS's `ServiceRemoved` never arrives (e.g. the peer's process was
+    /// killed rather than shut down cleanly) by aging entries out instead
+    /// of relying solely on that event.
+    pub fn start_expiry_sweep(
+        &self,
+        ttl: Duration,
+        sweep_interval: Duration,
+        event_tx: mpsc::Sender<DiscoveryEvent>,
+    ) {
+        let peers = self.peers.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(sweep_interval);
+
+            let expired: Vec<String> = {
+                let guard = peers.read().unwrap();
+                guard
+                    .values()
+                    .filter(|peer| !peer.manual && peer.last_seen.elapsed() > ttl)
+                    .map(|peer| peer.device_id.clone())
+                    .collect()
+            };
+
+            for device_id in expired {
+                let removed = peers.write().unwrap().remove(&device_id).is_some();
+                if removed {
+                    tracing::info!("Peer expired (no refresh within TTL): {}", device_id);
+                    let _ = event_tx.blocking_send(DiscoveryEvent::PeerLost(device_id));
+                }
+            }
+        });
+    }
+
     /// Unregister our service
     pub fn unregister(&mut self) {
         if let Some(ref service_id) = self.our_service {
@@ -354,6 +689,107 @@ impl DiscoveryManager {
     }
 }
 
+/// Convert mdns-sd's resolved addresses to plain `IpAddr`s for the peer
+/// map, pulling out a link-local IPv6 scope id along the way. mdns-sd's
+/// `ScopedIp::V6` carries a scope per address, but `DiscoveredPeer` keeps a
+/// single scope for the whole peer (same model [`parse_zoned_ip`] and
+/// [`DiscoveryManager::add_manual_peer`] use) - the first non-zero one
+/// wins, which is the only one that matters in practice since a peer only
+/// has one link-local address per interface. Without this, a link-local
+/// peer address is silently undialable: a bare `fe80::...` with no scope
+/// doesn't route.
+fn collect_addresses(info: &ServiceInfo) -> (Vec<IpAddr>, Option<u32>) {
+    let mut scope_id = None;
+    let addresses = info
+        .get_addresses()
+        .iter()
+        .filter_map(|ip| match ip {
+            mdns_sd::ScopedIp::V4(v4) => Some(IpAddr::V4(*v4.addr())),
+            mdns_sd::ScopedIp::V6(v6) => {
+                if scope_id.is_none() && v6.scope_id() != 0 {
+                    scope_id = Some(v6.scope_id());
+                }
+                Some(IpAddr::V6(*v6.addr()))
+            }
+            _ => None,
+        })
+        .collect();
+    (addresses, scope_id)
+}
+
+/// Parse an address that may carry an IPv6 zone suffix, e.g. `fe80::1%en0`
+/// or `fe80::1%5`. Plain (non-zoned) IPv4/IPv6 strings parse as before with
+/// `scope_id` set to `None`. The zone after `%` is resolved to a numeric
+/// scope id: a bare number is used as-is, otherwise it's treated as an
+/// interface name and resolved via the platform's network stack.
+pub fn parse_zoned_ip(s: &str) -> Option<(IpAddr, Option<u32>)> {
+    match s.split_once('%') {
+        Some((addr_part, zone_part)) => {
+            let ip: Ipv6Addr = addr_part.parse().ok()?;
+            Some((IpAddr::V6(ip), resolve_scope_id(zone_part)))
+        }
+        None => s.parse().ok().map(|ip| (ip, None)),
+    }
+}
+
+#[cfg(unix)]
+fn resolve_scope_id(zone: &str) -> Option<u32> {
+    if let Ok(id) = zone.parse::<u32>() {
+        return Some(id);
+    }
+
+    extern "C" {
+        fn if_nametoindex(ifname: *const std::os::raw::c_char) -> std::os::raw::c_uint;
+    }
+
+    let c_name = std::ffi::CString::new(zone).ok()?;
+    let index = unsafe { if_nametoindex(c_name.as_ptr()) };
+    if index == 0 {
+        None
+    } else {
+        Some(index)
+    }
+}
+
+#[cfg(not(unix))]
+fn resolve_scope_id(zone: &str) -> Option<u32> {
+    // Non-Unix targets (Windows) commonly hand us a numeric zone index
+    // directly; we don't have a portable name->index lookup without an
+    // extra platform crate, so that's all we support here.
+    zone.parse::<u32>().ok()
+}
+
+/// Reachability class used to rank candidate addresses for the same peer,
+/// lowest value first: a global address is more likely to actually work
+/// than a unique-local one, which in turn beats a link-local one.
+pub fn reachability_rank(ip: &IpAddr) -> u8 {
+    match ip {
+        IpAddr::V4(v4) => {
+            if v4.is_loopback() {
+                3
+            } else if v4.is_link_local() {
+                2
+            } else {
+                0
+            }
+        }
+        IpAddr::V6(v6) => {
+            let octets = v6.octets();
+            if v6.is_loopback() {
+                3
+            } else if octets[0] == 0xfe && (octets[1] & 0xc0) == 0x80 {
+                // fe80::/10 - link-local unicast
+                2
+            } else if (octets[0] & 0xfe) == 0xfc {
+                // fc00::/7 - unique local
+                1
+            } else {
+                0
+            }
+        }
+    }
+}
+
 impl Drop for DiscoveryManager {
     fn drop(&mut self) {
         self.unregister();
@@ -372,4 +808,32 @@ mod tests {
         let manager = DiscoveryManager::new();
         assert!(manager.is_ok());
     }
+
+    #[test]
+    fn test_parse_zoned_ip_with_numeric_scope() {
+        let (ip, scope) = parse_zoned_ip("fe80::1%5").unwrap();
+        assert_eq!(ip, "fe80::1".parse::<IpAddr>().unwrap());
+        assert_eq!(scope, Some(5));
+    }
+
+    #[test]
+    fn test_parse_zoned_ip_without_zone() {
+        let (ip, scope) = parse_zoned_ip("192.168.1.1").unwrap();
+        assert_eq!(ip, "192.168.1.1".parse::<IpAddr>().unwrap());
+        assert_eq!(scope, None);
+
+        let (ip, scope) = parse_zoned_ip("2001:db8::1").unwrap();
+        assert_eq!(ip, "2001:db8::1".parse::<IpAddr>().unwrap());
+        assert_eq!(scope, None);
+    }
+
+    #[test]
+    fn test_reachability_rank_prefers_global_over_ula_over_link_local() {
+        let global: IpAddr = "2001:db8::1".parse().unwrap();
+        let ula: IpAddr = "fd00::1".parse().unwrap();
+        let link_local: IpAddr = "fe80::1".parse().unwrap();
+
+        assert!(reachability_rank(&global) < reachability_rank(&ula));
+        assert!(reachability_rank(&ula) < reachability_rank(&link_local));
+    }
 }
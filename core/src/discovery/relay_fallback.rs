@@ -0,0 +1,254 @@
+//! Live relay-based discovery: periodically heartbeat to a directory
+//! server so peers off our local segment can still find each other
+//! automatically, without the manual publish/fetch step [`super::rendezvous`]
+//! requires.
+//!
+//! This has nothing to do with [`crate::relay`]'s data-plane socket-pairing
+//! relay - that one splices two already-rendezvoused streams together and
+//! never sees a peer roster. This module instead talks to a directory
+//! service: register our reachability, heartbeat it periodically, and poll
+//! back everyone else currently registered, feeding the result into the
+//! same [`super::DiscoveredPeer`] map the mDNS and [`super::broadcast`]
+//! backends populate.
+//!
+//! Wire protocol is raw TCP with fixed framing (matching [`crate::relay`]'s
+//! style rather than pulling in an HTTP client dependency):
+//! `magic(4) || id_len(1) || device_id || name_len(1) || device_name ||
+//! platform_len(1) || platform || port(2) || has_external(1) ||
+//! [external: tag(1) || ip_bytes || port(2)]`, and the server replies with
+//! `count(1) || roster_entry*`, each entry shaped like a single
+//! [`super::rendezvous`] beacon address record.
+
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use thiserror::Error;
+
+use super::DiscoveredPeer;
+
+/// Marks the start of a relay-discovery request, so a directory server can
+/// reject connections from something other than a VoidWarp client up front.
+pub const RELAY_DISCOVERY_MAGIC: u32 = 0x56575244; // "VWRD"
+
+/// Default interval between heartbeats; also the cadence `broadcast` uses
+/// for its own Hello re-announcements, kept the same so a peer doesn't look
+/// noticeably staler depending on which backend found it.
+pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// What we tell the directory server about ourselves on registration and
+/// every subsequent heartbeat.
+#[derive(Debug, Clone)]
+pub struct RelayRegistration {
+    pub device_id: String,
+    pub device_name: String,
+    pub platform: String,
+    pub port: u16,
+    /// Our externally-reachable address, if known (e.g. a manually
+    /// configured port forward). `None` leaves that field for the
+    /// directory server to fill in from the connection's source address.
+    pub external_addr: Option<SocketAddr>,
+}
+
+/// Errors from registering/heartbeating with a relay discovery server.
+#[derive(Error, Debug)]
+pub enum RelayDiscoveryError {
+    #[error("failed to connect to relay discovery server: {0}")]
+    ConnectFailed(String),
+    #[error("relay discovery server rejected the request: {0}")]
+    RequestFailed(String),
+    #[error("relay discovery response was truncated or malformed")]
+    Truncated,
+}
+
+/// Register with `relay_addr` (or refresh an existing registration - the
+/// server treats every call as an upsert keyed by `device_id`) and return
+/// the roster of other peers currently registered there. Call this on a
+/// timer to double as both registration and heartbeat; a single round trip
+/// keeps the caller from needing two separate code paths.
+pub fn heartbeat(
+    relay_addr: &str,
+    registration: &RelayRegistration,
+    timeout: Duration,
+) -> Result<Vec<DiscoveredPeer>, RelayDiscoveryError> {
+    let addr = relay_addr
+        .to_socket_addrs()
+        .map_err(|e| RelayDiscoveryError::ConnectFailed(e.to_string()))?
+        .next()
+        .ok_or_else(|| {
+            RelayDiscoveryError::ConnectFailed("relay address did not resolve".to_string())
+        })?;
+
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)
+        .map_err(|e| RelayDiscoveryError::ConnectFailed(e.to_string()))?;
+    stream
+        .set_read_timeout(Some(timeout))
+        .map_err(|e| RelayDiscoveryError::ConnectFailed(e.to_string()))?;
+    stream
+        .set_write_timeout(Some(timeout))
+        .map_err(|e| RelayDiscoveryError::ConnectFailed(e.to_string()))?;
+
+    let request = encode_registration(registration);
+    stream
+        .write_all(&request)
+        .map_err(|e| RelayDiscoveryError::RequestFailed(e.to_string()))?;
+
+    let mut count_buf = [0u8; 1];
+    stream
+        .read_exact(&mut count_buf)
+        .map_err(|e| RelayDiscoveryError::RequestFailed(e.to_string()))?;
+
+    let mut roster = Vec::with_capacity(count_buf[0] as usize);
+    for _ in 0..count_buf[0] {
+        roster.push(read_roster_entry(&mut stream)?);
+    }
+    Ok(roster)
+}
+
+fn encode_registration(registration: &RelayRegistration) -> Vec<u8> {
+    let id_bytes = registration.device_id.as_bytes();
+    let name_bytes = registration.device_name.as_bytes();
+    let platform_bytes = registration.platform.as_bytes();
+
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&RELAY_DISCOVERY_MAGIC.to_be_bytes());
+    buf.push(id_bytes.len() as u8);
+    buf.extend_from_slice(id_bytes);
+    buf.push(name_bytes.len() as u8);
+    buf.extend_from_slice(name_bytes);
+    buf.push(platform_bytes.len() as u8);
+    buf.extend_from_slice(platform_bytes);
+    buf.extend_from_slice(&registration.port.to_be_bytes());
+
+    match registration.external_addr {
+        Some(addr) => {
+            buf.push(1);
+            write_addr(&mut buf, addr);
+        }
+        None => buf.push(0),
+    }
+    buf
+}
+
+fn write_addr(buf: &mut Vec<u8>, addr: SocketAddr) {
+    match addr.ip() {
+        IpAddr::V4(v4) => {
+            buf.push(4);
+            buf.extend_from_slice(&v4.octets());
+        }
+        IpAddr::V6(v6) => {
+            buf.push(6);
+            buf.extend_from_slice(&v6.octets());
+        }
+    }
+    buf.extend_from_slice(&addr.port().to_be_bytes());
+}
+
+fn read_roster_entry(stream: &mut TcpStream) -> Result<DiscoveredPeer, RelayDiscoveryError> {
+    let id = read_len_prefixed_string(stream)?;
+    let name = read_len_prefixed_string(stream)?;
+
+    let mut port_buf = [0u8; 2];
+    stream
+        .read_exact(&mut port_buf)
+        .map_err(|_| RelayDiscoveryError::Truncated)?;
+    let port = u16::from_be_bytes(port_buf);
+
+    let mut addr_count_buf = [0u8; 1];
+    stream
+        .read_exact(&mut addr_count_buf)
+        .map_err(|_| RelayDiscoveryError::Truncated)?;
+
+    let mut addresses = Vec::with_capacity(addr_count_buf[0] as usize);
+    for _ in 0..addr_count_buf[0] {
+        addresses.push(read_addr(stream)?);
+    }
+
+    Ok(DiscoveredPeer {
+        device_id: id,
+        device_name: name,
+        addresses,
+        port,
+        scope_id: None,
+        last_seen: std::time::Instant::now(),
+        manual: false,
+        identity: None,
+    })
+}
+
+fn read_len_prefixed_string(stream: &mut TcpStream) -> Result<String, RelayDiscoveryError> {
+    let mut len_buf = [0u8; 1];
+    stream
+        .read_exact(&mut len_buf)
+        .map_err(|_| RelayDiscoveryError::Truncated)?;
+    let mut bytes = vec![0u8; len_buf[0] as usize];
+    stream
+        .read_exact(&mut bytes)
+        .map_err(|_| RelayDiscoveryError::Truncated)?;
+    String::from_utf8(bytes).map_err(|_| RelayDiscoveryError::Truncated)
+}
+
+fn read_addr(stream: &mut TcpStream) -> Result<IpAddr, RelayDiscoveryError> {
+    let mut tag_buf = [0u8; 1];
+    stream
+        .read_exact(&mut tag_buf)
+        .map_err(|_| RelayDiscoveryError::Truncated)?;
+    let ip = match tag_buf[0] {
+        4 => {
+            let mut octets = [0u8; 4];
+            stream
+                .read_exact(&mut octets)
+                .map_err(|_| RelayDiscoveryError::Truncated)?;
+            IpAddr::V4(Ipv4Addr::from(octets))
+        }
+        6 => {
+            let mut octets = [0u8; 16];
+            stream
+                .read_exact(&mut octets)
+                .map_err(|_| RelayDiscoveryError::Truncated)?;
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        _ => return Err(RelayDiscoveryError::Truncated),
+    };
+    // Each roster address is followed by a port, but `DiscoveredPeer`
+    // carries one port for the whole peer - discard the per-address port
+    // here, matching `rendezvous::import_into`'s "first address wins".
+    let mut port_buf = [0u8; 2];
+    stream
+        .read_exact(&mut port_buf)
+        .map_err(|_| RelayDiscoveryError::Truncated)?;
+    Ok(ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_registration_includes_magic_and_fields() {
+        let registration = RelayRegistration {
+            device_id: "abcd1234".to_string(),
+            device_name: "dana's desktop".to_string(),
+            platform: "linux".to_string(),
+            port: 4242,
+            external_addr: Some("203.0.113.5:4242".parse().unwrap()),
+        };
+        let encoded = encode_registration(&registration);
+        assert_eq!(&encoded[0..4], &RELAY_DISCOVERY_MAGIC.to_be_bytes());
+        assert!(encoded.len() > 4);
+    }
+
+    #[test]
+    fn encode_registration_without_external_addr_sets_flag_to_zero() {
+        let registration = RelayRegistration {
+            device_id: "a".to_string(),
+            device_name: "b".to_string(),
+            platform: "c".to_string(),
+            port: 1,
+            external_addr: None,
+        };
+        let encoded = encode_registration(&registration);
+        // magic(4) + id(1+1) + name(1+1) + platform(1+1) + port(2) + flag(1)
+        assert_eq!(encoded.last(), Some(&0u8));
+    }
+}
@@ -0,0 +1,372 @@
+//! Out-of-band WAN rendezvous: publish/fetch a compact beacon blob via a
+//! file or a user-supplied shell command, for devices that can't reach
+//! each other over LAN broadcast/multicast at all.
+//!
+//! Modeled on vpncloud's `BeaconSerializer`: encode this device's identity
+//! and reachable addresses into a blob once, then let the user move that
+//! blob across whatever channel actually bridges the two networks (a
+//! shared drive, a pasted chat message, an HTTP endpoint fetched by a
+//! script) - [`super::BeaconListener`]'s peer map doesn't care how an
+//! entry got there, so [`import_into`] feeds it the same way a UDP Hello
+//! would.
+//!
+//! Unlike [`super::beacon`]'s pairing-code-encrypted token, a rendezvous
+//! blob is unencrypted: the publishing channel itself (a private file
+//! share, a DM) is the trust boundary here, not a shared secret.
+
+use std::fs;
+use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use data_encoding::BASE32_NOPAD;
+use thiserror::Error;
+
+const BEGIN_MARKER: &str = "-----BEGIN VOIDWARP RENDEZVOUS-----";
+const END_MARKER: &str = "-----END VOIDWARP RENDEZVOUS-----";
+
+/// Errors that can occur while publishing or fetching a rendezvous beacon.
+#[derive(Error, Debug)]
+pub enum RendezvousError {
+    #[error("rendezvous blob is missing the expected begin/end markers")]
+    MalformedMarkers,
+    #[error("rendezvous body is not valid base32")]
+    InvalidEncoding,
+    #[error("rendezvous payload is truncated or malformed")]
+    Truncated,
+    #[error("I/O error publishing/fetching rendezvous blob: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("rendezvous command exited with status {0}")]
+    CommandFailed(std::process::ExitStatus),
+}
+
+/// This device's identity and reachable addresses, published for WAN
+/// rendezvous. `public_key_hex` duplicates `device_id` under this crate's
+/// identity model (`device_id` already *is* the hex-encoded Ed25519 public
+/// key, see [`crate::security::crypto::DeviceIdentity`]) - both are
+/// carried on the wire anyway so a future identity format that decouples
+/// them doesn't need a format change here.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RendezvousBeacon {
+    pub device_id: String,
+    pub public_key_hex: String,
+    pub device_name: String,
+    pub addresses: Vec<SocketAddr>,
+}
+
+/// Encode a beacon into a shareable, marker-wrapped, base32 text blob.
+pub fn encode_rendezvous_beacon(beacon: &RendezvousBeacon) -> String {
+    let body = encode_payload(beacon);
+    format!(
+        "{}\n{}\n{}",
+        BEGIN_MARKER,
+        BASE32_NOPAD.encode(&body),
+        END_MARKER
+    )
+}
+
+/// Decode a blob produced by [`encode_rendezvous_beacon`].
+pub fn parse_rendezvous_beacon(blob: &str) -> Result<RendezvousBeacon, RendezvousError> {
+    let body = strip_markers(blob)?;
+    let bytes = BASE32_NOPAD
+        .decode(body.as_bytes())
+        .map_err(|_| RendezvousError::InvalidEncoding)?;
+    decode_payload(&bytes)
+}
+
+/// Write the encoded blob to `path` with mode 0644 (unix only) so it's
+/// readable by anyone with access to the shared location (e.g. a shared
+/// drive) but only writable by us.
+pub fn write_to_file(path: &Path, beacon: &RendezvousBeacon) -> Result<(), RendezvousError> {
+    let blob = encode_rendezvous_beacon(beacon);
+    fs::write(path, blob)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o644))?;
+    }
+    Ok(())
+}
+
+/// Read and decode a blob previously written by [`write_to_file`].
+pub fn read_from_file(path: &Path) -> Result<RendezvousBeacon, RendezvousError> {
+    let blob = fs::read_to_string(path)?;
+    parse_rendezvous_beacon(&blob)
+}
+
+/// Publish the blob by invoking a user-supplied shell command, e.g. to
+/// `curl -T -` it to an HTTP endpoint or pipe it into a paste tool. The
+/// blob is passed both as the child's stdin and as env vars
+/// (`VOIDWARP_BEACON_BEGIN`/`_DATA`/`_END` for the individual parts,
+/// `VOIDWARP_BEACON` for the whole marked blob) so the command can use
+/// whichever is convenient.
+pub fn publish_via_command(command: &str, beacon: &RendezvousBeacon) -> Result<(), RendezvousError> {
+    let blob = encode_rendezvous_beacon(beacon);
+    let body = strip_markers(&blob)?;
+
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("VOIDWARP_BEACON_BEGIN", BEGIN_MARKER)
+        .env("VOIDWARP_BEACON_DATA", &body)
+        .env("VOIDWARP_BEACON_END", END_MARKER)
+        .env("VOIDWARP_BEACON", &blob)
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(blob.as_bytes());
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(RendezvousError::CommandFailed(status));
+    }
+    Ok(())
+}
+
+/// Fetch a blob by invoking a user-supplied shell command (e.g. `curl` a
+/// URL where a peer published its beacon) and decoding its stdout.
+pub fn fetch_via_command(command: &str) -> Result<RendezvousBeacon, RendezvousError> {
+    let output = Command::new("sh").arg("-c").arg(command).output()?;
+    if !output.status.success() {
+        return Err(RendezvousError::CommandFailed(output.status));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    parse_rendezvous_beacon(stdout.trim())
+}
+
+/// Turn a fetched beacon into a [`super::DiscoveredPeer`] and insert it
+/// into the shared peer map, reusing the exact plumbing
+/// [`super::broadcast::BeaconListener`] uses for LAN Hellos.
+pub fn import_into(
+    beacon: RendezvousBeacon,
+    peers: &std::sync::Arc<std::sync::RwLock<std::collections::HashMap<String, super::DiscoveredPeer>>>,
+) {
+    let addresses = beacon.addresses.iter().map(|a| a.ip()).collect();
+    // A rendezvous blob can carry several addresses but `DiscoveredPeer`
+    // wants a single port; the first address is authoritative, matching
+    // how `BeaconListener` only ever sees the Hello's own `port` field.
+    let port = beacon.addresses.first().map(|a| a.port()).unwrap_or(0);
+    let peer = super::DiscoveredPeer {
+        device_id: beacon.device_id.clone(),
+        device_name: beacon.device_name,
+        addresses,
+        port,
+        scope_id: None,
+        last_seen: std::time::Instant::now(),
+        // Rendezvous blobs are a one-shot manual import with no ongoing
+        // refresh, same as `add_manual_peer` - the expiry sweep shouldn't
+        // flush them out after one TTL window.
+        manual: true,
+        // `RendezvousBeacon` already carries `public_key_hex` (the Ed25519
+        // device_id, not an X25519 static key), so there's nothing to
+        // populate `identity` with here.
+        identity: None,
+    };
+    tracing::info!(
+        "Discovered peer via WAN rendezvous: {} ({})",
+        peer.device_name,
+        peer.device_id
+    );
+    peers.write().unwrap().insert(beacon.device_id, peer);
+}
+
+fn strip_markers(blob: &str) -> Result<String, RendezvousError> {
+    let begin = blob
+        .find(BEGIN_MARKER)
+        .ok_or(RendezvousError::MalformedMarkers)?;
+    let end = blob
+        .find(END_MARKER)
+        .ok_or(RendezvousError::MalformedMarkers)?;
+    let start = begin + BEGIN_MARKER.len();
+    if end < start {
+        return Err(RendezvousError::MalformedMarkers);
+    }
+    Ok(blob[start..end]
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect())
+}
+
+/// `id_len(1) || device_id || key_len(1) || public_key_hex || name_len(1)
+/// || device_name || addr_count(1) || addr*`, where each `addr` is
+/// `tag(1, 4=v4/6=v6) || ip_bytes || port(2, big-endian)` - the same
+/// per-address layout [`super::beacon`] uses.
+fn encode_payload(beacon: &RendezvousBeacon) -> Vec<u8> {
+    let id_bytes = beacon.device_id.as_bytes();
+    let key_bytes = beacon.public_key_hex.as_bytes();
+    let name_bytes = beacon.device_name.as_bytes();
+    let mut buf = Vec::new();
+    buf.push(id_bytes.len() as u8);
+    buf.extend_from_slice(id_bytes);
+    buf.push(key_bytes.len() as u8);
+    buf.extend_from_slice(key_bytes);
+    buf.push(name_bytes.len() as u8);
+    buf.extend_from_slice(name_bytes);
+    buf.push(beacon.addresses.len() as u8);
+    for addr in &beacon.addresses {
+        match addr.ip() {
+            IpAddr::V4(v4) => {
+                buf.push(4);
+                buf.extend_from_slice(&v4.octets());
+            }
+            IpAddr::V6(v6) => {
+                buf.push(6);
+                buf.extend_from_slice(&v6.octets());
+            }
+        }
+        buf.extend_from_slice(&addr.port().to_be_bytes());
+    }
+    buf
+}
+
+fn decode_payload(buf: &[u8]) -> Result<RendezvousBeacon, RendezvousError> {
+    let mut cursor = 0usize;
+
+    let id_len = *buf.get(cursor).ok_or(RendezvousError::Truncated)? as usize;
+    cursor += 1;
+    let device_id = read_string(buf, &mut cursor, id_len)?;
+
+    let key_len = *buf.get(cursor).ok_or(RendezvousError::Truncated)? as usize;
+    cursor += 1;
+    let public_key_hex = read_string(buf, &mut cursor, key_len)?;
+
+    let name_len = *buf.get(cursor).ok_or(RendezvousError::Truncated)? as usize;
+    cursor += 1;
+    let device_name = read_string(buf, &mut cursor, name_len)?;
+
+    let addr_count = *buf.get(cursor).ok_or(RendezvousError::Truncated)? as usize;
+    cursor += 1;
+
+    let mut addresses = Vec::with_capacity(addr_count);
+    for _ in 0..addr_count {
+        let tag = *buf.get(cursor).ok_or(RendezvousError::Truncated)?;
+        cursor += 1;
+        let ip = match tag {
+            4 => {
+                let octets: [u8; 4] = buf
+                    .get(cursor..cursor + 4)
+                    .and_then(|s| s.try_into().ok())
+                    .ok_or(RendezvousError::Truncated)?;
+                cursor += 4;
+                IpAddr::V4(Ipv4Addr::from(octets))
+            }
+            6 => {
+                let octets: [u8; 16] = buf
+                    .get(cursor..cursor + 16)
+                    .and_then(|s| s.try_into().ok())
+                    .ok_or(RendezvousError::Truncated)?;
+                cursor += 16;
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+            _ => return Err(RendezvousError::Truncated),
+        };
+        let port_bytes: [u8; 2] = buf
+            .get(cursor..cursor + 2)
+            .and_then(|s| s.try_into().ok())
+            .ok_or(RendezvousError::Truncated)?;
+        cursor += 2;
+        addresses.push(SocketAddr::new(ip, u16::from_be_bytes(port_bytes)));
+    }
+
+    Ok(RendezvousBeacon {
+        device_id,
+        public_key_hex,
+        device_name,
+        addresses,
+    })
+}
+
+fn read_string(buf: &[u8], cursor: &mut usize, len: usize) -> Result<String, RendezvousError> {
+    let bytes = buf
+        .get(*cursor..*cursor + len)
+        .ok_or(RendezvousError::Truncated)?;
+    let s = String::from_utf8(bytes.to_vec()).map_err(|_| RendezvousError::Truncated)?;
+    *cursor += len;
+    Ok(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, RwLock};
+
+    fn sample_beacon() -> RendezvousBeacon {
+        RendezvousBeacon {
+            device_id: "abcd1234".to_string(),
+            public_key_hex: "abcd1234".to_string(),
+            device_name: "dana's desktop".to_string(),
+            addresses: vec![
+                "203.0.113.5:4242".parse().unwrap(),
+                "[2001:db8::1]:4242".parse().unwrap(),
+            ],
+        }
+    }
+
+    #[test]
+    fn roundtrip_preserves_fields() {
+        let beacon = sample_beacon();
+        let blob = encode_rendezvous_beacon(&beacon);
+        let decoded = parse_rendezvous_beacon(&blob).expect("valid blob should parse");
+        assert_eq!(decoded, beacon);
+    }
+
+    #[test]
+    fn file_roundtrip_preserves_fields() {
+        let beacon = sample_beacon();
+        let path = std::env::temp_dir().join(format!("voidwarp-rendezvous-test-{}", std::process::id()));
+        write_to_file(&path, &beacon).expect("write should succeed");
+        let decoded = read_from_file(&path).expect("read should succeed");
+        let _ = fs::remove_file(&path);
+        assert_eq!(decoded, beacon);
+    }
+
+    #[test]
+    fn command_roundtrip_preserves_fields() {
+        let beacon = sample_beacon();
+        let path = std::env::temp_dir().join(format!(
+            "voidwarp-rendezvous-cmd-test-{}",
+            std::process::id()
+        ));
+
+        // `publish_via_command` feeds the blob to the command's stdin;
+        // redirect it to a file so `fetch_via_command` (which reads
+        // stdout) can read it back with a plain `cat`, avoiding any shell
+        // quoting of the blob's embedded newlines.
+        publish_via_command(&format!("cat > {}", path.display()), &beacon)
+            .expect("publish should succeed");
+        let decoded =
+            fetch_via_command(&format!("cat {}", path.display())).expect("fetch should succeed");
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(decoded, beacon);
+    }
+
+    #[test]
+    fn malformed_blob_is_rejected() {
+        assert!(parse_rendezvous_beacon("not a beacon").is_err());
+    }
+
+    #[test]
+    fn truncated_payload_is_rejected() {
+        let body = BASE32_NOPAD.encode(&[0xff]);
+        let blob = format!("{}\n{}\n{}", BEGIN_MARKER, body, END_MARKER);
+        assert!(parse_rendezvous_beacon(&blob).is_err());
+    }
+
+    #[test]
+    fn import_into_inserts_peer_using_first_address() {
+        let beacon = sample_beacon();
+        let peers = Arc::new(RwLock::new(HashMap::new()));
+        let device_id = beacon.device_id.clone();
+        import_into(beacon, &peers);
+
+        let guard = peers.read().unwrap();
+        let peer = guard.get(&device_id).expect("peer should be inserted");
+        assert_eq!(peer.port, 4242);
+        assert_eq!(peer.addresses.len(), 2);
+    }
+}
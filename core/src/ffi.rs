@@ -9,7 +9,7 @@ use std::os::raw::c_char;
 use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::ptr;
 use std::sync::{Mutex, OnceLock};
-use std::{net::IpAddr, net::Ipv4Addr, net::SocketAddr};
+use std::{net::IpAddr, net::SocketAddr, net::SocketAddrV6};
 
 use crate::discovery::DiscoveryManager;
 use crate::security::crypto::{DeviceIdentity, PairingCode};
@@ -19,6 +19,10 @@ use crate::transport::TransportServer;
 pub struct VoidWarpHandle {
     pub(crate) discovery: Option<DiscoveryManager>,
     pub(crate) identity: DeviceIdentity,
+    /// Rendezvous relay address (`host:port`), set via `voidwarp_set_relay`.
+    /// `None` means direct-connect-only - a failed connect is reported as
+    /// `ConnectionFailed` rather than falling back to a relay.
+    pub(crate) relay_url: Mutex<Option<String>>,
 }
 
 /// Initialize the VoidWarp engine
@@ -41,6 +45,7 @@ pub extern "C" fn voidwarp_init(device_name: *const c_char) -> *mut VoidWarpHand
     let handle = Box::new(VoidWarpHandle {
         discovery: None,
         identity,
+        relay_url: Mutex::new(None),
     });
 
     Box::into_raw(handle)
@@ -56,6 +61,31 @@ pub extern "C" fn voidwarp_destroy(handle: *mut VoidWarpHandle) {
     }
 }
 
+/// Configure (or clear, by passing null) the rendezvous relay address
+/// (`host:port`) this handle's TCP transfers fall back to when a direct
+/// connection can't be established. See `crate::relay` for how the relay
+/// never learns the pairing code or plaintext.
+#[no_mangle]
+pub extern "C" fn voidwarp_set_relay(handle: *mut VoidWarpHandle, relay_url: *const c_char) -> i32 {
+    if handle.is_null() {
+        return -1;
+    }
+
+    let new_value = if relay_url.is_null() {
+        None
+    } else {
+        Some(
+            unsafe { CStr::from_ptr(relay_url) }
+                .to_string_lossy()
+                .into_owned(),
+        )
+    };
+
+    let handle = unsafe { &*handle };
+    *handle.relay_url.lock().unwrap() = new_value;
+    0
+}
+
 /// Get the device ID (caller must free with voidwarp_free_string)
 #[no_mangle]
 pub extern "C" fn voidwarp_get_device_id(handle: *const VoidWarpHandle) -> *mut c_char {
@@ -119,7 +149,7 @@ fn start_discovery_internal(
                     &handle.identity.device_id,
                     &handle.identity.device_name,
                     port,
-                    explicit_ip,
+                    &crate::security::crypto::hex_encode(&handle.identity.x25519_public()),
                 ) {
                     tracing::warn!("Failed to register mDNS service (continuing anyway): {}", e);
                 }
@@ -206,7 +236,10 @@ pub extern "C" fn voidwarp_stop_discovery(handle: *mut VoidWarpHandle) {
     handle.discovery = None;
 }
 
-/// Manually add a peer (e.g. for localhost connection)
+/// Manually add a peer (e.g. for localhost connection). `ip_address` may
+/// carry an IPv6 zone suffix (`fe80::1%en0`); if it doesn't, and the
+/// address is link-local, `scope_id` is used instead (0 = no scope, since
+/// 0 is never a valid interface index).
 #[no_mangle]
 pub extern "C" fn voidwarp_add_manual_peer(
     handle: *mut VoidWarpHandle,
@@ -214,6 +247,7 @@ pub extern "C" fn voidwarp_add_manual_peer(
     device_name: *const c_char,
     ip_address: *const c_char,
     port: u16,
+    scope_id: u32,
 ) -> i32 {
     if handle.is_null() || device_id.is_null() || device_name.is_null() || ip_address.is_null() {
         return -1;
@@ -233,12 +267,13 @@ pub extern "C" fn voidwarp_add_manual_peer(
         .to_string();
     let ip_str = unsafe { CStr::from_ptr(ip_address) }.to_string_lossy();
 
-    let ip: std::net::IpAddr = match ip_str.parse() {
-        Ok(ip) => ip,
-        Err(_) => return -1,
+    let (ip, zone_scope) = match crate::discovery::parse_zoned_ip(&ip_str) {
+        Some(parsed) => parsed,
+        None => return -1,
     };
+    let scope_id = zone_scope.or(if scope_id != 0 { Some(scope_id) } else { None });
 
-    discovery.add_manual_peer(device_id, device_name, ip, port);
+    discovery.add_manual_peer(device_id, device_name, ip, port, scope_id);
     0
 }
 
@@ -249,6 +284,9 @@ pub struct FfiPeer {
     pub device_name: *mut c_char,
     pub ip_address: *mut c_char,
     pub port: u16,
+    /// IPv6 zone/scope id for a link-local address in `ip_address`, or 0
+    /// if not applicable (0 is never a valid interface index).
+    pub scope_id: u32,
 }
 
 /// Peer list for FFI
@@ -287,31 +325,37 @@ pub extern "C" fn voidwarp_get_peers(handle: *const VoidWarpHandle) -> FfiPeerLi
         .map(|p| {
             // Return ALL valid IPs as a comma-separated string
             // This allows the UI to show them all or try them sequentially
-            let valid_ips: Vec<String> = p
+            let mut valid_ips: Vec<std::net::IpAddr> = p
                 .addresses
                 .iter()
                 .filter(|ip| match ip {
                     std::net::IpAddr::V4(ipv4) => !ipv4.is_loopback() && !ipv4.is_link_local(),
-                    _ => false, // Focusing on IPv4 for now due to Android/Windows cross-compatibility quirks
+                    // IPv6 link-local addresses are kept (unlike IPv4's):
+                    // with a scope id attached they're actually dialable,
+                    // and are often the only address on hotspot networks.
+                    std::net::IpAddr::V6(ipv6) => !ipv6.is_loopback(),
                 })
-                .map(|ip| ip.to_string())
+                .copied()
                 .collect();
 
-            // Sort them to prioritize 192.168.x.x (typical home/office wifi)
-            let mut sorted_ips = valid_ips;
-            sorted_ips.sort_by(|a, b| {
-                let a_is_local = a.starts_with("192.168.");
-                let b_is_local = b.starts_with("192.168.");
-                if a_is_local && !b_is_local {
-                    std::cmp::Ordering::Less
-                } else if !a_is_local && b_is_local {
-                    std::cmp::Ordering::Greater
-                } else {
-                    a.cmp(b)
-                }
-            });
-
-            let ip_str = sorted_ips.join(",");
+            // Prefer addresses by reachability class (global > ULA > link-local)
+            // rather than assuming 192.168.x.x is always the "home wifi" address.
+            valid_ips.sort_by_key(crate::discovery::reachability_rank);
+
+            // Fold the scope id back into the IPv6 address string (the same
+            // `ip%scope` shape `parse_zoned_ip` understands) so a caller
+            // that passes one of these straight back into `voidwarp_send_to`
+            // dials the right interface, instead of the scope only
+            // reaching the caller via the separate (and easy to drop)
+            // `scope_id` field.
+            let ip_str = valid_ips
+                .iter()
+                .map(|ip| match (ip, p.scope_id) {
+                    (std::net::IpAddr::V6(v6), Some(scope)) => format!("{}%{}", v6, scope),
+                    (ip, _) => ip.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(",");
 
             FfiPeer {
                 device_id: CString::new(p.device_id.clone())
@@ -324,6 +368,7 @@ pub extern "C" fn voidwarp_get_peers(handle: *const VoidWarpHandle) -> FfiPeerLi
                     .map(|s| s.into_raw())
                     .unwrap_or(ptr::null_mut()),
                 port: p.port,
+                scope_id: p.scope_id.unwrap_or(0),
             }
         })
         .collect();
@@ -360,6 +405,90 @@ pub extern "C" fn voidwarp_free_peer_list(list: FfiPeerList) {
     }
 }
 
+// ============================================================================
+// Network Interface FFI
+// ============================================================================
+
+/// A local network interface/address pair for FFI, mirrored by
+/// `NativeLib$NetInterface` on the Java side (see `voidwarp_list_interfaces`).
+#[repr(C)]
+pub struct FfiNetInterface {
+    pub name: *mut c_char,
+    pub address: *mut c_char,
+    pub is_ipv4: bool,
+    pub is_loopback: bool,
+}
+
+/// Interface list for FFI
+#[repr(C)]
+pub struct FfiNetInterfaceList {
+    pub interfaces: *mut FfiNetInterface,
+    pub count: usize,
+}
+
+/// List up, non-loopback local network interfaces so a caller can pick a
+/// bind address for discovery instead of guessing or parsing platform
+/// connectivity APIs. Caller must free with `voidwarp_free_interface_list`.
+#[no_mangle]
+pub extern "C" fn voidwarp_list_interfaces() -> FfiNetInterfaceList {
+    let empty = FfiNetInterfaceList {
+        interfaces: ptr::null_mut(),
+        count: 0,
+    };
+
+    let interfaces = crate::netiface::list_interfaces();
+    if interfaces.is_empty() {
+        return empty;
+    }
+
+    let mut ffi_interfaces: Vec<FfiNetInterface> = interfaces
+        .into_iter()
+        .map(|iface| FfiNetInterface {
+            name: CString::new(iface.name)
+                .map(|s| s.into_raw())
+                .unwrap_or(ptr::null_mut()),
+            address: CString::new(iface.address.to_string())
+                .map(|s| s.into_raw())
+                .unwrap_or(ptr::null_mut()),
+            is_ipv4: iface.is_ipv4,
+            is_loopback: iface.is_loopback,
+        })
+        .collect();
+
+    // Ensure capacity equals length for safe reconstruction later
+    ffi_interfaces.shrink_to_fit();
+    if ffi_interfaces.capacity() != ffi_interfaces.len() {
+        let mut exact = Vec::with_capacity(ffi_interfaces.len());
+        exact.extend(ffi_interfaces);
+        ffi_interfaces = exact;
+    }
+
+    let count = ffi_interfaces.len();
+    let ptr = ffi_interfaces.as_mut_ptr();
+    std::mem::forget(ffi_interfaces);
+
+    FfiNetInterfaceList {
+        interfaces: ptr,
+        count,
+    }
+}
+
+/// Free an interface list
+#[no_mangle]
+pub extern "C" fn voidwarp_free_interface_list(list: FfiNetInterfaceList) {
+    if list.interfaces.is_null() || list.count == 0 {
+        return;
+    }
+
+    unsafe {
+        let interfaces = Vec::from_raw_parts(list.interfaces, list.count, list.count);
+        for iface in interfaces {
+            voidwarp_free_string(iface.name);
+            voidwarp_free_string(iface.address);
+        }
+    }
+}
+
 // ============================================================================
 // File Transfer FFI
 // ============================================================================
@@ -424,6 +553,38 @@ pub extern "C" fn voidwarp_create_sender(path: *const c_char) -> *mut FfiFileSen
     }
 }
 
+/// Create a file sender that slices the file into content-defined chunks
+/// (`dedup::ContentDefinedChunker`, via `FileSender::new_content_defined`)
+/// instead of fixed-size blocks, using the module's own FastCDC size
+/// bounds. `voidwarp_sender_read_chunk`'s `index` is already a byte offset
+/// rather than a fixed-size chunk number, so the same read/ack loop a
+/// caller already has for `voidwarp_create_sender` works unchanged here.
+/// Returns null on error.
+#[no_mangle]
+pub extern "C" fn voidwarp_create_sender_content_defined(
+    path: *const c_char,
+) -> *mut FfiFileSender {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+
+    let path_str = unsafe { CStr::from_ptr(path) }.to_string_lossy();
+    let path = Path::new(path_str.as_ref());
+
+    match FileSender::new_content_defined(
+        path,
+        crate::dedup::CDC_MIN_CHUNK,
+        crate::dedup::CDC_TARGET_CHUNK,
+        crate::dedup::CDC_MAX_CHUNK,
+    ) {
+        Ok(sender) => Box::into_raw(Box::new(FfiFileSender { sender })),
+        Err(e) => {
+            tracing::error!("Failed to create content-defined sender: {}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
 /// Get file metadata from sender
 #[no_mangle]
 pub extern "C" fn voidwarp_sender_get_size(sender: *const FfiFileSender) -> u64 {
@@ -449,6 +610,9 @@ pub extern "C" fn voidwarp_sender_get_name(sender: *const FfiFileSender) -> *mut
 /// Returns chunk data that must be freed with voidwarp_free_chunk
 #[repr(C)]
 pub struct FfiChunk {
+    /// Byte offset of this chunk within the file (fixed-size senders: a
+    /// multiple of `chunk_size`; content-defined senders: wherever the
+    /// previous chunk ended).
     pub index: u64,
     pub data: *mut u8,
     pub len: usize,
@@ -470,15 +634,18 @@ pub extern "C" fn voidwarp_sender_read_chunk(sender: *mut FfiFileSender) -> FfiC
 
     let sender = unsafe { &mut (*sender).sender };
     match sender.read_chunk() {
-        Ok(Some((index, data))) => {
-            let len = data.len();
-            let is_last = index + 1 >= sender.metadata().total_chunks;
+        Ok(Some((offset, len, data))) => {
+            // `offset` replaces the old fixed-size chunk index now that a
+            // sender can also be content-defined (`FileSender::
+            // new_content_defined`), where chunk lengths vary and a
+            // sequential index wouldn't locate the bytes on its own.
+            let is_last = offset + len as u64 >= sender.metadata().size;
             let mut boxed = data.into_boxed_slice();
             let ptr = boxed.as_mut_ptr();
             std::mem::forget(boxed);
 
             FfiChunk {
-                index,
+                index: offset,
                 data: ptr,
                 len,
                 is_last,
@@ -555,6 +722,10 @@ pub struct FfiPendingTransfer {
     pub file_name: *mut c_char,
     pub file_size: u64,
     pub is_valid: bool,
+    /// Whether the sender authenticated during the post-accept handshake.
+    /// Only meaningful once a transfer has been accepted via
+    /// `voidwarp_receiver_accept`; `false` for a transfer still pending.
+    pub is_authenticated: bool,
 }
 
 /// Create a file receiver server
@@ -588,6 +759,34 @@ pub extern "C" fn voidwarp_receiver_start(receiver: *mut FfiFileReceiver) {
     unsafe { (*receiver).server.start() }
 }
 
+/// Like `voidwarp_receiver_start`, but for a receiver with no reachable
+/// inbound port: rendezvous through `handle`'s configured relay (see
+/// `voidwarp_set_relay`) instead of binding a local listener. Returns -1
+/// if no relay address is configured.
+#[no_mangle]
+pub extern "C" fn voidwarp_receiver_start_relay(
+    receiver: *mut FfiFileReceiver,
+    handle: *const VoidWarpHandle,
+    pairing_code: *const c_char,
+) -> i32 {
+    if receiver.is_null() || handle.is_null() || pairing_code.is_null() {
+        return -1;
+    }
+
+    let relay_url = unsafe { (*handle).relay_url.lock().unwrap().clone() };
+    let relay_url = match relay_url {
+        Some(url) => url,
+        None => {
+            tracing::error!("No relay configured (see voidwarp_set_relay)");
+            return -1;
+        }
+    };
+    let code = unsafe { CStr::from_ptr(pairing_code) }.to_string_lossy();
+
+    unsafe { (*receiver).server.start_via_relay(&relay_url, &code) };
+    0
+}
+
 /// Stop listening
 #[no_mangle]
 pub extern "C" fn voidwarp_receiver_stop(receiver: *mut FfiFileReceiver) {
@@ -598,7 +797,7 @@ pub extern "C" fn voidwarp_receiver_stop(receiver: *mut FfiFileReceiver) {
 }
 
 /// Get receiver state
-/// Returns: 0=Idle, 1=Listening, 2=AwaitingAccept, 3=Receiving, 4=Completed, 5=Error
+/// Returns: 0=Idle, 1=Listening, 2=AwaitingAccept, 3=Receiving, 4=Completed, 5=Error, 6=Relaying
 #[no_mangle]
 pub extern "C" fn voidwarp_receiver_get_state(receiver: *const FfiFileReceiver) -> i32 {
     if receiver.is_null() {
@@ -611,6 +810,7 @@ pub extern "C" fn voidwarp_receiver_get_state(receiver: *const FfiFileReceiver)
         ReceiverState::Receiving => 3,
         ReceiverState::Completed => 4,
         ReceiverState::Error => 5,
+        ReceiverState::Relaying => 6,
     }
 }
 
@@ -627,12 +827,15 @@ pub extern "C" fn voidwarp_receiver_get_pending(
         file_name: ptr::null_mut(),
         file_size: 0,
         is_valid: false,
+        is_authenticated: false,
     };
 
     if receiver.is_null() {
         return empty;
     }
 
+    let is_authenticated = unsafe { (*receiver).server.is_authenticated() };
+
     match unsafe { (*receiver).server.pending_transfer() } {
         Some(transfer) => FfiPendingTransfer {
             sender_name: CString::new(transfer.sender_name)
@@ -646,6 +849,7 @@ pub extern "C" fn voidwarp_receiver_get_pending(
                 .unwrap_or(ptr::null_mut()),
             file_size: transfer.file_size,
             is_valid: true,
+            is_authenticated,
         },
         None => empty,
     }
@@ -659,21 +863,28 @@ pub extern "C" fn voidwarp_free_pending_transfer(transfer: FfiPendingTransfer) {
     voidwarp_free_string(transfer.file_name);
 }
 
-/// Accept the pending transfer and save to the given path
+/// Accept the pending transfer and save to the given path. `handle` and
+/// `pairing_code` authenticate the post-accept handshake (see
+/// `security::noise`) - both sides must have agreed on the same pairing
+/// code out of band.
 /// Returns 0 on success, -1 on error
 #[no_mangle]
 pub extern "C" fn voidwarp_receiver_accept(
     receiver: *mut FfiFileReceiver,
     save_path: *const c_char,
+    handle: *const VoidWarpHandle,
+    pairing_code: *const c_char,
 ) -> i32 {
-    if receiver.is_null() || save_path.is_null() {
+    if receiver.is_null() || save_path.is_null() || handle.is_null() || pairing_code.is_null() {
         return -1;
     }
 
     let path_str = unsafe { CStr::from_ptr(save_path) }.to_string_lossy();
     let path = PathBuf::from(path_str.as_ref());
+    let code = unsafe { CStr::from_ptr(pairing_code) }.to_string_lossy();
+    let identity = unsafe { &(*handle).identity };
 
-    match unsafe { (*receiver).server.accept_transfer(&path) } {
+    match unsafe { (*receiver).server.accept_transfer(&path, identity, &code) } {
         Ok(_) => 0,
         Err(e) => {
             tracing::error!("Accept transfer failed: {}", e);
@@ -682,6 +893,36 @@ pub extern "C" fn voidwarp_receiver_accept(
     }
 }
 
+/// Like `voidwarp_receiver_accept`, but writes straight into an
+/// already-open file descriptor (see `receiver::FileReceiverServer::accept_transfer_fd`)
+/// instead of a save path - for an Android caller holding a Storage Access
+/// Framework `content://` grant. Only a fresh single-file, non-multistream,
+/// non-deduplicated offer can be accepted this way.
+/// Returns 0 on success, -1 on error.
+#[cfg(unix)]
+#[no_mangle]
+pub extern "C" fn voidwarp_receiver_accept_fd(
+    receiver: *mut FfiFileReceiver,
+    fd: std::os::raw::c_int,
+    handle: *const VoidWarpHandle,
+    pairing_code: *const c_char,
+) -> i32 {
+    if receiver.is_null() || handle.is_null() || pairing_code.is_null() {
+        return -1;
+    }
+
+    let code = unsafe { CStr::from_ptr(pairing_code) }.to_string_lossy();
+    let identity = unsafe { &(*handle).identity };
+
+    match unsafe { (*receiver).server.accept_transfer_fd(fd, identity, &code) } {
+        Ok(_) => 0,
+        Err(e) => {
+            tracing::error!("Accept transfer (fd) failed: {}", e);
+            -1
+        }
+    }
+}
+
 /// Reject the pending transfer
 /// Returns 0 on success, -1 on error
 #[no_mangle]
@@ -732,10 +973,19 @@ pub extern "C" fn voidwarp_destroy_receiver(receiver: *mut FfiFileReceiver) {
 // ============================================================================
 
 use crate::sender::{TcpFileSender, TransferResult};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
 /// Opaque handle to a TCP file sender
+///
+/// Wrapped in `Arc` (rather than held by value) so that
+/// `voidwarp_tcp_sender_start_async` can hand a cloned reference to its
+/// background worker thread while the FFI handle itself stays alive and
+/// readable (progress/cancel) on the caller's thread.
 pub struct FfiTcpSender {
-    sender: TcpFileSender,
+    sender: Arc<TcpFileSender>,
 }
 
 /// Create a TCP file sender for the given path
@@ -749,7 +999,9 @@ pub extern "C" fn voidwarp_tcp_sender_create(path: *const c_char) -> *mut FfiTcp
     let path_str = unsafe { CStr::from_ptr(path) }.to_string_lossy();
 
     match TcpFileSender::new(&path_str) {
-        Ok(sender) => Box::into_raw(Box::new(FfiTcpSender { sender })),
+        Ok(sender) => Box::into_raw(Box::new(FfiTcpSender {
+            sender: Arc::new(sender),
+        })),
         Err(e) => {
             tracing::error!("Failed to create TCP sender: {}", e);
             ptr::null_mut()
@@ -757,41 +1009,108 @@ pub extern "C" fn voidwarp_tcp_sender_create(path: *const c_char) -> *mut FfiTcp
     }
 }
 
+/// Create a TCP file sender from an already-open file descriptor rather
+/// than a path - for an Android caller holding a Storage Access Framework
+/// `content://` grant, which only ever yields an `int fd`, never a real
+/// filesystem path `voidwarp_tcp_sender_create` could open. `fd` is
+/// `dup`'d internally (see `TcpFileSender::from_fd`), so the caller is
+/// free to close its own copy immediately after this returns.
+/// Returns null on error.
+#[cfg(unix)]
+#[no_mangle]
+pub extern "C" fn voidwarp_tcp_sender_create_from_fd(
+    fd: std::os::raw::c_int,
+    display_name: *const c_char,
+    size: u64,
+) -> *mut FfiTcpSender {
+    if display_name.is_null() {
+        return ptr::null_mut();
+    }
+    let name = unsafe { CStr::from_ptr(display_name) }.to_string_lossy();
+
+    match TcpFileSender::from_fd(fd, &name, size) {
+        Ok(sender) => Box::into_raw(Box::new(FfiTcpSender {
+            sender: Arc::new(sender),
+        })),
+        Err(e) => {
+            tracing::error!("Failed to create fd-backed TCP sender: {}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
 /// Set chunk size for the sender (in bytes)
 #[no_mangle]
 pub extern "C" fn voidwarp_tcp_sender_set_chunk_size(sender: *mut FfiTcpSender, size: usize) {
     if !sender.is_null() && size > 0 {
         unsafe {
-            (*sender).sender.set_chunk_size(size);
+            // Only succeeds while no async transfer has cloned the Arc yet;
+            // callers are expected to configure the chunk size before
+            // starting a transfer, same as the synchronous path.
+            if let Some(inner) = Arc::get_mut(&mut (*sender).sender) {
+                inner.set_chunk_size(size);
+            }
         }
     }
 }
 
-/// Start TCP transfer to the target address
-/// Returns: 0=Success, 1=Rejected, 2=ChecksumMismatch, 3=ConnectionFailed, 4=Timeout, 5=Cancelled, 6=IoError
+/// Start TCP transfer to the target address. `handle` and `pairing_code`
+/// authenticate the post-accept handshake (see `security::noise`) - both
+/// sides must have agreed on the same pairing code out of band. Falls back
+/// to the handle's relay (see `voidwarp_set_relay`) if a direct connection
+/// can't be established.
+/// Returns: 0=Success, 1=Rejected, 2=ChecksumMismatch, 3=ConnectionFailed, 4=Timeout,
+/// 5=Cancelled, 6=IoError, 7=AuthenticationFailed, 8=DecryptionFailed, 9=SuccessViaRelay
 #[no_mangle]
 pub extern "C" fn voidwarp_tcp_sender_start(
     sender: *const FfiTcpSender,
     ip_address: *const c_char,
     port: u16,
     sender_name: *const c_char,
+    handle: *const VoidWarpHandle,
+    pairing_code: *const c_char,
 ) -> i32 {
-    if sender.is_null() || ip_address.is_null() || sender_name.is_null() {
+    if sender.is_null()
+        || ip_address.is_null()
+        || sender_name.is_null()
+        || handle.is_null()
+        || pairing_code.is_null()
+    {
         return 3; // ConnectionFailed
     }
 
     let sender_ref = unsafe { &(*sender).sender };
     let ip_str = unsafe { CStr::from_ptr(ip_address) }.to_string_lossy();
     let name_str = unsafe { CStr::from_ptr(sender_name) }.to_string_lossy();
+    let code = unsafe { CStr::from_ptr(pairing_code) }.to_string_lossy();
+    let identity = unsafe { &(*handle).identity };
 
-    let ip: std::net::IpAddr = match ip_str.parse() {
-        Ok(ip) => ip,
-        Err(_) => return 3, // ConnectionFailed - invalid IP
+    let (ip, scope_id) = match crate::discovery::parse_zoned_ip(&ip_str) {
+        Some(parsed) => parsed,
+        None => return 3, // ConnectionFailed - invalid IP
     };
 
-    let peer_addr = std::net::SocketAddr::new(ip, port);
+    // Honor the zone/scope id for link-local IPv6 targets - without it the
+    // kernel has no way to pick which interface to send the SYN out of.
+    let peer_addr = match (ip, scope_id) {
+        (IpAddr::V6(v6), Some(scope)) => SocketAddr::V6(SocketAddrV6::new(v6, port, 0, scope)),
+        (ip, _) => SocketAddr::new(ip, port),
+    };
 
-    match sender_ref.send_to(peer_addr, &name_str) {
+    let relay_url = unsafe { (*handle).relay_url.lock().unwrap().clone() };
+    transfer_result_code(sender_ref.send_to_with_relay(
+        peer_addr,
+        &name_str,
+        identity,
+        &code,
+        relay_url.as_deref(),
+    ))
+}
+
+/// Map a `TransferResult` to the FFI status code shared by the sync and
+/// async TCP sender entry points.
+fn transfer_result_code(result: TransferResult) -> i32 {
+    match result {
         TransferResult::Success => 0,
         TransferResult::Rejected => 1,
         TransferResult::ChecksumMismatch => 2,
@@ -799,7 +1118,184 @@ pub extern "C" fn voidwarp_tcp_sender_start(
         TransferResult::Timeout => 4,
         TransferResult::Cancelled => 5,
         TransferResult::IoError(_) => 6,
+        TransferResult::AuthenticationFailed => 7,
+        TransferResult::DecryptionFailed => 8,
+        TransferResult::QuicError(_) => 6,
+        TransferResult::SuccessViaRelay => 9,
+    }
+}
+
+/// Handle to the host's `ProgressCallback` + its opaque user-data pointer.
+///
+/// Raw `extern "C" fn` pointers and `*mut c_void` are both safe to hand to
+/// another thread (the host is responsible for the data behind `user_data`
+/// outliving the transfer); there's just nothing in the type system to say
+/// so, hence the manual impl.
+struct ProgressCallbackHandle {
+    callback: ProgressCallback,
+    user_data: *mut std::ffi::c_void,
+}
+
+unsafe impl Send for ProgressCallbackHandle {}
+
+impl ProgressCallbackHandle {
+    /// Invoke the host callback, catching panics so one can never unwind
+    /// across the FFI boundary and abort the process (same discipline as
+    /// `start_discovery_internal`).
+    fn invoke(&self, progress: FfiTransferProgress) {
+        let callback = self.callback;
+        let user_data = self.user_data;
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            callback(progress, user_data);
+        }));
+        if result.is_err() {
+            tracing::error!("panic caught in progress callback, ignoring");
+        }
+    }
+}
+
+/// Start a TCP transfer on a background thread, reporting progress through
+/// `callback` instead of forcing the caller to poll
+/// `voidwarp_tcp_sender_get_progress` on a timer.
+///
+/// `callback` is invoked with a `Transferring` progress snapshot whenever
+/// the transferred byte count changes, followed by exactly one terminal
+/// call once the transfer finishes (state `Completed`, `Failed`, or
+/// `Cancelled`, with `percentage` reflecting the final outcome). Drives
+/// the transfer through `TcpFileSender::send_to_resilient`, so a dropped
+/// connection is automatically redialed and resumed rather than failing
+/// the whole transfer - poll `voidwarp_tcp_sender_get_state` alongside the
+/// callback to show a "reconnecting..." state while that happens. Returns
+/// immediately; use `voidwarp_tcp_sender_cancel` to cancel the in-flight
+/// transfer and `voidwarp_tcp_sender_get_progress` if polling is still
+/// wanted alongside the callback.
+///
+/// Returns: 0=started, 3=ConnectionFailed (bad args/IP up front). Final
+/// transfer outcome only reaches the caller via the terminal callback.
+#[no_mangle]
+pub extern "C" fn voidwarp_tcp_sender_start_async(
+    sender: *const FfiTcpSender,
+    ip_address: *const c_char,
+    port: u16,
+    sender_name: *const c_char,
+    handle: *const VoidWarpHandle,
+    pairing_code: *const c_char,
+    callback: ProgressCallback,
+    user_data: *mut std::ffi::c_void,
+) -> i32 {
+    if sender.is_null()
+        || ip_address.is_null()
+        || sender_name.is_null()
+        || handle.is_null()
+        || pairing_code.is_null()
+    {
+        return 3; // ConnectionFailed
     }
+
+    let sender_arc = unsafe { Arc::clone(&(*sender).sender) };
+    let ip_str = unsafe { CStr::from_ptr(ip_address) }.to_string_lossy();
+    let name_str = unsafe { CStr::from_ptr(sender_name) }.to_string_lossy().into_owned();
+    let code = unsafe { CStr::from_ptr(pairing_code) }.to_string_lossy().into_owned();
+    // The worker thread outlives this call, so it needs its own copy of the
+    // identity rather than a reference into `*handle` (DeviceIdentity isn't
+    // Clone; round-trip through its PKCS#8 export instead).
+    let identity_bytes = unsafe { (*handle).identity.export() };
+    let identity_name = unsafe { (*handle).identity.device_name.clone() };
+    let relay_url = unsafe { (*handle).relay_url.lock().unwrap().clone() };
+
+    let (ip, scope_id) = match crate::discovery::parse_zoned_ip(&ip_str) {
+        Some(parsed) => parsed,
+        None => return 3, // ConnectionFailed - invalid IP
+    };
+    let peer_addr = match (ip, scope_id) {
+        (IpAddr::V6(v6), Some(scope)) => SocketAddr::V6(SocketAddrV6::new(v6, port, 0, scope)),
+        (ip, _) => SocketAddr::new(ip, port),
+    };
+
+    let callback_handle = ProgressCallbackHandle { callback, user_data };
+
+    thread::spawn(move || {
+        let identity = match DeviceIdentity::import(&identity_name, &identity_bytes) {
+            Ok(identity) => identity,
+            Err(e) => {
+                tracing::error!("failed to re-import identity for async transfer: {}", e);
+                callback_handle.invoke(FfiTransferProgress {
+                    bytes_transferred: 0,
+                    total_bytes: sender_arc.file_size(),
+                    percentage: 0.0,
+                    speed_mbps: 0.0,
+                    state: 4, // Failed
+                });
+                return;
+            }
+        };
+
+        let (progress_tx, progress_rx) = mpsc::channel::<u64>();
+        let file_size = sender_arc.file_size();
+        let poller_sender = Arc::clone(&sender_arc);
+        let poller = thread::spawn(move || loop {
+            thread::sleep(Duration::from_millis(100));
+            let sent = poller_sender.bytes_sent();
+            if progress_tx.send(sent).is_err() {
+                break; // worker finished and dropped the receiver
+            }
+            if sent >= file_size {
+                break;
+            }
+        });
+
+        let result = sender_arc.send_to_resilient(
+            peer_addr,
+            &name_str,
+            &identity,
+            &code,
+            relay_url.as_deref(),
+        );
+
+        // Drain whatever progress samples piled up, then report the result.
+        let mut last_sent = 0u64;
+        while let Ok(sent) = progress_rx.try_recv() {
+            last_sent = sent;
+            callback_handle.invoke(FfiTransferProgress {
+                bytes_transferred: sent,
+                total_bytes: file_size,
+                percentage: if file_size == 0 {
+                    0.0
+                } else {
+                    (sent as f32 / file_size as f32) * 100.0
+                },
+                speed_mbps: 0.0,
+                state: 1, // Transferring
+            });
+        }
+        let _ = poller.join();
+
+        let final_bytes = sender_arc.bytes_sent().max(last_sent);
+        let state = match &result {
+            TransferResult::Success | TransferResult::SuccessViaRelay => 3, // Completed
+            TransferResult::Cancelled => 5,
+            _ => 4, // Failed
+        };
+        callback_handle.invoke(FfiTransferProgress {
+            bytes_transferred: final_bytes,
+            total_bytes: file_size,
+            percentage: if file_size == 0 {
+                0.0
+            } else {
+                (final_bytes as f32 / file_size as f32) * 100.0
+            },
+            speed_mbps: 0.0,
+            state,
+        });
+
+        tracing::info!(
+            "async transfer finished: {:?} ({})",
+            result,
+            transfer_result_code(result)
+        );
+    });
+
+    0
 }
 
 /// Get file checksum (caller must free with voidwarp_free_string)
@@ -832,6 +1328,23 @@ pub extern "C" fn voidwarp_tcp_sender_get_progress(sender: *const FfiTcpSender)
     unsafe { (*sender).sender.progress() }
 }
 
+/// Get the sender's current lifecycle state, as set by
+/// `voidwarp_tcp_sender_start_async`'s automatic-reconnect loop (see
+/// `TcpFileSender::send_to_resilient`). Returns: 0=Connecting,
+/// 1=Transferring, 2=Reconnecting, 3=Done.
+#[no_mangle]
+pub extern "C" fn voidwarp_tcp_sender_get_state(sender: *const FfiTcpSender) -> i32 {
+    if sender.is_null() {
+        return 3; // Done - nothing to report
+    }
+    match unsafe { (*sender).sender.state() } {
+        crate::sender::TransferState::Connecting => 0,
+        crate::sender::TransferState::Transferring => 1,
+        crate::sender::TransferState::Reconnecting => 2,
+        crate::sender::TransferState::Done => 3,
+    }
+}
+
 /// Cancel the transfer
 #[no_mangle]
 pub extern "C" fn voidwarp_tcp_sender_cancel(sender: *const FfiTcpSender) {
@@ -874,20 +1387,161 @@ pub extern "C" fn voidwarp_tcp_sender_destroy(sender: *mut FfiTcpSender) {
     }
 }
 
+// ============================================================================
+// ICE-style NAT traversal FFI
+// ============================================================================
+
+/// Gather this device's ICE candidates (host addresses plus, if
+/// `stun_server` ("host:port") is given, a STUN server-reflexive address)
+/// for `local_port`. Returns a comma-separated, priority-ordered candidate
+/// list to exchange with the peer out of band (alongside the pairing
+/// code), or null on failure. Caller must free with `voidwarp_free_string`.
+#[no_mangle]
+pub extern "C" fn voidwarp_ice_gather_candidates(
+    local_port: u16,
+    stun_server: *const c_char,
+) -> *mut c_char {
+    let stun_addr = if stun_server.is_null() {
+        None
+    } else {
+        let s = unsafe { CStr::from_ptr(stun_server) }.to_string_lossy();
+        match s.parse::<SocketAddr>() {
+            Ok(addr) => Some(addr),
+            Err(_) => {
+                tracing::error!("Invalid STUN server address: {}", s);
+                return ptr::null_mut();
+            }
+        }
+    };
+
+    match crate::ice::gather_candidates(local_port, stun_addr) {
+        Ok(candidates) => CString::new(crate::ice::encode_candidates(&candidates))
+            .map(|s| s.into_raw())
+            .unwrap_or(ptr::null_mut()),
+        Err(e) => {
+            tracing::error!("ICE candidate gathering failed: {}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Run ICE connectivity checks between this device's own candidates (as
+/// previously returned by `voidwarp_ice_gather_candidates`) and the peer's
+/// candidates (received out of band), racing simultaneous-open connects
+/// across every local×remote pair. On success, returns the winning peer
+/// address ("ip:port") as the caller should now dial with
+/// `voidwarp_tcp_sender_start` - by then the NAT mapping used for the
+/// check is warm, so that direct connect typically succeeds immediately.
+/// Returns null if no candidate pair was reachable.
+/// Caller must free the result with `voidwarp_free_string`.
+#[no_mangle]
+pub extern "C" fn voidwarp_ice_connect(
+    local_port: u16,
+    local_candidates: *const c_char,
+    remote_candidates: *const c_char,
+) -> *mut c_char {
+    if local_candidates.is_null() || remote_candidates.is_null() {
+        return ptr::null_mut();
+    }
+
+    let local_str = unsafe { CStr::from_ptr(local_candidates) }.to_string_lossy();
+    let remote_str = unsafe { CStr::from_ptr(remote_candidates) }.to_string_lossy();
+    let local = crate::ice::decode_candidates(&local_str);
+    let remote = crate::ice::decode_candidates(&remote_str);
+
+    match crate::ice::connect(&local, &remote, local_port) {
+        Ok(stream) => match stream.peer_addr() {
+            Ok(addr) => CString::new(addr.to_string())
+                .map(|s| s.into_raw())
+                .unwrap_or(ptr::null_mut()),
+            Err(_) => ptr::null_mut(),
+        },
+        Err(e) => {
+            tracing::error!("ICE connectivity check failed: {}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+// ============================================================================
+// NAT port mapping FFI
+// ============================================================================
+
+static NAT_MAPPING: OnceLock<Mutex<Option<crate::natmap::PortMapping>>> = OnceLock::new();
+
+fn nat_mapping_cell() -> &'static Mutex<Option<crate::natmap::PortMapping>> {
+    NAT_MAPPING.get_or_init(|| Mutex::new(None))
+}
+
+/// Ask the LAN gateway (UPnP-IGD, falling back to NAT-PMP/PCP) to forward
+/// `local_port` to this device, so peers off the LAN can dial straight in
+/// instead of needing a relay. The mapping is held open and renewed by a
+/// background thread until `voidwarp_natmap_stop` is called (or the
+/// process exits). `lease_seconds` of 0 falls back to
+/// `natmap::DEFAULT_LEASE`. Returns 0 on success, -1 if no gateway could be
+/// reached by either mechanism. Only one mapping is held at a time; a
+/// second call replaces the first.
+#[no_mangle]
+pub extern "C" fn voidwarp_natmap_start(local_port: u16, is_tcp: bool, lease_seconds: u32) -> i32 {
+    let protocol = if is_tcp {
+        crate::natmap::MappedProtocol::Tcp
+    } else {
+        crate::natmap::MappedProtocol::Udp
+    };
+    let lease = if lease_seconds == 0 {
+        crate::natmap::DEFAULT_LEASE
+    } else {
+        std::time::Duration::from_secs(lease_seconds as u64)
+    };
+
+    match crate::natmap::map_port(local_port, protocol, lease) {
+        Ok(mapping) => {
+            *nat_mapping_cell().lock().unwrap() = Some(mapping);
+            0
+        }
+        Err(e) => {
+            tracing::warn!("NAT port mapping failed: {}", e);
+            -1
+        }
+    }
+}
+
+/// The external address `voidwarp_natmap_start` obtained, or null if no
+/// mapping is currently held. Caller must free with `voidwarp_free_string`.
+#[no_mangle]
+pub extern "C" fn voidwarp_natmap_external_addr() -> *mut c_char {
+    match nat_mapping_cell().lock().unwrap().as_ref() {
+        Some(mapping) => CString::new(mapping.external_addr().to_string())
+            .map(|s| s.into_raw())
+            .unwrap_or(ptr::null_mut()),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Release the mapping started by `voidwarp_natmap_start`, if any.
+#[no_mangle]
+pub extern "C" fn voidwarp_natmap_stop() {
+    *nat_mapping_cell().lock().unwrap() = None;
+}
+
 static TRANSPORT_SERVER: OnceLock<Mutex<Option<TransportServer>>> = OnceLock::new();
 
 fn transport_server_cell() -> &'static Mutex<Option<TransportServer>> {
     TRANSPORT_SERVER.get_or_init(|| Mutex::new(None))
 }
 
+/// Start the transport server on `port`, or adopt a systemd-activated
+/// listening socket if one was handed to this process (see
+/// `TransportServer::bind_default_or_activated`) - `port` is then only used
+/// as a fallback for a non-activated start.
 #[no_mangle]
 pub extern "C" fn voidwarp_transport_start_server(port: u16) -> bool {
     let mut cell = transport_server_cell().lock().unwrap();
     if cell.is_some() {
         return true;
     }
-    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port);
-    match TransportServer::bind(addr) {
+    match TransportServer::bind_default_or_activated(port, crate::transport::DEFAULT_WORKER_POOL_SIZE)
+    {
         Ok(server) => {
             *cell = Some(server);
             true
@@ -896,6 +1550,43 @@ pub extern "C" fn voidwarp_transport_start_server(port: u16) -> bool {
     }
 }
 
+#[cfg(unix)]
+static UNIX_TRANSPORT_SERVER: OnceLock<Mutex<Option<crate::transport::UnixTransportServer>>> =
+    OnceLock::new();
+
+/// Start a Unix-domain-socket transport server at `path`, for fast,
+/// permission-gated local transfers between apps on the same machine.
+/// Always returns `false` on platforms without Unix domain sockets.
+#[no_mangle]
+pub extern "C" fn voidwarp_transport_start_server_unix(path: *const c_char) -> bool {
+    #[cfg(unix)]
+    {
+        if path.is_null() {
+            return false;
+        }
+        let path_str = unsafe { CStr::from_ptr(path) }.to_string_lossy();
+        let cell = UNIX_TRANSPORT_SERVER.get_or_init(|| Mutex::new(None));
+        let mut guard = cell.lock().unwrap();
+        if guard.is_some() {
+            return true;
+        }
+        match crate::transport::UnixTransportServer::bind(std::path::Path::new(path_str.as_ref()))
+        {
+            Ok(server) => {
+                *guard = Some(server);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        tracing::warn!("Unix domain socket transport is not supported on this platform");
+        false
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn voidwarp_transport_ping(ip_address: *const c_char, port: u16) -> bool {
     if ip_address.is_null() {
@@ -915,6 +1606,956 @@ pub extern "C" fn voidwarp_transport_ping(ip_address: *const c_char, port: u16)
     std::net::TcpStream::connect_timeout(&addr, std::time::Duration::from_secs(2)).is_ok()
 }
 
+// ============================================================================
+// Async transport FFI (crate::transport_async)
+// ============================================================================
+//
+// crate::transport's blocking server/client stay the default above; this is
+// the tokio-native counterpart for a caller that wants many concurrent
+// connections serviced as tasks on one runtime instead of one OS thread per
+// connection. Each function owns a dedicated current-thread runtime the same
+// way the QUIC and VWTP FFI sections do, since this module's API is async
+// and everything else here is synchronous.
+
+use crate::transport_async::{AsyncTransportClient, AsyncTransportServer};
+
+static ASYNC_TRANSPORT_SERVER: OnceLock<Mutex<Option<Arc<Mutex<Option<AsyncTransportServer>>>>>> =
+    OnceLock::new();
+
+fn async_transport_server_cell(
+) -> &'static Mutex<Option<Arc<Mutex<Option<AsyncTransportServer>>>>> {
+    ASYNC_TRANSPORT_SERVER.get_or_init(|| Mutex::new(None))
+}
+
+/// Start `crate::transport_async::AsyncTransportServer` on `port`, on a
+/// dedicated background thread driving its own tokio runtime for as long as
+/// the process runs. A second call while one is already running is a no-op
+/// that returns `true`.
+#[no_mangle]
+pub extern "C" fn voidwarp_async_transport_start_server(port: u16) -> bool {
+    let mut cell = async_transport_server_cell().lock().unwrap();
+    if cell.is_some() {
+        return true;
+    }
+
+    let slot: Arc<Mutex<Option<AsyncTransportServer>>> = Arc::new(Mutex::new(None));
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel::<bool>();
+
+    let slot_for_thread = slot.clone();
+    std::thread::spawn(move || {
+        let runtime = match tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(rt) => rt,
+            Err(e) => {
+                tracing::error!("failed to start async transport runtime: {}", e);
+                let _ = ready_tx.send(false);
+                return;
+            }
+        };
+
+        runtime.block_on(async move {
+            let addr = SocketAddr::new(IpAddr::from([0, 0, 0, 0]), port);
+            match AsyncTransportServer::bind(addr).await {
+                Ok(server) => {
+                    *slot_for_thread.lock().unwrap() = Some(server);
+                    let _ = ready_tx.send(true);
+                    // Keeps this thread's runtime alive (and thus its
+                    // accept task polled) for as long as the process runs;
+                    // the server itself has no shutdown path today.
+                    std::future::pending::<()>().await;
+                }
+                Err(e) => {
+                    tracing::error!("failed to bind async transport server: {}", e);
+                    let _ = ready_tx.send(false);
+                }
+            }
+        });
+    });
+
+    match ready_rx.recv() {
+        Ok(true) => {
+            *cell = Some(slot);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Number of connections the async transport server currently has open, or
+/// -1 if it isn't running.
+#[no_mangle]
+pub extern "C" fn voidwarp_async_transport_active_connections_count() -> i32 {
+    let Some(slot) = async_transport_server_cell().lock().unwrap().clone() else {
+        return -1;
+    };
+    match slot.lock().unwrap().as_ref() {
+        Some(server) => server.active_connections().len() as i32,
+        None => -1,
+    }
+}
+
+/// Ping `ip_address:port` with a real Offer-less Ping/Pong round trip over
+/// `AsyncTransportClient`, rather than `voidwarp_transport_ping`'s bare TCP
+/// connect check.
+#[no_mangle]
+pub extern "C" fn voidwarp_async_transport_ping(ip_address: *const c_char, port: u16) -> bool {
+    if ip_address.is_null() {
+        return false;
+    }
+
+    let ip_str = unsafe { CStr::from_ptr(ip_address) }.to_string_lossy();
+    let ip: std::net::IpAddr = match ip_str.parse() {
+        Ok(ip) => ip,
+        Err(_) => return false,
+    };
+    let addr = std::net::SocketAddr::new(ip, port);
+
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(rt) => rt,
+        Err(_) => return false,
+    };
+
+    runtime.block_on(async move {
+        let mut client = match AsyncTransportClient::connect(addr, Duration::from_secs(2)).await {
+            Ok(client) => client,
+            Err(_) => return false,
+        };
+        client.ping().await.unwrap_or(false)
+    })
+}
+
+// ============================================================================
+// Gossip-based peer discovery FFI
+// ============================================================================
+
+use crate::discovery::gossip::{GossipServer, PeerTable};
+
+static GOSSIP_SERVER: OnceLock<Mutex<Option<GossipServer>>> = OnceLock::new();
+static GOSSIP_STOP: OnceLock<Arc<std::sync::atomic::AtomicBool>> = OnceLock::new();
+
+fn gossip_table() -> Option<PeerTable> {
+    GOSSIP_SERVER
+        .get()?
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|server| server.table())
+}
+
+/// Start the gossip discovery listener on `port`, authenticating with the
+/// handle's device identity, and begin periodically gossiping with every
+/// peer it learns about (see `crate::discovery::gossip`).
+#[no_mangle]
+pub extern "C" fn voidwarp_gossip_start_server(
+    handle: *const VoidWarpHandle,
+    port: u16,
+) -> bool {
+    if handle.is_null() {
+        return false;
+    }
+    let identity = unsafe { &(*handle).identity };
+
+    let cell = GOSSIP_SERVER.get_or_init(|| Mutex::new(None));
+    let mut guard = cell.lock().unwrap();
+    if guard.is_some() {
+        return true;
+    }
+
+    let addr = SocketAddr::new(IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED), port);
+    match GossipServer::bind(addr, identity.device_id.clone(), identity.device_name.clone()) {
+        Ok(server) => {
+            let table = server.table();
+            let stop = GOSSIP_STOP
+                .get_or_init(|| Arc::new(std::sync::atomic::AtomicBool::new(false)))
+                .clone();
+            crate::discovery::gossip::start_gossip_loop(
+                Vec::new(),
+                identity.device_id.clone(),
+                identity.device_name.clone(),
+                table,
+                stop,
+            );
+            *guard = Some(server);
+            true
+        }
+        Err(e) => {
+            tracing::error!("Failed to start gossip server: {}", e);
+            false
+        }
+    }
+}
+
+/// Gossip once with a peer at `ip_address:port`, seeding the shared peer
+/// table with whatever it learns. This is the gossip equivalent of
+/// manually adding a peer: it's how a node first learns about a gossip
+/// network it isn't already part of.
+#[no_mangle]
+pub extern "C" fn voidwarp_gossip_connect(
+    handle: *const VoidWarpHandle,
+    ip_address: *const c_char,
+    port: u16,
+) -> bool {
+    if handle.is_null() || ip_address.is_null() {
+        return false;
+    }
+    let identity = unsafe { &(*handle).identity };
+    let ip_str = unsafe { CStr::from_ptr(ip_address) }.to_string_lossy();
+    let ip: std::net::IpAddr = match ip_str.parse() {
+        Ok(ip) => ip,
+        Err(_) => return false,
+    };
+    let addr = SocketAddr::new(ip, port);
+
+    // Requires the gossip server to already be running (`voidwarp_gossip_start_server`)
+    // since that's what owns the peer table this call feeds into.
+    let Some(table) = gossip_table() else {
+        tracing::warn!("voidwarp_gossip_connect called before voidwarp_gossip_start_server");
+        return false;
+    };
+    matches!(
+        crate::discovery::gossip::gossip_once(addr, &identity.device_id, &identity.device_name, &table),
+        Ok(true)
+    )
+}
+
+/// The gossip peer table, as a `;`-separated list of `device_id|ip|port`
+/// entries. Returns an empty string if the gossip server hasn't been
+/// started. Caller must free the result with `voidwarp_free_string`.
+#[no_mangle]
+pub extern "C" fn voidwarp_discovery_known_peers() -> *mut c_char {
+    let entries = gossip_table()
+        .map(|table| table.snapshot())
+        .unwrap_or_default()
+        .iter()
+        .map(|p| format!("{}|{}|{}", p.device_id, p.addr.ip(), p.addr.port()))
+        .collect::<Vec<_>>()
+        .join(";");
+
+    CString::new(entries).map(|s| s.into_raw()).unwrap_or(ptr::null_mut())
+}
+
+// ============================================================================
+// QUIC File Sender / Receiver FFI
+// ============================================================================
+
+use crate::quic::{QuicFileReceiverServer, QuicFileSender};
+
+/// Opaque handle to a QUIC file sender
+pub struct FfiQuicSender {
+    sender: QuicFileSender,
+}
+
+/// Create a QUIC file sender for the given path
+/// Returns null on error
+#[no_mangle]
+pub extern "C" fn voidwarp_quic_sender_create(path: *const c_char) -> *mut FfiQuicSender {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+
+    let path_str = unsafe { CStr::from_ptr(path) }.to_string_lossy();
+
+    match QuicFileSender::new(&path_str) {
+        Ok(sender) => Box::into_raw(Box::new(FfiQuicSender { sender })),
+        Err(e) => {
+            tracing::error!("Failed to create QUIC sender: {}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Start a QUIC transfer to the target address, authenticating with the
+/// handle's device identity.
+/// Returns: 0=Success, 1=Rejected, 2=ChecksumMismatch, 3=ConnectionFailed,
+/// 4=Timeout, 5=Cancelled, 6=IoError, 7=QuicError
+#[no_mangle]
+pub extern "C" fn voidwarp_quic_sender_start(
+    handle: *const VoidWarpHandle,
+    sender: *const FfiQuicSender,
+    ip_address: *const c_char,
+    port: u16,
+    sender_name: *const c_char,
+) -> i32 {
+    if handle.is_null() || sender.is_null() || ip_address.is_null() || sender_name.is_null() {
+        return 3; // ConnectionFailed
+    }
+
+    let identity = unsafe { &(*handle).identity };
+    let sender_ref = unsafe { &(*sender).sender };
+    let ip_str = unsafe { CStr::from_ptr(ip_address) }.to_string_lossy();
+    let name_str = unsafe { CStr::from_ptr(sender_name) }.to_string_lossy();
+
+    let ip: std::net::IpAddr = match ip_str.parse() {
+        Ok(ip) => ip,
+        Err(_) => return 3, // ConnectionFailed - invalid IP
+    };
+
+    let peer_addr = std::net::SocketAddr::new(ip, port);
+
+    quic_transfer_result_code(&sender_ref.send_to(peer_addr, &name_str, identity))
+}
+
+/// Maps a `TransferResult` to the code documented on
+/// `voidwarp_quic_sender_start`, shared with the batch sender so both
+/// report results the same way.
+fn quic_transfer_result_code(result: &TransferResult) -> i32 {
+    match result {
+        TransferResult::Success => 0,
+        TransferResult::Rejected => 1,
+        TransferResult::ChecksumMismatch => 2,
+        TransferResult::ConnectionFailed(_) => 3,
+        TransferResult::Timeout => 4,
+        TransferResult::Cancelled => 5,
+        TransferResult::IoError(_) => 6,
+        TransferResult::QuicError(_) => 7,
+        TransferResult::AuthenticationFailed => 8,
+        TransferResult::DecryptionFailed => 9,
+        // QuicFileSender has no relay fallback, so this arm is never
+        // actually produced here - kept only to stay exhaustive over the
+        // shared `TransferResult` enum.
+        TransferResult::SuccessViaRelay => 0,
+    }
+}
+
+/// Get transfer progress (0-100)
+#[no_mangle]
+pub extern "C" fn voidwarp_quic_sender_get_progress(sender: *const FfiQuicSender) -> f32 {
+    if sender.is_null() {
+        return 0.0;
+    }
+    unsafe { (*sender).sender.progress() }
+}
+
+/// Cancel the transfer
+#[no_mangle]
+pub extern "C" fn voidwarp_quic_sender_cancel(sender: *const FfiQuicSender) {
+    if !sender.is_null() {
+        unsafe {
+            (*sender).sender.cancel();
+        }
+    }
+}
+
+/// Destroy the sender
+#[no_mangle]
+pub extern "C" fn voidwarp_quic_sender_destroy(sender: *mut FfiQuicSender) {
+    if !sender.is_null() {
+        unsafe {
+            let _ = Box::from_raw(sender);
+        }
+    }
+}
+
+/// Opaque handle to a QUIC batch sender - several files sent concurrently
+/// over one connection, mirroring `FfiQuicSender` but for a file list.
+pub struct FfiQuicBatchSender {
+    sender: crate::quic::QuicBatchSender,
+}
+
+/// Create a QUIC batch sender for `count` files at `paths` (an array of
+/// `count` null-terminated C strings). Returns null on error.
+#[no_mangle]
+pub extern "C" fn voidwarp_quic_batch_sender_create(
+    paths: *const *const c_char,
+    count: usize,
+) -> *mut FfiQuicBatchSender {
+    if paths.is_null() || count == 0 {
+        return ptr::null_mut();
+    }
+
+    let mut owned_paths = Vec::with_capacity(count);
+    for i in 0..count {
+        let path_ptr = unsafe { *paths.add(i) };
+        if path_ptr.is_null() {
+            return ptr::null_mut();
+        }
+        owned_paths.push(unsafe { CStr::from_ptr(path_ptr) }.to_string_lossy().into_owned());
+    }
+
+    match crate::quic::QuicBatchSender::new(&owned_paths) {
+        Ok(sender) => Box::into_raw(Box::new(FfiQuicBatchSender { sender })),
+        Err(e) => {
+            tracing::error!("Failed to create QUIC batch sender: {}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Number of files in the batch
+#[no_mangle]
+pub extern "C" fn voidwarp_quic_batch_sender_file_count(sender: *const FfiQuicBatchSender) -> usize {
+    if sender.is_null() {
+        return 0;
+    }
+    unsafe { (*sender).sender.file_count() }
+}
+
+/// Start the batch transfer to the target address, authenticating with the
+/// handle's device identity. Blocks until every file has either completed
+/// or failed. Returns a comma-separated list of per-file result codes (see
+/// `voidwarp_quic_sender_start` for the code meanings), in the same order
+/// the paths were given to `voidwarp_quic_batch_sender_create`, or null on
+/// a setup error affecting the whole batch. Caller must free the result
+/// with `voidwarp_free_string`.
+#[no_mangle]
+pub extern "C" fn voidwarp_quic_batch_sender_start(
+    handle: *const VoidWarpHandle,
+    sender: *const FfiQuicBatchSender,
+    ip_address: *const c_char,
+    port: u16,
+    sender_name: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() || sender.is_null() || ip_address.is_null() || sender_name.is_null() {
+        return ptr::null_mut();
+    }
+
+    let identity = unsafe { &(*handle).identity };
+    let sender_ref = unsafe { &(*sender).sender };
+    let ip_str = unsafe { CStr::from_ptr(ip_address) }.to_string_lossy();
+    let name_str = unsafe { CStr::from_ptr(sender_name) }.to_string_lossy();
+
+    let ip: std::net::IpAddr = match ip_str.parse() {
+        Ok(ip) => ip,
+        Err(_) => return ptr::null_mut(),
+    };
+    let peer_addr = std::net::SocketAddr::new(ip, port);
+
+    let results = sender_ref.send_to(peer_addr, &name_str, identity);
+    let codes = results
+        .iter()
+        .map(|r| quic_transfer_result_code(r).to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    CString::new(codes).map(|s| s.into_raw()).unwrap_or(ptr::null_mut())
+}
+
+/// Per-file transfer progress, as a `;`-separated list of
+/// `file_name:bytes_sent:file_size` triples, in the same order the paths
+/// were given to `voidwarp_quic_batch_sender_create`. Caller must free the
+/// result with `voidwarp_free_string`.
+#[no_mangle]
+pub extern "C" fn voidwarp_quic_batch_sender_get_progress(
+    sender: *const FfiQuicBatchSender,
+) -> *mut c_char {
+    if sender.is_null() {
+        return ptr::null_mut();
+    }
+    let progress = unsafe { (*sender).sender.progress() };
+    let encoded = progress
+        .iter()
+        .map(|p| format!("{}:{}:{}", p.file_name, p.bytes_sent, p.file_size))
+        .collect::<Vec<_>>()
+        .join(";");
+    CString::new(encoded).map(|s| s.into_raw()).unwrap_or(ptr::null_mut())
+}
+
+/// Cancel every file still in flight in the batch
+#[no_mangle]
+pub extern "C" fn voidwarp_quic_batch_sender_cancel(sender: *const FfiQuicBatchSender) {
+    if !sender.is_null() {
+        unsafe {
+            (*sender).sender.cancel();
+        }
+    }
+}
+
+/// Destroy the batch sender
+#[no_mangle]
+pub extern "C" fn voidwarp_quic_batch_sender_destroy(sender: *mut FfiQuicBatchSender) {
+    if !sender.is_null() {
+        unsafe {
+            let _ = Box::from_raw(sender);
+        }
+    }
+}
+
+/// Opaque handle to a QUIC file receiver
+pub struct FfiQuicReceiver {
+    receiver: QuicFileReceiverServer,
+}
+
+/// Create a QUIC file receiver, authenticating with the handle's device
+/// identity. Returns null on error.
+#[no_mangle]
+pub extern "C" fn voidwarp_quic_receiver_create(
+    handle: *const VoidWarpHandle,
+) -> *mut FfiQuicReceiver {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+    let identity = unsafe { &(*handle).identity };
+
+    match QuicFileReceiverServer::new(identity) {
+        Ok(receiver) => Box::into_raw(Box::new(FfiQuicReceiver { receiver })),
+        Err(e) => {
+            tracing::error!("Failed to create QUIC receiver: {}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Get the UDP port the receiver is bound to
+#[no_mangle]
+pub extern "C" fn voidwarp_quic_receiver_get_port(receiver: *const FfiQuicReceiver) -> u16 {
+    if receiver.is_null() {
+        return 0;
+    }
+    unsafe { (*receiver).receiver.port() }
+}
+
+/// Start listening for an incoming QUIC transfer
+#[no_mangle]
+pub extern "C" fn voidwarp_quic_receiver_start(receiver: *mut FfiQuicReceiver) {
+    if !receiver.is_null() {
+        unsafe {
+            (*receiver).receiver.start();
+        }
+    }
+}
+
+/// Accept the pending QUIC transfer and receive it into `dest_dir`.
+/// Returns the same codes as `voidwarp_quic_sender_start`.
+#[no_mangle]
+pub extern "C" fn voidwarp_quic_receiver_accept(
+    receiver: *mut FfiQuicReceiver,
+    dest_dir: *const c_char,
+) -> i32 {
+    if receiver.is_null() || dest_dir.is_null() {
+        return 6; // IoError
+    }
+
+    let dir_str = unsafe { CStr::from_ptr(dest_dir) }.to_string_lossy();
+    quic_transfer_result_code(&unsafe { (*receiver).receiver.accept_transfer(&dir_str) })
+}
+
+/// Stop listening
+#[no_mangle]
+pub extern "C" fn voidwarp_quic_receiver_stop(receiver: *mut FfiQuicReceiver) {
+    if !receiver.is_null() {
+        unsafe {
+            (*receiver).receiver.stop();
+        }
+    }
+}
+
+/// Destroy the receiver
+#[no_mangle]
+pub extern "C" fn voidwarp_quic_receiver_destroy(receiver: *mut FfiQuicReceiver) {
+    if !receiver.is_null() {
+        unsafe {
+            let _ = Box::from_raw(receiver);
+        }
+    }
+}
+
+// ============================================================================
+// VWTP File Sender / Receiver FFI
+// ============================================================================
+//
+// Mirrors the QUIC FFI section above, but over `vwtp_transfer`'s
+// `TransportManager`-backed path instead of `quinn`.
+
+use crate::vwtp::CongestionAlgorithm;
+use crate::vwtp_transfer::{VwtpFileReceiverServer, VwtpFileSender};
+
+/// Opaque handle to a VWTP file sender
+pub struct FfiVwtpSender {
+    sender: VwtpFileSender,
+}
+
+/// Create a VWTP file sender for the given path. Returns null on error.
+#[no_mangle]
+pub extern "C" fn voidwarp_vwtp_sender_create(path: *const c_char) -> *mut FfiVwtpSender {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+
+    let path_str = unsafe { CStr::from_ptr(path) }.to_string_lossy();
+
+    match VwtpFileSender::new(&path_str) {
+        Ok(sender) => Box::into_raw(Box::new(FfiVwtpSender { sender })),
+        Err(e) => {
+            tracing::error!("Failed to create VWTP sender: {}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Create a VWTP file sender that yields to the peer's other traffic
+/// (LEDBAT congestion control) instead of competing with it for bandwidth -
+/// for a background transfer the caller doesn't want to slow down anything
+/// else on the link. Returns null on error.
+#[no_mangle]
+pub extern "C" fn voidwarp_vwtp_sender_create_background(
+    path: *const c_char,
+) -> *mut FfiVwtpSender {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+
+    let path_str = unsafe { CStr::from_ptr(path) }.to_string_lossy();
+
+    match VwtpFileSender::new_with_congestion(&path_str, CongestionAlgorithm::Ledbat) {
+        Ok(sender) => Box::into_raw(Box::new(FfiVwtpSender { sender })),
+        Err(e) => {
+            tracing::error!("Failed to create background VWTP sender: {}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Create a VWTP file sender that hashes chunks (and builds its Merkle
+/// tree) with `hash_method` instead of the `Md5` default - see
+/// `checksum::HashMethod::from_byte` for the byte encoding. Returns null on
+/// error, including an unrecognized `hash_method`.
+#[no_mangle]
+pub extern "C" fn voidwarp_vwtp_sender_create_with_hash(
+    path: *const c_char,
+    hash_method: u8,
+) -> *mut FfiVwtpSender {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+
+    let path_str = unsafe { CStr::from_ptr(path) }.to_string_lossy();
+
+    let hash_method = match crate::checksum::HashMethod::from_byte(hash_method) {
+        Ok(method) => method,
+        Err(e) => {
+            tracing::error!("Invalid hash method for VWTP sender: {}", e);
+            return ptr::null_mut();
+        }
+    };
+
+    match VwtpFileSender::new(&path_str) {
+        Ok(sender) => Box::into_raw(Box::new(FfiVwtpSender {
+            sender: sender.with_hash_method(hash_method),
+        })),
+        Err(e) => {
+            tracing::error!("Failed to create VWTP sender: {}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Create a VWTP file sender that seals every chunk with
+/// `security::chunk_aead::ChunkAead`, keyed from `passphrase` - see
+/// `vwtp_transfer::VwtpFileSender::with_passphrase`. Returns null on error,
+/// including a passphrase that fails `SecurePinValidator::for_passphrase`.
+#[no_mangle]
+pub extern "C" fn voidwarp_vwtp_sender_create_with_passphrase(
+    path: *const c_char,
+    passphrase: *const c_char,
+) -> *mut FfiVwtpSender {
+    if path.is_null() || passphrase.is_null() {
+        return ptr::null_mut();
+    }
+
+    let path_str = unsafe { CStr::from_ptr(path) }.to_string_lossy();
+    let passphrase_str = unsafe { CStr::from_ptr(passphrase) }.to_string_lossy();
+
+    let sender = match VwtpFileSender::new(&path_str) {
+        Ok(sender) => sender,
+        Err(e) => {
+            tracing::error!("Failed to create VWTP sender: {}", e);
+            return ptr::null_mut();
+        }
+    };
+
+    match sender.with_passphrase(&passphrase_str) {
+        Ok(sender) => Box::into_raw(Box::new(FfiVwtpSender { sender })),
+        Err(e) => {
+            tracing::error!("Rejected VWTP sender passphrase: {}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Start a VWTP transfer to the target address, authenticating with the
+/// handle's device identity. Returns the same codes as
+/// `voidwarp_quic_sender_start`.
+#[no_mangle]
+pub extern "C" fn voidwarp_vwtp_sender_start(
+    handle: *const VoidWarpHandle,
+    sender: *const FfiVwtpSender,
+    ip_address: *const c_char,
+    port: u16,
+    sender_name: *const c_char,
+) -> i32 {
+    if handle.is_null() || sender.is_null() || ip_address.is_null() || sender_name.is_null() {
+        return 3; // ConnectionFailed
+    }
+
+    // DeviceIdentity isn't Clone; round-trip through its PKCS#8 export
+    // instead - TransportManager needs to own an `Arc<DeviceIdentity>`.
+    let identity_bytes = unsafe { (*handle).identity.export() };
+    let identity_name = unsafe { (*handle).identity.device_name.clone() };
+    let identity = match DeviceIdentity::import(&identity_name, &identity_bytes) {
+        Ok(identity) => Arc::new(identity),
+        Err(e) => {
+            tracing::error!("failed to re-import identity for VWTP transfer: {}", e);
+            return 3; // ConnectionFailed
+        }
+    };
+
+    let sender_ref = unsafe { &(*sender).sender };
+    let ip_str = unsafe { CStr::from_ptr(ip_address) }.to_string_lossy();
+    let name_str = unsafe { CStr::from_ptr(sender_name) }.to_string_lossy();
+
+    let ip: std::net::IpAddr = match ip_str.parse() {
+        Ok(ip) => ip,
+        Err(_) => return 3, // ConnectionFailed - invalid IP
+    };
+    let peer_addr = std::net::SocketAddr::new(ip, port);
+
+    quic_transfer_result_code(&sender_ref.send_to(peer_addr, &name_str, identity))
+}
+
+/// Get transfer progress (0-100)
+#[no_mangle]
+pub extern "C" fn voidwarp_vwtp_sender_get_progress(sender: *const FfiVwtpSender) -> f32 {
+    if sender.is_null() {
+        return 0.0;
+    }
+    unsafe { (*sender).sender.progress() }
+}
+
+/// Cancel the transfer
+#[no_mangle]
+pub extern "C" fn voidwarp_vwtp_sender_cancel(sender: *const FfiVwtpSender) {
+    if !sender.is_null() {
+        unsafe {
+            (*sender).sender.cancel();
+        }
+    }
+}
+
+/// Destroy the sender
+#[no_mangle]
+pub extern "C" fn voidwarp_vwtp_sender_destroy(sender: *mut FfiVwtpSender) {
+    if !sender.is_null() {
+        unsafe {
+            let _ = Box::from_raw(sender);
+        }
+    }
+}
+
+/// Opaque handle to a VWTP file receiver
+pub struct FfiVwtpReceiver {
+    receiver: VwtpFileReceiverServer,
+}
+
+/// Create a VWTP file receiver, authenticating with the handle's device
+/// identity, and start listening immediately. Returns null on error.
+#[no_mangle]
+pub extern "C" fn voidwarp_vwtp_receiver_create(
+    handle: *const VoidWarpHandle,
+) -> *mut FfiVwtpReceiver {
+    if handle.is_null() {
+        return ptr::null_mut();
+    }
+
+    let identity_bytes = unsafe { (*handle).identity.export() };
+    let identity_name = unsafe { (*handle).identity.device_name.clone() };
+    let identity = match DeviceIdentity::import(&identity_name, &identity_bytes) {
+        Ok(identity) => Arc::new(identity),
+        Err(e) => {
+            tracing::error!("failed to re-import identity for VWTP receiver: {}", e);
+            return ptr::null_mut();
+        }
+    };
+
+    match VwtpFileReceiverServer::new(identity) {
+        Ok(receiver) => Box::into_raw(Box::new(FfiVwtpReceiver { receiver })),
+        Err(e) => {
+            tracing::error!("Failed to create VWTP receiver: {}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Get the UDP port the receiver is bound to
+#[no_mangle]
+pub extern "C" fn voidwarp_vwtp_receiver_get_port(receiver: *const FfiVwtpReceiver) -> u16 {
+    if receiver.is_null() {
+        return 0;
+    }
+    unsafe { (*receiver).receiver.port() }
+}
+
+/// Accept the pending VWTP transfer and receive it into `dest_dir`. Returns
+/// the same codes as `voidwarp_quic_sender_start`.
+#[no_mangle]
+pub extern "C" fn voidwarp_vwtp_receiver_accept(
+    receiver: *mut FfiVwtpReceiver,
+    dest_dir: *const c_char,
+) -> i32 {
+    if receiver.is_null() || dest_dir.is_null() {
+        return 6; // IoError
+    }
+
+    let dir_str = unsafe { CStr::from_ptr(dest_dir) }.to_string_lossy();
+    quic_transfer_result_code(&unsafe { (*receiver).receiver.accept_transfer(&dir_str) })
+}
+
+/// Accept a pending VWTP transfer that negotiated
+/// `security::chunk_aead::ChunkAead`, supplying the passphrase the sender
+/// used. Returns the same codes as `voidwarp_quic_sender_start`.
+#[no_mangle]
+pub extern "C" fn voidwarp_vwtp_receiver_accept_with_passphrase(
+    receiver: *mut FfiVwtpReceiver,
+    dest_dir: *const c_char,
+    passphrase: *const c_char,
+) -> i32 {
+    if receiver.is_null() || dest_dir.is_null() || passphrase.is_null() {
+        return 6; // IoError
+    }
+
+    let dir_str = unsafe { CStr::from_ptr(dest_dir) }.to_string_lossy();
+    let passphrase_str = unsafe { CStr::from_ptr(passphrase) }.to_string_lossy();
+    quic_transfer_result_code(&unsafe {
+        (*receiver)
+            .receiver
+            .accept_transfer_with_passphrase(&dir_str, &passphrase_str)
+    })
+}
+
+/// Destroy the receiver
+#[no_mangle]
+pub extern "C" fn voidwarp_vwtp_receiver_destroy(receiver: *mut FfiVwtpReceiver) {
+    if !receiver.is_null() {
+        unsafe {
+            let _ = Box::from_raw(receiver);
+        }
+    }
+}
+
+// ============================================================================
+// Resumable-transfer bitmap FFI
+// ============================================================================
+//
+// A UI showing "X of Y chunks left" for a paused/dropped transfer, without
+// a live receiver to ask, reads the same `.vwpart` sidecar
+// `receiver::FileReceiverServer::receive_windowed` itself resumes from -
+// see `io_utils::{load_chunk_bitmap, missing_chunks_from_bitmap}`.
+
+/// Missing chunk indices for `save_path`'s in-progress transfer, as a
+/// `,`-separated list, read straight from its `.vwpart` sidecar. Returns an
+/// empty string if there's no sidecar, it doesn't match
+/// `file_checksum`/`chunk_size`/`file_size`, or every chunk has already
+/// arrived. Caller must free the result with `voidwarp_free_string`.
+#[no_mangle]
+pub extern "C" fn voidwarp_resume_missing_chunks(
+    save_path: *const c_char,
+    file_checksum: *const c_char,
+    chunk_size: u32,
+    file_size: u64,
+) -> *mut c_char {
+    if save_path.is_null() || file_checksum.is_null() {
+        return CString::new("").unwrap().into_raw();
+    }
+
+    let path_str = unsafe { CStr::from_ptr(save_path) }.to_string_lossy();
+    let checksum_str = unsafe { CStr::from_ptr(file_checksum) }.to_string_lossy();
+    let total_chunks = file_size.div_ceil(chunk_size.max(1) as u64);
+
+    let entries = match crate::io_utils::load_chunk_bitmap(
+        Path::new(path_str.as_ref()),
+        &checksum_str,
+        chunk_size,
+        file_size,
+    ) {
+        Some(bitmap) => crate::io_utils::missing_chunks_from_bitmap(&bitmap, total_chunks)
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+        None => String::new(),
+    };
+
+    CString::new(entries).map(|s| s.into_raw()).unwrap_or(ptr::null_mut())
+}
+
+// ===== Beacon Peer Exchange FFI =====
+//
+// Out-of-band pairing for networks where mDNS is blocked entirely: one
+// side generates a short encrypted token carrying its reachable
+// addresses, the other pastes/scans it in. See `discovery::beacon`.
+
+/// Generate a shareable beacon token advertising this device's reachable
+/// addresses on `port`, encrypted with `pairing_code`. Caller must free
+/// the result with `voidwarp_free_string`. Returns null on error.
+#[no_mangle]
+pub extern "C" fn voidwarp_generate_beacon(
+    handle: *const VoidWarpHandle,
+    port: u16,
+    pairing_code: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() || pairing_code.is_null() {
+        return ptr::null_mut();
+    }
+
+    let handle = unsafe { &*handle };
+    let pairing_code = unsafe { CStr::from_ptr(pairing_code) }.to_string_lossy();
+
+    let addresses: Vec<SocketAddr> = local_ip_address::list_afinet_netifas()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(_, ip)| !ip.is_loopback())
+        .map(|(_, ip)| SocketAddr::new(ip, port))
+        .collect();
+
+    let token = handle.identity.device_id.clone();
+    let beacon =
+        crate::discovery::beacon::generate_beacon(&token, &addresses, &pairing_code);
+
+    match CString::new(beacon) {
+        Ok(s) => s.into_raw(),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Decode a beacon token produced by `voidwarp_generate_beacon`, add every
+/// advertised address as a manual peer, and return 0 on success. Returns
+/// -1 for null/invalid arguments, -2 if the handle has no active
+/// discovery manager, and -3 if the beacon is malformed, undecryptable,
+/// or expired.
+#[no_mangle]
+pub extern "C" fn voidwarp_parse_beacon(
+    handle: *mut VoidWarpHandle,
+    pairing_code: *const c_char,
+    beacon: *const c_char,
+) -> i32 {
+    if handle.is_null() || pairing_code.is_null() || beacon.is_null() {
+        return -1;
+    }
+
+    let handle = unsafe { &mut *handle };
+    let discovery = match &handle.discovery {
+        Some(d) => d,
+        None => return -2,
+    };
+
+    let pairing_code = unsafe { CStr::from_ptr(pairing_code) }.to_string_lossy();
+    let beacon = unsafe { CStr::from_ptr(beacon) }.to_string_lossy();
+
+    match discovery.ingest_beacon(&beacon, &pairing_code) {
+        Ok(device_id) => {
+            tracing::info!("Ingested beacon for peer: {}", device_id);
+            0
+        }
+        Err(e) => {
+            tracing::warn!("Failed to parse beacon: {}", e);
+            -3
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
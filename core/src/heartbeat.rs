@@ -1,12 +1,30 @@
 //! Heartbeat Module
 //!
 //! UDP-based heartbeat mechanism for connection stability detection.
+//!
+//! Ping and pong packets are signed with the sender's [`DeviceIdentity`] and
+//! verified against the *expected* peer's `device_id`, so a third host on
+//! the LAN can't keep [`HeartbeatManager::is_peer_alive`] artificially true
+//! by injecting forged pongs (or get a spoofed ping answered by
+//! [`HeartbeatResponder`]). A literal 8-byte signature field, as a
+//! byte-for-byte reading of the original request would have it, can't be
+//! made to verify anything - Ed25519 signatures are 64 bytes and don't
+//! truncate - so the packets below carry the full signature instead.
+//!
+//! Every ping also carries a monotonically increasing sequence number,
+//! which the pong echoes back alongside the timestamp. That lets
+//! `HeartbeatManager` turn the echo into real link-quality telemetry: an
+//! RTT sample per pong, fed into a TCP-style smoothed RTT/variance
+//! estimate, and a loss estimate from how many sent sequence numbers were
+//! never echoed back.
 
 use std::net::{SocketAddr, UdpSocket};
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::security::crypto::DeviceIdentity;
 
 /// Magic bytes for VoidWarp heartbeat packets
 const HEARTBEAT_MAGIC: [u8; 2] = [0x56, 0x57]; // "VW"
@@ -15,9 +33,27 @@ const HEARTBEAT_MAGIC: [u8; 2] = [0x56, 0x57]; // "VW"
 const PACKET_PING: u8 = 0x01;
 const PACKET_PONG: u8 = 0x02;
 
-/// Default timeout multiplier (miss this many pings = disconnected)
+/// Length of a detached Ed25519 signature.
+const SIGNATURE_LEN: usize = 64;
+
+/// Length of the signed prefix: magic(2) + type(1) + timestamp(8) + seq(4).
+const SIGNED_PREFIX_LEN: usize = 2 + 1 + 8 + 4;
+
+/// Full packet length: signed prefix + signature(64).
+const HEARTBEAT_PACKET_LEN: usize = SIGNED_PREFIX_LEN + SIGNATURE_LEN;
+
+/// Default timeout multiplier (miss this many pings = disconnected), used
+/// as a fallback before a smoothed RTT estimate exists.
 const TIMEOUT_MULTIPLIER: u64 = 3;
 
+/// EWMA gain for smoothed RTT (TCP's α = 1/8).
+const SRTT_SHIFT: u64 = 3;
+/// EWMA gain for RTT variance (TCP's β = 1/4).
+const RTTVAR_SHIFT: u64 = 2;
+/// Multiplier on `rttvar` added to `srtt` to derive an adaptive timeout
+/// (TCP uses the same `srtt + 4*rttvar` shape for its RTO).
+const RTO_RTTVAR_MULTIPLIER: u64 = 4;
+
 /// Heartbeat manager for maintaining connection status
 pub struct HeartbeatManager {
     socket: Option<UdpSocket>,
@@ -25,6 +61,13 @@ pub struct HeartbeatManager {
     last_pong: Arc<AtomicU64>,
     interval_ms: u64,
     peer_addr: Option<SocketAddr>,
+    next_seq: Arc<AtomicU32>,
+    sent_count: Arc<AtomicU64>,
+    acked_count: Arc<AtomicU64>,
+    /// Smoothed RTT in milliseconds, 0 until the first sample arrives.
+    srtt_ms: Arc<AtomicU64>,
+    /// RTT variance in milliseconds, used to derive the adaptive timeout.
+    rttvar_ms: Arc<AtomicU64>,
 }
 
 impl HeartbeatManager {
@@ -36,6 +79,11 @@ impl HeartbeatManager {
             last_pong: Arc::new(AtomicU64::new(0)),
             interval_ms: 5000, // 5 seconds default
             peer_addr: None,
+            next_seq: Arc::new(AtomicU32::new(0)),
+            sent_count: Arc::new(AtomicU64::new(0)),
+            acked_count: Arc::new(AtomicU64::new(0)),
+            srtt_ms: Arc::new(AtomicU64::new(0)),
+            rttvar_ms: Arc::new(AtomicU64::new(0)),
         })
     }
 
@@ -44,8 +92,15 @@ impl HeartbeatManager {
         self.interval_ms = ms;
     }
 
-    /// Start sending heartbeats to a peer
-    pub fn start(&mut self, peer_addr: SocketAddr) -> std::io::Result<()> {
+    /// Start sending heartbeats to `peer_addr`, signed with `identity` and
+    /// verified against `peer_device_id` (the identity that peer proved
+    /// ownership of during pairing/handshake).
+    pub fn start(
+        &mut self,
+        peer_addr: SocketAddr,
+        identity: &DeviceIdentity,
+        peer_device_id: String,
+    ) -> std::io::Result<()> {
         if self.running.load(Ordering::SeqCst) {
             return Ok(());
         }
@@ -53,7 +108,7 @@ impl HeartbeatManager {
         // Bind to any available port
         let socket = UdpSocket::bind("0.0.0.0:0")?;
         socket.set_nonblocking(true)?;
-        
+
         let socket_clone = socket.try_clone()?;
         self.socket = Some(socket);
         self.peer_addr = Some(peer_addr);
@@ -62,29 +117,62 @@ impl HeartbeatManager {
 
         let running = self.running.clone();
         let last_pong = self.last_pong.clone();
+        let next_seq = self.next_seq.clone();
+        let sent_count = self.sent_count.clone();
+        let acked_count = self.acked_count.clone();
+        let srtt_ms = self.srtt_ms.clone();
+        let rttvar_ms = self.rttvar_ms.clone();
         let interval = self.interval_ms;
 
+        // `DeviceIdentity` isn't `Clone`/`Send`; re-derive it in the sender
+        // thread from the exported PKCS#8 document instead, matching how
+        // `sender`/`discovery::broadcast` hand identities to background
+        // threads elsewhere in this crate.
+        let pkcs8 = identity.export();
+        let device_name = identity.device_name.clone();
+
         // Sender thread
         thread::spawn(move || {
+            let identity = match DeviceIdentity::import(&device_name, &pkcs8) {
+                Ok(identity) => identity,
+                Err(e) => {
+                    tracing::error!("heartbeat sender: failed to re-import identity: {}", e);
+                    return;
+                }
+            };
+
             tracing::info!("Heartbeat sender started for {}", peer_addr);
-            
+
             while running.load(Ordering::SeqCst) {
                 // Send ping
-                let ping = create_ping_packet();
+                let seq = next_seq.fetch_add(1, Ordering::SeqCst);
+                let sent_at = current_timestamp_ms();
+                let ping = create_ping_packet(&identity, seq, sent_at);
+                sent_count.fetch_add(1, Ordering::SeqCst);
                 if let Err(e) = socket_clone.send_to(&ping, peer_addr) {
                     tracing::warn!("Failed to send ping: {}", e);
                 }
 
                 // Check for pong responses
-                let mut buf = [0u8; 16];
+                let mut buf = [0u8; HEARTBEAT_PACKET_LEN];
                 match socket_clone.recv_from(&mut buf) {
-                    Ok((len, from)) if len >= 11 && from == peer_addr => {
-                        if buf[0] == HEARTBEAT_MAGIC[0] 
-                            && buf[1] == HEARTBEAT_MAGIC[1] 
-                            && buf[2] == PACKET_PONG 
+                    Ok((len, from)) if from == peer_addr => {
+                        if let Some(echoed) =
+                            verify_heartbeat_packet(&buf[..len], PACKET_PONG, &peer_device_id)
                         {
-                            last_pong.store(current_timestamp_ms(), Ordering::SeqCst);
-                            tracing::trace!("Received pong from {}", from);
+                            let now = current_timestamp_ms();
+                            let rtt = now.saturating_sub(echoed.timestamp_ms);
+                            update_rtt_estimate(&srtt_ms, &rttvar_ms, rtt);
+                            acked_count.fetch_add(1, Ordering::SeqCst);
+                            last_pong.store(now, Ordering::SeqCst);
+                            tracing::trace!(
+                                "Received authenticated pong from {} (seq={}, rtt={}ms)",
+                                from,
+                                echoed.seq,
+                                rtt
+                            );
+                        } else {
+                            tracing::warn!("Dropped unauthenticated/forged pong from {}", from);
                         }
                     }
                     _ => {}
@@ -92,7 +180,7 @@ impl HeartbeatManager {
 
                 thread::sleep(Duration::from_millis(interval));
             }
-            
+
             tracing::info!("Heartbeat sender stopped");
         });
 
@@ -106,7 +194,9 @@ impl HeartbeatManager {
         self.peer_addr = None;
     }
 
-    /// Check if peer is still alive (has responded within timeout)
+    /// Check if peer is still alive (has responded within an adaptive
+    /// timeout once RTT samples exist, falling back to a fixed multiple of
+    /// the ping interval before that).
     pub fn is_peer_alive(&self) -> bool {
         if !self.running.load(Ordering::SeqCst) {
             return false;
@@ -114,11 +204,23 @@ impl HeartbeatManager {
 
         let last = self.last_pong.load(Ordering::SeqCst);
         let now = current_timestamp_ms();
-        let timeout = self.interval_ms * TIMEOUT_MULTIPLIER;
+        let timeout = self.adaptive_timeout_ms();
 
         now - last < timeout
     }
 
+    /// `srtt + 4*rttvar`, the same shape TCP uses for its retransmission
+    /// timeout, falling back to `interval_ms * TIMEOUT_MULTIPLIER` until
+    /// the first RTT sample arrives.
+    fn adaptive_timeout_ms(&self) -> u64 {
+        let srtt = self.srtt_ms.load(Ordering::SeqCst);
+        if srtt == 0 {
+            return self.interval_ms * TIMEOUT_MULTIPLIER;
+        }
+        let rttvar = self.rttvar_ms.load(Ordering::SeqCst);
+        srtt + RTO_RTTVAR_MULTIPLIER * rttvar
+    }
+
     /// Get time since last pong in milliseconds
     pub fn time_since_last_pong(&self) -> u64 {
         let last = self.last_pong.load(Ordering::SeqCst);
@@ -129,6 +231,36 @@ impl HeartbeatManager {
     pub fn is_running(&self) -> bool {
         self.running.load(Ordering::SeqCst)
     }
+
+    /// Smoothed round-trip time in milliseconds, or `None` before the first
+    /// pong has been received.
+    pub fn smoothed_rtt_ms(&self) -> Option<u64> {
+        match self.srtt_ms.load(Ordering::SeqCst) {
+            0 => None,
+            srtt => Some(srtt),
+        }
+    }
+
+    /// RTT variance in milliseconds, or `None` before the first pong has
+    /// been received.
+    pub fn rtt_variance_ms(&self) -> Option<u64> {
+        if self.srtt_ms.load(Ordering::SeqCst) == 0 {
+            return None;
+        }
+        Some(self.rttvar_ms.load(Ordering::SeqCst))
+    }
+
+    /// Fraction of sent pings (0.0-1.0) that never got an authenticated
+    /// pong back, computed from the gap between sent and acked sequence
+    /// counts rather than a binary alive/dead check.
+    pub fn packet_loss_estimate(&self) -> f64 {
+        let sent = self.sent_count.load(Ordering::SeqCst);
+        if sent == 0 {
+            return 0.0;
+        }
+        let acked = self.acked_count.load(Ordering::SeqCst);
+        1.0 - (acked.min(sent) as f64 / sent as f64)
+    }
 }
 
 impl Drop for HeartbeatManager {
@@ -163,8 +295,9 @@ impl HeartbeatResponder {
         self.port
     }
 
-    /// Start responding to pings
-    pub fn start(&self) -> std::io::Result<()> {
+    /// Start responding to pings, signing pongs with `identity` and only
+    /// answering pings verified against `peer_device_id`.
+    pub fn start(&self, identity: &DeviceIdentity, peer_device_id: String) -> std::io::Result<()> {
         if self.running.load(Ordering::SeqCst) {
             return Ok(());
         }
@@ -173,22 +306,37 @@ impl HeartbeatResponder {
         let running = self.running.clone();
         running.store(true, Ordering::SeqCst);
 
+        let pkcs8 = identity.export();
+        let device_name = identity.device_name.clone();
+
         thread::spawn(move || {
+            let identity = match DeviceIdentity::import(&device_name, &pkcs8) {
+                Ok(identity) => identity,
+                Err(e) => {
+                    tracing::error!("heartbeat responder: failed to re-import identity: {}", e);
+                    return;
+                }
+            };
+
             tracing::info!("Heartbeat responder started");
-            let mut buf = [0u8; 16];
+            let mut buf = [0u8; HEARTBEAT_PACKET_LEN];
 
             while running.load(Ordering::SeqCst) {
                 match socket.recv_from(&mut buf) {
-                    Ok((len, from)) if len >= 11 => {
-                        if buf[0] == HEARTBEAT_MAGIC[0] 
-                            && buf[1] == HEARTBEAT_MAGIC[1] 
-                            && buf[2] == PACKET_PING 
+                    Ok((len, from)) => {
+                        if let Some(echoed) =
+                            verify_heartbeat_packet(&buf[..len], PACKET_PING, &peer_device_id)
                         {
-                            // Extract timestamp and send pong
-                            let pong = create_pong_packet(&buf[3..11]);
+                            let pong = create_pong_packet(
+                                &identity,
+                                echoed.seq,
+                                echoed.timestamp_ms,
+                            );
                             if let Err(e) = socket.send_to(&pong, from) {
                                 tracing::warn!("Failed to send pong to {}: {}", from, e);
                             }
+                        } else {
+                            tracing::debug!("Dropped unauthenticated/forged ping from {}", from);
                         }
                     }
                     Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
@@ -219,25 +367,113 @@ fn current_timestamp_ms() -> u64 {
         .as_millis() as u64
 }
 
-fn create_ping_packet() -> [u8; 11] {
-    let mut packet = [0u8; 11];
-    packet[0] = HEARTBEAT_MAGIC[0];
-    packet[1] = HEARTBEAT_MAGIC[1];
-    packet[2] = PACKET_PING;
-    let ts = current_timestamp_ms().to_be_bytes();
-    packet[3..11].copy_from_slice(&ts);
-    packet
+/// Jacobson/Karels-style integer EWMA update, the same approximation TCP
+/// uses to avoid floating point in the RTT estimator: `srtt += (rtt -
+/// srtt) / 8`, `rttvar += (|rtt - srtt| - rttvar) / 4`, computed from the
+/// *old* srtt so the two updates use consistent inputs.
+fn update_rtt_estimate(srtt_ms: &AtomicU64, rttvar_ms: &AtomicU64, rtt: u64) {
+    let old_srtt = srtt_ms.load(Ordering::SeqCst);
+    if old_srtt == 0 {
+        // First sample: seed srtt with the measurement and rttvar with
+        // half of it, as RFC 6298 recommends.
+        srtt_ms.store(rtt, Ordering::SeqCst);
+        rttvar_ms.store(rtt / 2, Ordering::SeqCst);
+        return;
+    }
+
+    // `new_rttvar = old_rttvar + (delta - old_rttvar) / 4` in real (signed)
+    // arithmetic; written as an if/else since these are unsigned counters.
+    let delta = rtt.abs_diff(old_srtt);
+    let old_rttvar = rttvar_ms.load(Ordering::SeqCst);
+    let new_rttvar = if delta >= old_rttvar {
+        old_rttvar + (delta - old_rttvar) / (1 << RTTVAR_SHIFT)
+    } else {
+        old_rttvar - (old_rttvar - delta) / (1 << RTTVAR_SHIFT)
+    };
+    rttvar_ms.store(new_rttvar, Ordering::SeqCst);
+
+    let new_srtt = if rtt >= old_srtt {
+        old_srtt + (rtt - old_srtt) / (1 << SRTT_SHIFT)
+    } else {
+        old_srtt - (old_srtt - rtt) / (1 << SRTT_SHIFT)
+    };
+    srtt_ms.store(new_srtt, Ordering::SeqCst);
 }
 
-fn create_pong_packet(timestamp: &[u8]) -> [u8; 11] {
-    let mut packet = [0u8; 11];
-    packet[0] = HEARTBEAT_MAGIC[0];
-    packet[1] = HEARTBEAT_MAGIC[1];
-    packet[2] = PACKET_PONG;
-    packet[3..11].copy_from_slice(&timestamp[..8]);
+/// The portion of a heartbeat packet that gets signed: `magic || type ||
+/// timestamp || seq`.
+fn signed_heartbeat_message(packet_type: u8, timestamp_ms: u64, seq: u32) -> [u8; SIGNED_PREFIX_LEN] {
+    let mut msg = [0u8; SIGNED_PREFIX_LEN];
+    msg[0] = HEARTBEAT_MAGIC[0];
+    msg[1] = HEARTBEAT_MAGIC[1];
+    msg[2] = packet_type;
+    msg[3..11].copy_from_slice(&timestamp_ms.to_be_bytes());
+    msg[11..15].copy_from_slice(&seq.to_be_bytes());
+    msg
+}
+
+fn create_ping_packet(
+    identity: &DeviceIdentity,
+    seq: u32,
+    timestamp_ms: u64,
+) -> [u8; HEARTBEAT_PACKET_LEN] {
+    sign_heartbeat_packet(identity, PACKET_PING, timestamp_ms, seq)
+}
+
+fn create_pong_packet(
+    identity: &DeviceIdentity,
+    echoed_seq: u32,
+    echoed_timestamp_ms: u64,
+) -> [u8; HEARTBEAT_PACKET_LEN] {
+    sign_heartbeat_packet(identity, PACKET_PONG, echoed_timestamp_ms, echoed_seq)
+}
+
+fn sign_heartbeat_packet(
+    identity: &DeviceIdentity,
+    packet_type: u8,
+    timestamp_ms: u64,
+    seq: u32,
+) -> [u8; HEARTBEAT_PACKET_LEN] {
+    let msg = signed_heartbeat_message(packet_type, timestamp_ms, seq);
+    let sig = identity.sign(&msg);
+
+    let mut packet = [0u8; HEARTBEAT_PACKET_LEN];
+    packet[..SIGNED_PREFIX_LEN].copy_from_slice(&msg);
+    packet[SIGNED_PREFIX_LEN..].copy_from_slice(sig.as_ref());
     packet
 }
 
+/// A verified ping/pong's timestamp and sequence number.
+struct EchoedFields {
+    timestamp_ms: u64,
+    seq: u32,
+}
+
+/// Verify a received packet is `expected_type`, well-formed, and
+/// authentically signed by `peer_device_id`; on success, return the
+/// timestamp and sequence number it carried.
+fn verify_heartbeat_packet(
+    buf: &[u8],
+    expected_type: u8,
+    peer_device_id: &str,
+) -> Option<EchoedFields> {
+    if buf.len() < HEARTBEAT_PACKET_LEN {
+        return None;
+    }
+    if buf[0] != HEARTBEAT_MAGIC[0] || buf[1] != HEARTBEAT_MAGIC[1] || buf[2] != expected_type {
+        return None;
+    }
+    let msg = &buf[..SIGNED_PREFIX_LEN];
+    let sig = &buf[SIGNED_PREFIX_LEN..HEARTBEAT_PACKET_LEN];
+    if DeviceIdentity::verify(peer_device_id, msg, sig).is_err() {
+        return None;
+    }
+
+    let timestamp_ms = u64::from_be_bytes(buf[3..11].try_into().ok()?);
+    let seq = u32::from_be_bytes(buf[11..15].try_into().ok()?);
+    Some(EchoedFields { timestamp_ms, seq })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -246,13 +482,69 @@ mod tests {
     fn test_heartbeat_creation() {
         let hb = HeartbeatManager::new().unwrap();
         assert!(!hb.is_running());
+        assert_eq!(hb.smoothed_rtt_ms(), None);
+        assert_eq!(hb.packet_loss_estimate(), 0.0);
     }
 
     #[test]
-    fn test_ping_packet() {
-        let ping = create_ping_packet();
+    fn test_ping_packet_signed_by_self_verifies() {
+        let identity = DeviceIdentity::generate("alice's phone");
+        let ping = create_ping_packet(&identity, 7, 1_000);
         assert_eq!(ping[0], HEARTBEAT_MAGIC[0]);
         assert_eq!(ping[1], HEARTBEAT_MAGIC[1]);
         assert_eq!(ping[2], PACKET_PING);
+
+        let echoed = verify_heartbeat_packet(&ping, PACKET_PING, &identity.device_id)
+            .expect("self-signed ping should verify");
+        assert_eq!(echoed.seq, 7);
+        assert_eq!(echoed.timestamp_ms, 1_000);
+    }
+
+    #[test]
+    fn test_pong_packet_echoes_seq_and_timestamp_and_verifies() {
+        let identity = DeviceIdentity::generate("bob's laptop");
+        let ping = create_ping_packet(&identity, 3, 5_000);
+        let ping_echo = verify_heartbeat_packet(&ping, PACKET_PING, &identity.device_id).unwrap();
+        let pong = create_pong_packet(&identity, ping_echo.seq, ping_echo.timestamp_ms);
+
+        let pong_echo = verify_heartbeat_packet(&pong, PACKET_PONG, &identity.device_id)
+            .expect("self-signed pong should verify");
+        assert_eq!(pong_echo.seq, 3);
+        assert_eq!(pong_echo.timestamp_ms, 5_000);
+    }
+
+    #[test]
+    fn test_packet_from_a_different_key_is_rejected() {
+        let identity = DeviceIdentity::generate("carol's tablet");
+        let impostor = DeviceIdentity::generate("mallory");
+        let forged = create_ping_packet(&impostor, 0, 0);
+
+        assert!(verify_heartbeat_packet(&forged, PACKET_PING, &identity.device_id).is_none());
+    }
+
+    #[test]
+    fn test_rtt_estimate_seeds_then_smooths_towards_new_samples() {
+        let srtt = AtomicU64::new(0);
+        let rttvar = AtomicU64::new(0);
+
+        update_rtt_estimate(&srtt, &rttvar, 100);
+        assert_eq!(srtt.load(Ordering::SeqCst), 100);
+        assert_eq!(rttvar.load(Ordering::SeqCst), 50);
+
+        update_rtt_estimate(&srtt, &rttvar, 100);
+        assert_eq!(srtt.load(Ordering::SeqCst), 100);
+
+        update_rtt_estimate(&srtt, &rttvar, 180);
+        // srtt should move towards 180 but not jump all the way there.
+        let new_srtt = srtt.load(Ordering::SeqCst);
+        assert!(new_srtt > 100 && new_srtt < 180);
+    }
+
+    #[test]
+    fn test_packet_loss_estimate_reflects_missed_acks() {
+        let hb = HeartbeatManager::new().unwrap();
+        hb.sent_count.store(4, Ordering::SeqCst);
+        hb.acked_count.store(3, Ordering::SeqCst);
+        assert!((hb.packet_loss_estimate() - 0.25).abs() < f64::EPSILON);
     }
 }
@@ -0,0 +1,408 @@
+//! ICE-style NAT traversal: candidate gathering (host + STUN
+//! server-reflexive addresses) and simultaneous-open TCP connectivity
+//! checks.
+//!
+//! `voidwarp_tcp_sender_test_link`/`voidwarp_transport_ping` only ever try
+//! a direct `TcpStream::connect`, which fails whenever both peers sit
+//! behind a NAT with no port forwarding. This module gives the two sides
+//! something to exchange out of band (alongside the pairing code) - a
+//! small list of [`Candidate`]s - and races simultaneous-open connect
+//! attempts across every local×remote pair so whichever route actually
+//! works wins, without requiring either side to configure their router.
+//! Candidates are priority-ordered with the host candidate first, so a
+//! same-LAN transfer is never worse off than a plain direct connect.
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use local_ip_address::list_afinet_netifas;
+use socket2::{Domain, Protocol, Socket, Type};
+use thiserror::Error;
+
+/// RFC 5389 magic cookie, present in every STUN message header.
+const STUN_MAGIC_COOKIE: u32 = 0x2112_A442;
+const STUN_BINDING_REQUEST: u16 = 0x0001;
+const STUN_BINDING_RESPONSE: u16 = 0x0101;
+const ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+
+/// How long to wait for a STUN server to answer a Binding Request.
+const STUN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long each simultaneous-open connectivity check gets before moving
+/// on to the next candidate pair.
+const CONNECTIVITY_CHECK_TIMEOUT: Duration = Duration::from_millis(800);
+
+/// Errors from candidate gathering or connectivity checking.
+#[derive(Error, Debug)]
+pub enum IceError {
+    #[error("STUN request failed: {0}")]
+    StunRequestFailed(String),
+    #[error("STUN response was malformed or missing a mapped address")]
+    StunResponseMalformed,
+    #[error("no local network interfaces found")]
+    NoLocalAddresses,
+    #[error("no candidate pair established a connection")]
+    NoConnectivity,
+}
+
+/// Where a candidate address came from, used to rank which pair to try
+/// first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandidateType {
+    /// This device's own address on a local interface.
+    Host,
+    /// This device's address as seen by a STUN server - the public side
+    /// of its NAT mapping.
+    ServerReflexive,
+}
+
+impl CandidateType {
+    /// ICE (RFC 8445 §5.1.2.1) type preference - higher tries first.
+    fn type_preference(self) -> u32 {
+        match self {
+            CandidateType::Host => 126,
+            CandidateType::ServerReflexive => 100,
+        }
+    }
+
+    fn encode(self) -> char {
+        match self {
+            CandidateType::Host => 'h',
+            CandidateType::ServerReflexive => 's',
+        }
+    }
+
+    fn decode(c: char) -> Option<Self> {
+        match c {
+            'h' => Some(CandidateType::Host),
+            's' => Some(CandidateType::ServerReflexive),
+            _ => None,
+        }
+    }
+}
+
+/// A single transport address this device might be reachable at, along
+/// with how strongly it should be preferred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Candidate {
+    pub addr: SocketAddr,
+    pub candidate_type: CandidateType,
+    pub priority: u32,
+}
+
+impl Candidate {
+    fn new(addr: SocketAddr, candidate_type: CandidateType) -> Self {
+        // Standard ICE priority formula, single component (component id 1
+        // - this is one TCP stream, not RTP+RTCP) and a fixed local
+        // preference since we don't juggle multiple host addresses of
+        // the same type:
+        //   priority = (2^24) * type_pref + (2^8) * local_pref + (256 - component_id)
+        let priority =
+            (candidate_type.type_preference() << 24) | (65535u32 << 8) | (256 - 1);
+        Candidate {
+            addr,
+            candidate_type,
+            priority,
+        }
+    }
+
+    /// Encode as `addr:h` or `addr:s`, for exchanging out of band
+    /// alongside the pairing code.
+    pub fn encode(&self) -> String {
+        format!("{}:{}", self.addr, self.candidate_type.encode())
+    }
+
+    pub fn decode(s: &str) -> Option<Self> {
+        let (addr_part, type_part) = s.rsplit_once(':')?;
+        let addr: SocketAddr = addr_part.parse().ok()?;
+        let candidate_type = CandidateType::decode(type_part.chars().next()?)?;
+        Some(Candidate::new(addr, candidate_type))
+    }
+}
+
+/// Encode a candidate list as a comma-separated string.
+pub fn encode_candidates(candidates: &[Candidate]) -> String {
+    candidates
+        .iter()
+        .map(Candidate::encode)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Decode a comma-separated candidate list, silently dropping any entries
+/// that fail to parse (a peer running a newer/older candidate format
+/// shouldn't take down the whole exchange).
+pub fn decode_candidates(s: &str) -> Vec<Candidate> {
+    s.split(',')
+        .filter(|part| !part.is_empty())
+        .filter_map(Candidate::decode)
+        .collect()
+}
+
+/// Gather this device's host candidates (one per non-loopback IPv4
+/// interface) plus, if `stun_server` is given, a server-reflexive
+/// candidate obtained from a STUN Binding Request sent from `local_port`.
+/// Candidates are returned sorted highest-priority first.
+pub fn gather_candidates(
+    local_port: u16,
+    stun_server: Option<SocketAddr>,
+) -> Result<Vec<Candidate>, IceError> {
+    let mut candidates = Vec::new();
+
+    let interfaces =
+        list_afinet_netifas().map_err(|e| IceError::StunRequestFailed(e.to_string()))?;
+    for (_name, ip) in interfaces {
+        if let IpAddr::V4(v4) = ip {
+            if v4.is_loopback() {
+                continue;
+            }
+            candidates.push(Candidate::new(
+                SocketAddr::new(IpAddr::V4(v4), local_port),
+                CandidateType::Host,
+            ));
+        }
+    }
+    if candidates.is_empty() {
+        return Err(IceError::NoLocalAddresses);
+    }
+
+    if let Some(stun_server) = stun_server {
+        match stun_binding_request(stun_server, local_port) {
+            Ok(reflexive) => {
+                candidates.push(Candidate::new(reflexive, CandidateType::ServerReflexive))
+            }
+            Err(e) => tracing::warn!(
+                "STUN binding request to {} failed, continuing with host candidates only: {}",
+                stun_server,
+                e
+            ),
+        }
+    }
+
+    candidates.sort_by(|a, b| b.priority.cmp(&a.priority));
+    Ok(candidates)
+}
+
+/// Send a STUN Binding Request to `stun_server` from `local_port` and
+/// return this device's server-reflexive address from the response.
+fn stun_binding_request(stun_server: SocketAddr, local_port: u16) -> Result<SocketAddr, IceError> {
+    let bind_addr = match stun_server {
+        SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), local_port),
+        SocketAddr::V6(_) => {
+            SocketAddr::new(IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED), local_port)
+        }
+    };
+    let socket = std::net::UdpSocket::bind(bind_addr)
+        .map_err(|e| IceError::StunRequestFailed(e.to_string()))?;
+    socket
+        .set_read_timeout(Some(STUN_TIMEOUT))
+        .map_err(|e| IceError::StunRequestFailed(e.to_string()))?;
+
+    let mut transaction_id = [0u8; 12];
+    ring::rand::SecureRandom::fill(&ring::rand::SystemRandom::new(), &mut transaction_id)
+        .map_err(|_| IceError::StunRequestFailed("failed to generate transaction id".into()))?;
+
+    let mut request = Vec::with_capacity(20);
+    request.extend_from_slice(&STUN_BINDING_REQUEST.to_be_bytes());
+    request.extend_from_slice(&0u16.to_be_bytes()); // no attributes
+    request.extend_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+    request.extend_from_slice(&transaction_id);
+
+    socket
+        .send_to(&request, stun_server)
+        .map_err(|e| IceError::StunRequestFailed(e.to_string()))?;
+
+    let mut buf = [0u8; 512];
+    let (len, _) = socket
+        .recv_from(&mut buf)
+        .map_err(|e| IceError::StunRequestFailed(e.to_string()))?;
+
+    parse_binding_response(&buf[..len], &transaction_id)
+}
+
+fn parse_binding_response(
+    msg: &[u8],
+    transaction_id: &[u8; 12],
+) -> Result<SocketAddr, IceError> {
+    if msg.len() < 20 {
+        return Err(IceError::StunResponseMalformed);
+    }
+    let msg_type = u16::from_be_bytes([msg[0], msg[1]]);
+    let msg_len = u16::from_be_bytes([msg[2], msg[3]]) as usize;
+    let cookie = u32::from_be_bytes([msg[4], msg[5], msg[6], msg[7]]);
+    if msg_type != STUN_BINDING_RESPONSE || cookie != STUN_MAGIC_COOKIE {
+        return Err(IceError::StunResponseMalformed);
+    }
+    if &msg[8..20] != transaction_id {
+        return Err(IceError::StunResponseMalformed);
+    }
+
+    let attrs = &msg[20..];
+    if attrs.len() < msg_len {
+        return Err(IceError::StunResponseMalformed);
+    }
+
+    let mut offset = 0;
+    let mut xor_addr = None;
+    let mut mapped_addr = None;
+    while offset + 4 <= msg_len {
+        let attr_type = u16::from_be_bytes([attrs[offset], attrs[offset + 1]]);
+        let attr_len = u16::from_be_bytes([attrs[offset + 2], attrs[offset + 3]]) as usize;
+        let value_start = offset + 4;
+        let value_end = value_start + attr_len;
+        if value_end > attrs.len() {
+            break;
+        }
+        let value = &attrs[value_start..value_end];
+        match attr_type {
+            ATTR_XOR_MAPPED_ADDRESS => xor_addr = decode_xor_mapped_address(value, transaction_id),
+            ATTR_MAPPED_ADDRESS => mapped_addr = decode_mapped_address(value),
+            _ => {}
+        }
+        // STUN attributes are padded to a 4-byte boundary.
+        offset = value_end + ((4 - (attr_len % 4)) % 4);
+    }
+
+    xor_addr.or(mapped_addr).ok_or(IceError::StunResponseMalformed)
+}
+
+fn decode_mapped_address(value: &[u8]) -> Option<SocketAddr> {
+    if value.len() < 8 || value[1] != 0x01 {
+        return None; // only IPv4 (family 0x01) is needed here
+    }
+    let port = u16::from_be_bytes([value[2], value[3]]);
+    let ip = Ipv4Addr::new(value[4], value[5], value[6], value[7]);
+    Some(SocketAddr::new(IpAddr::V4(ip), port))
+}
+
+fn decode_xor_mapped_address(value: &[u8], transaction_id: &[u8; 12]) -> Option<SocketAddr> {
+    if value.len() < 8 || value[1] != 0x01 {
+        return None; // only IPv4 (family 0x01) is needed here
+    }
+    let xport = u16::from_be_bytes([value[2], value[3]]);
+    let port = xport ^ ((STUN_MAGIC_COOKIE >> 16) as u16);
+    let xaddr = u32::from_be_bytes([value[4], value[5], value[6], value[7]]);
+    let _ = transaction_id; // only needed by the (rarer) IPv6 XOR-MAPPED-ADDRESS form
+    let ip = Ipv4Addr::from((xaddr ^ STUN_MAGIC_COOKIE).to_be_bytes());
+    Some(SocketAddr::new(IpAddr::V4(ip), port))
+}
+
+/// Pair local×remote candidates (both already sorted by priority) and
+/// race simultaneous-open TCP connects across the pairs, returning the
+/// stream for the first pair that completes a handshake. Candidates are
+/// tried in priority order, so a same-LAN host×host pair is attempted
+/// before anything that needs the STUN-derived reflexive address.
+pub fn connect(
+    local_candidates: &[Candidate],
+    remote_candidates: &[Candidate],
+    local_port: u16,
+) -> Result<TcpStream, IceError> {
+    for local in local_candidates {
+        for remote in remote_candidates {
+            match simultaneous_open(local_port, remote.addr) {
+                Ok(stream) => return Ok(stream),
+                Err(e) => tracing::debug!(
+                    "connectivity check {} -> {} failed: {}",
+                    local.addr,
+                    remote.addr,
+                    e
+                ),
+            }
+        }
+    }
+    Err(IceError::NoConnectivity)
+}
+
+/// Attempt one simultaneous-open connectivity check to `remote_addr`:
+/// listen and connect out from the same `local_port` at once, so whichever
+/// side's SYN gets through first completes the handshake (classic TCP hole
+/// punching). Returns the connected stream, from either the inbound accept
+/// or our own outbound connect, whichever wins.
+fn simultaneous_open(local_port: u16, remote_addr: SocketAddr) -> io::Result<TcpStream> {
+    let domain = match remote_addr {
+        SocketAddr::V4(_) => Domain::IPV4,
+        SocketAddr::V6(_) => Domain::IPV6,
+    };
+    let bind_addr = match remote_addr {
+        SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), local_port),
+        SocketAddr::V6(_) => {
+            SocketAddr::new(IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED), local_port)
+        }
+    };
+
+    let listen_socket = Socket::new(domain, Type::STREAM, Some(Protocol::TCP))?;
+    listen_socket.set_reuse_address(true)?;
+    listen_socket.bind(&bind_addr.into())?;
+    listen_socket.listen(1)?;
+    listen_socket.set_nonblocking(true)?;
+    let listener: TcpListener = listen_socket.into();
+
+    let connect_socket = Socket::new(domain, Type::STREAM, Some(Protocol::TCP))?;
+    connect_socket.set_reuse_address(true)?;
+    connect_socket.bind(&bind_addr.into())?;
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        if connect_socket
+            .connect_timeout(&remote_addr.into(), CONNECTIVITY_CHECK_TIMEOUT)
+            .is_ok()
+        {
+            let _ = tx.send(TcpStream::from(connect_socket));
+        }
+    });
+
+    let deadline = Instant::now() + CONNECTIVITY_CHECK_TIMEOUT;
+    loop {
+        if let Ok(stream) = rx.try_recv() {
+            return Ok(stream);
+        }
+        match listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false)?;
+                return Ok(stream);
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => return Err(e),
+        }
+        if Instant::now() >= deadline {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "connectivity check timed out",
+            ));
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn candidate_encode_decode_roundtrip() {
+        let c = Candidate::new("192.168.1.5:4000".parse().unwrap(), CandidateType::Host);
+        let decoded = Candidate::decode(&c.encode()).unwrap();
+        assert_eq!(c.addr, decoded.addr);
+        assert_eq!(c.candidate_type, decoded.candidate_type);
+    }
+
+    #[test]
+    fn host_candidates_outrank_server_reflexive() {
+        let host = Candidate::new("192.168.1.5:4000".parse().unwrap(), CandidateType::Host);
+        let srflx = Candidate::new(
+            "203.0.113.9:4000".parse().unwrap(),
+            CandidateType::ServerReflexive,
+        );
+        assert!(host.priority > srflx.priority);
+    }
+
+    #[test]
+    fn decode_candidates_skips_garbage_entries() {
+        let list = decode_candidates("192.168.1.5:4000:h,not-a-candidate,203.0.113.9:4000:s");
+        assert_eq!(list.len(), 2);
+    }
+}
@@ -1,8 +1,19 @@
 use crate::protocol::TransferManifest;
+use serde::{Deserialize, Serialize};
 use std::fs::{self, File};
 use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 
+/// Sidecar file `plan_folder_resume` reads back on a restarted folder
+/// transfer - the raw length-prefixed manifest frame bytes a sender would
+/// retransmit at the front of the content stream, saved verbatim the first
+/// time `handle_folder_write` parses one so its exact on-wire length is
+/// still known after the connection drops.
+pub const MANIFEST_SIDECAR_NAME: &str = ".voidwarp-manifest";
+
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+
 /// A reader that concatenates multiple sources (memory buffer + files) into a single stream.
 /// Used for sending Manifest + File1 + File2... as one continuous stream.
 pub struct MultiFileReader {
@@ -138,6 +149,212 @@ impl Seek for MultiFileReader {
     }
 }
 
+/// Minimum single-file size before [`ChunkSource::for_transfer`] switches
+/// from the buffered [`MultiFileReader`] to [`MmapFileReader`]. Below this,
+/// the `mmap`/`munmap` syscalls and page faults cost more than the extra
+/// copy they're meant to save.
+#[cfg(unix)]
+pub const MMAP_MIN_FILE_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Memory-maps a single file and reads straight out of the mapped pages
+/// instead of copying through a `read()`-sized heap buffer. Only handles
+/// one whole file - a folder transfer's manifest header plus several files
+/// don't form one contiguous region, so [`ChunkSource::for_transfer`] keeps
+/// those on [`MultiFileReader`].
+#[cfg(unix)]
+pub struct MmapFileReader {
+    ptr: *mut libc::c_void,
+    len: usize,
+    offset: usize,
+    _file: File,
+}
+
+#[cfg(unix)]
+impl MmapFileReader {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        if len == 0 {
+            // mmap of a zero-length region is undefined behavior, and
+            // there's nothing to read from an empty file anyway.
+            return Ok(Self {
+                ptr: std::ptr::null_mut(),
+                len: 0,
+                offset: 0,
+                _file: file,
+            });
+        }
+
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        // We only ever read the mapping front-to-back, so let the kernel
+        // read ahead more aggressively than its default heuristic.
+        unsafe {
+            libc::madvise(ptr, len, libc::MADV_SEQUENTIAL);
+        }
+
+        Ok(Self {
+            ptr,
+            len,
+            offset: 0,
+            _file: file,
+        })
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        if self.len == 0 {
+            &[]
+        } else {
+            // Safety: `ptr` was returned by a successful `mmap` of `len`
+            // bytes with `PROT_READ`, and is only unmapped in `Drop`.
+            unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Read for MmapFileReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let data = self.as_slice();
+        if self.offset >= data.len() {
+            return Ok(0);
+        }
+        let available = &data[self.offset..];
+        let to_copy = std::cmp::min(buf.len(), available.len());
+        buf[..to_copy].copy_from_slice(&available[..to_copy]);
+        self.offset += to_copy;
+        Ok(to_copy)
+    }
+}
+
+#[cfg(unix)]
+impl Seek for MmapFileReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_offset = match pos {
+            SeekFrom::Start(n) => n as i64,
+            SeekFrom::Current(n) => self.offset as i64 + n,
+            SeekFrom::End(n) => self.len as i64 + n,
+        };
+        if new_offset < 0 || new_offset as usize > self.len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Seek beyond end of stream",
+            ));
+        }
+        self.offset = new_offset as usize;
+        Ok(self.offset as u64)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for MmapFileReader {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            unsafe {
+                libc::munmap(self.ptr, self.len);
+            }
+        }
+    }
+}
+
+// The mapping is read-only (`PROT_READ` / `MAP_PRIVATE`) and touched from
+// exactly one thread at a time, same as the `File` it's backed by.
+#[cfg(unix)]
+unsafe impl Send for MmapFileReader {}
+
+/// Chooses between the portable [`MultiFileReader`] and, where it pays
+/// off, [`MmapFileReader`] for reading chunk data before it's sealed and
+/// sent.
+///
+/// A literal `sendfile`/`splice` path - moving bytes from the file
+/// descriptor to the socket without ever entering userspace - isn't an
+/// option here: every chunk this crate sends is passed through
+/// `SecureChannel::seal` first (see `sender::TcpFileSender::send_chunk`),
+/// and AEAD sealing has to read the plaintext and write ciphertext in
+/// userspace no matter how the plaintext got there. That's the one
+/// userspace touch `sendfile` is built to avoid, so it can't be used while
+/// per-chunk encryption stays mandatory - same kind of wire-level
+/// constraint as the version-negotiation handling in [`crate::quic`].
+/// What mmap still buys: `read()` copies from the page cache into a
+/// caller-supplied buffer on every call, while `MmapFileReader` hands
+/// `seal` a slice straight out of the mapping, cutting that copy out of
+/// the large-single-file path.
+pub enum ChunkSource {
+    Buffered(MultiFileReader),
+    #[cfg(unix)]
+    Mmap(MmapFileReader),
+    /// An already-open file handle, read directly rather than reopened by
+    /// path - see `ChunkSource::for_fd`.
+    #[cfg(unix)]
+    Fd(File),
+}
+
+impl ChunkSource {
+    /// Picks `Mmap` for large, single-file transfers on platforms that
+    /// support it; everything else (folder transfers, small files,
+    /// non-unix targets) uses the portable `Buffered` path.
+    pub fn for_transfer(
+        head_data: Vec<u8>,
+        file_paths: Vec<PathBuf>,
+        total_size: u64,
+    ) -> io::Result<Self> {
+        #[cfg(unix)]
+        {
+            if head_data.is_empty() && file_paths.len() == 1 && total_size >= MMAP_MIN_FILE_SIZE {
+                return Ok(ChunkSource::Mmap(MmapFileReader::open(&file_paths[0])?));
+            }
+        }
+        Ok(ChunkSource::Buffered(MultiFileReader::new(
+            head_data, file_paths,
+        )?))
+    }
+
+    /// Reads chunk data straight out of `file` - there's no `PathBuf` to
+    /// hand to `for_transfer` when the sender was built from a file
+    /// descriptor (see `sender::TcpFileSender::from_fd`) rather than a
+    /// path, so each connection attempt gets its own independent seek
+    /// position via `File::try_clone` instead of a fresh `File::open`.
+    #[cfg(unix)]
+    pub fn for_fd(file: File) -> Self {
+        ChunkSource::Fd(file)
+    }
+}
+
+impl Read for ChunkSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            ChunkSource::Buffered(r) => r.read(buf),
+            #[cfg(unix)]
+            ChunkSource::Mmap(r) => r.read(buf),
+            #[cfg(unix)]
+            ChunkSource::Fd(r) => r.read(buf),
+        }
+    }
+}
+
+impl Seek for ChunkSource {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            ChunkSource::Buffered(r) => r.seek(pos),
+            #[cfg(unix)]
+            ChunkSource::Mmap(r) => r.seek(pos),
+            #[cfg(unix)]
+            ChunkSource::Fd(r) => r.seek(pos),
+        }
+    }
+}
+
 /// A writer that can handle either a single file or a folder stream (Manifest + Files)
 pub enum ReceiverWriter {
     SingleFile(File),
@@ -203,6 +420,88 @@ impl ReceiverWriter {
         Ok(ReceiverWriter::SingleFile(file))
     }
 
+    /// Reopen an existing single file for a bitmap-backed resume, without
+    /// truncating it to any particular length - unlike [`resume_single`],
+    /// which assumes everything up to `len` is valid and everything past it
+    /// should be discarded, chunks past the contiguous edge may already
+    /// hold valid data written out of order by `receive_windowed` before a
+    /// previous connection dropped. `file_size` is only used to make sure
+    /// the file is at least that long, so later seeks never land past the
+    /// end of the file.
+    ///
+    /// [`resume_single`]: Self::resume_single
+    pub fn resume_single_sparse(path: &Path, file_size: u64) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() && !parent.exists() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        let file = std::fs::OpenOptions::new().write(true).open(path)?;
+        if file.metadata()?.len() < file_size {
+            file.set_len(file_size)?;
+        }
+        Ok(ReceiverWriter::SingleFile(file))
+    }
+
+    /// Resume a folder transfer partway through, for a [`ByteRange`]
+    /// request that starts somewhere inside the file contents rather than
+    /// at the manifest header. `content_offset` is relative to the start
+    /// of the concatenated file bytes (i.e. the same addressing
+    /// `MultiFileReader` uses once past its head data) and is mapped onto
+    /// a `(file_idx, offset_in_file)` pair with the same per-item-size walk
+    /// `MultiFileReader::read` does. The target file is opened with
+    /// `OpenOptions` rather than `File::create`'s implicit truncate, so
+    /// bytes already on disk - in this file before `content_offset`, and in
+    /// every earlier file - survive.
+    ///
+    /// [`ByteRange`]: crate::protocol::ByteRange
+    pub fn resume_folder(
+        base_path: &Path,
+        manifest: TransferManifest,
+        content_offset: u64,
+    ) -> io::Result<Self> {
+        fs::create_dir_all(base_path)?;
+
+        let mut offset_in_file = content_offset;
+        let mut file_idx = 0;
+        for item in &manifest.items {
+            if offset_in_file < item.size {
+                break;
+            }
+            offset_in_file -= item.size;
+            file_idx += 1;
+        }
+
+        let current_file = if file_idx < manifest.items.len() {
+            let item = &manifest.items[file_idx];
+            let path = base_path.join(&item.path);
+            if let Some(parent) = path.parent() {
+                if !parent.as_os_str().is_empty() {
+                    fs::create_dir_all(parent)?;
+                }
+            }
+            let mut file = fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .open(&path)?;
+            file.seek(SeekFrom::Start(offset_in_file))?;
+            Some(file)
+        } else {
+            None
+        };
+
+        Ok(ReceiverWriter::Folder {
+            state: FolderWriterState::WritingFiles {
+                manifest,
+                current_file_idx: file_idx,
+                current_offset_in_file: offset_in_file,
+                current_file,
+            },
+            base_path: base_path.to_path_buf(),
+            manifest_hash: None,
+        })
+    }
+
     pub fn flush(&mut self) -> io::Result<()> {
         match self {
             ReceiverWriter::SingleFile(f) => f.flush(),
@@ -226,6 +525,155 @@ impl ReceiverWriter {
     }
 }
 
+/// What a restarted folder transfer can pick up from: the manifest a
+/// previous attempt already saved as a [`MANIFEST_SIDECAR_NAME`] sidecar,
+/// and how far into the concatenated file contents the files already on
+/// disk reach.
+pub struct FolderResumePlan {
+    pub manifest: TransferManifest,
+    /// Length, in bytes, of the length-prefixed manifest frame
+    /// (`[len:u32][json]`) as the sender would retransmit it at the front
+    /// of the content stream - needed to translate `content_offset` into
+    /// the single `start_chunk_index` the existing resume-offer wire
+    /// format carries (see `sender::TcpFileSender::send_over_stream`).
+    pub manifest_frame_len: u64,
+    /// How many bytes into the concatenated file contents (i.e. past the
+    /// manifest frame) are already valid on disk.
+    pub content_offset: u64,
+}
+
+/// Work out how much of a folder transfer can be skipped on a restart, by
+/// reading back the `MANIFEST_SIDECAR_NAME` a previous attempt saved and
+/// walking its entries against whatever's actually on disk: a file whose
+/// size matches and whose checksum matches is complete; a shorter file is
+/// assumed valid up to its current length (the same trust-the-bytes-on-disk
+/// call `receiver::FileReceiverServer::accept_transfer` already makes for
+/// single-file resume); anything else stops the scan, since entries stream
+/// in manifest order and nothing past the first incomplete file exists yet.
+/// Returns `Ok(None)` if there's no sidecar (or it's unreadable) - the
+/// transfer should just start fresh.
+pub fn plan_folder_resume(base_path: &Path) -> io::Result<Option<FolderResumePlan>> {
+    let manifest_bytes = match fs::read(base_path.join(MANIFEST_SIDECAR_NAME)) {
+        Ok(b) => b,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let manifest: TransferManifest = match serde_json::from_slice(&manifest_bytes) {
+        Ok(m) => m,
+        Err(_) => return Ok(None), // Stale/corrupt sidecar - fall back to a fresh transfer.
+    };
+
+    let mut content_offset = 0u64;
+    for item in &manifest.items {
+        let on_disk_size = fs::metadata(base_path.join(&item.path))
+            .map(|m| m.len())
+            .unwrap_or(0);
+
+        if on_disk_size == item.size {
+            let hash = crate::checksum::calculate_file_checksum(&base_path.join(&item.path))?;
+            if hash == item.hash {
+                content_offset += item.size;
+                continue;
+            }
+            break; // Size matches but content doesn't - redo this file.
+        }
+        if on_disk_size < item.size {
+            content_offset += on_disk_size;
+        }
+        break;
+    }
+
+    Ok(Some(FolderResumePlan {
+        manifest,
+        manifest_frame_len: 4 + manifest_bytes.len() as u64,
+        content_offset,
+    }))
+}
+
+/// Sidecar extension persisted next to a single-file `save_path` so a
+/// dropped connection can resume at per-chunk granularity - unlike the
+/// whole-chunk-boundary truncation `accept_transfer` otherwise falls back
+/// to, chunks `receiver::FileReceiverServer::receive_windowed` wrote out of
+/// order ahead of the contiguous edge are remembered and don't need to be
+/// re-sent.
+pub const CHUNK_BITMAP_SIDECAR_EXT: &str = "vwpart";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ChunkBitmapState {
+    file_checksum: String,
+    chunk_size: u32,
+    file_size: u64,
+    bitmap: Vec<bool>,
+}
+
+fn chunk_bitmap_sidecar_path(save_path: &Path) -> PathBuf {
+    let mut name = save_path.as_os_str().to_owned();
+    name.push(".");
+    name.push(CHUNK_BITMAP_SIDECAR_EXT);
+    PathBuf::from(name)
+}
+
+/// Loads the per-chunk bitmap persisted for `save_path`, if a sidecar
+/// exists and still matches the transfer being resumed. A checksum, chunk
+/// size, or file size mismatch means it's a different (or re-encoded)
+/// file, so the safe thing is to ignore it and fall back to whole-file
+/// resume rather than trust a bitmap that might not describe what's
+/// actually on disk.
+pub fn load_chunk_bitmap(
+    save_path: &Path,
+    file_checksum: &str,
+    chunk_size: u32,
+    file_size: u64,
+) -> Option<Vec<bool>> {
+    let bytes = fs::read(chunk_bitmap_sidecar_path(save_path)).ok()?;
+    let state: ChunkBitmapState = serde_json::from_slice(&bytes).ok()?;
+    if state.file_checksum != file_checksum
+        || state.chunk_size != chunk_size
+        || state.file_size != file_size
+    {
+        return None;
+    }
+    Some(state.bitmap)
+}
+
+/// Persists `bitmap` so a later `load_chunk_bitmap` call can pick up
+/// exactly where this connection left off if it drops mid-transfer.
+pub fn save_chunk_bitmap(
+    save_path: &Path,
+    file_checksum: &str,
+    chunk_size: u32,
+    file_size: u64,
+    bitmap: &[bool],
+) -> io::Result<()> {
+    let state = ChunkBitmapState {
+        file_checksum: file_checksum.to_string(),
+        chunk_size,
+        file_size,
+        bitmap: bitmap.to_vec(),
+    };
+    let json = serde_json::to_vec(&state)?;
+    fs::write(chunk_bitmap_sidecar_path(save_path), json)
+}
+
+/// Removes the sidecar once a transfer verifies successfully - there's
+/// nothing left to resume.
+pub fn delete_chunk_bitmap(save_path: &Path) {
+    let _ = fs::remove_file(chunk_bitmap_sidecar_path(save_path));
+}
+
+/// Indices below `total_chunks` not yet marked received in `bitmap` - the
+/// complement of the `already_have` set `receiver::FileReceiverServer`
+/// sends back as a `protocol::ResumeOffer`. Exposed standalone so a caller
+/// that only has the persisted bitmap (e.g. a resume-progress UI reading
+/// the `.vwpart` sidecar directly, without a live receiver) can still ask
+/// "what's left".
+pub fn missing_chunks_from_bitmap(bitmap: &[bool], total_chunks: u64) -> Vec<u64> {
+    (0..total_chunks)
+        .filter(|&i| !bitmap.get(i as usize).copied().unwrap_or(false))
+        .collect()
+}
+
 impl Write for ReceiverWriter {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         match self {
@@ -308,6 +756,12 @@ fn handle_folder_write(
 
                     // Create directories
                     fs::create_dir_all(base_path)?;
+
+                    // Save the raw manifest frame as a sidecar so a later
+                    // `plan_folder_resume` call can work out a resume point
+                    // without the sender having to resend it first.
+                    fs::write(base_path.join(MANIFEST_SIDECAR_NAME), &m_buf)?;
+
                     for item in &manifest.items {
                         if let Some(parent) = Path::new(&item.path).parent() {
                             if !parent.as_os_str().is_empty() {
@@ -388,3 +842,189 @@ fn handle_folder_write(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::ManifestItem;
+    use std::io::Write;
+
+    #[cfg(unix)]
+    #[test]
+    fn chunk_source_picks_mmap_for_large_single_file_only() {
+        let mut big = tempfile::NamedTempFile::new().unwrap();
+        big.write_all(&vec![7u8; (MMAP_MIN_FILE_SIZE as usize) + 1])
+            .unwrap();
+
+        let source = ChunkSource::for_transfer(
+            vec![],
+            vec![big.path().to_path_buf()],
+            MMAP_MIN_FILE_SIZE + 1,
+        )
+        .unwrap();
+        assert!(matches!(source, ChunkSource::Mmap(_)));
+
+        let mut small = tempfile::NamedTempFile::new().unwrap();
+        small.write_all(b"tiny").unwrap();
+        let source = ChunkSource::for_transfer(vec![], vec![small.path().to_path_buf()], 4).unwrap();
+        assert!(matches!(source, ChunkSource::Buffered(_)));
+
+        // A folder transfer (non-empty manifest header) stays buffered
+        // even if the single file listed is large, since the mapping
+        // can't also cover the manifest bytes.
+        let source = ChunkSource::for_transfer(
+            vec![1, 2, 3],
+            vec![big.path().to_path_buf()],
+            MMAP_MIN_FILE_SIZE + 1,
+        )
+        .unwrap();
+        assert!(matches!(source, ChunkSource::Buffered(_)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn mmap_file_reader_reads_and_seeks_like_a_normal_reader() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"hello zero-copy world").unwrap();
+
+        let mut reader = MmapFileReader::open(file.path()).unwrap();
+        let mut buf = [0u8; 5];
+        assert_eq!(reader.read(&mut buf).unwrap(), 5);
+        assert_eq!(&buf, b"hello");
+
+        reader.seek(SeekFrom::Start(6)).unwrap();
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"zero-copy world");
+    }
+
+    #[test]
+    fn resume_folder_maps_a_content_offset_onto_the_right_file_and_picks_up_mid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = TransferManifest {
+            items: vec![
+                ManifestItem {
+                    path: "a.txt".into(),
+                    size: 5,
+                    hash: String::new(),
+                },
+                ManifestItem {
+                    path: "b.txt".into(),
+                    size: 10,
+                    hash: String::new(),
+                },
+            ],
+            total_size: 15,
+        };
+        std::fs::write(dir.path().join("a.txt"), b"AAAAA").unwrap();
+        std::fs::write(dir.path().join("b.txt"), b"BBBBBBBBBB").unwrap();
+
+        // Offset 7 is 2 bytes into "b.txt" (5 bytes of "a.txt" + 2).
+        let mut writer = ReceiverWriter::resume_folder(dir.path(), manifest, 7).unwrap();
+        writer.write_all(b"XX").unwrap();
+        writer.flush().unwrap();
+
+        let contents = std::fs::read(dir.path().join("b.txt")).unwrap();
+        assert_eq!(&contents[0..2], b"BB");
+        assert_eq!(&contents[2..4], b"XX");
+    }
+
+    fn write_manifest_sidecar(dir: &Path, manifest: &TransferManifest) {
+        let json = serde_json::to_vec(manifest).unwrap();
+        fs::write(dir.join(MANIFEST_SIDECAR_NAME), json).unwrap();
+    }
+
+    #[test]
+    fn plan_folder_resume_is_none_without_a_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(plan_folder_resume(dir.path()).unwrap().is_none());
+    }
+
+    #[test]
+    fn plan_folder_resume_skips_files_whose_checksum_already_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), b"AAAAA").unwrap();
+        let manifest = TransferManifest {
+            items: vec![
+                ManifestItem {
+                    path: "a.txt".into(),
+                    size: 5,
+                    hash: crate::checksum::calculate_chunk_checksum(b"AAAAA"),
+                },
+                ManifestItem {
+                    path: "b.txt".into(),
+                    size: 10,
+                    hash: "irrelevant".into(),
+                },
+            ],
+            total_size: 15,
+        };
+        write_manifest_sidecar(dir.path(), &manifest);
+
+        let plan = plan_folder_resume(dir.path()).unwrap().unwrap();
+        // "a.txt" is complete (5 bytes), "b.txt" doesn't exist yet (0 bytes
+        // on disk), so the scan stops right after "a.txt".
+        assert_eq!(plan.content_offset, 5);
+    }
+
+    #[test]
+    fn plan_folder_resume_picks_up_mid_file_when_bytes_are_short() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), b"AAA").unwrap(); // only 3 of 5 bytes
+        let manifest = TransferManifest {
+            items: vec![ManifestItem {
+                path: "a.txt".into(),
+                size: 5,
+                hash: "whatever-the-full-file-hashes-to".into(),
+            }],
+            total_size: 5,
+        };
+        write_manifest_sidecar(dir.path(), &manifest);
+
+        let plan = plan_folder_resume(dir.path()).unwrap().unwrap();
+        assert_eq!(plan.content_offset, 3);
+    }
+
+    #[test]
+    fn chunk_bitmap_round_trips_and_rejects_a_mismatched_transfer() {
+        let dir = tempfile::tempdir().unwrap();
+        let save_path = dir.path().join("movie.mp4");
+        let bitmap = vec![true, false, true, true];
+
+        save_chunk_bitmap(&save_path, "deadbeef", 1024, 4000, &bitmap).unwrap();
+        assert_eq!(
+            load_chunk_bitmap(&save_path, "deadbeef", 1024, 4000),
+            Some(bitmap)
+        );
+
+        // A different checksum means a different file entirely - ignore
+        // the sidecar rather than trust a bitmap that may not match.
+        assert_eq!(load_chunk_bitmap(&save_path, "other", 1024, 4000), None);
+    }
+
+    #[test]
+    fn delete_chunk_bitmap_removes_the_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        let save_path = dir.path().join("movie.mp4");
+        save_chunk_bitmap(&save_path, "deadbeef", 1024, 4000, &[true]).unwrap();
+
+        delete_chunk_bitmap(&save_path);
+
+        assert_eq!(load_chunk_bitmap(&save_path, "deadbeef", 1024, 4000), None);
+    }
+
+    #[test]
+    fn missing_chunks_from_bitmap_lists_every_unreceived_index() {
+        let bitmap = vec![true, false, true, false, false];
+        assert_eq!(missing_chunks_from_bitmap(&bitmap, 5), vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn missing_chunks_from_bitmap_treats_indices_past_the_bitmap_as_missing() {
+        // total_chunks can run ahead of a bitmap saved before the file's
+        // final size was known (or corrupted/truncated) - treat anything
+        // past the end as still missing rather than panicking.
+        let bitmap = vec![true];
+        assert_eq!(missing_chunks_from_bitmap(&bitmap, 3), vec![1, 2]);
+    }
+}
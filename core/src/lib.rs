@@ -6,15 +6,29 @@ pub mod ffi;
 
 #[cfg(target_os = "android")]
 mod android;
+#[cfg(target_os = "android")]
+mod cancel;
 pub mod checksum;
+pub mod dedup;
 pub mod heartbeat;
+pub mod ice;
 pub mod io_utils;
+pub mod merkle;
+pub mod natmap;
+pub mod netiface;
+pub mod pacing;
 pub mod protocol;
+pub mod quic;
+pub mod ratelimit;
 pub mod receiver;
+pub mod relay;
 pub mod security;
 pub mod sender;
 pub mod transfer;
 pub mod transport;
+pub mod transport_async;
+pub mod vwtp;
+pub mod vwtp_transfer;
 
 /// Initialize the core library (logging, runtime, etc.)
 pub fn init() {
@@ -27,6 +41,10 @@ pub fn init() {
                 .with_tag("VoidWarpCore"),
         );
         log::info!("VoidWarp Core Initialized (Android Logger)");
+
+        // Lets a blocked transfer thread be woken immediately on cancel
+        // instead of waiting out its read timeout - see `cancel`.
+        cancel::install_handler();
     }
 
     #[cfg(not(target_os = "android"))]
@@ -0,0 +1,141 @@
+//! Incremental Merkle tree over a transfer's ordered per-chunk hashes.
+//!
+//! `FileReceiver` folds each chunk's hash into a [`MerkleAccumulator`] as it
+//! arrives, so the instant the last chunk lands it already holds the
+//! completed file's root - no need for `checksum::verify_file_checksum` to
+//! re-read the finished file back off disk.
+
+use crate::checksum::{self, HashMethod};
+
+/// Hash `left || right` with `method` to make their parent node - the same
+/// algorithm a leaf was hashed with, so every level of the tree uses the
+/// one negotiated `HashMethod`.
+fn combine(method: HashMethod, left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(left.len() + right.len());
+    buf.extend_from_slice(left);
+    buf.extend_from_slice(right);
+    checksum::hash_bytes(method, &buf)
+}
+
+/// Builds a Merkle root over a stream of per-chunk hashes without ever
+/// holding the whole tree in memory: each level carries at most one node
+/// waiting for a sibling, which collapses into the level above the moment
+/// a second node arrives.
+pub struct MerkleAccumulator {
+    method: HashMethod,
+    /// `levels[i]` holds a node waiting for a sibling at tree level `i`
+    /// (leaves are level 0); `None` means that level has nothing pending.
+    levels: Vec<Option<Vec<u8>>>,
+    leaf_count: u64,
+}
+
+impl MerkleAccumulator {
+    pub fn new(method: HashMethod) -> Self {
+        MerkleAccumulator {
+            method,
+            levels: Vec::new(),
+            leaf_count: 0,
+        }
+    }
+
+    /// Fold in the next chunk's hash, in order.
+    pub fn push_leaf(&mut self, leaf_hash: Vec<u8>) {
+        self.leaf_count += 1;
+        let mut carry = leaf_hash;
+        let mut level = 0;
+        loop {
+            if level == self.levels.len() {
+                self.levels.push(None);
+            }
+            match self.levels[level].take() {
+                Some(sibling) => {
+                    carry = combine(self.method, &sibling, &carry);
+                    level += 1;
+                }
+                None => {
+                    self.levels[level] = Some(carry);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// The Merkle root over every leaf folded in so far, or `None` before
+    /// the first leaf arrives. A level left with no sibling (an odd
+    /// leaf count somewhere in the tree) is carried up and combined with
+    /// the accumulated root from the levels below it, the usual way of
+    /// completing an unbalanced tree.
+    pub fn root(&self) -> Option<Vec<u8>> {
+        let mut root: Option<Vec<u8>> = None;
+        for node in self.levels.iter().flatten() {
+            root = Some(match root {
+                Some(lower) => combine(self.method, &lower, node),
+                None => node.clone(),
+            });
+        }
+        root
+    }
+
+    pub fn leaf_count(&self) -> u64 {
+        self.leaf_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root_for(method: HashMethod, chunks: &[&[u8]]) -> Option<Vec<u8>> {
+        let mut acc = MerkleAccumulator::new(method);
+        for chunk in chunks {
+            acc.push_leaf(checksum::hash_bytes(method, chunk));
+        }
+        acc.root()
+    }
+
+    #[test]
+    fn empty_accumulator_has_no_root() {
+        let acc = MerkleAccumulator::new(HashMethod::Blake3);
+        assert_eq!(acc.root(), None);
+        assert_eq!(acc.leaf_count(), 0);
+    }
+
+    #[test]
+    fn same_chunks_in_the_same_order_produce_the_same_root() {
+        let chunks: Vec<&[u8]> = vec![b"alpha", b"beta", b"gamma", b"delta"];
+        let root_a = root_for(HashMethod::Sha256, &chunks);
+        let root_b = root_for(HashMethod::Sha256, &chunks);
+        assert_eq!(root_a, root_b);
+    }
+
+    #[test]
+    fn reordering_chunks_changes_the_root() {
+        let forward: Vec<&[u8]> = vec![b"alpha", b"beta", b"gamma"];
+        let reversed: Vec<&[u8]> = vec![b"gamma", b"beta", b"alpha"];
+        assert_ne!(
+            root_for(HashMethod::Sha256, &forward),
+            root_for(HashMethod::Sha256, &reversed)
+        );
+    }
+
+    #[test]
+    fn an_odd_number_of_leaves_still_produces_a_root() {
+        let chunks: Vec<&[u8]> = vec![b"one", b"two", b"three"];
+        assert!(root_for(HashMethod::Md5, &chunks).is_some());
+    }
+
+    #[test]
+    fn root_is_available_incrementally_as_leaves_arrive() {
+        let mut acc = MerkleAccumulator::new(HashMethod::Blake3);
+        assert_eq!(acc.root(), None);
+
+        acc.push_leaf(checksum::hash_bytes(HashMethod::Blake3, b"first"));
+        let root_after_one = acc.root().unwrap();
+
+        acc.push_leaf(checksum::hash_bytes(HashMethod::Blake3, b"second"));
+        let root_after_two = acc.root().unwrap();
+
+        assert_ne!(root_after_one, root_after_two);
+        assert_eq!(acc.leaf_count(), 2);
+    }
+}
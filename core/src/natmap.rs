@@ -0,0 +1,496 @@
+//! Automatic NAT port mapping (UPnP-IGD, falling back to NAT-PMP/PCP).
+//!
+//! [`ice`](crate::ice) discovers our *reflexive* address via STUN - useful
+//! for a connectivity check, but it can't make an inbound connection
+//! actually reach us if the router doesn't already forward the port.
+//! [`map_port`] asks the router itself to open one: it first tries
+//! UPnP-IGD (SSDP discovery of the gateway's control URL, then a SOAP
+//! `AddPortMapping` call), and if that fails - no UPnP gateway answered,
+//! or it actively rejected the request - falls back to the much simpler
+//! NAT-PMP/PCP wire protocol on port 5351. Either way the caller gets back
+//! a [`PortMapping`] that renews its lease on a timer and tears the
+//! mapping down on `Drop`/[`PortMapping::unregister`], the same
+//! hold-a-handle-to-release-it shape as [`crate::discovery::DiscoveryManager`].
+//!
+//! Entirely best-effort: every step degrades to an error rather than a
+//! panic, and a failed mapping just means the caller falls back to
+//! whatever connectivity [`ice`](crate::ice) or a relay can provide.
+
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use thiserror::Error;
+
+/// SSDP multicast address/port used to discover UPnP gateways on the LAN.
+const SSDP_ADDR: &str = "239.255.255.250:1900";
+/// Standard NAT-PMP/PCP port on the gateway.
+const NATPMP_PORT: u16 = 5351;
+
+/// How long to wait for an SSDP/NAT-PMP reply before giving up on that
+/// backend and (for SSDP) falling back to NAT-PMP.
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Requested mapping lifetime. Renewed at half this interval so a missed
+/// renewal (one slow SOAP call, one dropped NAT-PMP datagram) doesn't let
+/// the mapping lapse before the next attempt.
+pub const DEFAULT_LEASE: Duration = Duration::from_secs(600);
+
+/// Which transport protocol to map - routers track TCP and UDP mappings
+/// separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappedProtocol {
+    Tcp,
+    Udp,
+}
+
+impl MappedProtocol {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MappedProtocol::Tcp => "TCP",
+            MappedProtocol::Udp => "UDP",
+        }
+    }
+
+    /// NAT-PMP opcode for this protocol (RFC 6886 section 3.3).
+    fn natpmp_opcode(&self) -> u8 {
+        match self {
+            MappedProtocol::Tcp => 2,
+            MappedProtocol::Udp => 1,
+        }
+    }
+}
+
+/// Errors from gathering or maintaining a port mapping.
+#[derive(Error, Debug)]
+pub enum NatMapError {
+    #[error("no UPnP gateway responded to SSDP discovery")]
+    NoUpnpGateway,
+    #[error("UPnP gateway rejected the mapping request: {0}")]
+    UpnpRejected(String),
+    #[error("no NAT-PMP/PCP response from the default gateway")]
+    NoNatPmpGateway,
+    #[error("NAT-PMP/PCP gateway returned result code {0}")]
+    NatPmpRejected(u16),
+    #[error("could not determine the default gateway address")]
+    NoGateway,
+    #[error("I/O error talking to the gateway: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A live port mapping, held open by a background renewal thread. Dropping
+/// this (or calling [`Self::unregister`] explicitly) tears the mapping
+/// down and stops the thread.
+pub struct PortMapping {
+    external_addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    renewal_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl PortMapping {
+    /// The externally-reachable address the gateway mapped us to -
+    /// advertise this (e.g. as [`crate::discovery::relay_fallback::RelayRegistration::external_addr`])
+    /// so peers off our LAN can dial straight in instead of going through
+    /// a relay.
+    pub fn external_addr(&self) -> SocketAddr {
+        self.external_addr
+    }
+
+    /// Explicitly release the mapping and stop the renewal thread. Also
+    /// run automatically on `Drop`; calling this first just lets the
+    /// caller observe it happening at a known point instead of whenever
+    /// the value happens to go out of scope.
+    pub fn unregister(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.renewal_thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for PortMapping {
+    fn drop(&mut self) {
+        self.unregister();
+    }
+}
+
+/// Request an external mapping for `local_port` (on this host) with
+/// `lease`, trying UPnP-IGD first and falling back to NAT-PMP/PCP. Spawns
+/// a background thread that re-requests the mapping at half the lease
+/// interval for as long as the returned [`PortMapping`] is alive.
+pub fn map_port(
+    local_port: u16,
+    protocol: MappedProtocol,
+    lease: Duration,
+) -> Result<PortMapping, NatMapError> {
+    let external_addr = match upnp_add_mapping(local_port, protocol, lease) {
+        Ok(addr) => addr,
+        Err(upnp_err) => {
+            tracing::info!(
+                "UPnP port mapping unavailable ({}), falling back to NAT-PMP/PCP",
+                upnp_err
+            );
+            natpmp_add_mapping(local_port, protocol, lease)?
+        }
+    };
+
+    tracing::info!(
+        "Mapped {} local port {} to external {}",
+        protocol.as_str(),
+        local_port,
+        external_addr
+    );
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let renew_stop = stop.clone();
+    let renewal_thread = thread::spawn(move || {
+        let renew_interval = lease / 2;
+        while !renew_stop.load(Ordering::SeqCst) {
+            thread::sleep(renew_interval);
+            if renew_stop.load(Ordering::SeqCst) {
+                break;
+            }
+            let renewed = upnp_add_mapping(local_port, protocol, lease)
+                .or_else(|_| natpmp_add_mapping(local_port, protocol, lease));
+            if let Err(e) = renewed {
+                tracing::warn!("Failed to renew NAT port mapping: {}", e);
+            }
+        }
+    });
+
+    Ok(PortMapping {
+        external_addr,
+        stop,
+        renewal_thread: Some(renewal_thread),
+    })
+}
+
+// --- UPnP-IGD --------------------------------------------------------
+
+/// Discover a UPnP IGD's control URL via SSDP, then call `AddPortMapping`
+/// on it. Returns the external address the gateway reports.
+fn upnp_add_mapping(
+    local_port: u16,
+    protocol: MappedProtocol,
+    lease: Duration,
+) -> Result<SocketAddr, NatMapError> {
+    let location = ssdp_discover_location()?;
+    let (host, port, control_path) = fetch_control_url(&location)?;
+    let local_ip = local_lan_ip()?;
+
+    soap_add_port_mapping(&host, port, &control_path, local_ip, local_port, protocol, lease)?;
+    let external_ip = soap_get_external_ip(&host, port, &control_path)?;
+
+    Ok(SocketAddr::new(external_ip, local_port))
+}
+
+/// Send an SSDP M-SEARCH multicast and return the `LOCATION` header from
+/// the first IGD that answers.
+fn ssdp_discover_location() -> Result<String, NatMapError> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.set_read_timeout(Some(DISCOVERY_TIMEOUT))?;
+
+    let search = concat!(
+        "M-SEARCH * HTTP/1.1\r\n",
+        "HOST: 239.255.255.250:1900\r\n",
+        "MAN: \"ssdp:discover\"\r\n",
+        "MX: 2\r\n",
+        "ST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n",
+        "\r\n"
+    );
+    socket.send_to(search.as_bytes(), SSDP_ADDR)?;
+
+    let mut buf = [0u8; 2048];
+    let (len, _) = socket
+        .recv_from(&mut buf)
+        .map_err(|_| NatMapError::NoUpnpGateway)?;
+    let response = String::from_utf8_lossy(&buf[..len]);
+
+    response
+        .lines()
+        .find_map(|line| line.strip_prefix("LOCATION:").or_else(|| line.strip_prefix("Location:")))
+        .map(|loc| loc.trim().to_string())
+        .ok_or(NatMapError::NoUpnpGateway)
+}
+
+/// Fetch the device description XML at `location` and scrape out the
+/// `controlURL` for the WANIPConnection service. A full XML parser is
+/// overkill for a handful of known tags in a well-formed UPnP document, so
+/// this just looks for the literal `<controlURL>` tag nearest a
+/// `WANIPConnection`/`WANPPPConnection` service block.
+fn fetch_control_url(location: &str) -> Result<(String, u16, String), NatMapError> {
+    let url = location
+        .strip_prefix("http://")
+        .ok_or(NatMapError::NoUpnpGateway)?;
+    let (host_port, path) = url.split_once('/').unwrap_or((url, ""));
+    let (host, port) = host_port
+        .split_once(':')
+        .map(|(h, p)| (h, p.parse().unwrap_or(80)))
+        .unwrap_or((host_port, 80));
+
+    let body = http_get(host, port, &format!("/{}", path))?;
+
+    let control_path = body
+        .find("WANIPConnection")
+        .or_else(|| body.find("WANPPPConnection"))
+        .and_then(|idx| body[idx..].find("<controlURL>").map(|rel| idx + rel))
+        .and_then(|idx| {
+            let start = idx + "<controlURL>".len();
+            body[start..]
+                .find("</controlURL>")
+                .map(|end| body[start..start + end].trim().to_string())
+        })
+        .ok_or(NatMapError::NoUpnpGateway)?;
+
+    Ok((host.to_string(), port, control_path))
+}
+
+fn soap_add_port_mapping(
+    host: &str,
+    port: u16,
+    control_path: &str,
+    local_ip: Ipv4Addr,
+    local_port: u16,
+    protocol: MappedProtocol,
+    lease: Duration,
+) -> Result<(), NatMapError> {
+    let body = format!(
+        "<?xml version=\"1.0\"?>\
+<s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+<s:Body><u:AddPortMapping xmlns:u=\"urn:schemas-upnp-org:service:WANIPConnection:1\">\
+<NewRemoteHost></NewRemoteHost>\
+<NewExternalPort>{port}</NewExternalPort>\
+<NewProtocol>{proto}</NewProtocol>\
+<NewInternalPort>{port}</NewInternalPort>\
+<NewInternalClient>{ip}</NewInternalClient>\
+<NewEnabled>1</NewEnabled>\
+<NewPortMappingDescription>VoidWarp</NewPortMappingDescription>\
+<NewLeaseDuration>{lease}</NewLeaseDuration>\
+</u:AddPortMapping></s:Body></s:Envelope>",
+        port = local_port,
+        proto = protocol.as_str(),
+        ip = local_ip,
+        lease = lease.as_secs(),
+    );
+
+    let response = soap_post(
+        host,
+        port,
+        control_path,
+        "urn:schemas-upnp-org:service:WANIPConnection:1#AddPortMapping",
+        &body,
+    )?;
+
+    if response.contains("<s:Fault>") || response.contains("errorCode") {
+        return Err(NatMapError::UpnpRejected(response));
+    }
+    Ok(())
+}
+
+fn soap_get_external_ip(host: &str, port: u16, control_path: &str) -> Result<IpAddr, NatMapError> {
+    let body = "<?xml version=\"1.0\"?>\
+<s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+<s:Body><u:GetExternalIPAddress xmlns:u=\"urn:schemas-upnp-org:service:WANIPConnection:1\"/></s:Body></s:Envelope>";
+
+    let response = soap_post(
+        host,
+        port,
+        control_path,
+        "urn:schemas-upnp-org:service:WANIPConnection:1#GetExternalIPAddress",
+        body,
+    )?;
+
+    let start_tag = "<NewExternalIPAddress>";
+    let start = response
+        .find(start_tag)
+        .ok_or_else(|| NatMapError::UpnpRejected(response.clone()))?
+        + start_tag.len();
+    let end = response[start..]
+        .find("</NewExternalIPAddress>")
+        .ok_or_else(|| NatMapError::UpnpRejected(response.clone()))?;
+
+    response[start..start + end]
+        .trim()
+        .parse()
+        .map_err(|_| NatMapError::UpnpRejected(response))
+}
+
+fn soap_post(
+    host: &str,
+    port: u16,
+    path: &str,
+    action: &str,
+    body: &str,
+) -> Result<String, NatMapError> {
+    let request = format!(
+        "POST {path} HTTP/1.1\r\n\
+Host: {host}:{port}\r\n\
+Content-Type: text/xml; charset=\"utf-8\"\r\n\
+SOAPAction: \"{action}\"\r\n\
+Content-Length: {len}\r\n\
+Connection: close\r\n\r\n{body}",
+        path = path,
+        host = host,
+        port = port,
+        action = action,
+        len = body.len(),
+        body = body,
+    );
+
+    let mut stream = TcpStream::connect_timeout(
+        &format!("{}:{}", host, port)
+            .parse()
+            .map_err(|_| NatMapError::NoUpnpGateway)?,
+        DISCOVERY_TIMEOUT,
+    )?;
+    stream.set_read_timeout(Some(DISCOVERY_TIMEOUT))?;
+    stream.set_write_timeout(Some(DISCOVERY_TIMEOUT))?;
+    stream.write_all(request.as_bytes())?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+
+    // Strip the HTTP headers, leaving just the SOAP body.
+    match response.find("\r\n\r\n") {
+        Some(idx) => Ok(response[idx + 4..].to_string()),
+        None => Ok(response),
+    }
+}
+
+fn http_get(host: &str, port: u16, path: &str) -> Result<String, NatMapError> {
+    let mut stream = TcpStream::connect_timeout(
+        &format!("{}:{}", host, port)
+            .parse()
+            .map_err(|_| NatMapError::NoUpnpGateway)?,
+        DISCOVERY_TIMEOUT,
+    )?;
+    stream.set_read_timeout(Some(DISCOVERY_TIMEOUT))?;
+    write!(
+        stream,
+        "GET {path} HTTP/1.1\r\nHost: {host}\r\nConnection: close\r\n\r\n",
+        path = path,
+        host = host
+    )?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    match response.find("\r\n\r\n") {
+        Some(idx) => Ok(response[idx + 4..].to_string()),
+        None => Ok(response),
+    }
+}
+
+/// Best-effort local LAN IPv4 address, used as `NewInternalClient` in the
+/// `AddPortMapping` call. Reuses the same interface enumeration
+/// [`crate::ice`] does for host candidates rather than inventing a second
+/// way to find it.
+fn local_lan_ip() -> Result<Ipv4Addr, NatMapError> {
+    use local_ip_address::list_afinet_netifas;
+
+    let interfaces = list_afinet_netifas().map_err(|_| NatMapError::NoGateway)?;
+    interfaces
+        .into_iter()
+        .find_map(|(_, ip)| match ip {
+            IpAddr::V4(v4) if !v4.is_loopback() => Some(v4),
+            _ => None,
+        })
+        .ok_or(NatMapError::NoGateway)
+}
+
+// --- NAT-PMP/PCP -------------------------------------------------------
+
+/// Guess the default gateway by taking our own LAN /24 and assuming `.1`
+/// - there's no portable "ask the OS for the default route" API in std,
+/// and pulling in a routing-table crate for this one lookup isn't worth
+/// it given NAT-PMP is already the fallback-of-a-fallback.
+fn guess_gateway() -> Result<Ipv4Addr, NatMapError> {
+    let local = local_lan_ip()?;
+    let octets = local.octets();
+    Ok(Ipv4Addr::new(octets[0], octets[1], octets[2], 1))
+}
+
+/// NAT-PMP (RFC 6886) request/response: ask the gateway to map
+/// `local_port` and return the external port/address it assigned.
+fn natpmp_add_mapping(
+    local_port: u16,
+    protocol: MappedProtocol,
+    lease: Duration,
+) -> Result<SocketAddr, NatMapError> {
+    let gateway = guess_gateway()?;
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.set_read_timeout(Some(DISCOVERY_TIMEOUT))?;
+
+    // version(1=0) || opcode(1) || reserved(2) || internal_port(2) ||
+    // external_port(2) || lease_seconds(4), all big-endian (RFC 6886 §3.3).
+    let mut request = Vec::with_capacity(12);
+    request.push(0); // version 0
+    request.push(protocol.natpmp_opcode());
+    request.extend_from_slice(&[0, 0]); // reserved
+    request.extend_from_slice(&local_port.to_be_bytes());
+    request.extend_from_slice(&local_port.to_be_bytes()); // request same external port
+    request.extend_from_slice(&(lease.as_secs() as u32).to_be_bytes());
+
+    socket.send_to(&request, (gateway, NATPMP_PORT))?;
+
+    let mut buf = [0u8; 16];
+    let (len, _) = socket
+        .recv_from(&mut buf)
+        .map_err(|_| NatMapError::NoNatPmpGateway)?;
+    if len < 16 {
+        return Err(NatMapError::NoNatPmpGateway);
+    }
+
+    let result_code = u16::from_be_bytes([buf[2], buf[3]]);
+    if result_code != 0 {
+        return Err(NatMapError::NatPmpRejected(result_code));
+    }
+    let external_port = u16::from_be_bytes([buf[10], buf[11]]);
+
+    let external_ip = natpmp_external_address(&socket, gateway)?;
+    Ok(SocketAddr::new(IpAddr::V4(external_ip), external_port))
+}
+
+/// NAT-PMP "public address request" (opcode 0), used to learn the
+/// gateway's WAN address after a successful mapping.
+fn natpmp_external_address(socket: &UdpSocket, gateway: Ipv4Addr) -> Result<Ipv4Addr, NatMapError> {
+    let request = [0u8, 0u8]; // version 0, opcode 0
+    socket.send_to(&request, (gateway, NATPMP_PORT))?;
+
+    let mut buf = [0u8; 12];
+    let (len, _) = socket
+        .recv_from(&mut buf)
+        .map_err(|_| NatMapError::NoNatPmpGateway)?;
+    if len < 12 {
+        return Err(NatMapError::NoNatPmpGateway);
+    }
+
+    let result_code = u16::from_be_bytes([buf[2], buf[3]]);
+    if result_code != 0 {
+        return Err(NatMapError::NatPmpRejected(result_code));
+    }
+    Ok(Ipv4Addr::new(buf[8], buf[9], buf[10], buf[11]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mapped_protocol_natpmp_opcodes_are_distinct() {
+        assert_ne!(
+            MappedProtocol::Tcp.natpmp_opcode(),
+            MappedProtocol::Udp.natpmp_opcode()
+        );
+    }
+
+    #[test]
+    fn guess_gateway_falls_back_to_dot_one_of_local_subnet() {
+        // Not asserting a specific address (depends on the test host's
+        // network), just that it doesn't error when a LAN interface
+        // exists - the common case in CI containers with a bridge network.
+        let _ = guess_gateway();
+    }
+}
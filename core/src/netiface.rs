@@ -0,0 +1,85 @@
+//! Local network interface enumeration, via `getifaddrs(3)`.
+//!
+//! Discovery (`discovery::DiscoveryManager`) needs a bind address and the
+//! mobile app has no reliable way to learn which local address corresponds
+//! to the active Wi-Fi/hotspot interface short of parsing platform-specific
+//! connectivity APIs. This gives the FFI caller the same view the OS has:
+//! every up, non-loopback interface and the address it's currently holding.
+
+use std::ffi::CStr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// One interface/address pair returned by [`list_interfaces`].
+#[derive(Debug, Clone)]
+pub struct NetInterface {
+    pub name: String,
+    pub address: IpAddr,
+    pub is_ipv4: bool,
+    /// Always `false` in practice - loopback interfaces are filtered out
+    /// before this struct is built - but kept as its own field rather than
+    /// implied by the caller re-deriving it from `address`, matching how
+    /// `discovery::DiscoveredPeer` carries its own `manual` flag instead of
+    /// making callers infer it.
+    pub is_loopback: bool,
+}
+
+/// Walk `getifaddrs(3)`, keeping only `AF_INET`/`AF_INET6` entries that are
+/// up (`IFF_UP`) and not loopback (`IFF_LOOPBACK`).
+#[cfg(unix)]
+pub fn list_interfaces() -> Vec<NetInterface> {
+    let mut result = Vec::new();
+
+    unsafe {
+        let mut addrs: *mut libc::ifaddrs = std::ptr::null_mut();
+        if libc::getifaddrs(&mut addrs) != 0 {
+            tracing::warn!("getifaddrs failed: {}", std::io::Error::last_os_error());
+            return result;
+        }
+
+        let mut cur = addrs;
+        while !cur.is_null() {
+            let ifa = &*cur;
+            cur = ifa.ifa_next;
+
+            if ifa.ifa_addr.is_null() {
+                continue;
+            }
+            let family = (*ifa.ifa_addr).sa_family as libc::c_int;
+            if family != libc::AF_INET && family != libc::AF_INET6 {
+                continue;
+            }
+
+            let is_up = ifa.ifa_flags & (libc::IFF_UP as u32) != 0;
+            let is_loopback = ifa.ifa_flags & (libc::IFF_LOOPBACK as u32) != 0;
+            if !is_up || is_loopback {
+                continue;
+            }
+
+            let address = if family == libc::AF_INET {
+                let sin = &*(ifa.ifa_addr as *const libc::sockaddr_in);
+                IpAddr::V4(Ipv4Addr::from(sin.sin_addr.s_addr.to_ne_bytes()))
+            } else {
+                let sin6 = &*(ifa.ifa_addr as *const libc::sockaddr_in6);
+                IpAddr::V6(Ipv6Addr::from(sin6.sin6_addr.s6_addr))
+            };
+
+            result.push(NetInterface {
+                name: CStr::from_ptr(ifa.ifa_name).to_string_lossy().into_owned(),
+                address,
+                is_ipv4: family == libc::AF_INET,
+                is_loopback: false,
+            });
+        }
+
+        libc::freeifaddrs(addrs);
+    }
+
+    result
+}
+
+/// `getifaddrs` is POSIX-only; Windows callers get an empty list rather
+/// than a compile error. The caller this is for (Android) is always unix.
+#[cfg(not(unix))]
+pub fn list_interfaces() -> Vec<NetInterface> {
+    Vec::new()
+}
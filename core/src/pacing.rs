@@ -0,0 +1,128 @@
+//! Chunk-counted CUBIC pacing for the pipelined transfer window.
+//!
+//! [`receiver::FileReceiverServer::receive_windowed`](crate::receiver::FileReceiverServer::receive_windowed)
+//! pipelines chunks under a fixed `WINDOW_SIZE`, but a fixed window either
+//! leaves a fast LAN underused or floods a congested Wi-Fi hop hard enough
+//! to cause the very gaps the selective ACK has to chase down. `ChunkCubic`
+//! gives the receiver a window that grows on sustained clean delivery and
+//! backs off the moment a gap shows up, the same CUBIC shape
+//! [`crate::vwtp::congestion::Cubic`] uses for the QUIC-style
+//! transport - just counted in whole chunks instead of bytes, since that's
+//! the unit `SelectiveAck` already reports in.
+//!
+//! Unlike `vwtp::congestion::Cubic`, this has no TCP-friendly region:
+//! the pipelined TCP sender isn't sharing a bottleneck with a NewReno flow
+//! in any way this crate can observe, so there's nothing to stay fair
+//! against.
+
+use std::time::Instant;
+
+/// Multiplicative decrease factor applied to `cwnd` on a detected gap.
+const CUBIC_BETA: f64 = 0.7;
+/// Window-growth aggressiveness; mirrors `vwtp::congestion::CUBIC_C`.
+const CUBIC_C: f64 = 0.4;
+
+/// Tracks a congestion window, in whole chunks, for one side of a
+/// pipelined transfer. The receiver owns one (see `receive_windowed`) and
+/// reports `cwnd()` back to the sender in every [`crate::protocol::SelectiveAck`].
+#[derive(Debug)]
+pub struct ChunkCubic {
+    cwnd: f64,
+    /// Window size at the last loss event; `None` before the first one,
+    /// during which `cwnd` just grows by one chunk per clean ACK batch
+    /// (plain slow start - there's no `w_max` to grow back towards yet).
+    w_max: Option<f64>,
+    epoch_start: Option<Instant>,
+}
+
+impl ChunkCubic {
+    /// `initial_window` is the starting `cwnd`, in chunks - typically a
+    /// small slow-start value, not the hard `WINDOW_SIZE` ceiling the
+    /// replay-protection window imposes.
+    pub fn new(initial_window: u32) -> Self {
+        ChunkCubic {
+            cwnd: initial_window.max(1) as f64,
+            w_max: None,
+            epoch_start: None,
+        }
+    }
+
+    /// `W_cubic(t) = C*(t - K)^3 + w_max`, `K = cbrt(w_max*beta/C)`.
+    fn w_cubic(&self, w_max: f64, t: f64) -> f64 {
+        let k = (w_max * CUBIC_BETA / CUBIC_C).cbrt();
+        CUBIC_C * (t - k).powi(3) + w_max
+    }
+
+    /// A batch of chunks was acknowledged with no gaps below the window
+    /// edge - grow the window.
+    pub fn on_ack(&mut self) {
+        match (self.w_max, self.epoch_start) {
+            (Some(w_max), Some(epoch)) => {
+                let t = epoch.elapsed().as_secs_f64();
+                self.cwnd = self.w_cubic(w_max, t).max(1.0);
+            }
+            _ => {
+                // No congestion event yet: plain slow start.
+                self.cwnd += 1.0;
+            }
+        }
+    }
+
+    /// A gap (missing chunk still unfilled below the window edge) or a
+    /// checksum/AEAD failure was observed - treat it as a loss event: snap
+    /// `w_max` to the pre-loss window, cut `cwnd` by `CUBIC_BETA`, and
+    /// restart the epoch the cubic growth function measures `t` from.
+    pub fn on_loss(&mut self) {
+        self.w_max = Some(self.cwnd);
+        self.cwnd = (self.cwnd * CUBIC_BETA).max(1.0);
+        self.epoch_start = Some(Instant::now());
+    }
+
+    /// Current window, in whole chunks (rounded, minimum 1).
+    pub fn cwnd(&self) -> u32 {
+        self.cwnd.round().max(1.0) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_by_one_chunk_per_ack_before_the_first_loss() {
+        let mut pacer = ChunkCubic::new(4);
+        pacer.on_ack();
+        pacer.on_ack();
+        assert_eq!(pacer.cwnd(), 6);
+    }
+
+    #[test]
+    fn loss_sets_w_max_and_shrinks_by_beta() {
+        let mut pacer = ChunkCubic::new(10);
+        pacer.on_loss();
+        assert_eq!(pacer.w_max, Some(10.0));
+        assert_eq!(pacer.cwnd(), 7); // round(10 * 0.7)
+    }
+
+    #[test]
+    fn window_recovers_towards_w_max_after_a_loss() {
+        let mut pacer = ChunkCubic::new(20);
+        pacer.on_loss();
+        let just_after_loss = pacer.cwnd();
+        // Force enough wall-clock time to pass that the cubic function
+        // should have climbed back up noticeably, without depending on a
+        // fragile instant-zero assumption.
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        pacer.on_ack();
+        assert!(pacer.cwnd() >= just_after_loss);
+    }
+
+    #[test]
+    fn window_never_drops_below_one_chunk() {
+        let mut pacer = ChunkCubic::new(1);
+        pacer.on_loss();
+        pacer.on_loss();
+        pacer.on_loss();
+        assert!(pacer.cwnd() >= 1);
+    }
+}
@@ -2,14 +2,79 @@
 //!
 //! This module defines the exact byte layout for the handshake and data transfer.
 
+use serde::{Deserialize, Serialize};
 use std::io::{self, Read, Write};
 
+use crate::checksum::HashMethod;
+
 /// P2P Protocol Version (increment when changing handshake format)
-pub const PROTOCOL_VERSION: u8 = 1;
+pub const PROTOCOL_VERSION: u8 = 2;
+
+/// Whether a transfer carries a single file or a whole folder. A folder
+/// transfer's payload is prefixed with a length-delimited JSON
+/// [`TransferManifest`] (see `io_utils::handle_folder_write`) so the
+/// receiver knows the file list and per-file sizes before any file bytes
+/// arrive; a single-file transfer has no such header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferType {
+    SingleFile,
+    Folder,
+}
+
+impl TransferType {
+    fn to_byte(self) -> u8 {
+        match self {
+            TransferType::SingleFile => 0,
+            TransferType::Folder => 1,
+        }
+    }
+
+    fn from_byte(byte: u8) -> io::Result<Self> {
+        match byte {
+            0 => Ok(TransferType::SingleFile),
+            1 => Ok(TransferType::Folder),
+            other => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown transfer type byte: {}", other),
+            )),
+        }
+    }
+}
+
+/// One file within a [`TransferManifest`], in the order it's streamed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestItem {
+    /// Forward-slash-separated path relative to the transfer root.
+    pub path: String,
+    pub size: u64,
+    pub hash: String,
+}
+
+/// Describes a folder transfer's contents: sent as the length-prefixed JSON
+/// header in front of the concatenated file bytes (see
+/// `sender::TcpFileSender::new_folder` and `io_utils::handle_folder_write`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferManifest {
+    pub items: Vec<ManifestItem>,
+    /// Sum of `items[*].size` - excludes the manifest header itself.
+    pub total_size: u64,
+}
+
+/// Salt and nonce base a sender negotiating `security::chunk_aead::ChunkAead`
+/// carries to the receiver in `HandshakeRequest::aead_params` - see that
+/// module's doc comment for why this is a separate, passphrase-derived
+/// layer from `chunk_cipher::ChunkCipher`'s X25519-exchanged one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AeadParams {
+    pub salt: [u8; 16],
+    pub nonce_base: [u8; 4],
+}
 
 /// Handshake Request sent by Sender
 /// [VERSION:u8][SENDER_NAME_LEN:u8][SENDER_NAME:bytes][FILE_NAME_LEN:u16][FILE_NAME:bytes]
-/// [FILE_SIZE:u64][CHUNK_SIZE:u32][CHECKSUM_LEN:u8][CHECKSUM:bytes]
+/// [FILE_SIZE:u64][CHUNK_SIZE:u32][CHECKSUM_LEN:u8][CHECKSUM:bytes][TRANSFER_TYPE:u8][ENCRYPTED:u8]
+/// [TRANSFER_ID:u64][STREAM_COUNT:u32][DEDUPLICATED:u8][AEAD_PARAMS_PRESENT:u8][AEAD_PARAMS:20 bytes if present]
+/// [HASH_METHOD:u8][MERKLE_ROOT_PRESENT:u8][MERKLE_ROOT_LEN:u8][MERKLE_ROOT:bytes if present]
 #[derive(Debug, Clone)]
 pub struct HandshakeRequest {
     pub version: u8,
@@ -18,6 +83,46 @@ pub struct HandshakeRequest {
     pub file_size: u64,
     pub chunk_size: u32,
     pub file_checksum: String,
+    pub transfer_type: TransferType,
+    /// Whether the sender will run `security::chunk_cipher::exchange_key`
+    /// and encrypt chunks with the result before sealing them with the
+    /// handshake's `SecureChannel` - see `security::chunk_cipher`'s module
+    /// doc for why this is a layer on top of that channel, not instead of
+    /// it.
+    pub encrypted: bool,
+    /// Identifies every connection belonging to the same multi-stream
+    /// transfer (see `sender::TcpFileSender::send_multi_stream`). `0` for
+    /// an ordinary single-connection transfer, where it's meaningless.
+    pub transfer_id: u64,
+    /// How many concurrent connections this transfer is split across. `1`
+    /// (the default) means an ordinary single-connection transfer; the
+    /// receiver only registers a shared `receiver::MultiStreamTransfer`
+    /// and dispatches later connections without a UI prompt when this is
+    /// greater than 1.
+    pub stream_count: u32,
+    /// Whether this is a content-defined-chunking transfer (see the
+    /// `dedup` module and `sender::TcpFileSender::send_deduplicated`):
+    /// the receiver answers with a `protocol::KnownChunks` right after
+    /// the handshake instead of the usual resume offer, and the data
+    /// phase is a stream of `dedup::ContentChunk`-framed references or
+    /// full chunks rather than fixed-size, position-indexed ones.
+    pub deduplicated: bool,
+    /// Present when this transfer encrypts chunks with
+    /// `security::chunk_aead::ChunkAead` instead of (or in addition to)
+    /// `chunk_cipher` - see `AeadParams`'s doc comment. `None` when this
+    /// transfer isn't using passphrase AEAD.
+    pub aead_params: Option<AeadParams>,
+    /// Which algorithm `checksum::calculate_file_checksum_with_method` and
+    /// `merkle::MerkleAccumulator` use for this transfer's checksums and
+    /// Merkle tree. Defaults to `HashMethod::Md5` for compatibility with
+    /// senders that don't negotiate one.
+    pub hash_method: HashMethod,
+    /// The sender's `merkle::MerkleAccumulator::root` over every chunk's
+    /// hash, sent up front so the receiver can fold chunks into its own
+    /// accumulator as they arrive and compare roots the instant the last
+    /// one lands, rather than re-hashing the finished file. `None` when the
+    /// sender didn't build one.
+    pub merkle_root: Option<Vec<u8>>,
 }
 
 impl HandshakeRequest {
@@ -27,6 +132,8 @@ impl HandshakeRequest {
         file_size: u64,
         chunk_size: u32,
         file_checksum: &str,
+        transfer_type: TransferType,
+        encrypted: bool,
     ) -> Self {
         Self {
             version: PROTOCOL_VERSION,
@@ -35,6 +142,86 @@ impl HandshakeRequest {
             file_size,
             chunk_size,
             file_checksum: file_checksum.to_string(),
+            transfer_type,
+            encrypted,
+            transfer_id: 0,
+            stream_count: 1,
+            deduplicated: false,
+            aead_params: None,
+            hash_method: HashMethod::Md5,
+            merkle_root: None,
+        }
+    }
+
+    /// Like `new`, but negotiating `security::chunk_aead::ChunkAead` for
+    /// this transfer - see `AeadParams`'s doc comment.
+    pub fn with_aead_params(mut self, aead_params: AeadParams) -> Self {
+        self.aead_params = Some(aead_params);
+        self
+    }
+
+    /// Like `new`, but negotiating a `HashMethod` other than the `Md5`
+    /// default for this transfer's checksums and Merkle tree.
+    pub fn with_hash_method(mut self, hash_method: HashMethod) -> Self {
+        self.hash_method = hash_method;
+        self
+    }
+
+    /// Like `new`, but carrying a `merkle::MerkleAccumulator::root` built
+    /// over this transfer's chunks - see `merkle_root`'s doc comment.
+    pub fn with_merkle_root(mut self, merkle_root: Vec<u8>) -> Self {
+        self.merkle_root = Some(merkle_root);
+        self
+    }
+
+    /// Like `new`, but for a connection that's one of several sharing a
+    /// multi-stream transfer - see `sender::TcpFileSender::send_multi_stream`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_multi_stream(
+        sender_name: &str,
+        file_name: &str,
+        file_size: u64,
+        chunk_size: u32,
+        file_checksum: &str,
+        transfer_type: TransferType,
+        transfer_id: u64,
+        stream_count: u32,
+    ) -> Self {
+        Self {
+            transfer_id,
+            stream_count,
+            ..Self::new(
+                sender_name,
+                file_name,
+                file_size,
+                chunk_size,
+                file_checksum,
+                transfer_type,
+                false,
+            )
+        }
+    }
+
+    /// Like `new`, but for `sender::TcpFileSender::send_deduplicated` -
+    /// see `deduplicated`'s doc comment.
+    pub fn new_deduplicated(
+        sender_name: &str,
+        file_name: &str,
+        file_size: u64,
+        chunk_size: u32,
+        file_checksum: &str,
+    ) -> Self {
+        Self {
+            deduplicated: true,
+            ..Self::new(
+                sender_name,
+                file_name,
+                file_size,
+                chunk_size,
+                file_checksum,
+                TransferType::SingleFile,
+                false,
+            )
         }
     }
 
@@ -64,6 +251,32 @@ impl HandshakeRequest {
         writer.write_all(&[check_len])?;
         writer.write_all(&checksum_bytes[..check_len as usize])?;
 
+        writer.write_all(&[self.transfer_type.to_byte()])?;
+        writer.write_all(&[self.encrypted as u8])?;
+        writer.write_all(&self.transfer_id.to_be_bytes())?;
+        writer.write_all(&self.stream_count.to_be_bytes())?;
+        writer.write_all(&[self.deduplicated as u8])?;
+
+        match &self.aead_params {
+            Some(params) => {
+                writer.write_all(&[1])?;
+                writer.write_all(&params.salt)?;
+                writer.write_all(&params.nonce_base)?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+
+        writer.write_all(&[self.hash_method.to_byte()])?;
+        match &self.merkle_root {
+            Some(root) => {
+                writer.write_all(&[1])?;
+                let root_len = std::cmp::min(root.len(), 255) as u8;
+                writer.write_all(&[root_len])?;
+                writer.write_all(&root[..root_len as usize])?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+
         Ok(())
     }
 
@@ -115,6 +328,54 @@ impl HandshakeRequest {
         reader.read_exact(&mut check_buf)?;
         let file_checksum = String::from_utf8_lossy(&check_buf).to_string();
 
+        let mut transfer_type_buf = [0u8; 1];
+        reader.read_exact(&mut transfer_type_buf)?;
+        let transfer_type = TransferType::from_byte(transfer_type_buf[0])?;
+
+        let mut encrypted_buf = [0u8; 1];
+        reader.read_exact(&mut encrypted_buf)?;
+        let encrypted = encrypted_buf[0] != 0;
+
+        let mut transfer_id_buf = [0u8; 8];
+        reader.read_exact(&mut transfer_id_buf)?;
+        let transfer_id = u64::from_be_bytes(transfer_id_buf);
+
+        let mut stream_count_buf = [0u8; 4];
+        reader.read_exact(&mut stream_count_buf)?;
+        let stream_count = u32::from_be_bytes(stream_count_buf);
+
+        let mut deduplicated_buf = [0u8; 1];
+        reader.read_exact(&mut deduplicated_buf)?;
+        let deduplicated = deduplicated_buf[0] != 0;
+
+        let mut aead_present_buf = [0u8; 1];
+        reader.read_exact(&mut aead_present_buf)?;
+        let aead_params = if aead_present_buf[0] != 0 {
+            let mut salt = [0u8; 16];
+            reader.read_exact(&mut salt)?;
+            let mut nonce_base = [0u8; 4];
+            reader.read_exact(&mut nonce_base)?;
+            Some(AeadParams { salt, nonce_base })
+        } else {
+            None
+        };
+
+        let mut hash_method_buf = [0u8; 1];
+        reader.read_exact(&mut hash_method_buf)?;
+        let hash_method = HashMethod::from_byte(hash_method_buf[0])?;
+
+        let mut merkle_present_buf = [0u8; 1];
+        reader.read_exact(&mut merkle_present_buf)?;
+        let merkle_root = if merkle_present_buf[0] != 0 {
+            let mut root_len_buf = [0u8; 1];
+            reader.read_exact(&mut root_len_buf)?;
+            let mut root = vec![0u8; root_len_buf[0] as usize];
+            reader.read_exact(&mut root)?;
+            Some(root)
+        } else {
+            None
+        };
+
         Ok(Self {
             version,
             sender_name,
@@ -122,6 +383,1018 @@ impl HandshakeRequest {
             file_size,
             chunk_size,
             file_checksum,
+            transfer_type,
+            encrypted,
+            transfer_id,
+            stream_count,
+            deduplicated,
+            aead_params,
+            hash_method,
+            merkle_root,
+        })
+    }
+}
+
+/// Stable content-addressed identifier for a chunk cut by
+/// `dedup::cut_content_chunks` - a BLAKE3 hash of the chunk's plaintext
+/// bytes. Unlike the position-indexed chunks everywhere else in this
+/// module, an id depends only on content, so it's the same on both ends
+/// without either side needing to agree on offsets up front.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContentChunkId(pub [u8; 32]);
+
+impl ContentChunkId {
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.0)
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut buf = [0u8; 32];
+        reader.read_exact(&mut buf)?;
+        Ok(Self(buf))
+    }
+}
+
+/// Sent by the receiver right after the handshake in a deduplicated
+/// transfer (see `sender::TcpFileSender::send_deduplicated`): every
+/// content id it already holds locally, read off whatever partial or
+/// older copy of the destination file is already on disk. The sender
+/// diffs its own chunk list against this set and skips resending
+/// anything already in it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KnownChunks {
+    pub ids: Vec<ContentChunkId>,
+}
+
+impl KnownChunks {
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&(self.ids.len() as u32).to_be_bytes())?;
+        for id in &self.ids {
+            id.write_to(writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut count_buf = [0u8; 4];
+        reader.read_exact(&mut count_buf)?;
+        let count = u32::from_be_bytes(count_buf) as usize;
+        let mut ids = Vec::with_capacity(count.min(1 << 16));
+        for _ in 0..count {
+            ids.push(ContentChunkId::read_from(reader)?);
+        }
+        Ok(Self { ids })
+    }
+}
+
+/// An HTTP-Range-style request for a slice of the global transfer stream
+/// (manifest header, if any, plus the concatenated file bytes - the same
+/// addressing `io_utils::MultiFileReader`/`ChunkSource` use). Lets a
+/// receiver ask for less than the whole stream: resuming a folder transfer
+/// partway through, or splitting a transfer across several connections that
+/// each pull a disjoint slice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// Everything from `start` to the end: `start..total_size`.
+    From(u64),
+    /// An inclusive byte window: `start..=end`.
+    Full(u64, u64),
+    /// The final `n` bytes, i.e. `(total_size - n)..total_size`.
+    Suffix(u64),
+}
+
+impl ByteRange {
+    /// Resolve against the stream's actual `total_size`, producing the
+    /// `(start, len)` pair a sender reads with: `seek(SeekFrom::Start(start))`
+    /// on its `ChunkSource`, then stream exactly `len` bytes.
+    pub fn resolve(self, total_size: u64) -> io::Result<(u64, u64)> {
+        let (start, end_exclusive) = match self {
+            ByteRange::From(start) => (start, total_size),
+            ByteRange::Full(start, end) => (start, end.saturating_add(1)),
+            ByteRange::Suffix(n) => (total_size.saturating_sub(n), total_size),
+        };
+
+        if start > total_size || end_exclusive > total_size || start > end_exclusive {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "range {:?} out of bounds for stream of {} bytes",
+                    self, total_size
+                ),
+            ));
+        }
+
+        Ok((start, end_exclusive - start))
+    }
+}
+
+/// Wire form of a [`ByteRange`] request: `[TAG:u8][A:u64 BE][B:u64 BE]`.
+/// `B` is unused (sent as zero) for `From`/`Suffix`, which only need one
+/// operand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RangeRequest {
+    pub range: ByteRange,
+}
+
+impl RangeRequest {
+    pub fn new(range: ByteRange) -> Self {
+        Self { range }
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let (tag, a, b): (u8, u64, u64) = match self.range {
+            ByteRange::From(start) => (0, start, 0),
+            ByteRange::Full(start, end) => (1, start, end),
+            ByteRange::Suffix(n) => (2, n, 0),
+        };
+        writer.write_all(&[tag])?;
+        writer.write_all(&a.to_be_bytes())?;
+        writer.write_all(&b.to_be_bytes())?;
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut tag_buf = [0u8; 1];
+        reader.read_exact(&mut tag_buf)?;
+
+        let mut a_buf = [0u8; 8];
+        reader.read_exact(&mut a_buf)?;
+        let a = u64::from_be_bytes(a_buf);
+
+        let mut b_buf = [0u8; 8];
+        reader.read_exact(&mut b_buf)?;
+        let b = u64::from_be_bytes(b_buf);
+
+        let range = match tag_buf[0] {
+            0 => ByteRange::From(a),
+            1 => ByteRange::Full(a, b),
+            2 => ByteRange::Suffix(a),
+            other => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown range tag: {}", other),
+                ))
+            }
+        };
+
+        Ok(Self { range })
+    }
+}
+
+/// Batched ACK for a windowed/pipelined transfer (see
+/// `sender::TcpFileSender::send_windowed` and
+/// `receiver::FileReceiverServer::receive_windowed`): instead of one ACK
+/// per chunk, the receiver periodically reports the highest index it has
+/// received with no gaps below it, plus the indices still missing below
+/// the window edge, so the sender only has to retransmit actual gaps.
+///
+/// Wire form: `[HIGHEST_CONTIGUOUS:u64 BE][MISSING_COUNT:u32 BE][MISSING:u64 BE, ...][CWND:u32 BE]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectiveAck {
+    /// Every chunk index `0..=highest_contiguous` has been received. `None`
+    /// if not even chunk 0 has landed yet.
+    pub highest_contiguous: Option<u64>,
+    /// Indices above `highest_contiguous` (or anywhere, for a stalled
+    /// first chunk) that the receiver still doesn't have.
+    pub missing: Vec<u64>,
+    /// The receiver's current `pacing::ChunkCubic` window, in chunks - the
+    /// sender paces its window to this rather than a fixed size, so a fast
+    /// LAN can ramp up and a congested link backs off without either side
+    /// needing a separate control message.
+    pub cwnd: u32,
+}
+
+impl SelectiveAck {
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        // `u64::MAX` doubles as the "nothing contiguous yet" sentinel so
+        // the frame doesn't need an extra presence byte.
+        let highest = self.highest_contiguous.unwrap_or(u64::MAX);
+        writer.write_all(&highest.to_be_bytes())?;
+        writer.write_all(&(self.missing.len() as u32).to_be_bytes())?;
+        for idx in &self.missing {
+            writer.write_all(&idx.to_be_bytes())?;
+        }
+        writer.write_all(&self.cwnd.to_be_bytes())?;
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut highest_buf = [0u8; 8];
+        reader.read_exact(&mut highest_buf)?;
+        let highest = u64::from_be_bytes(highest_buf);
+        let highest_contiguous = if highest == u64::MAX { None } else { Some(highest) };
+
+        let mut count_buf = [0u8; 4];
+        reader.read_exact(&mut count_buf)?;
+        let count = u32::from_be_bytes(count_buf) as usize;
+
+        let mut missing = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut idx_buf = [0u8; 8];
+            reader.read_exact(&mut idx_buf)?;
+            missing.push(u64::from_be_bytes(idx_buf));
+        }
+
+        let mut cwnd_buf = [0u8; 4];
+        reader.read_exact(&mut cwnd_buf)?;
+        let cwnd = u32::from_be_bytes(cwnd_buf);
+
+        Ok(Self {
+            highest_contiguous,
+            missing,
+            cwnd,
         })
     }
 }
+
+/// Sent by the receiver right after `WINDOW_SIZE` for a single-file
+/// transfer (see `receiver::FileReceiverServer::accept_transfer`): chunk
+/// indices at or beyond the handshake's resume-chunk offer that already
+/// landed on disk out of order before a previous connection dropped,
+/// persisted in the `io_utils` chunk-bitmap sidecar. `send_windowed` skips
+/// these during its initial window fill instead of blindly retransmitting
+/// everything from the resume offer onward.
+///
+/// Wire form: `[COUNT:u32 BE][INDEX:u64 BE, ...]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResumeOffer {
+    pub already_have: Vec<u64>,
+}
+
+impl ResumeOffer {
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&(self.already_have.len() as u32).to_be_bytes())?;
+        for idx in &self.already_have {
+            writer.write_all(&idx.to_be_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut count_buf = [0u8; 4];
+        reader.read_exact(&mut count_buf)?;
+        let count = u32::from_be_bytes(count_buf) as usize;
+
+        let mut already_have = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut idx_buf = [0u8; 8];
+            reader.read_exact(&mut idx_buf)?;
+            already_have.push(u64::from_be_bytes(idx_buf));
+        }
+
+        Ok(Self { already_have })
+    }
+}
+
+/// Sent by the receiver to one connection of a multi-stream transfer (see
+/// `receiver::MultiStreamTransfer`), right after the per-connection Noise
+/// handshake completes, assigning it the disjoint half-open chunk range
+/// `start_chunk..end_chunk` to send. Every connection of the transfer gets
+/// exactly one of these before streaming any chunk data.
+///
+/// Wire form: `[START_CHUNK:u64 BE][END_CHUNK:u64 BE]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamRangeOffer {
+    pub start_chunk: u64,
+    pub end_chunk: u64,
+}
+
+impl StreamRangeOffer {
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.start_chunk.to_be_bytes())?;
+        writer.write_all(&self.end_chunk.to_be_bytes())?;
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut start_buf = [0u8; 8];
+        reader.read_exact(&mut start_buf)?;
+        let start_chunk = u64::from_be_bytes(start_buf);
+
+        let mut end_buf = [0u8; 8];
+        reader.read_exact(&mut end_buf)?;
+        let end_chunk = u64::from_be_bytes(end_buf);
+
+        Ok(Self {
+            start_chunk,
+            end_chunk,
+        })
+    }
+}
+
+/// One chunk of file data, framed with its own checksum so a corrupted
+/// chunk is caught the moment it arrives instead of only at the final
+/// whole-file checksum (see `transfer::FileReceiver::write_chunk`), which
+/// otherwise forces restarting the entire transfer to fix a single bad
+/// chunk.
+///
+/// Wire form: `[CHUNK_INDEX:u64 BE][OFFSET:u64 BE][DATA_LEN:u32 BE][CSUM_LEN:u8][CSUM:bytes][DATA:bytes]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkFrame {
+    pub chunk_index: u64,
+    pub offset: u64,
+    pub checksum: [u8; 16],
+    pub data: Vec<u8>,
+}
+
+impl ChunkFrame {
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.chunk_index.to_be_bytes())?;
+        writer.write_all(&self.offset.to_be_bytes())?;
+        writer.write_all(&(self.data.len() as u32).to_be_bytes())?;
+        writer.write_all(&[self.checksum.len() as u8])?;
+        writer.write_all(&self.checksum)?;
+        writer.write_all(&self.data)?;
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut index_buf = [0u8; 8];
+        reader.read_exact(&mut index_buf)?;
+        let chunk_index = u64::from_be_bytes(index_buf);
+
+        let mut offset_buf = [0u8; 8];
+        reader.read_exact(&mut offset_buf)?;
+        let offset = u64::from_be_bytes(offset_buf);
+
+        let mut len_buf = [0u8; 4];
+        reader.read_exact(&mut len_buf)?;
+        let data_len = u32::from_be_bytes(len_buf) as usize;
+
+        let mut csum_len_buf = [0u8; 1];
+        reader.read_exact(&mut csum_len_buf)?;
+        let csum_len = csum_len_buf[0] as usize;
+        if csum_len != 16 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unexpected chunk checksum length: {}", csum_len),
+            ));
+        }
+        let mut checksum = [0u8; 16];
+        reader.read_exact(&mut checksum)?;
+
+        let mut data = vec![0u8; data_len];
+        reader.read_exact(&mut data)?;
+
+        Ok(Self {
+            chunk_index,
+            offset,
+            checksum,
+            data,
+        })
+    }
+}
+
+/// Sent by the receiver when a `ChunkFrame`'s checksum doesn't match its
+/// data, so the sender can re-read and resend just that one chunk instead
+/// of the whole transfer aborting.
+///
+/// Wire form: `[CHUNK_INDEX:u64 BE]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NackChunk {
+    pub chunk_index: u64,
+}
+
+impl NackChunk {
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.chunk_index.to_be_bytes())?;
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut index_buf = [0u8; 8];
+        reader.read_exact(&mut index_buf)?;
+        Ok(Self {
+            chunk_index: u64::from_be_bytes(index_buf),
+        })
+    }
+}
+
+/// A `ChunkFrame` sealed with `security::chunk_aead::ChunkAead` instead of
+/// carrying a plain checksum - the AEAD tag authenticates the data, so
+/// there's no separate `CSUM` field. `plaintext_len` rides alongside
+/// `ciphertext`'s own length so the receiver can allocate the decrypted
+/// buffer up front rather than only discovering the real size after the
+/// tag check passes.
+///
+/// Wire form: `[CHUNK_INDEX:u64 BE][OFFSET:u64 BE][PLAINTEXT_LEN:u32 BE][CIPHERTEXT_LEN:u32 BE][CIPHERTEXT+TAG:bytes]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedChunkFrame {
+    pub chunk_index: u64,
+    pub offset: u64,
+    pub plaintext_len: u32,
+    /// Ciphertext with the Poly1305 tag appended - what
+    /// `security::chunk_aead::ChunkAead::seal` returns.
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedChunkFrame {
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.chunk_index.to_be_bytes())?;
+        writer.write_all(&self.offset.to_be_bytes())?;
+        writer.write_all(&self.plaintext_len.to_be_bytes())?;
+        writer.write_all(&(self.ciphertext.len() as u32).to_be_bytes())?;
+        writer.write_all(&self.ciphertext)?;
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut index_buf = [0u8; 8];
+        reader.read_exact(&mut index_buf)?;
+        let chunk_index = u64::from_be_bytes(index_buf);
+
+        let mut offset_buf = [0u8; 8];
+        reader.read_exact(&mut offset_buf)?;
+        let offset = u64::from_be_bytes(offset_buf);
+
+        let mut plaintext_len_buf = [0u8; 4];
+        reader.read_exact(&mut plaintext_len_buf)?;
+        let plaintext_len = u32::from_be_bytes(plaintext_len_buf);
+
+        let mut ciphertext_len_buf = [0u8; 4];
+        reader.read_exact(&mut ciphertext_len_buf)?;
+        let ciphertext_len = u32::from_be_bytes(ciphertext_len_buf) as usize;
+
+        let mut ciphertext = vec![0u8; ciphertext_len];
+        reader.read_exact(&mut ciphertext)?;
+
+        Ok(Self {
+            chunk_index,
+            offset,
+            plaintext_len,
+            ciphertext,
+        })
+    }
+}
+
+/// A compact declaration that `count` consecutive `FileMetadata::chunk_size`
+/// chunks starting at `start_chunk` are all zero bytes, sent instead of a
+/// `ChunkFrame`/`EncryptedChunkFrame` per chunk in that run - see
+/// `transfer::FileReceiver::write_zero_run`. Lets a sparse disk image or VM
+/// file skip transmitting, and the receiver skip materializing, its empty
+/// regions.
+///
+/// Wire form: `[START_CHUNK:u64 BE][COUNT:u64 BE]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZeroRun {
+    pub start_chunk: u64,
+    pub count: u64,
+}
+
+impl ZeroRun {
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.start_chunk.to_be_bytes())?;
+        writer.write_all(&self.count.to_be_bytes())?;
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut start_chunk_buf = [0u8; 8];
+        reader.read_exact(&mut start_chunk_buf)?;
+        let mut count_buf = [0u8; 8];
+        reader.read_exact(&mut count_buf)?;
+        Ok(ZeroRun {
+            start_chunk: u64::from_be_bytes(start_chunk_buf),
+            count: u64::from_be_bytes(count_buf),
+        })
+    }
+}
+
+/// Maximum frame length `FrameReader::read_packet` will accept, including
+/// the 4-byte length prefix itself - guards against a corrupt or hostile
+/// length field making the reader allocate gigabytes up front.
+pub const MAX_PACKET_LEN: u32 = 16 * 1024 * 1024;
+
+/// Reserved `LEN` marking a flush: the data channel is done, no more
+/// packets follow until the connection is dropped or reopened.
+///
+/// `pub(crate)` rather than private: a caller reading frames off something
+/// that isn't `std::io::Read` (e.g. `quic`'s async streams) can't hand its
+/// bytes to `FrameReader` until it already knows how many bytes to read,
+/// which means checking for these sentinels itself first.
+pub(crate) const FLUSH_LEN: u32 = 0x0000;
+/// Reserved `LEN` marking a delimiter: a boundary between this
+/// connection's logical phases (handshake -> data -> finalize), carrying
+/// no payload of its own. See [`FLUSH_LEN`] for why this is `pub(crate)`.
+pub(crate) const DELIM_LEN: u32 = 0x0001;
+
+/// Lets `transfer::TransferState::apply_packet` react to a control message
+/// (pause/cancel/nack/resume) interleaved with data on the same
+/// connection, instead of only at connection boundaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketType {
+    Data,
+    Pause,
+    Cancel,
+    Nack,
+    Resume,
+}
+
+impl PacketType {
+    fn to_byte(self) -> u8 {
+        match self {
+            PacketType::Data => 0,
+            PacketType::Pause => 1,
+            PacketType::Cancel => 2,
+            PacketType::Nack => 3,
+            PacketType::Resume => 4,
+        }
+    }
+
+    fn from_byte(byte: u8) -> io::Result<Self> {
+        match byte {
+            0 => Ok(PacketType::Data),
+            1 => Ok(PacketType::Pause),
+            2 => Ok(PacketType::Cancel),
+            3 => Ok(PacketType::Nack),
+            4 => Ok(PacketType::Resume),
+            other => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown packet type byte: {}", other),
+            )),
+        }
+    }
+}
+
+/// One frame off a `FrameReader`: either a sentinel marking a phase
+/// boundary / channel end, or an ordinary packet carrying a typed
+/// payload.
+///
+/// Wire form per packet: `[LEN:u32 BE][TYPE:u8][PAYLOAD:bytes]`, where
+/// `LEN` counts itself plus `TYPE` plus `PAYLOAD` (pkt-line style, after
+/// Git's). `LEN` values `0x0000` and `0x0001` are reserved sentinels with
+/// no `TYPE` or `PAYLOAD` at all - see `Packet::Flush`/`Packet::Delimiter`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Packet {
+    /// `LEN == 0x0000`: the data channel is done.
+    Flush,
+    /// `LEN == 0x0001`: a boundary between logical phases (handshake ->
+    /// data -> finalize).
+    Delimiter,
+    /// An ordinary packet.
+    Message {
+        packet_type: PacketType,
+        payload: Vec<u8>,
+    },
+}
+
+impl Packet {
+    /// Build the raw bytes for an ordinary packet's body - a type byte
+    /// followed by `payload` - ready to hand to `FrameWriter::write_packet`.
+    pub fn encode_message(packet_type: PacketType, payload: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(1 + payload.len());
+        buf.push(packet_type.to_byte());
+        buf.extend_from_slice(payload);
+        buf
+    }
+}
+
+/// Reads `Packet`s off anything `Read`, one frame at a time, enforcing
+/// `MAX_PACKET_LEN`.
+pub struct FrameReader<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> FrameReader<R> {
+    pub fn new(reader: R) -> Self {
+        FrameReader { reader }
+    }
+
+    pub fn read_packet(&mut self) -> io::Result<Packet> {
+        let mut len_buf = [0u8; 4];
+        self.reader.read_exact(&mut len_buf)?;
+        let len = u32::from_be_bytes(len_buf);
+
+        if len == FLUSH_LEN {
+            return Ok(Packet::Flush);
+        }
+        if len == DELIM_LEN {
+            return Ok(Packet::Delimiter);
+        }
+        if len > MAX_PACKET_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("packet length {} exceeds MAX_PACKET_LEN", len),
+            ));
+        }
+        if (len as usize) < 5 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("packet length {} too short for a type byte", len),
+            ));
+        }
+
+        let mut body = vec![0u8; len as usize - 4];
+        self.reader.read_exact(&mut body)?;
+        let packet_type = PacketType::from_byte(body[0])?;
+        let payload = body.split_off(1);
+
+        Ok(Packet::Message {
+            packet_type,
+            payload,
+        })
+    }
+}
+
+/// Writes `Packet`s to anything `Write`, one frame at a time, computing
+/// each one's length prefix.
+pub struct FrameWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> FrameWriter<W> {
+    pub fn new(writer: W) -> Self {
+        FrameWriter { writer }
+    }
+
+    /// Write an ordinary packet's body (see `Packet::encode_message`) with
+    /// its length prefix - `data.len() + 4` including the prefix itself.
+    pub fn write_packet(&mut self, data: &[u8]) -> io::Result<()> {
+        let len = 4 + data.len();
+        if len > MAX_PACKET_LEN as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("packet length {} exceeds MAX_PACKET_LEN", len),
+            ));
+        }
+        self.writer.write_all(&(len as u32).to_be_bytes())?;
+        self.writer.write_all(data)?;
+        Ok(())
+    }
+
+    /// Write the flush sentinel (`LEN == 0x0000`): the data channel is
+    /// done.
+    pub fn write_flush(&mut self) -> io::Result<()> {
+        self.writer.write_all(&FLUSH_LEN.to_be_bytes())
+    }
+
+    /// Write the delimiter sentinel (`LEN == 0x0001`): a boundary between
+    /// logical phases.
+    pub fn write_delimiter(&mut self) -> io::Result<()> {
+        self.writer.write_all(&DELIM_LEN.to_be_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selective_ack_roundtrips_through_the_wire_format() {
+        for ack in [
+            SelectiveAck {
+                highest_contiguous: None,
+                missing: vec![],
+                cwnd: 4,
+            },
+            SelectiveAck {
+                highest_contiguous: Some(41),
+                missing: vec![10, 25],
+                cwnd: 17,
+            },
+        ] {
+            let mut buf = Vec::new();
+            ack.write_to(&mut buf).unwrap();
+            let decoded = SelectiveAck::read_from(&mut &buf[..]).unwrap();
+            assert_eq!(decoded, ack);
+        }
+    }
+
+    #[test]
+    fn resume_offer_roundtrips_through_the_wire_format() {
+        for offer in [
+            ResumeOffer { already_have: vec![] },
+            ResumeOffer {
+                already_have: vec![12, 13, 40],
+            },
+        ] {
+            let mut buf = Vec::new();
+            offer.write_to(&mut buf).unwrap();
+            let decoded = ResumeOffer::read_from(&mut &buf[..]).unwrap();
+            assert_eq!(decoded, offer);
+        }
+    }
+
+    #[test]
+    fn stream_range_offer_roundtrips_through_the_wire_format() {
+        let offer = StreamRangeOffer {
+            start_chunk: 10,
+            end_chunk: 25,
+        };
+        let mut buf = Vec::new();
+        offer.write_to(&mut buf).unwrap();
+        let decoded = StreamRangeOffer::read_from(&mut &buf[..]).unwrap();
+        assert_eq!(decoded, offer);
+    }
+
+    #[test]
+    fn handshake_request_roundtrips_multi_stream_fields() {
+        let request = HandshakeRequest::new_multi_stream(
+            "sender",
+            "file.bin",
+            1000,
+            64,
+            "deadbeef",
+            TransferType::SingleFile,
+            42,
+            4,
+        );
+        let mut buf = Vec::new();
+        request.write_to(&mut buf).unwrap();
+        let decoded = HandshakeRequest::read_from(&mut &buf[..]).unwrap();
+        assert_eq!(decoded.transfer_id, 42);
+        assert_eq!(decoded.stream_count, 4);
+        assert!(!decoded.encrypted);
+    }
+
+    #[test]
+    fn handshake_request_roundtrips_deduplicated_flag() {
+        let request = HandshakeRequest::new_deduplicated("sender", "file.bin", 1000, 64, "deadbeef");
+        let mut buf = Vec::new();
+        request.write_to(&mut buf).unwrap();
+        let decoded = HandshakeRequest::read_from(&mut &buf[..]).unwrap();
+        assert!(decoded.deduplicated);
+        assert_eq!(decoded.stream_count, 1);
+    }
+
+    #[test]
+    fn handshake_request_roundtrips_aead_params() {
+        let request = HandshakeRequest::new(
+            "sender",
+            "file.bin",
+            1000,
+            64,
+            "deadbeef",
+            TransferType::SingleFile,
+            false,
+        )
+        .with_aead_params(AeadParams {
+            salt: [9u8; 16],
+            nonce_base: [1, 2, 3, 4],
+        });
+
+        let mut buf = Vec::new();
+        request.write_to(&mut buf).unwrap();
+        let decoded = HandshakeRequest::read_from(&mut &buf[..]).unwrap();
+        assert_eq!(
+            decoded.aead_params,
+            Some(AeadParams {
+                salt: [9u8; 16],
+                nonce_base: [1, 2, 3, 4],
+            })
+        );
+    }
+
+    #[test]
+    fn handshake_request_without_aead_params_roundtrips_to_none() {
+        let request = HandshakeRequest::new(
+            "sender",
+            "file.bin",
+            1000,
+            64,
+            "deadbeef",
+            TransferType::SingleFile,
+            false,
+        );
+        let mut buf = Vec::new();
+        request.write_to(&mut buf).unwrap();
+        let decoded = HandshakeRequest::read_from(&mut &buf[..]).unwrap();
+        assert_eq!(decoded.aead_params, None);
+    }
+
+    #[test]
+    fn handshake_request_roundtrips_hash_method_and_merkle_root() {
+        let request = HandshakeRequest::new(
+            "sender",
+            "file.bin",
+            1000,
+            64,
+            "deadbeef",
+            TransferType::SingleFile,
+            false,
+        )
+        .with_hash_method(HashMethod::Blake3)
+        .with_merkle_root(vec![7u8; 32]);
+
+        let mut buf = Vec::new();
+        request.write_to(&mut buf).unwrap();
+        let decoded = HandshakeRequest::read_from(&mut &buf[..]).unwrap();
+        assert_eq!(decoded.hash_method, HashMethod::Blake3);
+        assert_eq!(decoded.merkle_root, Some(vec![7u8; 32]));
+    }
+
+    #[test]
+    fn handshake_request_defaults_to_md5_with_no_merkle_root() {
+        let request = HandshakeRequest::new(
+            "sender",
+            "file.bin",
+            1000,
+            64,
+            "deadbeef",
+            TransferType::SingleFile,
+            false,
+        );
+        let mut buf = Vec::new();
+        request.write_to(&mut buf).unwrap();
+        let decoded = HandshakeRequest::read_from(&mut &buf[..]).unwrap();
+        assert_eq!(decoded.hash_method, HashMethod::Md5);
+        assert_eq!(decoded.merkle_root, None);
+    }
+
+    #[test]
+    fn known_chunks_roundtrips_through_the_wire_format() {
+        for known in [
+            KnownChunks { ids: vec![] },
+            KnownChunks {
+                ids: vec![ContentChunkId([1u8; 32]), ContentChunkId([2u8; 32])],
+            },
+        ] {
+            let mut buf = Vec::new();
+            known.write_to(&mut buf).unwrap();
+            let decoded = KnownChunks::read_from(&mut &buf[..]).unwrap();
+            assert_eq!(decoded, known);
+        }
+    }
+
+    #[test]
+    fn from_range_resolves_to_the_end_of_the_stream() {
+        assert_eq!(ByteRange::From(10).resolve(100).unwrap(), (10, 90));
+    }
+
+    #[test]
+    fn full_range_is_inclusive_of_the_end_byte() {
+        // bytes 10..=19 is 10 bytes, not 9
+        assert_eq!(ByteRange::Full(10, 19).resolve(100).unwrap(), (10, 10));
+    }
+
+    #[test]
+    fn suffix_range_counts_back_from_the_end() {
+        assert_eq!(ByteRange::Suffix(10).resolve(100).unwrap(), (90, 10));
+    }
+
+    #[test]
+    fn range_starting_or_ending_past_total_size_is_rejected() {
+        assert!(ByteRange::From(200).resolve(100).is_err());
+        assert!(ByteRange::Full(0, 200).resolve(100).is_err());
+    }
+
+    #[test]
+    fn suffix_longer_than_the_stream_clamps_to_the_whole_stream() {
+        // Same semantics as HTTP's suffix-byte-range-spec: a requested
+        // suffix length longer than the resource just means "everything".
+        assert_eq!(ByteRange::Suffix(200).resolve(100).unwrap(), (0, 100));
+    }
+
+    #[test]
+    fn range_request_roundtrips_through_the_wire_format() {
+        for range in [
+            ByteRange::From(42),
+            ByteRange::Full(10, 99),
+            ByteRange::Suffix(7),
+        ] {
+            let mut buf = Vec::new();
+            RangeRequest::new(range).write_to(&mut buf).unwrap();
+            let decoded = RangeRequest::read_from(&mut &buf[..]).unwrap();
+            assert_eq!(decoded.range, range);
+        }
+    }
+
+    #[test]
+    fn chunk_frame_roundtrips_through_the_wire_format() {
+        let frame = ChunkFrame {
+            chunk_index: 7,
+            offset: 7 * 1024,
+            checksum: crate::checksum::calculate_chunk_checksum_raw(b"hello world"),
+            data: b"hello world".to_vec(),
+        };
+
+        let mut buf = Vec::new();
+        frame.write_to(&mut buf).unwrap();
+        let decoded = ChunkFrame::read_from(&mut &buf[..]).unwrap();
+        assert_eq!(decoded, frame);
+    }
+
+    #[test]
+    fn nack_chunk_roundtrips_through_the_wire_format() {
+        let nack = NackChunk { chunk_index: 19 };
+
+        let mut buf = Vec::new();
+        nack.write_to(&mut buf).unwrap();
+        let decoded = NackChunk::read_from(&mut &buf[..]).unwrap();
+        assert_eq!(decoded, nack);
+    }
+
+    #[test]
+    fn encrypted_chunk_frame_roundtrips_through_the_wire_format() {
+        let aead = crate::security::chunk_aead::ChunkAead::new([3u8; 32], [1, 2, 3, 4]);
+        let plaintext = b"hello world";
+        let ciphertext = aead.seal(7, plaintext);
+
+        let frame = EncryptedChunkFrame {
+            chunk_index: 7,
+            offset: 7 * 1024,
+            plaintext_len: plaintext.len() as u32,
+            ciphertext,
+        };
+
+        let mut buf = Vec::new();
+        frame.write_to(&mut buf).unwrap();
+        let decoded = EncryptedChunkFrame::read_from(&mut &buf[..]).unwrap();
+        assert_eq!(decoded, frame);
+
+        let opened = aead.open(decoded.chunk_index, &decoded.ciphertext).unwrap();
+        assert_eq!(opened.len(), decoded.plaintext_len as usize);
+        assert_eq!(opened, plaintext);
+    }
+
+    #[test]
+    fn zero_run_roundtrips_through_the_wire_format() {
+        let run = ZeroRun {
+            start_chunk: 12,
+            count: 4096,
+        };
+
+        let mut buf = Vec::new();
+        run.write_to(&mut buf).unwrap();
+        let decoded = ZeroRun::read_from(&mut &buf[..]).unwrap();
+        assert_eq!(decoded, run);
+    }
+
+    #[test]
+    fn frame_writer_and_reader_roundtrip_a_message_packet() {
+        let mut buf = Vec::new();
+        let mut writer = FrameWriter::new(&mut buf);
+        writer
+            .write_packet(&Packet::encode_message(PacketType::Nack, b"chunk 9"))
+            .unwrap();
+
+        let mut reader = FrameReader::new(&buf[..]);
+        let packet = reader.read_packet().unwrap();
+        assert_eq!(
+            packet,
+            Packet::Message {
+                packet_type: PacketType::Nack,
+                payload: b"chunk 9".to_vec(),
+            }
+        );
+    }
+
+    #[test]
+    fn frame_writer_and_reader_roundtrip_flush_and_delimiter() {
+        let mut buf = Vec::new();
+        let mut writer = FrameWriter::new(&mut buf);
+        writer.write_delimiter().unwrap();
+        writer.write_flush().unwrap();
+
+        let mut reader = FrameReader::new(&buf[..]);
+        assert_eq!(reader.read_packet().unwrap(), Packet::Delimiter);
+        assert_eq!(reader.read_packet().unwrap(), Packet::Flush);
+    }
+
+    #[test]
+    fn control_and_data_packets_interleave_on_one_stream() {
+        let mut buf = Vec::new();
+        let mut writer = FrameWriter::new(&mut buf);
+        writer
+            .write_packet(&Packet::encode_message(PacketType::Data, b"first chunk"))
+            .unwrap();
+        writer
+            .write_packet(&Packet::encode_message(PacketType::Pause, &[]))
+            .unwrap();
+        writer
+            .write_packet(&Packet::encode_message(PacketType::Resume, &[]))
+            .unwrap();
+        writer
+            .write_packet(&Packet::encode_message(PacketType::Data, b"second chunk"))
+            .unwrap();
+        writer.write_flush().unwrap();
+
+        let mut reader = FrameReader::new(&buf[..]);
+        let mut packet_types = Vec::new();
+        loop {
+            match reader.read_packet().unwrap() {
+                Packet::Flush => break,
+                Packet::Delimiter => continue,
+                Packet::Message { packet_type, .. } => packet_types.push(packet_type),
+            }
+        }
+        assert_eq!(
+            packet_types,
+            vec![
+                PacketType::Data,
+                PacketType::Pause,
+                PacketType::Resume,
+                PacketType::Data,
+            ]
+        );
+    }
+
+    #[test]
+    fn read_packet_rejects_a_length_over_max_packet_len() {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&(MAX_PACKET_LEN + 1).to_be_bytes());
+
+        let mut reader = FrameReader::new(&buf[..]);
+        assert!(reader.read_packet().is_err());
+    }
+}
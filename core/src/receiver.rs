@@ -2,15 +2,20 @@
 //!
 //! TCP-based file receiver for accepting incoming file transfers.
 
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{Read, Seek, Write};
 use std::net::{SocketAddr, TcpListener, TcpStream};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 
-use crate::checksum::{calculate_chunk_checksum, calculate_file_checksum};
+use crate::checksum::calculate_file_checksum;
+use crate::security::chunk_cipher::{self, ChunkCipher};
+use crate::security::crypto::DeviceIdentity;
+use crate::security::noise;
+use crate::security::spake2::Role;
 use std::time::Duration;
 
 // Timeouts
@@ -21,7 +26,111 @@ const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(60);
 // Data timeout - for receiving chunks during active transfer
 const DATA_TIMEOUT: Duration = Duration::from_secs(30);
 
-use crate::protocol::TransferType;
+/// How long `RelayTransport` blocks dialing the relay server, waiting for
+/// it to pair this receiver with a sender presenting the same pairing
+/// code (see `relay::connect`). Generous compared to `CONNECT_TIMEOUT` in
+/// `sender.rs` since the sender may not dial in for a while.
+const RELAY_PAIR_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Abstracts how `start`'s listener thread obtains the next incoming
+/// connection, so the same accept loop works whether the receiver has a
+/// reachable inbound port (`DirectTransport`) or is behind NAT and has to
+/// rendezvous through a relay server instead (`RelayTransport`).
+/// `accept_one` mirrors `TcpListener`'s non-blocking `accept()`: an
+/// `ErrorKind::WouldBlock` error means "nothing yet, poll again" rather
+/// than a hard failure.
+trait IncomingTransport: Send + Sync {
+    fn accept_one(&self) -> std::io::Result<(TcpStream, SocketAddr)>;
+}
+
+/// The ordinary case: a locally bound `TcpListener` (see `FileReceiverServer::new`).
+struct DirectTransport {
+    listener: TcpListener,
+}
+
+impl IncomingTransport for DirectTransport {
+    fn accept_one(&self) -> std::io::Result<(TcpStream, SocketAddr)> {
+        self.listener.accept()
+    }
+}
+
+/// Dials `relay_addr` and blocks (up to `RELAY_PAIR_TIMEOUT`) until the
+/// relay pairs this socket with a sender presenting the same
+/// `pairing_code` - see `relay::rendezvous_token` and `relay::connect`.
+/// Used by `FileReceiverServer::start_via_relay` when the receiver has no
+/// reachable inbound port; once paired, the existing handshake + chunk
+/// loop run unchanged over the relayed stream. A relay session only ever
+/// pairs with one sender, so `accept_one` reports "nothing more" after
+/// its first successful pairing instead of dialing again.
+struct RelayTransport {
+    relay_addr: String,
+    pairing_code: String,
+    paired: AtomicBool,
+}
+
+impl IncomingTransport for RelayTransport {
+    fn accept_one(&self) -> std::io::Result<(TcpStream, SocketAddr)> {
+        if self.paired.swap(true, Ordering::SeqCst) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::WouldBlock,
+                "relay session already paired with a sender",
+            ));
+        }
+
+        match crate::relay::connect(
+            &self.relay_addr,
+            &self.pairing_code,
+            crate::relay::RelayRole::Receiver,
+            RELAY_PAIR_TIMEOUT,
+        ) {
+            Ok(stream) => {
+                let addr = stream
+                    .peer_addr()
+                    .unwrap_or_else(|_| ([0, 0, 0, 0], 0).into());
+                Ok((stream, addr))
+            }
+            Err(crate::relay::RelayError::PairingTimeout) => Err(std::io::Error::new(
+                std::io::ErrorKind::WouldBlock,
+                "timed out waiting for relay pairing",
+            )),
+            Err(e) => Err(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())),
+        }
+    }
+}
+
+/// Window size advertised to the sender for a windowed single-file
+/// transfer (see `receive_windowed`). Kept comfortably under
+/// `security::channel::REPLAY_WINDOW_SIZE` (64) so every chunk in flight
+/// is still inside the `SecureChannel`'s replay-protection window when it
+/// arrives out of order.
+const WINDOW_SIZE: u32 = 32;
+
+/// How often (in newly-received chunks) `receive_windowed` flushes a
+/// `SelectiveAck` frame, trading ACK frequency for throughput - the
+/// sender only needs to hear about gaps often enough to keep the window
+/// moving, not after every single chunk.
+const ACK_BATCH_CHUNKS: u64 = 8;
+
+/// Starting window for `pacing::ChunkCubic` - a conservative slow-start
+/// value, not the hard `WINDOW_SIZE` ceiling the replay-protection window
+/// imposes. The controller grows (or shrinks) from here based on whether
+/// each ACK batch sees a gap.
+const INITIAL_CHUNK_WINDOW: u32 = 4;
+
+use crate::pacing::ChunkCubic;
+use crate::protocol::{ResumeOffer, SelectiveAck, TransferType};
+
+/// How many chunks a file of `file_size` splits into at `chunk_size`, the
+/// same ceiling-division both `accept_transfer`'s bitmap-resume lookup and
+/// `receive_windowed` need to agree on for a sidecar to line up with the
+/// transfer it's being matched against.
+fn expected_total_chunks(file_size: u64, chunk_size: u32) -> u64 {
+    if chunk_size == 0 {
+        0
+    } else {
+        (file_size + chunk_size as u64 - 1) / chunk_size as u64
+    }
+}
 
 /// Incoming transfer request information
 #[derive(Debug, Clone)]
@@ -33,6 +142,226 @@ pub struct IncomingTransfer {
     pub chunk_size: u32,
     pub file_checksum: String, // Hex string
     pub transfer_type: TransferType,
+    /// Whether the sender negotiated the `security::chunk_cipher` layer for
+    /// this transfer - lets the UI show a lock indicator.
+    pub encrypted: bool,
+    /// Shared by every connection of a multi-stream transfer (see
+    /// `MultiStreamTransfer`). `0` outside of that mode.
+    pub transfer_id: u64,
+    /// How many connections this transfer is split across. `1` for an
+    /// ordinary single-connection transfer.
+    pub stream_count: u32,
+    /// Whether this is a content-defined-chunking transfer - see
+    /// `accept_deduplicated_transfer` and `crate::dedup`.
+    pub deduplicated: bool,
+}
+
+/// Shared coordination state for a single-file transfer split across
+/// `stream_count` concurrent TCP connections (see
+/// `FileReceiverServer::accept_multistream_transfer` and the `stream_count
+/// > 1` dispatch in `start()`'s listener loop). Every connection writes
+/// into the same `file` at `chunk_index * chunk_size` via `seek`, so
+/// streams don't need to coordinate write order - only which disjoint
+/// range of chunk indices is theirs, handed out by `claim_range`.
+struct MultiStreamTransfer {
+    transfer_id: u64,
+    file: Mutex<File>,
+    chunk_size: u64,
+    total_chunks: u64,
+    file_checksum: String,
+    save_path: PathBuf,
+    /// How many chunks `claim_range` hands out per call - `ceil(total_chunks
+    /// / stream_count)`, so the last claimed range may come up short of a
+    /// full helping rather than overshoot `total_chunks`.
+    chunks_per_stream: u64,
+    next_range_start: AtomicU64,
+    completed_chunks: AtomicU64,
+    bitmap: Mutex<Vec<bool>>,
+    bytes_received: Arc<AtomicU64>,
+}
+
+impl MultiStreamTransfer {
+    /// Hands out the next unclaimed disjoint chunk range, or `None` once
+    /// every chunk already belongs to some connection.
+    fn claim_range(&self) -> Option<(u64, u64)> {
+        let start = self
+            .next_range_start
+            .fetch_add(self.chunks_per_stream, Ordering::SeqCst);
+        if start >= self.total_chunks {
+            return None;
+        }
+        Some((start, (start + self.chunks_per_stream).min(self.total_chunks)))
+    }
+
+    /// Marks `index` as received. Returns `true` exactly once across every
+    /// connection of this transfer - for whichever chunk turns out to be
+    /// the very last one still outstanding - so the caller knows when to
+    /// run the final whole-file checksum verification.
+    fn mark_received(&self, index: u64) -> bool {
+        let mut bitmap = self.bitmap.lock().unwrap();
+        if bitmap[index as usize] {
+            return false;
+        }
+        bitmap[index as usize] = true;
+        drop(bitmap);
+        self.completed_chunks.fetch_add(1, Ordering::SeqCst) + 1 == self.total_chunks
+    }
+
+    /// Receives exactly the chunks in `range_start..range_end` over one
+    /// connection, sealing/opening with that connection's own
+    /// `SecureChannel`. Unlike `receive_windowed`, there's no pipelining
+    /// here - each stream's range is sequential and modest enough relative
+    /// to `stream_count` that a plain per-chunk ACK is enough; the
+    /// parallelism comes from running several of these concurrently, not
+    /// from any one of them running ahead of its own ACKs. Returns whether
+    /// this connection happened to land the transfer's very last chunk.
+    fn run_worker(
+        &self,
+        conn: &mut TcpStream,
+        channel: &mut crate::security::channel::SecureChannel,
+        range_start: u64,
+        range_end: u64,
+    ) -> std::io::Result<bool> {
+        let mut completed_last = false;
+
+        for _ in range_start..range_end {
+            let mut header_buf = [0u8; 12];
+            conn.read_exact(&mut header_buf)?;
+            let chunk_index = u64::from_be_bytes(header_buf[0..8].try_into().unwrap());
+            let packet_len = u32::from_be_bytes(header_buf[8..12].try_into().unwrap()) as usize;
+
+            let mut packet = vec![0u8; packet_len];
+            conn.read_exact(&mut packet)?;
+
+            let data = match channel.open(&packet) {
+                Ok(data) => data,
+                Err(_) => {
+                    tracing::error!(
+                        "✗ AEAD authentication failed for chunk {} (multi-stream), aborting stream",
+                        chunk_index
+                    );
+                    let _ = conn.write_all(&chunk_index.to_be_bytes());
+                    let _ = conn.write_all(&[2u8]);
+                    let _ = conn.flush();
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "AEAD authentication failed",
+                    ));
+                }
+            };
+
+            {
+                let mut file = self.file.lock().unwrap();
+                file.seek(std::io::SeekFrom::Start(chunk_index * self.chunk_size))?;
+                file.write_all(&data)?;
+            }
+            self.bytes_received.fetch_add(data.len() as u64, Ordering::SeqCst);
+            if self.mark_received(chunk_index) {
+                completed_last = true;
+            }
+
+            conn.write_all(&chunk_index.to_be_bytes())?;
+            conn.write_all(&[0u8])?;
+            conn.flush()?;
+        }
+
+        Ok(completed_last)
+    }
+}
+
+/// Finishes one connection of a multi-stream transfer once its claimed
+/// range has either errored out or been fully received: acks the
+/// connection's own completion, and - only for whichever connection
+/// reports landing the transfer's very last chunk - runs the final
+/// whole-file checksum verification exactly once and retires `state` from
+/// `active_multistream`.
+fn finish_stream_connection(
+    conn: &mut TcpStream,
+    state: &Arc<MultiStreamTransfer>,
+    active_multistream: &Arc<Mutex<HashMap<u64, Arc<MultiStreamTransfer>>>>,
+    receiver_state: &Arc<Mutex<ReceiverState>>,
+    result: std::io::Result<bool>,
+) -> std::io::Result<()> {
+    match result {
+        Ok(true) => {
+            state.file.lock().unwrap().flush()?;
+            let final_checksum = calculate_file_checksum(&state.save_path)?;
+            let success = final_checksum == state.file_checksum;
+            tracing::info!(
+                "Multi-stream transfer {} {}",
+                state.transfer_id,
+                if success {
+                    "completed successfully"
+                } else {
+                    "failed checksum verification"
+                }
+            );
+            let _ = conn.write_all(&[success as u8]);
+            let _ = conn.flush();
+            active_multistream.lock().unwrap().remove(&state.transfer_id);
+            *receiver_state.lock().unwrap() = if success {
+                ReceiverState::Completed
+            } else {
+                ReceiverState::Error
+            };
+            Ok(())
+        }
+        Ok(false) => {
+            // This connection's range is done, but other streams are still
+            // in flight - just ack this connection's own chunks.
+            let _ = conn.write_all(&[1u8]);
+            let _ = conn.flush();
+            Ok(())
+        }
+        Err(e) => {
+            *receiver_state.lock().unwrap() = ReceiverState::Error;
+            Err(e)
+        }
+    }
+}
+
+/// Dispatches a secondary connection of an already-accepted multi-stream
+/// transfer straight to its own worker, with no UI round trip: the user
+/// already approved the transfer via its first connection (see
+/// `FileReceiverServer::accept_multistream_transfer`). Runs its own Noise
+/// handshake using the identity/pairing code cached at accept time, claims
+/// a chunk range, and streams it.
+fn handle_multistream_connection(
+    mut conn: TcpStream,
+    state: Arc<MultiStreamTransfer>,
+    identity: &DeviceIdentity,
+    pairing_code: &str,
+    active_multistream: &Arc<Mutex<HashMap<u64, Arc<MultiStreamTransfer>>>>,
+    receiver_state: &Arc<Mutex<ReceiverState>>,
+) -> std::io::Result<()> {
+    if let Err(e) = conn.set_read_timeout(Some(DATA_TIMEOUT)) {
+        tracing::warn!("Failed to set data read timeout: {}", e);
+    }
+    if let Err(e) = conn.set_write_timeout(Some(DATA_TIMEOUT)) {
+        tracing::warn!("Failed to set data write timeout: {}", e);
+    }
+
+    // Auto-accept - the user already approved this transfer via its first
+    // connection.
+    conn.write_all(&[1u8])?;
+
+    let mut channel = noise::run_handshake(&mut conn, Role::Responder, identity, pairing_code)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::PermissionDenied, e.to_string()))?;
+
+    let (range_start, range_end) = match state.claim_range() {
+        Some(r) => r,
+        None => return Ok(()), // every chunk is already spoken for
+    };
+
+    let offer = crate::protocol::StreamRangeOffer {
+        start_chunk: range_start,
+        end_chunk: range_end,
+    };
+    offer.write_to(&mut conn)?;
+    conn.flush()?;
+
+    let result = state.run_worker(&mut conn, &mut channel, range_start, range_end);
+    finish_stream_connection(&mut conn, &state, active_multistream, receiver_state, result)
 }
 
 /// Receiver state
@@ -40,6 +369,10 @@ pub struct IncomingTransfer {
 pub enum ReceiverState {
     Idle,
     Listening,
+    /// Dialed out to a relay server (see `start_via_relay`) and waiting
+    /// for it to pair this session with a sender presenting the same
+    /// pairing code - the NATed-receiver analogue of `Listening`.
+    Relaying,
     AwaitingAccept,
     Receiving,
     Completed,
@@ -56,6 +389,21 @@ pub struct FileReceiverServer {
     pending_stream: Arc<Mutex<Option<TcpStream>>>,
     bytes_received: Arc<AtomicU64>,
     total_bytes: Arc<AtomicU64>,
+    /// Whether the sender authenticated during the post-accept handshake
+    /// (see `security::noise`). Only meaningful once a transfer has been
+    /// accepted; `false` beforehand.
+    authenticated: Arc<AtomicBool>,
+    /// In-flight multi-stream transfers, keyed by the handshake's
+    /// `transfer_id` - see `accept_multistream_transfer` and the
+    /// `stream_count > 1` dispatch in `start()`'s listener loop.
+    active_multistream: Arc<Mutex<HashMap<u64, Arc<MultiStreamTransfer>>>>,
+    /// An owned copy of the identity/pairing code `accept_multistream_transfer`
+    /// was called with, cached so the listener thread's detached secondary-
+    /// connection dispatch can run its own Noise handshake after the
+    /// borrowed `&DeviceIdentity` passed into that call has gone out of
+    /// scope (`DeviceIdentity` isn't `Clone` - see `security::crypto`, so
+    /// this goes through its pkcs8 `export`/`import` round trip instead).
+    multistream_identity: Arc<Mutex<Option<(Arc<DeviceIdentity>, String)>>>,
 }
 
 impl FileReceiverServer {
@@ -105,6 +453,9 @@ impl FileReceiverServer {
             pending_stream: Arc::new(Mutex::new(None)),
             bytes_received: Arc::new(AtomicU64::new(0)),
             total_bytes: Arc::new(AtomicU64::new(0)),
+            authenticated: Arc::new(AtomicBool::new(false)),
+            active_multistream: Arc::new(Mutex::new(HashMap::new())),
+            multistream_identity: Arc::new(Mutex::new(None)),
         })
     }
 
@@ -123,26 +474,62 @@ impl FileReceiverServer {
         self.pending_transfer.lock().unwrap().clone()
     }
 
+    /// Whether the sender authenticated during the handshake that runs
+    /// right after `accept_transfer`. `false` until a transfer has been
+    /// accepted and the handshake has completed.
+    pub fn is_authenticated(&self) -> bool {
+        self.authenticated.load(Ordering::SeqCst)
+    }
+
     /// Start listening for incoming connections
     pub fn start(&self) {
         if self.running.load(Ordering::SeqCst) {
             return;
         }
 
+        let listener = self.listener.as_ref().unwrap().try_clone().unwrap();
+        self.spawn_accept_loop(Arc::new(DirectTransport { listener }), ReceiverState::Listening);
+    }
+
+    /// Like `start`, but for a receiver with no reachable inbound port:
+    /// dials `relay_addr` and waits for it to pair this session with a
+    /// sender presenting the same `pairing_code` (see `RelayTransport`),
+    /// then runs the same handshake + chunk loop as a direct connection
+    /// would, over the relayed stream. `ReceiverState::Relaying` surfaces
+    /// the "waiting to be paired" phase to the UI.
+    pub fn start_via_relay(&self, relay_addr: &str, pairing_code: &str) {
+        if self.running.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let transport = Arc::new(RelayTransport {
+            relay_addr: relay_addr.to_string(),
+            pairing_code: pairing_code.to_string(),
+            paired: AtomicBool::new(false),
+        });
+        self.spawn_accept_loop(transport, ReceiverState::Relaying);
+    }
+
+    /// Shared accept loop behind `start`/`start_via_relay`: polls
+    /// `transport` for the next connection, then runs the handshake setup
+    /// and multi-stream dispatch exactly as before regardless of which
+    /// transport produced the stream.
+    fn spawn_accept_loop(&self, transport: Arc<dyn IncomingTransport>, listening_state: ReceiverState) {
         self.running.store(true, Ordering::SeqCst);
-        *self.state.lock().unwrap() = ReceiverState::Listening;
+        *self.state.lock().unwrap() = listening_state;
 
-        let listener = self.listener.as_ref().unwrap().try_clone().unwrap();
         let running = self.running.clone();
         let state = self.state.clone();
         let pending_transfer = self.pending_transfer.clone();
         let pending_stream = self.pending_stream.clone();
+        let active_multistream = self.active_multistream.clone();
+        let multistream_identity = self.multistream_identity.clone();
 
         thread::spawn(move || {
             tracing::info!("Receiver thread started, listening for incoming transfers...");
 
             while running.load(Ordering::SeqCst) {
-                match listener.accept() {
+                match transport.accept_one() {
                     Ok((mut stream, addr)) => {
                         tracing::info!("✓ Incoming connection from {}", addr);
 
@@ -180,6 +567,41 @@ impl FileReceiverServer {
                             addr
                         );
 
+                        // A later connection of a transfer already accepted
+                        // via `accept_multistream_transfer` - dispatch it
+                        // straight to its own worker, no UI round trip.
+                        if handshake.stream_count > 1 {
+                            let existing =
+                                active_multistream.lock().unwrap().get(&handshake.transfer_id).cloned();
+                            if let Some(mstream) = existing {
+                                let creds = multistream_identity.lock().unwrap().clone();
+                                let active_multistream_for_thread = active_multistream.clone();
+                                let receiver_state_for_thread = state.clone();
+                                thread::spawn(move || {
+                                    if let Some((identity, pairing_code)) = creds {
+                                        if let Err(e) = handle_multistream_connection(
+                                            stream,
+                                            mstream,
+                                            &identity,
+                                            &pairing_code,
+                                            &active_multistream_for_thread,
+                                            &receiver_state_for_thread,
+                                        ) {
+                                            tracing::error!(
+                                                "Multi-stream worker connection failed: {}",
+                                                e
+                                            );
+                                        }
+                                    } else {
+                                        tracing::warn!(
+                                            "No cached identity for multi-stream dispatch, dropping connection"
+                                        );
+                                    }
+                                });
+                                continue;
+                            }
+                        }
+
                         // Store pending transfer info
                         let transfer = IncomingTransfer {
                             sender_name: handshake.sender_name,
@@ -189,6 +611,10 @@ impl FileReceiverServer {
                             chunk_size: handshake.chunk_size,
                             file_checksum: handshake.file_checksum,
                             transfer_type: handshake.transfer_type,
+                            encrypted: handshake.encrypted,
+                            transfer_id: handshake.transfer_id,
+                            stream_count: handshake.stream_count,
+                            deduplicated: handshake.deduplicated,
                         };
 
                         *pending_transfer.lock().unwrap() = Some(transfer);
@@ -223,8 +649,16 @@ impl FileReceiverServer {
         *self.pending_stream.lock().unwrap() = None;
     }
 
-    /// Accept the pending transfer and save to the given path
-    pub fn accept_transfer(&self, save_path: &PathBuf) -> std::io::Result<()> {
+    /// Accept the pending transfer and save to the given path. `identity`
+    /// and `pairing_code` authenticate the handshake (see
+    /// `security::noise`) that runs right after the accept byte, before
+    /// any chunk is accepted.
+    pub fn accept_transfer(
+        &self,
+        save_path: &PathBuf,
+        identity: &DeviceIdentity,
+        pairing_code: &str,
+    ) -> std::io::Result<()> {
         let transfer = self.pending_transfer.lock().unwrap().take();
         let stream = self.pending_stream.lock().unwrap().take();
 
@@ -234,6 +668,7 @@ impl FileReceiverServer {
                 *self.state.lock().unwrap() = ReceiverState::Receiving;
                 self.total_bytes.store(info.file_size, Ordering::SeqCst);
                 self.bytes_received.store(0, Ordering::SeqCst);
+                self.authenticated.store(false, Ordering::SeqCst);
 
                 // Set data transfer timeouts (longer than handshake)
                 if let Err(e) = conn.set_read_timeout(Some(DATA_TIMEOUT)) {
@@ -251,19 +686,141 @@ impl FileReceiverServer {
                     return Err(e);
                 }
 
-                use crate::io_utils::ReceiverWriter;
+                tracing::info!("Running authenticated key exchange with sender...");
+                let mut channel =
+                    match noise::run_handshake(&mut conn, Role::Responder, identity, pairing_code)
+                    {
+                        Ok(channel) => {
+                            self.authenticated.store(true, Ordering::SeqCst);
+                            channel
+                        }
+                        Err(e) => {
+                            tracing::error!("Authenticated handshake with sender failed: {}", e);
+                            *self.state.lock().unwrap() = ReceiverState::Error;
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::PermissionDenied,
+                                format!("handshake failed: {}", e),
+                            ));
+                        }
+                    };
+                tracing::info!("Handshake complete, sender authenticated");
+
+                let chunk_cipher = if info.encrypted {
+                    tracing::info!("Running chunk cipher key exchange with sender...");
+                    match chunk_cipher::exchange_key(&mut conn, Role::Responder) {
+                        Ok(key) => Some(ChunkCipher::new(key)),
+                        Err(e) => {
+                            tracing::error!("Chunk cipher key exchange failed: {}", e);
+                            *self.state.lock().unwrap() = ReceiverState::Error;
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::PermissionDenied,
+                                format!("chunk cipher key exchange failed: {}", e),
+                            ));
+                        }
+                    }
+                } else {
+                    None
+                };
+
+                if info.deduplicated {
+                    return self.accept_deduplicated_transfer(conn, &info, save_path, channel);
+                }
+
+                if info.transfer_type == TransferType::SingleFile && info.stream_count > 1 {
+                    return self
+                        .accept_multistream_transfer(conn, &info, save_path, channel, identity, pairing_code);
+                }
+
+                use crate::io_utils::{self, ReceiverWriter};
                 use crate::protocol::TransferType;
 
                 // Create or open the writer for resume
                 let mut start_chunk_index: u64 = 0;
                 let mut received: u64 = 0;
                 let mut writer: ReceiverWriter;
+                // Chunks beyond `start_chunk_index` the sidecar bitmap says
+                // are already on disk (see `receive_windowed`'s sidecar
+                // writes below) - reported to the sender as a `ResumeOffer`
+                // so it skips retransmitting them. Empty unless a matching
+                // `.vwpart` sidecar was found for a single-file transfer.
+                let mut already_have: Vec<u64> = Vec::new();
 
                 if info.transfer_type == TransferType::Folder {
-                    // For folder, we always start fresh for now
-                    // TODO: Implement advanced resume for folders
-                    tracing::info!("Starting folder transfer (fresh)");
-                    writer = ReceiverWriter::new_folder(save_path);
+                    // `plan_folder_resume` reads back the manifest a
+                    // previous attempt saved as a sidecar (see
+                    // `io_utils::handle_folder_write`) and walks it against
+                    // whatever's on disk; chunk-align the resulting byte
+                    // offset since the resume-offer wire field is a chunk
+                    // index, not a byte offset.
+                    let resumed = io_utils::plan_folder_resume(save_path)
+                        .ok()
+                        .flatten()
+                        .and_then(|plan| {
+                            let chunk_size = info.chunk_size as u64;
+                            if chunk_size == 0 {
+                                return None;
+                            }
+                            let total_offset = plan.manifest_frame_len + plan.content_offset;
+                            let aligned_chunks = total_offset / chunk_size;
+                            if aligned_chunks == 0 {
+                                return None;
+                            }
+                            let aligned_offset = aligned_chunks * chunk_size;
+                            let aligned_content_offset =
+                                aligned_offset.saturating_sub(plan.manifest_frame_len);
+                            Some((plan.manifest, aligned_content_offset, aligned_chunks, aligned_offset))
+                        });
+
+                    if let Some((manifest, content_offset, chunks, bytes)) = resumed {
+                        tracing::info!(
+                            "Resuming folder transfer from chunk {} ({} bytes already on disk)",
+                            chunks,
+                            bytes
+                        );
+                        writer = ReceiverWriter::resume_folder(save_path, manifest, content_offset)?;
+                        start_chunk_index = chunks;
+                        received = bytes;
+                    } else {
+                        tracing::info!("Starting folder transfer (fresh)");
+                        writer = ReceiverWriter::new_folder(save_path);
+                    }
+                } else if let Some(bitmap) = io_utils::load_chunk_bitmap(
+                    save_path,
+                    &info.file_checksum,
+                    info.chunk_size,
+                    info.file_size,
+                )
+                .filter(|b| {
+                    b.len() as u64 == expected_total_chunks(info.file_size, info.chunk_size)
+                })
+                {
+                    // A sidecar from a previous connection matches this
+                    // exact transfer (same file checksum/chunk size/file
+                    // size) - reopen the file without truncating, since
+                    // chunks past the contiguous edge may already hold
+                    // valid out-of-order data (see `receive_windowed`).
+                    let mut next = 0u64;
+                    while (next as usize) < bitmap.len() && bitmap[next as usize] {
+                        next += 1;
+                    }
+                    start_chunk_index = next;
+                    received = bitmap
+                        .iter()
+                        .filter(|&&got| got)
+                        .count() as u64
+                        * info.chunk_size as u64;
+                    received = received.min(info.file_size);
+                    already_have = (start_chunk_index..bitmap.len() as u64)
+                        .filter(|&i| bitmap[i as usize])
+                        .collect();
+
+                    tracing::info!(
+                        "Found per-chunk resume state, reopening {:?} from chunk {} ({} chunks already have out of order)",
+                        save_path,
+                        start_chunk_index,
+                        already_have.len()
+                    );
+                    writer = ReceiverWriter::resume_single_sparse(save_path, info.file_size)?;
                 } else if save_path.exists() {
                      // Check existing file for resume
                     let metadata = std::fs::metadata(save_path)?;
@@ -299,6 +856,27 @@ impl FileReceiverServer {
                     tracing::error!("Failed to send resume index: {}", e);
                     return Err(e);
                 }
+                // For single-file transfers, also advertise the pipeline
+                // window size so the sender knows it can run ahead of the
+                // per-chunk ACK instead of the legacy stop-and-wait scheme
+                // (see `receive_windowed`). Folder transfers stay lockstep.
+                if info.transfer_type == TransferType::SingleFile {
+                    if let Err(e) = conn.write_all(&WINDOW_SIZE.to_be_bytes()) {
+                        tracing::error!("Failed to send window size: {}", e);
+                        return Err(e);
+                    }
+                    // Tell the sender which chunks beyond the resume index
+                    // it doesn't need to retransmit (see `already_have`'s
+                    // doc comment above).
+                    let offer = ResumeOffer {
+                        already_have: already_have.clone(),
+                    };
+                    if let Err(e) = offer.write_to(&mut conn) {
+                        tracing::error!("Failed to send resume offer: {}", e);
+                        return Err(e);
+                    }
+                }
+
                 // CRITICAL: flush to ensure sender receives accept + resume index immediately
                 if let Err(e) = conn.flush() {
                     tracing::error!("Failed to flush accept response: {}", e);
@@ -306,68 +884,97 @@ impl FileReceiverServer {
                 }
 
                 tracing::info!("Starting to receive file chunks...");
-                let mut last_log_chunk = 0u64;
-                loop {
-                    // Check if transfer is complete
-                    if received >= info.file_size {
-                        break;
-                    }
-
-                    // Read chunk header: [index: u64][len: u32]
-                    let mut header_buf = [0u8; 12];
-                    match conn.read_exact(&mut header_buf) {
-                        Ok(_) => {}
-                        Err(ref e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
-                        Err(e) => return Err(e),
-                    }
+                if info.transfer_type == TransferType::SingleFile {
+                    // Windowed receive allows out-of-order chunks, so it
+                    // needs seek-based writes - only `ReceiverWriter::SingleFile`
+                    // offers that (a folder's `handle_folder_write` state
+                    // machine is strictly sequential).
+                    let file = match &mut writer {
+                        ReceiverWriter::SingleFile(f) => f,
+                        ReceiverWriter::Folder { .. } => unreachable!(
+                            "transfer_type == SingleFile implies a SingleFile writer"
+                        ),
+                    };
+                    received = self.receive_windowed(
+                        &mut conn,
+                        file,
+                        &mut channel,
+                        chunk_cipher.as_ref(),
+                        Some(save_path.as_path()),
+                        &info.file_checksum,
+                        info.file_size,
+                        info.chunk_size,
+                        start_chunk_index,
+                        received,
+                        already_have,
+                    )?;
+                } else {
+                    let mut last_log_chunk = 0u64;
+                    loop {
+                        // Check if transfer is complete
+                        if received >= info.file_size {
+                            break;
+                        }
 
-                    let chunk_index = u64::from_be_bytes(header_buf[0..8].try_into().unwrap());
-                    let chunk_len =
-                        u32::from_be_bytes(header_buf[8..12].try_into().unwrap()) as usize;
+                        // Read chunk header: [index: u64][sealed_packet_len: u32]
+                        let mut header_buf = [0u8; 12];
+                        match conn.read_exact(&mut header_buf) {
+                            Ok(_) => {}
+                            Err(ref e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                            Err(e) => return Err(e),
+                        }
 
-                    // Log progress periodically (every 100 chunks)
-                    if chunk_index - last_log_chunk >= 100 || chunk_index == 0 {
-                        tracing::debug!("Receiving chunk {} ({} bytes)", chunk_index, chunk_len);
-                        last_log_chunk = chunk_index;
-                    }
+                        let chunk_index = u64::from_be_bytes(header_buf[0..8].try_into().unwrap());
+                        let packet_len =
+                            u32::from_be_bytes(header_buf[8..12].try_into().unwrap()) as usize;
 
-                    // Read chunk data
-                    let mut data = vec![0u8; chunk_len];
-                    conn.read_exact(&mut data)?;
+                        // Log progress periodically (every 100 chunks)
+                        if chunk_index - last_log_chunk >= 100 || chunk_index == 0 {
+                            tracing::debug!("Receiving chunk {} ({} bytes)", chunk_index, packet_len);
+                            last_log_chunk = chunk_index;
+                        }
 
-                    // Read checksum (16 bytes)
-                    let mut chunk_checksum_buf = [0u8; 16];
-                    conn.read_exact(&mut chunk_checksum_buf)?;
+                        // Read the sealed packet (AEAD ciphertext + tag, see SecureChannel::seal)
+                        let mut packet = vec![0u8; packet_len];
+                        conn.read_exact(&mut packet)?;
+
+                        let data = match channel.open(&packet) {
+                            Ok(data) => {
+                                if let Some(cipher) = &chunk_cipher {
+                                    cipher.decrypt(chunk_index, &data)
+                                } else {
+                                    data
+                                }
+                            }
+                            Err(_) => {
+                                tracing::error!(
+                                    "✗ AEAD authentication failed for chunk {}, aborting transfer",
+                                    chunk_index
+                                );
+                                // Send ACK with auth-failure status (2) - retrying won't help,
+                                // the channel's nonce counter has already moved on.
+                                conn.write_all(&chunk_index.to_be_bytes())?;
+                                conn.write_all(&[2u8])?;
+                                conn.flush()?;
+                                *self.state.lock().unwrap() = ReceiverState::Error;
+                                return Err(std::io::Error::new(
+                                    std::io::ErrorKind::InvalidData,
+                                    "AEAD authentication failed",
+                                ));
+                            }
+                        };
 
-                    // Verify checksum
-                    let calculated_hex = calculate_chunk_checksum(&data);
-                    let calculated_bytes: Vec<u8> = (0..std::cmp::min(32, calculated_hex.len()))
-                        .step_by(2)
-                        .filter_map(|i| u8::from_str_radix(&calculated_hex[i..i + 2], 16).ok())
-                        .collect();
+                        // Write to file
+                        writer.write_all(&data)?;
+                        received += data.len() as u64; // Use actual data len
+                        self.bytes_received.store(received, Ordering::SeqCst);
 
-                    if calculated_bytes != chunk_checksum_buf {
-                        tracing::warn!(
-                            "✗ Checksum mismatch for chunk {}, requesting retransmit",
-                            chunk_index
-                        );
-                        // Send ACK with error (1)
+                        // Send ACK success (0)
+                        tracing::trace!("✓ Chunk {} verified, sending ACK", chunk_index);
                         conn.write_all(&chunk_index.to_be_bytes())?;
-                        conn.write_all(&[1u8])?;
-                        conn.flush()?; // Flush ACK immediately
-                        continue;
+                        conn.write_all(&[0u8])?;
+                        conn.flush()?; // Flush ACK immediately to prevent sender timeout
                     }
-
-                    // Write to file
-                    writer.write_all(&data)?;
-                    received += data.len() as u64; // Use actual data len
-                    self.bytes_received.store(received, Ordering::SeqCst);
-
-                    // Send ACK success (0)
-                    tracing::trace!("✓ Chunk {} verified, sending ACK", chunk_index);
-                    conn.write_all(&chunk_index.to_be_bytes())?;
-                    conn.write_all(&[0u8])?;
-                    conn.flush()?; // Flush ACK immediately to prevent sender timeout
                 }
 
                 // Final verification
@@ -387,6 +994,9 @@ impl FileReceiverServer {
                     tracing::info!("✓ Transfer completed successfully! Final checksum verified.");
                     tracing::info!("  Expected: {}", info.file_checksum);
                     tracing::info!("  Received: {}", final_checksum);
+                    if info.transfer_type == TransferType::SingleFile {
+                        io_utils::delete_chunk_bitmap(save_path);
+                    }
                     conn.write_all(&[1u8])?; // Final success
                     let _ = conn.flush(); // Ensure sender receives final result
                     *self.state.lock().unwrap() = ReceiverState::Completed;
@@ -416,6 +1026,495 @@ impl FileReceiverServer {
         }
     }
 
+    /// Like `accept_transfer`, but writes straight into an already-open
+    /// file descriptor instead of creating (or reopening) a file by path -
+    /// the receiver-side counterpart of `sender::TcpFileSender::from_fd`,
+    /// for an Android caller holding a Storage Access Framework
+    /// `content://` grant rather than a filesystem path. `fd` is `dup`'d
+    /// immediately, so this call takes no ownership of the caller's own
+    /// descriptor.
+    ///
+    /// Only a fresh single-file transfer can land here: folder transfers
+    /// need a real directory to write each manifest entry under, and both
+    /// the per-chunk bitmap resume and the plain truncate-and-resume paths
+    /// `accept_transfer` falls back to key their sidecars off `save_path`,
+    /// which an fd has none of. A multi-stream or deduplicated offer, or a
+    /// resumed single-file one, is rejected outright rather than silently
+    /// falling back to something that wouldn't resume correctly next time.
+    #[cfg(unix)]
+    pub fn accept_transfer_fd(
+        &self,
+        fd: std::os::unix::io::RawFd,
+        identity: &DeviceIdentity,
+        pairing_code: &str,
+    ) -> std::io::Result<()> {
+        use std::os::unix::io::FromRawFd;
+
+        let transfer = self.pending_transfer.lock().unwrap().take();
+        let stream = self.pending_stream.lock().unwrap().take();
+
+        let (info, mut conn) = match (transfer, stream) {
+            (Some(info), Some(conn)) => (info, conn),
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "No pending transfer to accept",
+                ))
+            }
+        };
+
+        if info.transfer_type != TransferType::SingleFile
+            || info.stream_count > 1
+            || info.deduplicated
+        {
+            *self.state.lock().unwrap() = ReceiverState::Error;
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "fd-backed accept only supports a plain single-file transfer",
+            ));
+        }
+
+        let dup_fd = unsafe { libc::dup(fd) };
+        if dup_fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let mut file = unsafe { File::from_raw_fd(dup_fd) };
+
+        tracing::info!("✓ User accepted transfer, saving to fd-backed destination");
+        *self.state.lock().unwrap() = ReceiverState::Receiving;
+        self.total_bytes.store(info.file_size, Ordering::SeqCst);
+        self.bytes_received.store(0, Ordering::SeqCst);
+        self.authenticated.store(false, Ordering::SeqCst);
+
+        if let Err(e) = conn.set_read_timeout(Some(DATA_TIMEOUT)) {
+            tracing::warn!("Failed to set data read timeout: {}", e);
+        }
+        if let Err(e) = conn.set_write_timeout(Some(DATA_TIMEOUT)) {
+            tracing::warn!("Failed to set data write timeout: {}", e);
+        }
+
+        tracing::info!("Sending acceptance confirmation to sender...");
+        conn.write_all(&[1u8])?; // 1 = accepted
+
+        tracing::info!("Running authenticated key exchange with sender...");
+        let mut channel = match noise::run_handshake(&mut conn, Role::Responder, identity, pairing_code)
+        {
+            Ok(channel) => {
+                self.authenticated.store(true, Ordering::SeqCst);
+                channel
+            }
+            Err(e) => {
+                tracing::error!("Authenticated handshake with sender failed: {}", e);
+                *self.state.lock().unwrap() = ReceiverState::Error;
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::PermissionDenied,
+                    format!("handshake failed: {}", e),
+                ));
+            }
+        };
+        tracing::info!("Handshake complete, sender authenticated");
+
+        let chunk_cipher = if info.encrypted {
+            tracing::info!("Running chunk cipher key exchange with sender...");
+            match chunk_cipher::exchange_key(&mut conn, Role::Responder) {
+                Ok(key) => Some(ChunkCipher::new(key)),
+                Err(e) => {
+                    tracing::error!("Chunk cipher key exchange failed: {}", e);
+                    *self.state.lock().unwrap() = ReceiverState::Error;
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::PermissionDenied,
+                        format!("chunk cipher key exchange failed: {}", e),
+                    ));
+                }
+            }
+        } else {
+            None
+        };
+
+        tracing::info!("Sending resume index 0 to sender (fd-backed destinations always start fresh)");
+        conn.write_all(&0u64.to_be_bytes())?;
+        conn.write_all(&WINDOW_SIZE.to_be_bytes())?;
+        let offer = ResumeOffer {
+            already_have: Vec::new(),
+        };
+        offer.write_to(&mut conn)?;
+        conn.flush()?;
+
+        tracing::info!("Starting to receive file chunks...");
+        let received = self.receive_windowed(
+            &mut conn,
+            &mut file,
+            &mut channel,
+            chunk_cipher.as_ref(),
+            None,
+            &info.file_checksum,
+            info.file_size,
+            info.chunk_size,
+            0,
+            0,
+            Vec::new(),
+        )?;
+        self.bytes_received.store(received, Ordering::SeqCst);
+
+        tracing::info!("All chunks received, flushing and verifying...");
+        file.flush()?;
+        file.seek(std::io::SeekFrom::Start(0))?;
+        let final_checksum = crate::checksum::calculate_reader_checksum(file)?;
+        let success = final_checksum == info.file_checksum;
+
+        if success {
+            tracing::info!("✓ Transfer completed successfully! Final checksum verified.");
+            conn.write_all(&[1u8])?;
+            let _ = conn.flush();
+            *self.state.lock().unwrap() = ReceiverState::Completed;
+        } else {
+            tracing::error!(
+                "✗ Final checksum verification failed! Expected: {}, received: {}",
+                info.file_checksum,
+                final_checksum
+            );
+            conn.write_all(&[0u8])?;
+            let _ = conn.flush();
+            *self.state.lock().unwrap() = ReceiverState::Error;
+        }
+
+        self.running.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Entry point for the first connection of a `stream_count > 1`
+    /// transfer (see `IncomingTransfer::stream_count`): creates the shared
+    /// `MultiStreamTransfer` coordination state, claims this connection's
+    /// own chunk range, and restarts the listener so the remaining
+    /// `stream_count - 1` connections - already in flight from
+    /// `sender::TcpFileSender::send_multi_stream` - get dispatched straight
+    /// to a worker without a second user prompt (see the `stream_count > 1`
+    /// branch in `start()`'s listener loop). Multi-stream transfers always
+    /// start fresh - the bitmap/truncation resume paths `accept_transfer`
+    /// otherwise uses don't apply here.
+    fn accept_multistream_transfer(
+        &self,
+        mut conn: TcpStream,
+        info: &IncomingTransfer,
+        save_path: &Path,
+        mut channel: crate::security::channel::SecureChannel,
+        identity: &DeviceIdentity,
+        pairing_code: &str,
+    ) -> std::io::Result<()> {
+        use crate::io_utils::ReceiverWriter;
+
+        let file = match ReceiverWriter::new_single(save_path)? {
+            ReceiverWriter::SingleFile(f) => f,
+            ReceiverWriter::Folder { .. } => unreachable!("new_single always returns SingleFile"),
+        };
+
+        let total_chunks = expected_total_chunks(info.file_size, info.chunk_size);
+        let chunks_per_stream = if info.stream_count == 0 {
+            total_chunks.max(1)
+        } else {
+            (total_chunks + info.stream_count as u64 - 1) / info.stream_count as u64
+        }
+        .max(1);
+
+        let state = Arc::new(MultiStreamTransfer {
+            transfer_id: info.transfer_id,
+            file: Mutex::new(file),
+            chunk_size: info.chunk_size as u64,
+            total_chunks,
+            file_checksum: info.file_checksum.clone(),
+            save_path: save_path.to_path_buf(),
+            chunks_per_stream,
+            next_range_start: AtomicU64::new(0),
+            completed_chunks: AtomicU64::new(0),
+            bitmap: Mutex::new(vec![false; total_chunks as usize]),
+            bytes_received: self.bytes_received.clone(),
+        });
+
+        // Re-import the identity so an owned copy can be handed to the
+        // listener thread for the remaining connections' own Noise
+        // handshakes (`DeviceIdentity` can't be cloned directly).
+        match DeviceIdentity::import(&identity.device_name, &identity.export()) {
+            Ok(owned_identity) => {
+                *self.multistream_identity.lock().unwrap() =
+                    Some((Arc::new(owned_identity), pairing_code.to_string()));
+            }
+            Err(e) => {
+                tracing::error!("Failed to clone identity for multi-stream dispatch: {}", e);
+            }
+        }
+
+        self.active_multistream
+            .lock()
+            .unwrap()
+            .insert(info.transfer_id, state.clone());
+
+        // The listener thread stopped after handing this connection to
+        // `accept_transfer` - restart it so the rest of `stream_count`'s
+        // connections, already dialing in, get picked up.
+        self.running.store(false, Ordering::SeqCst);
+        self.start();
+
+        let (range_start, range_end) = state.claim_range().unwrap_or((0, 0));
+        let offer = crate::protocol::StreamRangeOffer {
+            start_chunk: range_start,
+            end_chunk: range_end,
+        };
+        offer.write_to(&mut conn)?;
+        conn.flush()?;
+
+        let result = state.run_worker(&mut conn, &mut channel, range_start, range_end);
+        finish_stream_connection(&mut conn, &state, &self.active_multistream, &self.state, result)
+    }
+
+    /// Receive a content-defined-chunking transfer (see `crate::dedup`):
+    /// first tells the sender every content id it already holds - read off
+    /// whatever's already at `save_path`, if anything - then reads a
+    /// stream of `[marker][id]` frames, each followed by a sealed chunk
+    /// for a novel id (`marker == 0`) or nothing at all for one already in
+    /// the known set (`marker == 1`), which is instead copied out of the
+    /// existing local file.
+    fn accept_deduplicated_transfer(
+        &self,
+        mut conn: TcpStream,
+        info: &IncomingTransfer,
+        save_path: &Path,
+        mut channel: crate::security::channel::SecureChannel,
+    ) -> std::io::Result<()> {
+        let existing_bytes = std::fs::read(save_path).unwrap_or_default();
+        let known_index = crate::dedup::index_known_chunks(&existing_bytes);
+
+        let known = crate::protocol::KnownChunks {
+            ids: known_index
+                .keys()
+                .map(|id| crate::protocol::ContentChunkId(*id))
+                .collect(),
+        };
+        known.write_to(&mut conn)?;
+        conn.flush()?;
+
+        let mut count_buf = [0u8; 4];
+        conn.read_exact(&mut count_buf)?;
+        let total_chunks = u32::from_be_bytes(count_buf);
+
+        let mut file = File::create(save_path)?;
+        let mut received = 0u64;
+
+        for _ in 0..total_chunks {
+            let mut marker = [0u8; 1];
+            conn.read_exact(&mut marker)?;
+            let mut id = [0u8; 32];
+            conn.read_exact(&mut id)?;
+
+            if marker[0] == 1 {
+                let (offset, len) = known_index.get(&id).copied().ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "sender referenced a content id we never advertised as known",
+                    )
+                })?;
+                let bytes = &existing_bytes[offset as usize..(offset + len) as usize];
+                file.write_all(bytes)?;
+                received += bytes.len() as u64;
+            } else {
+                let mut len_buf = [0u8; 4];
+                conn.read_exact(&mut len_buf)?;
+                let packet_len = u32::from_be_bytes(len_buf) as usize;
+                let mut packet = vec![0u8; packet_len];
+                conn.read_exact(&mut packet)?;
+
+                let data = channel.open(&packet).map_err(|_| {
+                    *self.state.lock().unwrap() = ReceiverState::Error;
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "AEAD authentication failed",
+                    )
+                })?;
+                file.write_all(&data)?;
+                received += data.len() as u64;
+            }
+
+            self.bytes_received.store(received, Ordering::SeqCst);
+        }
+
+        file.flush()?;
+        drop(file);
+
+        let final_checksum = calculate_file_checksum(save_path)?;
+        let success = final_checksum == info.file_checksum;
+        conn.write_all(&[success as u8])?;
+        conn.flush()?;
+
+        *self.state.lock().unwrap() = if success {
+            ReceiverState::Completed
+        } else {
+            ReceiverState::Error
+        };
+
+        if success {
+            Ok(())
+        } else {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "deduplicated transfer failed final checksum verification",
+            ))
+        }
+    }
+
+    /// Pipelined receive for a single-file transfer: chunks may arrive out
+    /// of order (the sender runs ahead of individual ACKs, up to
+    /// `WINDOW_SIZE`), so each chunk is written at `chunk_index *
+    /// chunk_size` via `File::seek` rather than appended sequentially, and
+    /// a `received` bitset tracks which indices have actually landed.
+    /// Instead of one ACK per chunk, a [`SelectiveAck`] is flushed back
+    /// every `ACK_BATCH_CHUNKS` newly-filled chunks (or sooner, on auth
+    /// failure), reporting the highest contiguous index plus any gaps
+    /// below the window edge so the sender only retransmits what's
+    /// actually missing. Returns the total bytes received (`already_received`
+    /// plus everything newly written this call).
+    ///
+    /// The bitmap is also persisted to a `.vwpart` sidecar (see
+    /// `io_utils::save_chunk_bitmap`) on the same cadence as the ACK
+    /// batching, so a connection that drops mid-transfer can resume at
+    /// per-chunk granularity next time `accept_transfer` runs instead of
+    /// falling back to whole-chunk-boundary truncation. `save_path` is
+    /// `None` for `accept_transfer_fd`'s fd-backed destinations, which skip
+    /// the sidecar entirely - there's no path to key it by, and a fresh fd
+    /// handed across a JNI boundary has no way to resume into anyway.
+    #[allow(clippy::too_many_arguments)]
+    fn receive_windowed(
+        &self,
+        conn: &mut TcpStream,
+        file: &mut File,
+        channel: &mut crate::security::channel::SecureChannel,
+        chunk_cipher: Option<&ChunkCipher>,
+        save_path: Option<&std::path::Path>,
+        file_checksum: &str,
+        file_size: u64,
+        chunk_size: u32,
+        start_chunk_index: u64,
+        already_received: u64,
+        already_have: Vec<u64>,
+    ) -> std::io::Result<u64> {
+        if file_size == 0 {
+            return Ok(0);
+        }
+
+        let chunk_size_u32 = chunk_size;
+        let chunk_size = chunk_size as u64;
+        let total_chunks = (file_size + chunk_size - 1) / chunk_size;
+        let mut received_bitmap = vec![false; total_chunks as usize];
+        for i in 0..start_chunk_index.min(total_chunks) {
+            received_bitmap[i as usize] = true;
+        }
+        for i in already_have {
+            if i < total_chunks {
+                received_bitmap[i as usize] = true;
+            }
+        }
+        let mut highest_contiguous: Option<u64> =
+            if start_chunk_index == 0 { None } else { Some(start_chunk_index - 1) };
+
+        let mut received = already_received;
+        let mut since_last_ack = 0u64;
+        let mut pacer = ChunkCubic::new(INITIAL_CHUNK_WINDOW);
+
+        while highest_contiguous.map(|h| h + 1) != Some(total_chunks) {
+            // Read chunk header: [index: u64][sealed_packet_len: u32]
+            let mut header_buf = [0u8; 12];
+            conn.read_exact(&mut header_buf)?;
+            let chunk_index = u64::from_be_bytes(header_buf[0..8].try_into().unwrap());
+            let packet_len = u32::from_be_bytes(header_buf[8..12].try_into().unwrap()) as usize;
+
+            let mut packet = vec![0u8; packet_len];
+            conn.read_exact(&mut packet)?;
+
+            let data = match channel.open(&packet) {
+                Ok(data) => {
+                    if let Some(cipher) = chunk_cipher {
+                        cipher.decrypt(chunk_index, &data)
+                    } else {
+                        data
+                    }
+                }
+                Err(_) => {
+                    tracing::error!(
+                        "✗ AEAD authentication failed for chunk {}, aborting transfer",
+                        chunk_index
+                    );
+                    *self.state.lock().unwrap() = ReceiverState::Error;
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "AEAD authentication failed",
+                    ));
+                }
+            };
+
+            if chunk_index >= total_chunks {
+                tracing::warn!("Received out-of-range chunk index {}, ignoring", chunk_index);
+                continue;
+            }
+
+            if !received_bitmap[chunk_index as usize] {
+                file.seek(std::io::SeekFrom::Start(chunk_index * chunk_size))?;
+                file.write_all(&data)?;
+                received_bitmap[chunk_index as usize] = true;
+                received += data.len() as u64;
+                self.bytes_received.store(received, Ordering::SeqCst);
+                since_last_ack += 1;
+            }
+
+            // Advance the contiguous edge as far as the bitmap allows.
+            let mut next = highest_contiguous.map(|h| h + 1).unwrap_or(0);
+            while next < total_chunks && received_bitmap[next as usize] {
+                highest_contiguous = Some(next);
+                next += 1;
+            }
+
+            let done = highest_contiguous.map(|h| h + 1) == Some(total_chunks);
+            if since_last_ack >= ACK_BATCH_CHUNKS || done {
+                let window_edge = (highest_contiguous.map(|h| h + 1).unwrap_or(0) + WINDOW_SIZE as u64)
+                    .min(total_chunks);
+                let missing: Vec<u64> = (highest_contiguous.map(|h| h + 1).unwrap_or(0)..window_edge)
+                    .filter(|i| !received_bitmap[*i as usize])
+                    .collect();
+
+                // A gap below the window edge is this scheme's loss signal
+                // (see `pacing::ChunkCubic::on_loss`'s doc comment) - an
+                // AEAD authentication failure would be too, but that case
+                // already aborts the transfer outright above rather than
+                // reaching this batch boundary.
+                if missing.is_empty() {
+                    pacer.on_ack();
+                } else {
+                    pacer.on_loss();
+                }
+
+                let ack = SelectiveAck {
+                    highest_contiguous,
+                    missing,
+                    cwnd: pacer.cwnd().min(WINDOW_SIZE),
+                };
+                ack.write_to(conn)?;
+                conn.flush()?;
+                since_last_ack = 0;
+
+                if let Some(save_path) = save_path {
+                    if let Err(e) = crate::io_utils::save_chunk_bitmap(
+                        save_path,
+                        file_checksum,
+                        chunk_size_u32,
+                        file_size,
+                        &received_bitmap,
+                    ) {
+                        tracing::warn!("Failed to persist chunk resume state: {}", e);
+                    }
+                }
+            }
+        }
+
+        Ok(received)
+    }
+
     /// Reject the pending transfer
     pub fn reject_transfer(&self) -> std::io::Result<()> {
         let transfer = self.pending_transfer.lock().unwrap().take();
@@ -0,0 +1,132 @@
+//! Relay/rendezvous fallback for when a direct peer connection fails.
+//!
+//! `TcpFileSender::send_to` assumes both devices can reach each other
+//! directly (LAN, or a port-forwarded/public address); across NATs or an
+//! isolated guest network the `TcpStream::connect` simply fails. A relay
+//! server sidesteps that: both peers make *outbound* connections to it
+//! (which traverse almost any NAT/firewall), each presenting the same
+//! rendezvous token, and the relay pairs the two sockets and then blindly
+//! forwards bytes between them.
+//!
+//! The relay never needs the pairing code itself, and never sees
+//! plaintext: [`rendezvous_token`] is a one-way HKDF derivation of the
+//! code (so a relay operator can't recover it), and the bytes it forwards
+//! are already the Noise-encrypted stream produced by
+//! [`crate::security::channel::SecureChannel`]. The relay is purely a
+//! dumb socket pairing service - it needs no knowledge of VoidWarp's file
+//! transfer protocol at all.
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use hkdf::Hkdf;
+use sha2::Sha256;
+use thiserror::Error;
+
+/// Marks the start of the rendezvous hello, so a relay server can reject
+/// connections from something other than a VoidWarp client up front.
+pub const RELAY_MAGIC: u32 = 0x564C4159; // "VLAY"
+
+/// Which side of the transfer a rendezvous connection is for. The relay
+/// uses this only to decide which two sockets to splice together, never
+/// to interpret the data flowing over them.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayRole {
+    Sender = 1,
+    Receiver = 2,
+}
+
+/// Errors from dialing a relay and waiting to be paired with a peer.
+#[derive(Error, Debug)]
+pub enum RelayError {
+    #[error("failed to connect to relay server: {0}")]
+    ConnectFailed(String),
+    #[error("relay rejected the rendezvous handshake: {0}")]
+    HandshakeFailed(String),
+    #[error("timed out waiting for the relay to pair us with the peer")]
+    PairingTimeout,
+}
+
+/// Derive the rendezvous token two peers use to find each other on the
+/// relay, from their out-of-band pairing code. One-way (HKDF, not the code
+/// itself or anything invertible) so a relay operator who logs tokens
+/// still learns nothing about the pairing code.
+pub fn rendezvous_token(pairing_code: &str) -> [u8; 32] {
+    let hk = Hkdf::<Sha256>::new(Some(b"voidwarp-relay-rendezvous"), pairing_code.as_bytes());
+    let mut token = [0u8; 32];
+    hk.expand(b"voidwarp relay token", &mut token)
+        .expect("32 bytes is a valid HKDF output length");
+    token
+}
+
+/// Connect to `relay_addr` and block until the relay has paired this
+/// socket with a peer presenting the same rendezvous token (derived from
+/// `pairing_code`), or `timeout` elapses. On success, the returned stream
+/// behaves exactly like a direct `TcpStream` to that peer - everything
+/// written to it is forwarded verbatim by the relay.
+pub fn connect(
+    relay_addr: &str,
+    pairing_code: &str,
+    role: RelayRole,
+    timeout: Duration,
+) -> Result<TcpStream, RelayError> {
+    let addr = relay_addr
+        .to_socket_addrs()
+        .map_err(|e| RelayError::ConnectFailed(e.to_string()))?
+        .next()
+        .ok_or_else(|| RelayError::ConnectFailed("relay address did not resolve".to_string()))?;
+
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)
+        .map_err(|e| RelayError::ConnectFailed(e.to_string()))?;
+    stream
+        .set_read_timeout(Some(timeout))
+        .map_err(|e| RelayError::ConnectFailed(e.to_string()))?;
+    stream
+        .set_write_timeout(Some(timeout))
+        .map_err(|e| RelayError::ConnectFailed(e.to_string()))?;
+
+    let token = rendezvous_token(pairing_code);
+    let mut hello = Vec::with_capacity(4 + token.len() + 1);
+    hello.extend_from_slice(&RELAY_MAGIC.to_be_bytes());
+    hello.extend_from_slice(&token);
+    hello.push(role as u8);
+    stream
+        .write_all(&hello)
+        .map_err(|e| RelayError::HandshakeFailed(e.to_string()))?;
+
+    // The relay replies with a single status byte once it has paired this
+    // socket with a matching peer (0 = paired, anything else = rejected),
+    // which may take a while if the peer hasn't dialed in yet - hence the
+    // caller-supplied timeout rather than the usual short read.
+    let mut status = [0u8; 1];
+    match stream.read_exact(&mut status) {
+        Ok(_) if status[0] == 0 => Ok(stream),
+        Ok(_) => Err(RelayError::HandshakeFailed(format!(
+            "relay returned status {}",
+            status[0]
+        ))),
+        Err(e)
+            if e.kind() == std::io::ErrorKind::WouldBlock
+                || e.kind() == std::io::ErrorKind::TimedOut =>
+        {
+            Err(RelayError::PairingTimeout)
+        }
+        Err(e) => Err(RelayError::HandshakeFailed(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendezvous_token_is_deterministic_and_code_specific() {
+        let a = rendezvous_token("123456");
+        let b = rendezvous_token("123456");
+        let c = rendezvous_token("654321");
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+}
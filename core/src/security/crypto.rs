@@ -2,7 +2,14 @@
 //!
 //! Uses the `ring` crate for cryptographic operations.
 
+use hkdf::Hkdf;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{Ed25519KeyPair, KeyPair, Signature, UnparsedPublicKey, ED25519};
+use sha2::Sha256;
 use thiserror::Error;
+use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret as X25519StaticSecret};
+
+use super::validator::SecurePinValidator;
 
 /// Errors that can occur during cryptographic operations
 #[derive(Error, Debug)]
@@ -15,6 +22,12 @@ pub enum CryptoError {
     DecryptionFailed,
     #[error("Invalid key length")]
     InvalidKeyLength,
+    #[error("Not enough shares to recover the secret, or shares were ambiguous")]
+    InsufficientShares,
+    #[error("Signature verification failed")]
+    SignatureInvalid,
+    #[error("Secret does not carry enough entropy for secure use")]
+    WeakSecret,
 }
 
 /// Represents a 6-digit pairing code
@@ -26,20 +39,23 @@ pub struct PairingCode {
 impl PairingCode {
     /// Generate a new random 6-digit pairing code
     pub fn generate() -> Self {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        let seed = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_nanos();
-
-        // Simple PRNG for demo (in production, use ring::rand)
-        let code = format!("{:06}", (seed % 1_000_000) as u32);
+        let rng = SystemRandom::new();
+        let mut bytes = [0u8; 4];
+        rng.fill(&mut bytes).expect("system RNG is available");
+        let seed = u32::from_le_bytes(bytes);
+
+        let code = format!("{:06}", seed % 1_000_000);
         PairingCode { code }
     }
 
-    /// Create from a user-entered string
+    /// Create from a user-entered string. Rejected via
+    /// `SecurePinValidator::for_live_code` if it's a trivial pattern
+    /// (`111111`, `123456`, `121212`, ...) a real attacker would try first.
     pub fn parse(s: &str) -> Option<Self> {
-        if s.len() == 6 && s.chars().all(|c| c.is_ascii_digit()) {
+        if s.len() == 6
+            && s.chars().all(|c| c.is_ascii_digit())
+            && SecurePinValidator::for_live_code().validate(s).is_ok()
+        {
             Some(PairingCode {
                 code: s.to_string(),
             })
@@ -67,30 +83,20 @@ impl std::str::FromStr for PairingCode {
     }
 }
 
-/// Session key derived from pairing
+/// Session key established between two devices.
+///
+/// Keys are produced by [`crate::security::spake2::Spake2`], a
+/// password-authenticated key exchange keyed by a [`PairingCode`]. There is
+/// deliberately no constructor here that takes a raw pairing code directly -
+/// see the `spake2` module for how a `SessionKey` is established.
 #[derive(Debug)]
 pub struct SessionKey {
     key: [u8; 32], // AES-256
 }
 
 impl SessionKey {
-    /// Derive a session key from pairing code and connection ID
-    /// (Simplified PBKDF - in production use SPAKE2+ or similar PAKE)
-    pub fn derive(pairing_code: &PairingCode, salt: &[u8]) -> Self {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-
-        let mut key = [0u8; 32];
-
-        // Simple key derivation (NOT for production - use ring::pbkdf2)
-        for (i, byte) in key.iter_mut().enumerate() {
-            let mut hasher = DefaultHasher::new();
-            pairing_code.raw().hash(&mut hasher);
-            salt.hash(&mut hasher);
-            (i as u64).hash(&mut hasher);
-            *byte = (hasher.finish() & 0xFF) as u8;
-        }
-
+    /// Wrap raw key bytes produced by a completed key exchange.
+    pub(crate) fn from_bytes(key: [u8; 32]) -> Self {
         SessionKey { key }
     }
 
@@ -100,27 +106,122 @@ impl SessionKey {
     }
 }
 
-/// Device identity (Ed25519 public key placeholder)
-#[derive(Debug, Clone)]
+/// A device's Ed25519 signing identity.
+///
+/// `device_id` is the hex-encoded public key, so any peer that has seen it
+/// (e.g. over mDNS or a QR code) can verify signatures from this device
+/// without a separate certificate exchange. This is what lets pairing
+/// detect a man-in-the-middle: the relayed handshake transcript won't carry
+/// a valid signature from the `device_id` the victim actually saw.
 pub struct DeviceIdentity {
     pub device_id: String,
     pub device_name: String,
+    keypair: Ed25519KeyPair,
+    /// PKCS#8 document backing `keypair`, kept around so [`Self::export`]
+    /// doesn't need to re-derive or re-generate anything.
+    pkcs8: Vec<u8>,
+    /// Long-term X25519 static key, used by [`crate::security::noise`] for
+    /// authenticated transfer handshakes. Deterministically derived from
+    /// `pkcs8` via HKDF rather than separately generated and stored, so
+    /// [`Self::export`]/[`Self::import`] don't need a second key format.
+    x25519_static: X25519StaticSecret,
 }
 
 impl DeviceIdentity {
-    /// Generate a new device identity
+    /// Generate a new device identity with a fresh Ed25519 keypair.
     pub fn generate(name: &str) -> Self {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        let id = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_nanos();
+        let rng = SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng)
+            .expect("system RNG is available")
+            .as_ref()
+            .to_vec();
+        let keypair =
+            Ed25519KeyPair::from_pkcs8(&pkcs8).expect("just-generated pkcs8 document is valid");
+
+        let device_id = hex_encode(keypair.public_key().as_ref());
+        let x25519_static = derive_x25519_static(&pkcs8);
 
         DeviceIdentity {
-            device_id: format!("{:016x}", id),
+            device_id,
             device_name: name.to_string(),
+            keypair,
+            pkcs8,
+            x25519_static,
         }
     }
+
+    /// This device's long-term X25519 static public key, as used in the
+    /// `security::noise` handshake transcript.
+    pub fn x25519_public(&self) -> [u8; 32] {
+        X25519PublicKey::from(&self.x25519_static).to_bytes()
+    }
+
+    /// Perform an X25519 Diffie-Hellman exchange between this device's
+    /// static secret and a peer's static or ephemeral public key.
+    pub(crate) fn x25519_diffie_hellman(&self, peer_public: &[u8; 32]) -> [u8; 32] {
+        let peer = X25519PublicKey::from(*peer_public);
+        self.x25519_static.diffie_hellman(&peer).to_bytes()
+    }
+
+    /// Sign a message with this device's private key.
+    pub fn sign(&self, msg: &[u8]) -> Signature {
+        self.keypair.sign(msg)
+    }
+
+    /// Verify a signature against a peer's hex-encoded public key
+    /// (typically the `device_id` the verifier already saw for that peer).
+    pub fn verify(pubkey_hex: &str, msg: &[u8], sig: &[u8]) -> Result<(), CryptoError> {
+        let pubkey = hex_decode(pubkey_hex).ok_or(CryptoError::SignatureInvalid)?;
+        UnparsedPublicKey::new(&ED25519, pubkey)
+            .verify(msg, sig)
+            .map_err(|_| CryptoError::SignatureInvalid)
+    }
+
+    /// Export the private key as a PKCS#8 document, for persisting this
+    /// identity across restarts.
+    pub fn export(&self) -> Vec<u8> {
+        self.pkcs8.clone()
+    }
+
+    /// Reconstruct a previously [`Self::export`]ed identity.
+    pub fn import(name: &str, pkcs8: &[u8]) -> Result<Self, CryptoError> {
+        let keypair = Ed25519KeyPair::from_pkcs8(pkcs8).map_err(|_| CryptoError::KeyGenFailed)?;
+        let device_id = hex_encode(keypair.public_key().as_ref());
+        let x25519_static = derive_x25519_static(pkcs8);
+
+        Ok(DeviceIdentity {
+            device_id,
+            device_name: name.to_string(),
+            keypair,
+            pkcs8: pkcs8.to_vec(),
+            x25519_static,
+        })
+    }
+}
+
+/// Derive a device's X25519 static secret from its Ed25519 PKCS#8
+/// document. HKDF over the whole document (rather than, say, the raw seed
+/// bytes) means this has no dependency on PKCS#8's internal layout.
+fn derive_x25519_static(pkcs8: &[u8]) -> X25519StaticSecret {
+    let hk = Hkdf::<Sha256>::new(Some(b"voidwarp-x25519-static"), pkcs8);
+    let mut seed = [0u8; 32];
+    hk.expand(b"voidwarp x25519 static key", &mut seed)
+        .expect("32 bytes is a valid HKDF output length");
+    X25519StaticSecret::from(seed)
+}
+
+pub(crate) fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
 }
 
 #[cfg(test)]
@@ -136,24 +237,55 @@ mod tests {
         let display = code.display();
         assert!(display.contains('-'));
 
-        let parsed = PairingCode::from_str("123456").unwrap();
-        assert_eq!(parsed.raw(), "123456");
+        let parsed = PairingCode::from_str("503971").unwrap();
+        assert_eq!(parsed.raw(), "503971");
 
         assert!(PairingCode::from_str("12345").is_err());
         assert!(PairingCode::from_str("12345a").is_err());
+        assert!(PairingCode::from_str("123456").is_err());
+        assert!(PairingCode::from_str("111111").is_err());
+    }
+
+    #[test]
+    fn test_device_identity_sign_and_verify() {
+        let device = DeviceIdentity::generate("alice's phone");
+        let msg = b"handshake transcript";
+        let sig = device.sign(msg);
+
+        assert!(DeviceIdentity::verify(&device.device_id, msg, sig.as_ref()).is_ok());
+        assert!(matches!(
+            DeviceIdentity::verify(&device.device_id, b"tampered", sig.as_ref()),
+            Err(CryptoError::SignatureInvalid)
+        ));
     }
 
     #[test]
-    fn test_session_key_derivation() {
-        let code = PairingCode::from_str("123456").unwrap();
-        let salt = b"test_salt";
+    fn test_device_identity_export_import_roundtrip() {
+        let device = DeviceIdentity::generate("bob's laptop");
+        let exported = device.export();
 
-        let key1 = SessionKey::derive(&code, salt);
-        let key2 = SessionKey::derive(&code, salt);
+        let restored = DeviceIdentity::import("bob's laptop", &exported).unwrap();
+        assert_eq!(restored.device_id, device.device_id);
+
+        let msg = b"some message";
+        let sig = restored.sign(msg);
+        assert!(DeviceIdentity::verify(&device.device_id, msg, sig.as_ref()).is_ok());
+    }
 
-        assert_eq!(key1.as_bytes(), key2.as_bytes());
+    #[test]
+    fn test_x25519_static_key_stable_across_import() {
+        let device = DeviceIdentity::generate("carol's tablet");
+        let restored = DeviceIdentity::import("carol's tablet", &device.export()).unwrap();
+        assert_eq!(device.x25519_public(), restored.x25519_public());
+    }
+
+    #[test]
+    fn test_x25519_diffie_hellman_agrees() {
+        let alice = DeviceIdentity::generate("alice");
+        let bob = DeviceIdentity::generate("bob");
 
-        let key3 = SessionKey::derive(&code, b"different_salt");
-        assert_ne!(key1.as_bytes(), key3.as_bytes());
+        let alice_shared = alice.x25519_diffie_hellman(&bob.x25519_public());
+        let bob_shared = bob.x25519_diffie_hellman(&alice.x25519_public());
+        assert_eq!(alice_shared, bob_shared);
     }
 }
@@ -0,0 +1,251 @@
+//! BIP-39-style mnemonic recovery phrases.
+//!
+//! `PairingCode` is great for live pairing but its 6 digits are far too
+//! little entropy (and too short-lived) for long-term device recovery.
+//! `RecoveryPhrase` instead encodes a device's root key material as a 12-
+//! or 24-word phrase (mirroring the mnemonic-util work in the Keyfork
+//! docs), so a user can re-derive their `DeviceIdentity` on a new device
+//! from the phrase alone.
+//!
+//! Encoding: generate 128 or 256 bits of entropy, append a SHA-256
+//! checksum (the first `entropy_bits / 32` bits of the hash), and split the
+//! combined bitstream into 11-bit indices into a fixed 2048-word list.
+
+use ring::rand::{SecureRandom, SystemRandom};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use super::crypto::CryptoError;
+
+/// The 2048-word list used to encode mnemonic indices, one word per line.
+const WORDLIST: &str = include_str!("../../assets/wordlist_english.txt");
+
+/// Entropy strength for a generated phrase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strength {
+    /// 128 bits of entropy -> 12 words.
+    Words12,
+    /// 256 bits of entropy -> 24 words.
+    Words24,
+}
+
+impl Strength {
+    fn entropy_bytes(self) -> usize {
+        match self {
+            Strength::Words12 => 16,
+            Strength::Words24 => 32,
+        }
+    }
+}
+
+/// Errors specific to mnemonic phrase parsing.
+#[derive(Error, Debug)]
+pub enum MnemonicError {
+    #[error("phrase must have 12 or 24 words, got {0}")]
+    WrongWordCount(usize),
+    #[error("unknown word: {0:?}")]
+    UnknownWord(String),
+    #[error("checksum did not match - phrase is mistyped or corrupted")]
+    ChecksumMismatch,
+    #[error("random number generation failed")]
+    RngFailed,
+}
+
+/// A BIP-39-style recovery phrase.
+#[derive(Debug, Clone)]
+pub struct RecoveryPhrase {
+    words: Vec<String>,
+}
+
+impl RecoveryPhrase {
+    /// Generate a new recovery phrase from fresh entropy.
+    pub fn generate(strength: Strength) -> Result<Self, MnemonicError> {
+        let mut entropy = vec![0u8; strength.entropy_bytes()];
+        SystemRandom::new()
+            .fill(&mut entropy)
+            .map_err(|_| MnemonicError::RngFailed)?;
+        Ok(Self::from_entropy(&entropy))
+    }
+
+    /// Parse and validate a phrase entered by the user.
+    pub fn parse(phrase: &str) -> Result<Self, MnemonicError> {
+        let words: Vec<&str> = phrase.split_whitespace().collect();
+        if words.len() != 12 && words.len() != 24 {
+            return Err(MnemonicError::WrongWordCount(words.len()));
+        }
+
+        let word_list = wordlist();
+        let mut bits = Vec::with_capacity(words.len() * 11);
+        for word in &words {
+            let index = word_list
+                .iter()
+                .position(|w| w == word)
+                .ok_or_else(|| MnemonicError::UnknownWord(word.to_string()))?;
+            push_bits(&mut bits, index as u32, 11);
+        }
+
+        let entropy_bits = bits.len() * 32 / 33; // total = entropy_bits + entropy_bits/32
+        let (entropy_bits_buf, checksum_bits) = bits.split_at(entropy_bits);
+        let entropy = bits_to_bytes(entropy_bits_buf);
+
+        let expected_checksum = checksum_bits_for(&entropy, checksum_bits.len());
+        if expected_checksum != checksum_bits {
+            return Err(MnemonicError::ChecksumMismatch);
+        }
+
+        Ok(RecoveryPhrase {
+            words: words.into_iter().map(String::from).collect(),
+        })
+    }
+
+    /// Render the phrase as a space-separated string.
+    pub fn phrase(&self) -> String {
+        self.words.join(" ")
+    }
+
+    /// Derive a 64-byte seed from this phrase, following BIP-39: PBKDF2-
+    /// HMAC-SHA512 over 2048 iterations with salt `"voidwarp" + passphrase`.
+    pub fn to_seed(&self, passphrase: &str) -> [u8; 64] {
+        let mut salt = String::from("voidwarp");
+        salt.push_str(passphrase);
+
+        let mut seed = [0u8; 64];
+        ring::pbkdf2::derive(
+            ring::pbkdf2::PBKDF2_HMAC_SHA512,
+            std::num::NonZeroU32::new(2048).unwrap(),
+            salt.as_bytes(),
+            self.phrase().as_bytes(),
+            &mut seed,
+        );
+        seed
+    }
+
+    fn from_entropy(entropy: &[u8]) -> Self {
+        let checksum_bit_count = entropy.len() * 8 / 32;
+        let checksum_bits = checksum_bits_for(entropy, checksum_bit_count);
+
+        let mut bits = Vec::with_capacity(entropy.len() * 8 + checksum_bit_count);
+        for byte in entropy {
+            push_bits(&mut bits, *byte as u32, 8);
+        }
+        bits.extend_from_slice(&checksum_bits);
+
+        let word_list = wordlist();
+        let words = bits
+            .chunks(11)
+            .map(|chunk| {
+                let index = bits_to_index(chunk);
+                word_list[index as usize].to_string()
+            })
+            .collect();
+
+        RecoveryPhrase { words }
+    }
+}
+
+fn wordlist() -> Vec<&'static str> {
+    WORDLIST.lines().collect()
+}
+
+/// Append the low `count` bits of `value` (MSB-first) to `bits`.
+fn push_bits(bits: &mut Vec<bool>, value: u32, count: u32) {
+    for i in (0..count).rev() {
+        bits.push((value >> i) & 1 == 1);
+    }
+}
+
+fn bits_to_index(bits: &[bool]) -> u32 {
+    bits.iter().fold(0u32, |acc, &b| (acc << 1) | (b as u32))
+}
+
+fn bits_to_bytes(bits: &[bool]) -> Vec<u8> {
+    bits.chunks(8)
+        .map(|chunk| chunk.iter().fold(0u8, |acc, &b| (acc << 1) | (b as u8)))
+        .collect()
+}
+
+/// First `count` bits of `SHA-256(entropy)`, MSB-first.
+fn checksum_bits_for(entropy: &[u8], count: usize) -> Vec<bool> {
+    let digest = Sha256::digest(entropy);
+    let mut bits = Vec::with_capacity(count);
+    'outer: for byte in digest.iter() {
+        for i in (0..8).rev() {
+            if bits.len() >= count {
+                break 'outer;
+            }
+            bits.push((byte >> i) & 1 == 1);
+        }
+    }
+    bits
+}
+
+impl From<MnemonicError> for CryptoError {
+    fn from(_: MnemonicError) -> Self {
+        CryptoError::KeyGenFailed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wordlist_has_2048_unique_words() {
+        let words = wordlist();
+        assert_eq!(words.len(), 2048);
+        let unique: std::collections::HashSet<&&str> = words.iter().collect();
+        assert_eq!(unique.len(), 2048);
+    }
+
+    #[test]
+    fn test_generate_and_parse_roundtrip_12_words() {
+        let phrase = RecoveryPhrase::generate(Strength::Words12).unwrap();
+        assert_eq!(phrase.words.len(), 12);
+
+        let parsed = RecoveryPhrase::parse(&phrase.phrase()).unwrap();
+        assert_eq!(parsed.phrase(), phrase.phrase());
+    }
+
+    #[test]
+    fn test_generate_and_parse_roundtrip_24_words() {
+        let phrase = RecoveryPhrase::generate(Strength::Words24).unwrap();
+        assert_eq!(phrase.words.len(), 24);
+
+        let parsed = RecoveryPhrase::parse(&phrase.phrase()).unwrap();
+        assert_eq!(parsed.phrase(), phrase.phrase());
+    }
+
+    #[test]
+    fn test_corrupted_phrase_fails_checksum() {
+        let phrase = RecoveryPhrase::generate(Strength::Words12).unwrap();
+        let words = wordlist();
+
+        let mut corrupted_words = phrase.words.clone();
+        let last_word_idx = words.iter().position(|w| *w == corrupted_words[11]).unwrap();
+        let replacement_idx = (last_word_idx + 1) % words.len();
+        corrupted_words[11] = words[replacement_idx].to_string();
+
+        let corrupted = corrupted_words.join(" ");
+        assert!(matches!(
+            RecoveryPhrase::parse(&corrupted),
+            Err(MnemonicError::ChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_wrong_word_count_rejected() {
+        let err = RecoveryPhrase::parse("only a few words").unwrap_err();
+        assert!(matches!(err, MnemonicError::WrongWordCount(4)));
+    }
+
+    #[test]
+    fn test_seed_derivation_is_deterministic() {
+        let phrase = RecoveryPhrase::generate(Strength::Words12).unwrap();
+        let seed1 = phrase.to_seed("");
+        let seed2 = phrase.to_seed("");
+        assert_eq!(seed1, seed2);
+
+        let seed3 = phrase.to_seed("extra passphrase");
+        assert_ne!(seed1, seed3);
+    }
+}
@@ -0,0 +1,12 @@
+//! Security primitives: device identity, pairing, and session key establishment.
+
+pub mod channel;
+pub mod chunk_aead;
+pub mod chunk_cipher;
+pub mod crypto;
+pub mod mnemonic;
+pub mod noise;
+pub mod shares;
+pub mod spake2;
+pub mod trust;
+pub mod validator;
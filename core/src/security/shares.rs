@@ -0,0 +1,219 @@
+//! Shamir secret sharing for device-key backup and multi-device recovery.
+//!
+//! Splits a 32-byte master key into `n` shares with a `t`-of-`n` recovery
+//! threshold, so a device key can be backed up across trusted peers without
+//! any single peer holding a usable copy (see the Keyfork docs for the
+//! general shard workflow this mirrors).
+//!
+//! Arithmetic is done over GF(256) using the AES field polynomial
+//! `0x11B`: each secret byte gets its own random degree-`t-1` polynomial
+//! whose constant term is that byte, evaluated at one nonzero x-coordinate
+//! per share. Recovery reconstructs each byte via Lagrange interpolation at
+//! `x = 0` from any `t` of the shares.
+
+use rand_core::{OsRng, RngCore};
+
+use super::crypto::CryptoError;
+
+/// One share of a split 32-byte secret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Share {
+    /// Nonzero x-coordinate identifying this share (1..=255).
+    pub x: u8,
+    /// y-coordinates, one per secret byte.
+    pub y: [u8; 32],
+}
+
+/// Shamir `t`-of-`n` secret sharing over a 32-byte master key.
+pub struct SecretShares;
+
+impl SecretShares {
+    /// Split `secret` into `n` shares such that any `t` of them reconstruct
+    /// it, but any `t - 1` reveal nothing.
+    ///
+    /// Panics if `t == 0`, `t > n`, or `n > 255` (there are only 255 nonzero
+    /// x-coordinates in GF(256)).
+    pub fn split(secret: &[u8; 32], t: u8, n: u8) -> Vec<Share> {
+        assert!(t > 0, "threshold must be at least 1");
+        assert!(t <= n, "threshold cannot exceed share count");
+        assert!(n as usize <= 255, "GF(256) supports at most 255 shares");
+
+        // coeffs[byte_idx] = [a_0, a_1, .., a_{t-1}] with a_0 = secret byte
+        // and a_1..a_{t-1} random - one polynomial per secret byte.
+        let degree = (t - 1) as usize;
+        let mut coeffs = vec![[0u8; 32]; degree];
+        for c in coeffs.iter_mut() {
+            OsRng.fill_bytes(c);
+        }
+
+        (1..=n)
+            .map(|x| {
+                let mut y = [0u8; 32];
+                for (byte_idx, y_byte) in y.iter_mut().enumerate() {
+                    *y_byte = eval_polynomial(secret[byte_idx], &coeffs, byte_idx, x);
+                }
+                Share { x, y }
+            })
+            .collect()
+    }
+
+    /// Reconstruct the original secret from at least `t` shares.
+    ///
+    /// Returns [`CryptoError::InsufficientShares`] if fewer than 2 shares
+    /// are provided (the minimum for any nontrivial threshold) or if two
+    /// shares share the same x-coordinate, which would make interpolation
+    /// ambiguous.
+    pub fn recover(shares: &[Share]) -> Result<[u8; 32], CryptoError> {
+        if shares.len() < 2 {
+            return Err(CryptoError::InsufficientShares);
+        }
+
+        for i in 0..shares.len() {
+            for j in (i + 1)..shares.len() {
+                if shares[i].x == shares[j].x {
+                    return Err(CryptoError::InsufficientShares);
+                }
+            }
+        }
+
+        let mut secret = [0u8; 32];
+        for byte_idx in 0..32 {
+            secret[byte_idx] = lagrange_interpolate_at_zero(shares, byte_idx);
+        }
+        Ok(secret)
+    }
+}
+
+/// Evaluate `a_0 + a_1*x + .. + a_{t-1}*x^{t-1}` at `x` in GF(256), where
+/// `a_0` is `constant_term` and `a_1..a_{t-1}` are `coeffs[i][byte_idx]`.
+fn eval_polynomial(constant_term: u8, coeffs: &[[u8; 32]], byte_idx: usize, x: u8) -> u8 {
+    // Horner's method, high-degree coefficient first.
+    let mut result = 0u8;
+    for c in coeffs.iter().rev() {
+        result = gf256_add(gf256_mul(result, x), c[byte_idx]);
+    }
+    gf256_add(gf256_mul(result, x), constant_term)
+}
+
+/// Reconstruct `secret[byte_idx]` via Lagrange interpolation at `x = 0`.
+fn lagrange_interpolate_at_zero(shares: &[Share], byte_idx: usize) -> u8 {
+    let mut result = 0u8;
+
+    for (i, share_i) in shares.iter().enumerate() {
+        let mut numerator = 1u8;
+        let mut denominator = 1u8;
+
+        for (j, share_j) in shares.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+            // basis_i(0) = product over j != i of (0 - x_j) / (x_i - x_j)
+            numerator = gf256_mul(numerator, share_j.x);
+            denominator = gf256_mul(denominator, gf256_add(share_i.x, share_j.x));
+        }
+
+        let basis = gf256_mul(numerator, gf256_inv(denominator));
+        result = gf256_add(result, gf256_mul(share_i.y[byte_idx], basis));
+    }
+
+    result
+}
+
+// --- GF(256) arithmetic (AES field polynomial 0x11B), via log/antilog tables ---
+
+/// Addition (and subtraction) in GF(256) is just XOR.
+fn gf256_add(a: u8, b: u8) -> u8 {
+    a ^ b
+}
+
+fn gf256_mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    let log = gf256_log_table();
+    let exp = gf256_exp_table();
+    let sum = log[a as usize] as u16 + log[b as usize] as u16;
+    exp[(sum % 255) as usize]
+}
+
+fn gf256_inv(a: u8) -> u8 {
+    assert!(a != 0, "cannot invert zero in GF(256)");
+    let log = gf256_log_table();
+    let exp = gf256_exp_table();
+    let inv_log = (255 - log[a as usize] as u16) % 255;
+    exp[inv_log as usize]
+}
+
+/// Log/antilog tables for GF(256) under the AES reduction polynomial
+/// `x^8 + x^4 + x^3 + x + 1` (0x11B), generated from primitive element 0x03
+/// and cached in a process-wide static on first use.
+fn gf256_exp_table() -> &'static [u8; 256] {
+    static TABLE: std::sync::OnceLock<[u8; 256]> = std::sync::OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut exp = [0u8; 256];
+        let mut x = 1u8;
+        for e in exp.iter_mut().take(255) {
+            *e = x;
+            // Multiply x by the primitive element 0x03 (i.e. x*2 XOR x),
+            // reducing mod 0x11B whenever doubling overflows into degree 8.
+            let doubled = if x & 0x80 != 0 {
+                (x << 1) ^ 0x1B
+            } else {
+                x << 1
+            };
+            x = doubled ^ x;
+        }
+        exp
+    })
+}
+
+fn gf256_log_table() -> &'static [u8; 256] {
+    static TABLE: std::sync::OnceLock<[u8; 256]> = std::sync::OnceLock::new();
+    TABLE.get_or_init(|| {
+        let exp = gf256_exp_table();
+        let mut log = [0u8; 256];
+        for i in 0..255u16 {
+            log[exp[i as usize] as usize] = i as u8;
+        }
+        log
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_and_recover_exact_threshold() {
+        let secret = *b"0123456789abcdef0123456789abcde";
+        let shares = SecretShares::split(&secret, 3, 5);
+        assert_eq!(shares.len(), 5);
+
+        let recovered = SecretShares::recover(&shares[0..3]).unwrap();
+        assert_eq!(recovered, secret);
+    }
+
+    #[test]
+    fn test_recover_with_any_subset() {
+        let secret = *b"deadbeefdeadbeefdeadbeefdeadbeef";
+        let shares = SecretShares::split(&secret, 2, 4);
+
+        let recovered = SecretShares::recover(&[shares[1].clone(), shares[3].clone()]).unwrap();
+        assert_eq!(recovered, secret);
+    }
+
+    #[test]
+    fn test_insufficient_shares_rejected() {
+        let secret = [7u8; 32];
+        let shares = SecretShares::split(&secret, 3, 5);
+        let err = SecretShares::recover(&shares[0..1]).unwrap_err();
+        assert!(matches!(err, CryptoError::InsufficientShares));
+    }
+
+    #[test]
+    fn test_duplicate_x_rejected() {
+        let share = Share { x: 1, y: [0u8; 32] };
+        let err = SecretShares::recover(&[share.clone(), share]).unwrap_err();
+        assert!(matches!(err, CryptoError::InsufficientShares));
+    }
+}
@@ -0,0 +1,379 @@
+//! SPAKE2 password-authenticated key exchange over ristretto255.
+//!
+//! Two devices that both know the same low-entropy [`PairingCode`] run this
+//! two-message handshake to agree on a strong, uniformly random
+//! [`SessionKey`] without ever putting the pairing code (or anything
+//! brute-forceable from it) on the wire. This replaces the old
+//! `SessionKey::derive`, which hashed the pairing code directly and was
+//! vulnerable to offline dictionary attacks against anyone who captured the
+//! salt.
+//!
+//! Protocol sketch (see the UKEY2 authenticated-key-exchange write-up for
+//! background): fix public generator points `M` and `N`, and let
+//! `w = H(pairing_code, salt)` reduced mod the group order. The initiator
+//! picks random scalar `x` and sends `T = x*G + w*M`; the responder picks
+//! `y` and sends `S = y*G + w*N`. The initiator computes
+//! `K = x*(S - w*N)`, the responder computes `K = y*(T - w*M)`; both equal
+//! `x*y*G`. The session key is `HKDF(transcript || K)` where the transcript
+//! binds both device IDs and both public messages, which also doubles as
+//! the key-confirmation MAC input so a wrong pairing code fails the
+//! confirmation check instead of silently producing mismatched keys.
+//!
+//! SPAKE2 alone only proves "the peer knows the pairing code" - a relay
+//! sitting between two devices that are both (unknowingly) pairing with it
+//! can complete the handshake with each victim and splice their traffic
+//! together. [`Spake2Output::attestation_transcript`] exists for a third
+//! flight that closes this gap: each side signs the transcript with its
+//! [`DeviceIdentity`] and the peer checks the signature against the
+//! `device_id` it already knows them by, which a mere relay can't forge.
+
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT as G;
+use curve25519_dalek::ristretto::CompressedRistretto;
+use curve25519_dalek::scalar::Scalar;
+use hkdf::Hkdf;
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256, Sha512};
+use thiserror::Error;
+
+use super::crypto::{DeviceIdentity, PairingCode, SessionKey};
+
+/// Errors that can occur while running the SPAKE2 handshake.
+#[derive(Error, Debug)]
+pub enum Spake2Error {
+    #[error("peer sent a point that is not on the curve")]
+    InvalidPoint,
+    #[error("key confirmation failed - pairing code mismatch or tampering")]
+    ConfirmationFailed,
+    #[error("peer identity attestation failed - possible man-in-the-middle")]
+    AttestationFailed,
+}
+
+/// Which side of the handshake this device is playing.
+///
+/// The two roles use different blinding generators (`M` vs `N`) so that the
+/// same pairing code doesn't let either side dictate the other's message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Initiator,
+    Responder,
+}
+
+/// The single protocol message exchanged in each direction: a compressed
+/// ristretto255 point.
+#[derive(Debug, Clone, Copy)]
+pub struct Spake2Message(pub [u8; 32]);
+
+/// Output of a completed handshake: the derived session key plus the data
+/// needed to run key confirmation and identity attestation as a third
+/// flight.
+pub struct Spake2Output {
+    pub session_key: SessionKey,
+    /// MAC we send to the peer proving we derived the same key.
+    pub our_confirmation: [u8; 32],
+    /// MAC we expect to receive from the peer.
+    expected_peer_confirmation: [u8; 32],
+    /// Bytes identifying this exact handshake (both public keys, both
+    /// confirmation tags) that each side signs with its [`DeviceIdentity`]
+    /// to prove it - not a relay - is the one holding the session key. A
+    /// MITM relaying the handshake between two victims can still complete
+    /// SPAKE2 and confirmation, but can't forge a signature under either
+    /// victim's `device_id`.
+    attestation_transcript: Vec<u8>,
+}
+
+impl Spake2Output {
+    /// Bytes that identity attestation signatures are computed over.
+    pub fn attestation_transcript(&self) -> &[u8] {
+        &self.attestation_transcript
+    }
+
+    /// Sign the attestation transcript with our device identity, to send to
+    /// the peer alongside our confirmation tag.
+    pub fn sign_attestation(&self, identity: &DeviceIdentity) -> ring::signature::Signature {
+        identity.sign(&self.attestation_transcript)
+    }
+
+    /// Verify the peer's attestation signature against the `device_id` we
+    /// already know them by (e.g. from discovery or a scanned QR code).
+    /// This is what catches a relayed handshake: the relay has no way to
+    /// produce a signature that verifies under the real peer's key.
+    pub fn verify_peer_attestation(
+        &self,
+        peer_device_id: &str,
+        peer_sig: &[u8],
+    ) -> Result<(), Spake2Error> {
+        DeviceIdentity::verify(peer_device_id, &self.attestation_transcript, peer_sig)
+            .map_err(|_| Spake2Error::AttestationFailed)
+    }
+
+    /// Verify the confirmation MAC received from the peer. Returns an error
+    /// (rather than a mismatched key) if the pairing code didn't match.
+    pub fn verify_peer_confirmation(self, peer_tag: &[u8]) -> Result<SessionKey, Spake2Error> {
+        if constant_time_eq(&self.expected_peer_confirmation, peer_tag) {
+            Ok(self.session_key)
+        } else {
+            Err(Spake2Error::ConfirmationFailed)
+        }
+    }
+}
+
+/// A single-use SPAKE2 handshake state machine.
+///
+/// ```ignore
+/// let (initiator, msg_a) = Spake2::start(&code, salt, Role::Initiator, "alice", "bob");
+/// let (responder, msg_b) = Spake2::start(&code, salt, Role::Responder, "bob", "alice");
+/// let out_a = initiator.finish(msg_b)?;
+/// let out_b = responder.finish(msg_a)?;
+/// let key_a = out_a.verify_peer_confirmation(&out_b.our_confirmation)?;
+/// let key_b = out_b.verify_peer_confirmation(&out_a.our_confirmation)?;
+/// assert_eq!(key_a.as_bytes(), key_b.as_bytes());
+/// ```
+pub struct Spake2 {
+    role: Role,
+    x: Scalar,
+    w: Scalar,
+    our_message: Spake2Message,
+    our_device_id: String,
+    peer_device_id: String,
+}
+
+impl Spake2 {
+    /// Begin a handshake, producing the message to send to the peer.
+    pub fn start(
+        pairing_code: &PairingCode,
+        salt: &[u8],
+        role: Role,
+        our_device_id: &str,
+        peer_device_id: &str,
+    ) -> (Self, Spake2Message) {
+        let w = derive_password_scalar(pairing_code, salt);
+
+        let mut scalar_bytes = [0u8; 64];
+        OsRng.fill_bytes(&mut scalar_bytes);
+        let x = Scalar::from_bytes_mod_order_wide(&scalar_bytes);
+
+        let blind = match role {
+            Role::Initiator => m_point(),
+            Role::Responder => n_point(),
+        };
+        let our_message = Spake2Message((x * G + w * blind).compress().to_bytes());
+
+        (
+            Spake2 {
+                role,
+                x,
+                w,
+                our_message,
+                our_device_id: our_device_id.to_string(),
+                peer_device_id: peer_device_id.to_string(),
+            },
+            our_message,
+        )
+    }
+
+    /// Complete the handshake given the peer's message, deriving the shared
+    /// session key and the key-confirmation MACs.
+    pub fn finish(self, peer_message: Spake2Message) -> Result<Spake2Output, Spake2Error> {
+        let peer_point = CompressedRistretto(peer_message.0)
+            .decompress()
+            .ok_or(Spake2Error::InvalidPoint)?;
+
+        // Subtract the peer's blinding term to recover y*G (or x*G), then
+        // scale by our own secret scalar to land on the shared x*y*G.
+        let peer_blind = match self.role {
+            Role::Initiator => n_point(),
+            Role::Responder => m_point(),
+        };
+        let shared_point = self.x * (peer_point - self.w * peer_blind);
+
+        // Normalize device-id order to (initiator, responder) - same trick
+        // used for message ordering below - so both sides hash identical
+        // bytes regardless of which one is "us" in this instance.
+        let (initiator_device_id, responder_device_id) = match self.role {
+            Role::Initiator => (self.our_device_id.as_str(), self.peer_device_id.as_str()),
+            Role::Responder => (self.peer_device_id.as_str(), self.our_device_id.as_str()),
+        };
+
+        let mut transcript = Sha512::new();
+        transcript.update(b"VoidWarp-SPAKE2-Transcript-v1");
+        transcript.update(initiator_device_id.as_bytes());
+        transcript.update([0u8]);
+        transcript.update(responder_device_id.as_bytes());
+        transcript.update([0u8]);
+        match self.role {
+            Role::Initiator => {
+                transcript.update(self.our_message.0);
+                transcript.update(peer_message.0);
+            }
+            Role::Responder => {
+                transcript.update(peer_message.0);
+                transcript.update(self.our_message.0);
+            }
+        }
+        transcript.update(shared_point.compress().as_bytes());
+        let transcript_hash = transcript.finalize();
+
+        let hk = Hkdf::<Sha256>::new(None, &transcript_hash);
+
+        let mut key = [0u8; 32];
+        hk.expand(b"voidwarp session key", &mut key)
+            .expect("32 bytes is a valid HKDF output length");
+
+        // Each side's confirmation MAC is bound to "who's speaking" so the
+        // two tags aren't trivially interchangeable.
+        let our_confirmation = confirmation_tag(&hk, self.our_device_id.as_bytes());
+        let expected_peer_confirmation = confirmation_tag(&hk, self.peer_device_id.as_bytes());
+
+        // Sort the two tags so the attestation transcript is identical on
+        // both sides - it's bound to "this exact handshake", not to who's
+        // signing it.
+        let mut tags = [our_confirmation, expected_peer_confirmation];
+        tags.sort();
+        let mut attestation_transcript = transcript_hash.to_vec();
+        attestation_transcript.extend_from_slice(&tags[0]);
+        attestation_transcript.extend_from_slice(&tags[1]);
+
+        Ok(Spake2Output {
+            session_key: SessionKey::from_bytes(key),
+            our_confirmation,
+            expected_peer_confirmation,
+            attestation_transcript,
+        })
+    }
+}
+
+fn confirmation_tag(hk: &Hkdf<Sha256>, speaker_device_id: &[u8]) -> [u8; 32] {
+    let mut tag = [0u8; 32];
+    let mut info = b"voidwarp key confirmation".to_vec();
+    info.extend_from_slice(speaker_device_id);
+    hk.expand(&info, &mut tag)
+        .expect("32 bytes is a valid HKDF output length");
+    tag
+}
+
+/// Reduce `H(pairing_code, salt)` into a scalar mod the group order.
+fn derive_password_scalar(pairing_code: &PairingCode, salt: &[u8]) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(b"VoidWarp-SPAKE2-Password-v1");
+    hasher.update(pairing_code.raw().as_bytes());
+    hasher.update(salt);
+    let digest = hasher.finalize();
+    let mut wide = [0u8; 64];
+    wide.copy_from_slice(&digest);
+    Scalar::from_bytes_mod_order_wide(&wide)
+}
+
+/// Fixed generator `M`, derived deterministically so every build agrees on
+/// the same point without needing to ship a literal table of field bytes.
+fn m_point() -> curve25519_dalek::ristretto::RistrettoPoint {
+    curve25519_dalek::ristretto::RistrettoPoint::hash_from_bytes::<Sha512>(
+        b"VoidWarp-SPAKE2-M-generator",
+    )
+}
+
+/// Fixed generator `N`, analogous to [`m_point`] but used for the other role.
+fn n_point() -> curve25519_dalek::ristretto::RistrettoPoint {
+    curve25519_dalek::ristretto::RistrettoPoint::hash_from_bytes::<Sha512>(
+        b"VoidWarp-SPAKE2-N-generator",
+    )
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut diff = 0u8;
+    for (x, y) in a.iter().zip(b.iter()) {
+        diff |= x ^ y;
+    }
+    diff == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_handshake_agrees_on_same_key() {
+        let code = PairingCode::from_str("503971").unwrap();
+        let salt = b"connection-salt";
+
+        let (initiator, msg_a) = Spake2::start(&code, salt, Role::Initiator, "alice", "bob");
+        let (responder, msg_b) = Spake2::start(&code, salt, Role::Responder, "bob", "alice");
+
+        let out_a = initiator.finish(msg_b).unwrap();
+        let out_b = responder.finish(msg_a).unwrap();
+
+        let confirm_a = out_b.our_confirmation;
+        let confirm_b = out_a.our_confirmation;
+
+        let key_a = out_a.verify_peer_confirmation(&confirm_a).unwrap();
+        let key_b = out_b.verify_peer_confirmation(&confirm_b).unwrap();
+
+        assert_eq!(key_a.as_bytes(), key_b.as_bytes());
+    }
+
+    #[test]
+    fn test_wrong_pairing_code_fails_confirmation() {
+        let salt = b"connection-salt";
+        let code_a = PairingCode::from_str("503971").unwrap();
+        let code_b = PairingCode::from_str("284756").unwrap();
+
+        let (initiator, msg_a) = Spake2::start(&code_a, salt, Role::Initiator, "alice", "bob");
+        let (responder, msg_b) = Spake2::start(&code_b, salt, Role::Responder, "bob", "alice");
+
+        let out_a = initiator.finish(msg_b).unwrap();
+        let out_b = responder.finish(msg_a).unwrap();
+
+        let confirm_a = out_b.our_confirmation;
+        assert!(matches!(
+            out_a.verify_peer_confirmation(&confirm_a),
+            Err(Spake2Error::ConfirmationFailed)
+        ));
+    }
+
+    #[test]
+    fn test_garbage_message_rejected() {
+        let code = PairingCode::from_str("503971").unwrap();
+        let (initiator, _) = Spake2::start(&code, b"salt", Role::Initiator, "alice", "bob");
+        // All-0xFF is extremely unlikely to decompress to a valid point.
+        let bogus = Spake2Message([0xFFu8; 32]);
+        assert!(matches!(initiator.finish(bogus), Err(Spake2Error::InvalidPoint)));
+    }
+
+    #[test]
+    fn test_attestation_roundtrip_and_relay_rejected() {
+        let code = PairingCode::from_str("503971").unwrap();
+        let salt = b"connection-salt";
+        let alice_identity = DeviceIdentity::generate("alice");
+        let bob_identity = DeviceIdentity::generate("bob");
+
+        let (initiator, msg_a) = Spake2::start(&code, salt, Role::Initiator, "alice", "bob");
+        let (responder, msg_b) = Spake2::start(&code, salt, Role::Responder, "bob", "alice");
+
+        let out_a = initiator.finish(msg_b).unwrap();
+        let out_b = responder.finish(msg_a).unwrap();
+        assert_eq!(out_a.attestation_transcript(), out_b.attestation_transcript());
+
+        let sig_a = out_a.sign_attestation(&alice_identity);
+        let sig_b = out_b.sign_attestation(&bob_identity);
+
+        assert!(out_b
+            .verify_peer_attestation(&alice_identity.device_id, sig_a.as_ref())
+            .is_ok());
+        assert!(out_a
+            .verify_peer_attestation(&bob_identity.device_id, sig_b.as_ref())
+            .is_ok());
+
+        // A relay that merely forwards the handshake can't produce a
+        // signature under the real peer's device_id, since it doesn't hold
+        // that private key.
+        let mallory_identity = DeviceIdentity::generate("mallory");
+        let forged_sig = out_b.sign_attestation(&mallory_identity);
+        assert!(matches!(
+            out_b.verify_peer_attestation(&alice_identity.device_id, forged_sig.as_ref()),
+            Err(Spake2Error::AttestationFailed)
+        ));
+    }
+}
@@ -0,0 +1,80 @@
+//! Trust-on-first-use pinning for device identities.
+//!
+//! In this crate `device_id` *is* the hex-encoded Ed25519 public key (see
+//! [`crate::security::crypto::DeviceIdentity`]), so "the same `device_id`
+//! under a different key" is not a forgery an attacker can mount - changing
+//! the key changes the id by definition, and signature verification already
+//! catches anyone who doesn't hold the matching private key. [`TrustStore`]
+//! exists anyway as cheap defense-in-depth (in case a future identity format
+//! decouples the two) and to give callers a single place to drop a pin when
+//! a device is re-paired under a new key.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Pins `device_id -> public_key` on first sight.
+pub struct TrustStore {
+    pins: RwLock<HashMap<String, String>>,
+}
+
+impl TrustStore {
+    pub fn new() -> Self {
+        TrustStore {
+            pins: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Record `device_id -> public_key_hex` the first time it's seen.
+    /// Returns `false` (without updating the store) if `device_id` was
+    /// already pinned to a *different* key - callers should treat that as a
+    /// dropped packet.
+    pub fn pin(&self, device_id: &str, public_key_hex: &str) -> bool {
+        let mut pins = self.pins.write().unwrap();
+        match pins.get(device_id) {
+            Some(existing) if existing != public_key_hex => {
+                tracing::warn!(
+                    "Rejected {}: claimed a different key than the one pinned on first sight",
+                    device_id
+                );
+                false
+            }
+            Some(_) => true,
+            None => {
+                pins.insert(device_id.to_string(), public_key_hex.to_string());
+                true
+            }
+        }
+    }
+
+    /// Forget a pin, e.g. so a device can be re-paired under a new key.
+    pub fn clear(&self, device_id: &str) {
+        self.pins.write().unwrap().remove(device_id);
+    }
+}
+
+impl Default for TrustStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pins_on_first_sight_and_rejects_later_key_changes() {
+        let store = TrustStore::new();
+        assert!(store.pin("abc123", "keyA"));
+        assert!(store.pin("abc123", "keyA"));
+        assert!(!store.pin("abc123", "keyB"));
+    }
+
+    #[test]
+    fn clear_allows_re_pairing_under_a_new_key() {
+        let store = TrustStore::new();
+        assert!(store.pin("abc123", "keyA"));
+        store.clear("abc123");
+        assert!(store.pin("abc123", "keyB"));
+    }
+}
@@ -3,15 +3,25 @@
 //! Handles sending files over TCP with checksum verification, chunking,
 //! acknowledgments, and resume support.
 
-use crate::checksum::{calculate_chunk_checksum, calculate_file_checksum};
-use crate::io_utils::MultiFileReader;
-use crate::protocol::TransferType;
+use crate::checksum::calculate_file_checksum;
+use crate::io_utils::ChunkSource;
+use crate::protocol::{ByteRange, TransferType};
+use crate::ratelimit::RateLimiter;
+use crate::security::channel::SecureChannel;
+use crate::security::chunk_cipher::{self, ChunkCipher};
+use crate::security::crypto::DeviceIdentity;
+use crate::security::noise;
+use crate::security::spake2::Role;
+use rand_core::{OsRng, RngCore};
 use std::fs::File;
 use std::io::{BufReader, Read, Seek, Write};
 use std::net::{SocketAddr, TcpStream};
+#[cfg(unix)]
+use std::os::unix::io::{FromRawFd, RawFd};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::thread;
 use std::time::Duration;
 
 /// Default chunk size (1MB)
@@ -30,6 +40,29 @@ const ACK_TIMEOUT: Duration = Duration::from_secs(30);
 /// Max retries per chunk
 const MAX_RETRIES: u32 = 3;
 
+/// Max times `TcpFileSender::send_to_resilient` will redial a peer after
+/// the connection drops mid-transfer before giving up.
+const MAX_RECONNECT_ATTEMPTS: u32 = 5;
+
+/// Delay before each redial attempt. Fixed rather than exponential since
+/// these are short-lived Wi-Fi hiccups, not a congested server - retrying
+/// sooner gets the transfer moving again faster without hammering a peer
+/// that's actually gone.
+const RECONNECT_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Coarse lifecycle state for an in-flight [`TcpFileSender::send_to_resilient`]
+/// transfer, polled via `voidwarp_tcp_sender_get_state` so a UI can show
+/// "reconnecting..." instead of the transfer just going quiet when the
+/// connection drops and is automatically redialed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransferState {
+    #[default]
+    Connecting,
+    Transferring,
+    Reconnecting,
+    Done,
+}
+
 /// Transfer result
 #[derive(Debug, Clone)]
 pub enum TransferResult {
@@ -40,6 +73,21 @@ pub enum TransferResult {
     Timeout,
     Cancelled,
     IoError(String),
+    QuicError(String),
+    /// The pre-transfer authenticated key exchange failed - wrong pairing
+    /// code, or a tampered/relayed handshake. Distinct from
+    /// `ChecksumMismatch` since nothing was sent yet.
+    AuthenticationFailed,
+    /// A chunk's AEAD tag failed to verify after a successful handshake -
+    /// distinct from the legacy `ChecksumMismatch` since this means the
+    /// data was tampered with or corrupted in a way integrity-checking
+    /// alone can't recover from by retrying.
+    DecryptionFailed,
+    /// Like `Success`, but the direct connection failed and the transfer
+    /// completed over a [`crate::relay`] instead. Distinguished from
+    /// `Success` so callers can surface "worked, but slower than LAN" to
+    /// the user if they want to.
+    SuccessViaRelay,
 }
 
 /// File sender for TCP transfer
@@ -54,6 +102,27 @@ pub struct TcpFileSender {
     pub transfer_type: TransferType,
     manifest_bytes: Vec<u8>,
     files_to_send: Vec<PathBuf>,
+    state: Arc<Mutex<TransferState>>,
+    /// Whether to negotiate the `security::chunk_cipher` layer on top of
+    /// the handshake's `SecureChannel`. Off by default - see
+    /// `set_chunk_encryption`.
+    chunk_encryption: bool,
+    /// Caps outgoing throughput; unlimited (a no-op) until `set_rate_limit`
+    /// configures a byte rate. Shared via `Arc` so every connection of a
+    /// `send_multi_stream` transfer draws from the same budget instead of
+    /// each one getting its own independent allowance.
+    rate_limiter: Arc<RateLimiter>,
+    /// Set by `from_fd`: chunk data is read from this already-open handle
+    /// (via `ChunkSource::for_fd`) instead of reopening `files_to_send` by
+    /// path. `None` for every sender built from `new`/`new_single_file`/
+    /// `new_folder`.
+    #[cfg(unix)]
+    source_fd: Option<File>,
+    /// Identifies this sender's blocking calls to `crate::cancel`'s thread
+    /// registry on Android, so `cancel()` can wake a thread parked in
+    /// `wait_for_ack` immediately instead of waiting out `ACK_TIMEOUT`.
+    #[cfg(target_os = "android")]
+    cancel_token: u64,
 }
 
 impl TcpFileSender {
@@ -94,6 +163,13 @@ impl TcpFileSender {
             transfer_type: TransferType::SingleFile,
             manifest_bytes: vec![],
             files_to_send: vec![path.to_path_buf()],
+            state: Arc::new(Mutex::new(TransferState::default())),
+            chunk_encryption: false,
+            rate_limiter: Arc::new(RateLimiter::new(0)),
+            #[cfg(unix)]
+            source_fd: None,
+            #[cfg(target_os = "android")]
+            cancel_token: crate::cancel::new_token(),
         })
     }
 
@@ -178,6 +254,63 @@ impl TcpFileSender {
             transfer_type: TransferType::Folder,
             manifest_bytes: full_manifest_data,
             files_to_send,
+            state: Arc::new(Mutex::new(TransferState::default())),
+            chunk_encryption: false,
+            rate_limiter: Arc::new(RateLimiter::new(0)),
+            #[cfg(unix)]
+            source_fd: None,
+            #[cfg(target_os = "android")]
+            cancel_token: crate::cancel::new_token(),
+        })
+    }
+
+    /// Create a sender from an already-open file descriptor rather than a
+    /// path - for Android callers holding a Storage Access Framework
+    /// `content://` grant, where there's no filesystem path to `new` with,
+    /// only an `int fd` handed across the JNI boundary (see
+    /// `voidwarp_tcp_sender_create_from_fd`). `fd` is `dup`'d immediately so
+    /// this sender owns an independent descriptor the caller remains free to
+    /// close; `display_name` is used only for the handshake's file name and
+    /// has no bearing on where bytes are read from.
+    ///
+    /// Only single-file, non-folder sends are possible this way - a folder
+    /// transfer's manifest is built from a directory walk, which an fd has
+    /// no path for. `send_multi_stream` works unchanged, since every
+    /// connection just asks `chunk_source()` for its own `File::try_clone`.
+    /// `send_deduplicated` doesn't: it reads the whole file by path up
+    /// front (`std::fs::read(&self.file_path)`), so it falls back to the
+    /// ordinary `send_to` for an fd-backed sender instead.
+    #[cfg(unix)]
+    pub fn from_fd(fd: RawFd, display_name: &str, size: u64) -> std::io::Result<Self> {
+        let dup_fd = unsafe { libc::dup(fd) };
+        if dup_fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let mut file = unsafe { File::from_raw_fd(dup_fd) };
+
+        tracing::info!("Calculating checksum for fd-backed sender: {}", display_name);
+        let checksum_handle = file.try_clone()?;
+        let file_checksum = crate::checksum::calculate_reader_checksum(checksum_handle)?;
+        file.seek(std::io::SeekFrom::Start(0))?;
+        tracing::info!("File checksum: {}", file_checksum);
+
+        Ok(TcpFileSender {
+            file_path: display_name.to_string(),
+            file_size: size,
+            file_checksum,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            bytes_sent: Arc::new(AtomicU64::new(0)),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            resume_from_chunk: 0,
+            transfer_type: TransferType::SingleFile,
+            manifest_bytes: vec![],
+            files_to_send: vec![],
+            state: Arc::new(Mutex::new(TransferState::default())),
+            chunk_encryption: false,
+            rate_limiter: Arc::new(RateLimiter::new(0)),
+            source_fd: Some(file),
+            #[cfg(target_os = "android")]
+            cancel_token: crate::cancel::new_token(),
         })
     }
 
@@ -191,6 +324,26 @@ impl TcpFileSender {
         self.resume_from_chunk = chunk_index;
     }
 
+    /// Negotiate the additional `security::chunk_cipher` encryption layer
+    /// for this transfer (see that module's doc comment). Off by default.
+    ///
+    /// There's deliberately no equivalent toggle to disable the base
+    /// `security::noise` handshake and `SecureChannel` - every transfer
+    /// is authenticated and encrypted from the first byte, and a
+    /// plaintext fallback would reopen exactly the tamper/eavesdrop gap
+    /// that handshake was added to close. `chunk_encryption` only adds a
+    /// second key on top; it's never a way to remove the first.
+    pub fn set_chunk_encryption(&mut self, enabled: bool) {
+        self.chunk_encryption = enabled;
+    }
+
+    /// Cap outgoing throughput to `bytes_per_sec` (see `ratelimit`). `0`
+    /// (the default) is unlimited. Use `ratelimit::parse_rate` to turn a
+    /// human-friendly size like `"10MB"` into the raw rate this expects.
+    pub fn set_rate_limit(&mut self, bytes_per_sec: u64) {
+        self.rate_limiter = Arc::new(RateLimiter::new(bytes_per_sec));
+    }
+
     /// Get bytes sent so far
     pub fn bytes_sent(&self) -> u64 {
         self.bytes_sent.load(Ordering::SeqCst)
@@ -204,9 +357,25 @@ impl TcpFileSender {
         (self.bytes_sent() as f32 / self.file_size as f32) * 100.0
     }
 
-    /// Cancel the transfer
+    /// Cancel the transfer. On Android this also wakes a thread already
+    /// parked in `wait_for_ack` via `crate::cancel`, instead of leaving it
+    /// to notice the flag only once its read timeout fires.
     pub fn cancel(&self) {
         self.cancelled.store(true, Ordering::SeqCst);
+        #[cfg(target_os = "android")]
+        crate::cancel::cancel(self.cancel_token);
+    }
+
+    /// Current lifecycle state of an in-flight `send_to_resilient` call.
+    /// Stays `Connecting` before the first attempt and `Done` once the
+    /// transfer has returned (whatever the outcome) - it's a UI hint, not
+    /// a replacement for the returned `TransferResult`.
+    pub fn state(&self) -> TransferState {
+        *self.state.lock().unwrap()
+    }
+
+    fn set_state(&self, state: TransferState) {
+        *self.state.lock().unwrap() = state;
     }
 
     /// Get file size
@@ -214,11 +383,51 @@ impl TcpFileSender {
         self.file_size
     }
 
+    /// Resolve a [`ByteRange`] request against this transfer's total stream
+    /// size (manifest header, if any, plus file bytes - see
+    /// `ChunkSource::for_transfer`), returning the `(start, len)` pair to
+    /// seek a reader to and then stream exactly `len` bytes from. Shared
+    /// groundwork for folder-transfer resume and for splitting a transfer
+    /// across several connections that each pull a disjoint slice.
+    pub fn resolve_range(&self, range: ByteRange) -> std::io::Result<(u64, u64)> {
+        range.resolve(self.file_size)
+    }
+
     /// Get file checksum
     pub fn checksum(&self) -> &str {
         &self.file_checksum
     }
 
+    /// Whether this sender was built by `from_fd` rather than a path.
+    #[cfg(unix)]
+    fn is_fd_backed(&self) -> bool {
+        self.source_fd.is_some()
+    }
+
+    #[cfg(not(unix))]
+    fn is_fd_backed(&self) -> bool {
+        false
+    }
+
+    /// Builds the `ChunkSource` this transfer reads chunk data from: an
+    /// `from_fd` sender clones its already-open handle (see
+    /// `ChunkSource::for_fd`), every other sender reopens `files_to_send`
+    /// by path (see `ChunkSource::for_transfer`). Called fresh for every
+    /// connection attempt, same as the path-based case already was, so a
+    /// redial (`send_to_resilient`) or a multi-stream connection each get
+    /// their own independent seek position.
+    fn chunk_source(&self) -> std::io::Result<ChunkSource> {
+        #[cfg(unix)]
+        if let Some(file) = &self.source_fd {
+            return Ok(ChunkSource::for_fd(file.try_clone()?));
+        }
+        ChunkSource::for_transfer(
+            self.manifest_bytes.clone(),
+            self.files_to_send.clone(),
+            self.file_size,
+        )
+    }
+
     /// Get file name
     pub fn file_name(&self) -> String {
         Path::new(&self.file_path)
@@ -227,6 +436,358 @@ impl TcpFileSender {
             .unwrap_or_else(|| "unknown".to_string())
     }
 
+    /// Send a single file across `stream_count` concurrent TCP connections,
+    /// each pulling a disjoint range of chunk indices assigned by the
+    /// receiver (see `receiver::MultiStreamTransfer`) - the "improve speed
+    /// with multiple connections" approach, for links where one TCP flow
+    /// can't fill the pipe. Only supported for single-file transfers
+    /// without the `security::chunk_cipher` layer: that layer's nonce
+    /// scheme is keyed by absolute chunk index across the *whole* file
+    /// (see its module doc), which only holds if one key is shared for the
+    /// whole transfer rather than negotiated independently per connection.
+    /// Falls back to the ordinary single-connection `send_to` for a folder
+    /// transfer, `chunk_encryption`, or a `stream_count` of 1 or less.
+    pub fn send_multi_stream(
+        &self,
+        peer_addr: SocketAddr,
+        sender_name: &str,
+        identity: &DeviceIdentity,
+        pairing_code: &str,
+        stream_count: u32,
+    ) -> TransferResult {
+        if self.transfer_type != TransferType::SingleFile
+            || self.chunk_encryption
+            || stream_count <= 1
+        {
+            tracing::info!(
+                "Multi-stream transfer not applicable (folder/encrypted/stream_count<=1), falling back to a single connection"
+            );
+            return self.send_to(peer_addr, sender_name, identity, pairing_code);
+        }
+
+        let transfer_id = OsRng.next_u64();
+        let streams: Vec<u32> = (0..stream_count).collect();
+
+        let results: Vec<TransferResult> = thread::scope(|scope| {
+            let handles: Vec<_> = streams
+                .iter()
+                .map(|_| {
+                    scope.spawn(|| {
+                        self.run_multi_stream_connection(
+                            peer_addr,
+                            sender_name,
+                            identity,
+                            pairing_code,
+                            transfer_id,
+                            stream_count,
+                        )
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap_or_else(|_| TransferResult::IoError("stream thread panicked".to_string())))
+                .collect()
+        });
+
+        results
+            .into_iter()
+            .find(|r| !matches!(r, TransferResult::Success))
+            .unwrap_or(TransferResult::Success)
+    }
+
+    /// One connection's worth of work for `send_multi_stream`: handshake,
+    /// wait for accept, authenticate, read this connection's assigned
+    /// `StreamRangeOffer`, and stream exactly that range.
+    fn run_multi_stream_connection(
+        &self,
+        peer_addr: SocketAddr,
+        sender_name: &str,
+        identity: &DeviceIdentity,
+        pairing_code: &str,
+        transfer_id: u64,
+        stream_count: u32,
+    ) -> TransferResult {
+        let mut stream = match TcpStream::connect_timeout(&peer_addr, CONNECT_TIMEOUT) {
+            Ok(s) => s,
+            Err(e) => return TransferResult::ConnectionFailed(e.to_string()),
+        };
+        if let Err(e) = stream.set_read_timeout(Some(ACK_TIMEOUT)) {
+            tracing::warn!("Failed to set read timeout: {}", e);
+        }
+        if let Err(e) = stream.set_write_timeout(Some(ACK_TIMEOUT)) {
+            tracing::warn!("Failed to set write timeout: {}", e);
+        }
+
+        use crate::protocol::HandshakeRequest;
+        let request = HandshakeRequest::new_multi_stream(
+            sender_name,
+            &self.file_name(),
+            self.file_size,
+            self.chunk_size as u32,
+            &self.file_checksum,
+            self.transfer_type,
+            transfer_id,
+            stream_count,
+        );
+        if let Err(e) = request.write_to(&mut stream) {
+            return TransferResult::IoError(format!("Handshake failed: {}", e));
+        }
+        if let Err(e) = stream.flush() {
+            return TransferResult::IoError(format!("Handshake flush failed: {}", e));
+        }
+
+        if let Err(e) = stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)) {
+            tracing::warn!("Failed to set handshake timeout: {}", e);
+        }
+        let mut response = [0u8; 1];
+        if let Err(e) = stream.read_exact(&mut response) {
+            return if e.kind() == std::io::ErrorKind::WouldBlock
+                || e.kind() == std::io::ErrorKind::TimedOut
+            {
+                TransferResult::Timeout
+            } else {
+                TransferResult::IoError(format!("Failed to read response: {}", e))
+            };
+        }
+        if response[0] == 0 {
+            return TransferResult::Rejected;
+        }
+        if let Err(e) = stream.set_read_timeout(Some(ACK_TIMEOUT)) {
+            tracing::warn!("Failed to set ACK timeout: {}", e);
+        }
+
+        let mut channel = match noise::run_handshake(&mut stream, Role::Initiator, identity, pairing_code)
+        {
+            Ok(channel) => channel,
+            Err(e) => {
+                tracing::error!("Authenticated handshake failed: {}", e);
+                return TransferResult::AuthenticationFailed;
+            }
+        };
+
+        let offer = match crate::protocol::StreamRangeOffer::read_from(&mut stream) {
+            Ok(offer) => offer,
+            Err(e) => return TransferResult::IoError(format!("Failed to read range offer: {}", e)),
+        };
+
+        let mut reader = match self.chunk_source() {
+            Ok(r) => r,
+            Err(e) => return TransferResult::IoError(e.to_string()),
+        };
+
+        let chunk_size = self.chunk_size as u64;
+        for chunk_index in offer.start_chunk..offer.end_chunk {
+            if self.cancelled.load(Ordering::SeqCst) {
+                return TransferResult::Cancelled;
+            }
+
+            let offset = chunk_index * chunk_size;
+            let len = std::cmp::min(chunk_size, self.file_size - offset) as usize;
+            let mut buf = vec![0u8; len];
+            if let Err(e) = reader
+                .seek(std::io::SeekFrom::Start(offset))
+                .and_then(|_| reader.read_exact(&mut buf))
+            {
+                return TransferResult::IoError(e.to_string());
+            }
+
+            if let Err(e) = self.send_chunk(&mut stream, &mut channel, None, chunk_index, &buf) {
+                return TransferResult::IoError(format!("Failed to send chunk {}: {}", chunk_index, e));
+            }
+
+            match self.wait_for_ack(&mut stream, chunk_index) {
+                Ok(AckStatus::Ok) => {
+                    self.bytes_sent.fetch_add(buf.len() as u64, Ordering::SeqCst);
+                }
+                Ok(AckStatus::AuthenticationFailed) => return TransferResult::DecryptionFailed,
+                Ok(AckStatus::Rejected) => {
+                    return TransferResult::IoError(format!(
+                        "chunk {} rejected by receiver",
+                        chunk_index
+                    ))
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted
+                    && self.cancelled.load(Ordering::SeqCst) =>
+                {
+                    return TransferResult::Cancelled;
+                }
+                Err(e) => {
+                    return TransferResult::IoError(format!(
+                        "ACK timeout for chunk {}: {}",
+                        chunk_index, e
+                    ))
+                }
+            }
+        }
+
+        // Whichever connection lands the transfer's last chunk runs final
+        // verification on the receiver's side (see
+        // `receiver::finish_stream_connection`) and reports the result
+        // here; every other connection just gets a plain completion byte.
+        let mut final_result = [0u8; 1];
+        match stream.read_exact(&mut final_result) {
+            Ok(_) => {
+                if final_result[0] == 1 {
+                    TransferResult::Success
+                } else {
+                    TransferResult::ChecksumMismatch
+                }
+            }
+            Err(e) => TransferResult::IoError(format!("Failed to read final result: {}", e)),
+        }
+    }
+
+    /// Send a single file using content-defined chunking (see the `dedup`
+    /// module) instead of fixed-size, position-indexed chunks: the
+    /// receiver tells us up front which content ids it already has (from
+    /// an existing partial or older copy of the destination file), and
+    /// any chunk whose id is in that set is sent as a 32-byte reference
+    /// instead of its full bytes. Falls back to the ordinary `send_to` for
+    /// a folder transfer, since the manifest/multi-file byte stream
+    /// doesn't yet carry a chunk-id list the way `new_folder`'s resume
+    /// logic carries byte offsets.
+    pub fn send_deduplicated(
+        &self,
+        peer_addr: SocketAddr,
+        sender_name: &str,
+        identity: &DeviceIdentity,
+        pairing_code: &str,
+    ) -> TransferResult {
+        if self.transfer_type != TransferType::SingleFile || self.is_fd_backed() {
+            tracing::info!(
+                "Deduplicated transfer only supports single files read by path, falling back to send_to"
+            );
+            return self.send_to(peer_addr, sender_name, identity, pairing_code);
+        }
+
+        let (mut stream, via_relay) = match self.connect_or_relay(peer_addr, pairing_code, None) {
+            Ok(pair) => pair,
+            Err(result) => return result,
+        };
+
+        use crate::protocol::HandshakeRequest;
+        let request = HandshakeRequest::new_deduplicated(
+            sender_name,
+            &self.file_name(),
+            self.file_size,
+            self.chunk_size as u32,
+            &self.file_checksum,
+        );
+        if let Err(e) = request.write_to(&mut stream) {
+            return TransferResult::IoError(format!("Handshake failed: {}", e));
+        }
+        if let Err(e) = stream.flush() {
+            return TransferResult::IoError(format!("Handshake flush failed: {}", e));
+        }
+
+        if let Err(e) = stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)) {
+            tracing::warn!("Failed to set handshake timeout: {}", e);
+        }
+        let mut response = [0u8; 1];
+        if let Err(e) = stream.read_exact(&mut response) {
+            return if e.kind() == std::io::ErrorKind::WouldBlock
+                || e.kind() == std::io::ErrorKind::TimedOut
+            {
+                TransferResult::Timeout
+            } else {
+                TransferResult::IoError(format!("Failed to read response: {}", e))
+            };
+        }
+        if response[0] == 0 {
+            return TransferResult::Rejected;
+        }
+        if let Err(e) = stream.set_read_timeout(Some(ACK_TIMEOUT)) {
+            tracing::warn!("Failed to set ACK timeout: {}", e);
+        }
+
+        let mut channel = match noise::run_handshake(&mut stream, Role::Initiator, identity, pairing_code)
+        {
+            Ok(channel) => channel,
+            Err(e) => {
+                tracing::error!("Authenticated handshake failed: {}", e);
+                return TransferResult::AuthenticationFailed;
+            }
+        };
+
+        let known = match crate::protocol::KnownChunks::read_from(&mut stream) {
+            Ok(known) => known,
+            Err(e) => return TransferResult::IoError(format!("Failed to read known chunks: {}", e)),
+        };
+        let known: std::collections::HashSet<[u8; 32]> =
+            known.ids.into_iter().map(|id| id.0).collect();
+
+        let data = match std::fs::read(&self.file_path) {
+            Ok(data) => data,
+            Err(e) => return TransferResult::IoError(e.to_string()),
+        };
+        let chunks = crate::dedup::cut_content_chunks(&data);
+
+        if let Err(e) = stream.write_all(&(chunks.len() as u32).to_be_bytes()) {
+            return TransferResult::IoError(e.to_string());
+        }
+
+        let mut deduped_bytes = 0u64;
+        for chunk in &chunks {
+            if self.cancelled.load(Ordering::SeqCst) {
+                return TransferResult::Cancelled;
+            }
+
+            let is_known = known.contains(&chunk.id);
+            let marker = [if is_known { 1u8 } else { 0u8 }];
+            if let Err(e) = stream
+                .write_all(&marker)
+                .and_then(|_| stream.write_all(&chunk.id))
+            {
+                return TransferResult::IoError(e.to_string());
+            }
+
+            if is_known {
+                deduped_bytes += chunk.len;
+                continue;
+            }
+
+            let sleep = self.rate_limiter.acquire(chunk.len as usize);
+            if sleep > Duration::ZERO {
+                thread::sleep(sleep);
+            }
+
+            let plaintext = &data[chunk.offset as usize..(chunk.offset + chunk.len) as usize];
+            let packet = match channel.seal(plaintext) {
+                Ok(packet) => packet,
+                Err(_) => return TransferResult::IoError("failed to encrypt chunk".to_string()),
+            };
+            if let Err(e) = stream
+                .write_all(&(packet.len() as u32).to_be_bytes())
+                .and_then(|_| stream.write_all(&packet))
+            {
+                return TransferResult::IoError(e.to_string());
+            }
+            self.bytes_sent.fetch_add(chunk.len, Ordering::SeqCst);
+        }
+        if let Err(e) = stream.flush() {
+            return TransferResult::IoError(e.to_string());
+        }
+        if deduped_bytes > 0 {
+            tracing::info!(
+                "Deduplicated transfer skipped {} bytes already known to the receiver",
+                deduped_bytes
+            );
+        }
+        self.bytes_sent.store(self.file_size, Ordering::SeqCst);
+
+        let mut final_result = [0u8; 1];
+        match stream.read_exact(&mut final_result) {
+            Ok(_) => match (final_result[0] == 1, via_relay) {
+                (true, true) => TransferResult::SuccessViaRelay,
+                (true, false) => TransferResult::Success,
+                (false, _) => TransferResult::ChecksumMismatch,
+            },
+            Err(e) => TransferResult::IoError(format!("Failed to read final result: {}", e)),
+        }
+    }
+
     /// Test connection to a peer
     pub fn test_connection(peer_addr: SocketAddr) -> TransferResult {
         tracing::info!("Testing connection to {}...", peer_addr);
@@ -245,17 +806,179 @@ impl TcpFileSender {
         }
     }
 
-    /// Send file to a peer
-    pub fn send_to(&self, peer_addr: SocketAddr, sender_name: &str) -> TransferResult {
-        tracing::info!("Connecting to {} for file transfer...", peer_addr);
+    /// Send file to a peer. `identity` and `pairing_code` authenticate the
+    /// handshake (see `security::noise`) that runs right after the
+    /// receiver accepts, before any chunk is sent.
+    pub fn send_to(
+        &self,
+        peer_addr: SocketAddr,
+        sender_name: &str,
+        identity: &DeviceIdentity,
+        pairing_code: &str,
+    ) -> TransferResult {
+        self.send_to_with_relay(peer_addr, sender_name, identity, pairing_code, None)
+    }
 
-        // Connect with timeout
-        let stream = match TcpStream::connect_timeout(&peer_addr, CONNECT_TIMEOUT) {
-            Ok(s) => s,
-            Err(e) => {
-                tracing::error!("Failed to connect: {}", e);
-                return TransferResult::ConnectionFailed(e.to_string());
+    /// Like `send_to`, but falls back to `relay_addr` (if given) when the
+    /// direct connection can't be established within `CONNECT_TIMEOUT`.
+    /// Routing through the relay doesn't weaken anything end-to-end: the
+    /// Noise handshake and per-chunk AEAD sealing still run on top of
+    /// whichever stream wins, so the relay only ever forwards ciphertext.
+    pub fn send_to_with_relay(
+        &self,
+        peer_addr: SocketAddr,
+        sender_name: &str,
+        identity: &DeviceIdentity,
+        pairing_code: &str,
+        relay_addr: Option<&str>,
+    ) -> TransferResult {
+        let (stream, via_relay) = match self.connect_or_relay(peer_addr, pairing_code, relay_addr)
+        {
+            Ok(pair) => pair,
+            Err(result) => return result,
+        };
+
+        match self.send_over_stream(stream, sender_name, identity, pairing_code, None) {
+            TransferResult::Success if via_relay => TransferResult::SuccessViaRelay,
+            other => other,
+        }
+    }
+
+    /// Like `send_to_with_relay`, but keeps going if the connection drops
+    /// mid-transfer: on a retryable failure (a dropped connection or I/O
+    /// error, not a rejection or a failed decryption/authentication check)
+    /// it redials `peer_addr`, re-runs the Noise handshake from scratch
+    /// with the same `pairing_code`, and resumes from the last
+    /// *acknowledged* chunk rather than restarting the file. Intended for
+    /// long transfers over flaky Wi-Fi; `state()` reports which phase the
+    /// retry loop is in (`Connecting`/`Transferring`/`Reconnecting`) so a
+    /// UI can say "reconnecting..." instead of going quiet.
+    pub fn send_to_resilient(
+        &self,
+        peer_addr: SocketAddr,
+        sender_name: &str,
+        identity: &DeviceIdentity,
+        pairing_code: &str,
+        relay_addr: Option<&str>,
+    ) -> TransferResult {
+        let mut attempt = 0u32;
+        let mut resume_override = if self.resume_from_chunk > 0 {
+            Some(self.resume_from_chunk)
+        } else {
+            None
+        };
+
+        loop {
+            if self.cancelled.load(Ordering::SeqCst) {
+                self.set_state(TransferState::Done);
+                return TransferResult::Cancelled;
             }
+
+            self.set_state(TransferState::Connecting);
+            let (stream, via_relay) =
+                match self.connect_or_relay(peer_addr, pairing_code, relay_addr) {
+                    Ok(pair) => pair,
+                    Err(result) => {
+                        if attempt >= MAX_RECONNECT_ATTEMPTS || !Self::is_retryable(&result) {
+                            self.set_state(TransferState::Done);
+                            return result;
+                        }
+                        attempt += 1;
+                        self.set_state(TransferState::Reconnecting);
+                        thread::sleep(RECONNECT_BACKOFF);
+                        continue;
+                    }
+                };
+
+            self.set_state(TransferState::Transferring);
+            let result = self.send_over_stream(
+                stream,
+                sender_name,
+                identity,
+                pairing_code,
+                resume_override,
+            );
+            let result = match result {
+                TransferResult::Success if via_relay => TransferResult::SuccessViaRelay,
+                other => other,
+            };
+
+            if !Self::is_retryable(&result) || attempt >= MAX_RECONNECT_ATTEMPTS {
+                self.set_state(TransferState::Done);
+                return result;
+            }
+
+            attempt += 1;
+            resume_override = Some(self.bytes_sent() / self.chunk_size as u64);
+            tracing::warn!(
+                "Transfer to {} dropped ({:?}), redialing from chunk {} (attempt {}/{})",
+                peer_addr,
+                result,
+                resume_override.unwrap(),
+                attempt,
+                MAX_RECONNECT_ATTEMPTS
+            );
+            self.set_state(TransferState::Reconnecting);
+            thread::sleep(RECONNECT_BACKOFF);
+        }
+    }
+
+    /// Whether a `send_to_resilient` retry loop should redial after this
+    /// result. Excludes anything that isn't a transient connection/IO
+    /// problem - a rejected offer, a bad pairing code, or a failed AEAD
+    /// check all mean redialing would just fail the same way again.
+    fn is_retryable(result: &TransferResult) -> bool {
+        matches!(
+            result,
+            TransferResult::ConnectionFailed(_)
+                | TransferResult::IoError(_)
+                | TransferResult::Timeout
+        )
+    }
+
+    /// Connect directly to `peer_addr`, falling back to `relay_addr` (if
+    /// given) when the direct attempt fails within `CONNECT_TIMEOUT`.
+    /// Shared by `send_to_with_relay` and `send_to_resilient` so both go
+    /// through the same relay-fallback and timeout setup.
+    fn connect_or_relay(
+        &self,
+        peer_addr: SocketAddr,
+        pairing_code: &str,
+        relay_addr: Option<&str>,
+    ) -> Result<(TcpStream, bool), TransferResult> {
+        tracing::info!("Connecting to {} for file transfer...", peer_addr);
+
+        let (stream, via_relay) = match TcpStream::connect_timeout(&peer_addr, CONNECT_TIMEOUT) {
+            Ok(s) => (s, false),
+            Err(direct_err) => match relay_addr {
+                None => {
+                    tracing::error!("Failed to connect: {}", direct_err);
+                    return Err(TransferResult::ConnectionFailed(direct_err.to_string()));
+                }
+                Some(relay_addr) => {
+                    tracing::warn!(
+                        "Direct connect to {} failed ({}), falling back to relay {}",
+                        peer_addr,
+                        direct_err,
+                        relay_addr
+                    );
+                    match crate::relay::connect(
+                        relay_addr,
+                        pairing_code,
+                        crate::relay::RelayRole::Sender,
+                        CONNECT_TIMEOUT,
+                    ) {
+                        Ok(s) => (s, true),
+                        Err(relay_err) => {
+                            tracing::error!("Relay fallback failed: {}", relay_err);
+                            return Err(TransferResult::ConnectionFailed(format!(
+                                "direct: {}; relay: {}",
+                                direct_err, relay_err
+                            )));
+                        }
+                    }
+                }
+            },
         };
 
         if let Err(e) = stream.set_read_timeout(Some(ACK_TIMEOUT)) {
@@ -265,11 +988,22 @@ impl TcpFileSender {
             tracing::warn!("Failed to set write timeout: {}", e);
         }
 
-        self.send_over_stream(stream, sender_name)
+        Ok((stream, via_relay))
     }
 
-    /// Send file over an established stream
-    fn send_over_stream(&self, mut stream: TcpStream, sender_name: &str) -> TransferResult {
+    /// Send file over an established stream. `resume_override`, when set,
+    /// takes priority over both `self.resume_from_chunk` and the
+    /// receiver's own resume offer (used by `send_to_resilient` to resume
+    /// from the last acknowledged chunk after a redial, since the sender
+    /// knows that better than the receiver's on-disk file length does).
+    fn send_over_stream(
+        &self,
+        mut stream: TcpStream,
+        sender_name: &str,
+        identity: &DeviceIdentity,
+        pairing_code: &str,
+        resume_override: Option<u64>,
+    ) -> TransferResult {
         // Send handshake
         tracing::info!("Sending file offer handshake to receiver...");
         if let Err(e) = self.send_handshake(&mut stream, sender_name) {
@@ -315,37 +1049,97 @@ impl TcpFileSender {
             tracing::warn!("Failed to set ACK timeout: {}", e);
         }
 
-        tracing::info!("Transfer accepted, starting file transfer...");
+        tracing::info!("Transfer accepted, running authenticated key exchange...");
 
-        // If resuming, read the resume chunk index from receiver
-        let start_chunk = if self.resume_from_chunk > 0 {
-            tracing::info!(
-                "Resuming from chunk {} (requested by sender)",
-                self.resume_from_chunk
-            );
-            self.resume_from_chunk
+        let mut channel = match noise::run_handshake(&mut stream, Role::Initiator, identity, pairing_code)
+        {
+            Ok(channel) => channel,
+            Err(e) => {
+                tracing::error!("Authenticated handshake failed: {}", e);
+                return TransferResult::AuthenticationFailed;
+            }
+        };
+
+        let chunk_cipher = if self.chunk_encryption {
+            tracing::info!("Running chunk cipher key exchange with receiver...");
+            match chunk_cipher::exchange_key(&mut stream, Role::Initiator) {
+                Ok(key) => Some(ChunkCipher::new(key)),
+                Err(e) => {
+                    tracing::error!("Chunk cipher key exchange failed: {}", e);
+                    return TransferResult::AuthenticationFailed;
+                }
+            }
         } else {
-            // Check if receiver wants to resume
-            let mut resume_buf = [0u8; 8];
-            match stream.read_exact(&mut resume_buf) {
+            None
+        };
+
+        tracing::info!("Handshake complete, starting encrypted file transfer...");
+
+        // The receiver always sends its own resume-chunk offer right after
+        // the handshake, so this read happens unconditionally to keep the
+        // stream in sync - but its value is only *used* when nothing else
+        // already decided the resume point.
+        let mut resume_buf = [0u8; 8];
+        let receiver_offer = match stream.read_exact(&mut resume_buf) {
+            Ok(_) => u64::from_be_bytes(resume_buf),
+            Err(e) => {
+                tracing::warn!("Failed to read resume chunk index: {}, starting from 0", e);
+                0
+            }
+        };
+
+        // Single-file transfers get a pipeline window right behind the
+        // resume index (see `receiver::FileReceiverServer::accept_transfer`);
+        // folder transfers stay on the legacy stop-and-wait ACK loop below.
+        let mut already_have: Vec<u64> = Vec::new();
+        let window_size = if self.transfer_type == TransferType::SingleFile {
+            let mut window_buf = [0u8; 4];
+            match stream.read_exact(&mut window_buf) {
                 Ok(_) => {
-                    let chunk = u64::from_be_bytes(resume_buf);
-                    if chunk > 0 {
-                        tracing::info!("Receiver requested resume from chunk {}", chunk);
-                    } else {
-                        tracing::info!("Starting fresh transfer from chunk 0");
+                    // Right behind the window size, the receiver also
+                    // offers up any chunks its `.vwpart` sidecar says it
+                    // already has out of order, so they're not blindly
+                    // retransmitted (see `protocol::ResumeOffer`).
+                    match crate::protocol::ResumeOffer::read_from(&mut stream) {
+                        Ok(offer) => already_have = offer.already_have,
+                        Err(e) => {
+                            tracing::warn!("Failed to read resume offer: {}, assuming none", e)
+                        }
                     }
-                    chunk
+                    Some(u32::from_be_bytes(window_buf))
                 }
                 Err(e) => {
-                    tracing::warn!("Failed to read resume chunk index: {}, starting from 0", e);
-                    0
+                    tracing::warn!("Failed to read window size: {}, falling back to lockstep", e);
+                    None
                 }
             }
+        } else {
+            None
         };
 
-        // Create MultiFileReader
-        let mut reader = match MultiFileReader::new(self.manifest_bytes.clone(), self.files_to_send.clone()) {
+        let start_chunk = if let Some(chunk) = resume_override {
+            tracing::info!("Resuming from chunk {} (redial after drop)", chunk);
+            chunk
+        } else if self.resume_from_chunk > 0 {
+            tracing::info!(
+                "Resuming from chunk {} (requested by sender)",
+                self.resume_from_chunk
+            );
+            self.resume_from_chunk
+        } else {
+            if receiver_offer > 0 {
+                tracing::info!("Receiver requested resume from chunk {}", receiver_offer);
+            } else {
+                tracing::info!("Starting fresh transfer from chunk 0");
+            }
+            receiver_offer
+        };
+
+        // Large single-file transfers read through an mmap-backed
+        // ChunkSource instead of the buffered one - see its doc comment
+        // for why that's as close to zero-copy as per-chunk encryption
+        // allows.
+        let mut reader = match self.chunk_source() {
             Ok(r) => r,
             Err(e) => return TransferResult::IoError(e.to_string()),
         };
@@ -366,92 +1160,131 @@ impl TcpFileSender {
             self.bytes_sent.store(start_offset, Ordering::SeqCst);
         }
 
-        // Send file in chunks
-        let mut chunk_buffer = vec![0u8; self.chunk_size];
-        let mut chunk_index = start_chunk;
-
-        loop {
-            if self.cancelled.load(Ordering::SeqCst) {
-                tracing::info!("Transfer cancelled");
-                return TransferResult::Cancelled;
+        if let Some(window) = window_size {
+            if let Err(result) = self.send_windowed(
+                &mut stream,
+                &mut channel,
+                chunk_cipher.as_ref(),
+                &mut reader,
+                start_chunk,
+                window,
+                &already_have,
+            ) {
+                return result;
             }
+        } else {
+            // Send file in chunks
+            let mut chunk_buffer = vec![0u8; self.chunk_size];
+            let mut chunk_index = start_chunk;
 
-            let bytes_read = match reader.read(&mut chunk_buffer) {
-                Ok(0) => break, // EOF
-                Ok(n) => n,
-                Err(e) => return TransferResult::IoError(e.to_string()),
-            };
-
-            let chunk_data = &chunk_buffer[..bytes_read];
-            let chunk_checksum = calculate_chunk_checksum(chunk_data);
-
-            // Send chunk with retries
-            let mut retries = 0;
             loop {
-                if retries == 0 {
-                    tracing::debug!("Sending chunk {} ({} bytes)", chunk_index, bytes_read);
-                } else {
-                    tracing::warn!(
-                        "Retrying chunk {} (attempt {}/{})",
-                        chunk_index,
-                        retries + 1,
-                        MAX_RETRIES
-                    );
+                if self.cancelled.load(Ordering::SeqCst) {
+                    tracing::info!("Transfer cancelled");
+                    return TransferResult::Cancelled;
                 }
 
-                if let Err(e) =
-                    self.send_chunk(&mut stream, chunk_index, chunk_data, &chunk_checksum)
-                {
-                    tracing::error!("Failed to send chunk {}: {}", chunk_index, e);
-                    retries += 1;
-                    if retries >= MAX_RETRIES {
-                        tracing::error!("Max retries exceeded for chunk {}", chunk_index);
-                        return TransferResult::IoError(format!("Max retries exceeded: {}", e));
-                    }
-                    continue;
-                }
+                let bytes_read = match reader.read(&mut chunk_buffer) {
+                    Ok(0) => break, // EOF
+                    Ok(n) => n,
+                    Err(e) => return TransferResult::IoError(e.to_string()),
+                };
 
-                // Wait for ACK
-                tracing::trace!("Waiting for ACK for chunk {}...", chunk_index);
-                match self.wait_for_ack(&mut stream, chunk_index) {
-                    Ok(true) => {
-                        tracing::trace!("Received ACK for chunk {}", chunk_index);
-                        break; // ACK received
-                    }
-                    Ok(false) => {
+                let chunk_data = &chunk_buffer[..bytes_read];
+
+                // Send chunk with retries
+                let mut retries = 0;
+                loop {
+                    if retries == 0 {
+                        tracing::debug!("Sending chunk {} ({} bytes)", chunk_index, bytes_read);
+                    } else {
                         tracing::warn!(
-                            "Chunk {} checksum verification failed on receiver, retransmitting",
-                            chunk_index
+                            "Retrying chunk {} (attempt {}/{})",
+                            chunk_index,
+                            retries + 1,
+                            MAX_RETRIES
                         );
+                    }
+
+                    if let Err(e) = self.send_chunk(
+                        &mut stream,
+                        &mut channel,
+                        chunk_cipher.as_ref(),
+                        chunk_index,
+                        chunk_data,
+                    ) {
+                        tracing::error!("Failed to send chunk {}: {}", chunk_index, e);
                         retries += 1;
                         if retries >= MAX_RETRIES {
+                            tracing::error!("Max retries exceeded for chunk {}", chunk_index);
+                            return TransferResult::IoError(format!("Max retries exceeded: {}", e));
+                        }
+                        continue;
+                    }
+
+                    // Wait for ACK
+                    tracing::trace!("Waiting for ACK for chunk {}...", chunk_index);
+                    match self.wait_for_ack(&mut stream, chunk_index) {
+                        Ok(AckStatus::Ok) => {
+                            tracing::trace!("Received ACK for chunk {}", chunk_index);
+                            break; // ACK received
+                        }
+                        Ok(AckStatus::AuthenticationFailed) => {
                             tracing::error!(
-                                "Max retries exceeded due to checksum mismatch for chunk {}",
+                                "Receiver failed to authenticate chunk {} - aborting transfer",
                                 chunk_index
                             );
-                            return TransferResult::ChecksumMismatch;
+                            return TransferResult::DecryptionFailed;
                         }
-                    }
-                    Err(e) => {
-                        tracing::error!("Timeout waiting for ACK for chunk {}: {}", chunk_index, e);
-                        retries += 1;
-                        if retries >= MAX_RETRIES {
-                            tracing::error!(
-                                "Max retries exceeded due to ACK timeout for chunk {}",
+                        Ok(AckStatus::Rejected) => {
+                            tracing::warn!(
+                                "Chunk {} rejected by receiver, retransmitting",
+                                chunk_index
+                            );
+                            retries += 1;
+                            if retries >= MAX_RETRIES {
+                                tracing::error!(
+                                    "Max retries exceeded due to rejection for chunk {}",
+                                    chunk_index
+                                );
+                                return TransferResult::ChecksumMismatch;
+                            }
+                        }
+                        Err(e)
+                            if e.kind() == std::io::ErrorKind::Interrupted
+                                && self.cancelled.load(Ordering::SeqCst) =>
+                        {
+                            tracing::info!(
+                                "Transfer cancelled while waiting for ACK for chunk {}",
                                 chunk_index
                             );
-                            return TransferResult::Timeout;
+                            return TransferResult::Cancelled;
+                        }
+                        Err(e) => {
+                            tracing::error!("Timeout waiting for ACK for chunk {}: {}", chunk_index, e);
+                            retries += 1;
+                            if retries >= MAX_RETRIES {
+                                tracing::error!(
+                                    "Max retries exceeded due to ACK timeout for chunk {}",
+                                    chunk_index
+                                );
+                                return TransferResult::Timeout;
+                            }
                         }
                     }
                 }
-            }
-
-            self.bytes_sent
-                .fetch_add(bytes_read as u64, Ordering::SeqCst);
-            chunk_index += 1;
 
-            if chunk_index % 100 == 0 {
-                tracing::debug!("Sent {} chunks, {:.1}%", chunk_index, self.progress());
+                // Only counted once the receiver's ACK for this chunk has come
+                // back, so `progress()`/`bytes_sent()` already track bytes the
+                // other end confirmed receiving - a stronger guarantee than
+                // "handed to the kernel" would be, and the only one this
+                // protocol's per-chunk ACK round-trip can actually observe.
+                self.bytes_sent
+                    .fetch_add(bytes_read as u64, Ordering::SeqCst);
+                chunk_index += 1;
+
+                if chunk_index % 100 == 0 {
+                    tracing::debug!("Sent {} chunks, {:.1}%", chunk_index, self.progress());
+                }
             }
         }
 
@@ -486,6 +1319,7 @@ impl TcpFileSender {
             self.chunk_size as u32,
             &self.file_checksum,
             self.transfer_type,
+            self.chunk_encryption,
         );
 
         request.write_to(stream)?;
@@ -493,44 +1327,202 @@ impl TcpFileSender {
         Ok(())
     }
 
-    /// Send a chunk
+    /// Seal a chunk with the handshake's `SecureChannel` and send it. When
+    /// `chunk_cipher` is set (see `set_chunk_encryption`), `data` is run
+    /// through it first, so the channel ends up sealing already-encrypted
+    /// bytes - see `security::chunk_cipher`'s module doc for why that's a
+    /// layer on top, not a replacement. The AEAD tag replaces the old
+    /// per-chunk MD5 checksum as the integrity check either way.
     fn send_chunk(
         &self,
         stream: &mut TcpStream,
+        channel: &mut SecureChannel,
+        chunk_cipher: Option<&ChunkCipher>,
         index: u64,
         data: &[u8],
-        checksum: &str,
     ) -> std::io::Result<()> {
-        // [chunk_index: u64 BE][chunk_len: u32 BE][data][checksum: 16 bytes md5 binary]
+        let sleep = self.rate_limiter.acquire(data.len());
+        if sleep > Duration::ZERO {
+            thread::sleep(sleep);
+        }
+
+        let encrypted_data;
+        let data = if let Some(cipher) = chunk_cipher {
+            encrypted_data = cipher.encrypt(index, data);
+            &encrypted_data
+        } else {
+            data
+        };
+
+        let packet = channel.seal(data).map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::Other, "failed to encrypt chunk")
+        })?;
+
+        // [chunk_index: u64 BE][packet_len: u32 BE][sealed packet]
         stream.write_all(&index.to_be_bytes())?;
-        stream.write_all(&(data.len() as u32).to_be_bytes())?;
-        stream.write_all(data)?;
+        stream.write_all(&(packet.len() as u32).to_be_bytes())?;
+        stream.write_all(&packet)?;
+        stream.flush()?;
+        Ok(())
+    }
 
-        // Convert hex checksum to bytes (full 16 bytes/128 bits)
-        let checksum_bytes: Vec<u8> = (0..checksum.len())
-            .step_by(2)
-            .filter_map(|i| u8::from_str_radix(&checksum[i..i + 2], 16).ok())
-            .collect();
+    /// Pipelined send for a single-file transfer: instead of waiting for
+    /// a per-chunk ACK, keeps up to `window` chunks unacknowledged at
+    /// once, reading back a [`crate::protocol::SelectiveAck`] every so
+    /// often (see `receiver::FileReceiverServer::receive_windowed`) and
+    /// retransmitting only the indices it reports missing. `reader` must
+    /// support `Seek` since retransmits and the initial pass both address
+    /// chunks by index rather than assuming sequential delivery.
+    /// `already_have` skips indices the receiver's `ResumeOffer` already
+    /// reported as landed out of order before a previous connection
+    /// dropped.
+    ///
+    /// A `SelectiveAck` missing from the receiver's side (dropped on the
+    /// wire rather than arriving with a gap list) would otherwise stall
+    /// the whole transfer until `ACK_TIMEOUT` aborts it outright, even
+    /// though every chunk in the window was delivered fine. Instead, a
+    /// read timeout here just retransmits the oldest unacknowledged
+    /// chunk and keeps waiting, the same `MAX_RETRIES`-bounded way
+    /// `wait_for_ack` callers already retry a lockstep chunk.
+    fn send_windowed(
+        &self,
+        stream: &mut TcpStream,
+        channel: &mut SecureChannel,
+        chunk_cipher: Option<&ChunkCipher>,
+        reader: &mut ChunkSource,
+        start_chunk: u64,
+        window: u32,
+        already_have: &[u64],
+    ) -> Result<(), TransferResult> {
+        use crate::protocol::SelectiveAck;
+        use std::collections::HashSet;
 
-        // Ensure we send exactly 16 bytes
-        if checksum_bytes.len() != 16 {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Invalid checksum length",
-            ));
+        if self.file_size == 0 {
+            return Ok(());
         }
 
-        stream.write_all(&checksum_bytes)?;
+        let already_have: HashSet<u64> = already_have.iter().copied().collect();
 
-        stream.flush()?;
+        let chunk_size = self.chunk_size as u64;
+        let total_chunks = (self.file_size + chunk_size - 1) / chunk_size;
+
+        let read_chunk = |reader: &mut ChunkSource, index: u64| -> std::io::Result<Vec<u8>> {
+            let offset = index * chunk_size;
+            let len = std::cmp::min(chunk_size, self.file_size - offset) as usize;
+            reader.seek(std::io::SeekFrom::Start(offset))?;
+            let mut buf = vec![0u8; len];
+            reader.read_exact(&mut buf)?;
+            Ok(buf)
+        };
+
+        let mut highest_contiguous: Option<u64> = if start_chunk == 0 {
+            None
+        } else {
+            Some(start_chunk - 1)
+        };
+        let mut next_to_send = start_chunk;
+        // `window` is the hard ceiling the receiver advertised at the
+        // handshake (safely under the `SecureChannel` replay window); the
+        // pacing::ChunkCubic-driven `cwnd` each SelectiveAck reports takes
+        // over from there, so the sender ramps up on a fast link and backs
+        // off on a lossy one instead of running at a fixed size throughout.
+        let mut current_window = window as u64;
+        // Counts consecutive stalls (reads that time out with no
+        // `SelectiveAck` at all) retried for the oldest outstanding
+        // chunk, keyed by its index. Cleared whenever the window
+        // advances past that index, same as a fresh chunk's budget.
+        let mut stall_retries: std::collections::BTreeMap<u64, u32> = std::collections::BTreeMap::new();
+
+        while highest_contiguous.map(|h| h + 1) != Some(total_chunks) {
+            if self.cancelled.load(Ordering::SeqCst) {
+                tracing::info!("Transfer cancelled");
+                return Err(TransferResult::Cancelled);
+            }
+
+            let window_edge =
+                (highest_contiguous.map(|h| h + 1).unwrap_or(0) + current_window).min(total_chunks);
+            while next_to_send < window_edge {
+                if already_have.contains(&next_to_send) {
+                    next_to_send += 1;
+                    continue;
+                }
+                let data = read_chunk(reader, next_to_send)
+                    .map_err(|e| TransferResult::IoError(e.to_string()))?;
+                self.send_chunk(stream, channel, chunk_cipher, next_to_send, &data)
+                    .map_err(|e| TransferResult::IoError(e.to_string()))?;
+                next_to_send += 1;
+            }
+
+            let ack = match SelectiveAck::read_from(stream) {
+                Ok(ack) => ack,
+                Err(e)
+                    if e.kind() == std::io::ErrorKind::WouldBlock
+                        || e.kind() == std::io::ErrorKind::TimedOut =>
+                {
+                    let oldest = highest_contiguous.map(|h| h + 1).unwrap_or(0);
+                    let retries = stall_retries.entry(oldest).or_insert(0);
+                    if *retries >= MAX_RETRIES {
+                        return Err(TransferResult::Timeout);
+                    }
+                    *retries += 1;
+                    tracing::warn!(
+                        "No ACK within {:?}, retransmitting stalled chunk {} (attempt {}/{})",
+                        ACK_TIMEOUT,
+                        oldest,
+                        retries,
+                        MAX_RETRIES
+                    );
+                    let data = read_chunk(reader, oldest)
+                        .map_err(|e| TransferResult::IoError(e.to_string()))?;
+                    self.send_chunk(stream, channel, chunk_cipher, oldest, &data)
+                        .map_err(|e| TransferResult::IoError(e.to_string()))?;
+                    continue;
+                }
+                Err(e) => return Err(TransferResult::IoError(e.to_string())),
+            };
+
+            if ack.highest_contiguous > highest_contiguous {
+                stall_retries.retain(|&index, _| Some(index) > ack.highest_contiguous);
+                let newly_covered_up_to = ack.highest_contiguous.map(|h| h + 1).unwrap_or(0);
+                let previously_covered_up_to = highest_contiguous.map(|h| h + 1).unwrap_or(0);
+                let newly_acked_chunks = newly_covered_up_to - previously_covered_up_to;
+                let last_full_chunks = newly_acked_chunks.saturating_sub(1);
+                let covers_final_chunk = newly_covered_up_to == total_chunks;
+                let newly_acked_bytes = if covers_final_chunk {
+                    last_full_chunks * chunk_size + (self.file_size - (total_chunks - 1) * chunk_size)
+                } else {
+                    newly_acked_chunks * chunk_size
+                };
+                self.bytes_sent
+                    .fetch_add(newly_acked_bytes, Ordering::SeqCst);
+                highest_contiguous = ack.highest_contiguous;
+                tracing::debug!(
+                    "Window advanced to {:?}, {:.1}%",
+                    highest_contiguous,
+                    self.progress()
+                );
+            }
+
+            current_window = (ack.cwnd as u64).min(window as u64).max(1);
+
+            for &missing_index in &ack.missing {
+                tracing::debug!("Retransmitting gap chunk {}", missing_index);
+                let data = read_chunk(reader, missing_index)
+                    .map_err(|e| TransferResult::IoError(e.to_string()))?;
+                self.send_chunk(stream, channel, chunk_cipher, missing_index, &data)
+                    .map_err(|e| TransferResult::IoError(e.to_string()))?;
+            }
+        }
+
+        self.bytes_sent.store(self.file_size, Ordering::SeqCst);
         Ok(())
     }
 
     /// Wait for chunk ACK
-    fn wait_for_ack(&self, stream: &mut TcpStream, expected_index: u64) -> std::io::Result<bool> {
-        // [acked_chunk_index: u64 BE][status: u8] where 0=ok, 1=checksum_fail
+    fn wait_for_ack(&self, stream: &mut TcpStream, expected_index: u64) -> std::io::Result<AckStatus> {
+        // [acked_chunk_index: u64 BE][status: u8] where 0=ok, 1=rejected, 2=auth_failed
         let mut ack_buf = [0u8; 9];
-        stream.read_exact(&mut ack_buf)?;
+        self.read_exact_cancellable(stream, &mut ack_buf)?;
 
         let acked_index = u64::from_be_bytes(ack_buf[0..8].try_into().unwrap());
         let status = ack_buf[8];
@@ -541,11 +1533,53 @@ impl TcpFileSender {
                 expected_index,
                 acked_index
             );
-            return Ok(false);
+            return Ok(AckStatus::Rejected);
         }
 
-        Ok(status == 0)
+        Ok(match status {
+            0 => AckStatus::Ok,
+            2 => AckStatus::AuthenticationFailed,
+            _ => AckStatus::Rejected,
+        })
     }
+
+    /// Like `Read::read_exact`, but on Android checks for cancellation right
+    /// after an `EINTR` instead of retrying the syscall - see `crate::cancel`'s
+    /// module doc for why `TcpStream::read` itself can't do this. A thread
+    /// sitting here is woken by `cancel()` via `self.cancel_token` as soon as
+    /// it's registered as blocked on `stream`'s fd, rather than only once
+    /// `ACK_TIMEOUT` elapses. Falls straight through to the ordinary
+    /// `read_exact` everywhere else, where `cancel()` still works, just not
+    /// as promptly.
+    #[cfg(target_os = "android")]
+    fn read_exact_cancellable(
+        &self,
+        stream: &mut TcpStream,
+        buf: &mut [u8],
+    ) -> std::io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+        crate::cancel::interruptible_recv_exact(stream.as_raw_fd(), self.cancel_token, buf)
+    }
+
+    #[cfg(not(target_os = "android"))]
+    fn read_exact_cancellable(
+        &self,
+        stream: &mut TcpStream,
+        buf: &mut [u8],
+    ) -> std::io::Result<()> {
+        stream.read_exact(buf)
+    }
+}
+
+/// Outcome of a chunk ACK, as reported by the receiver.
+enum AckStatus {
+    Ok,
+    /// The receiver couldn't verify the chunk against the acked index -
+    /// retry with a fresh send.
+    Rejected,
+    /// The chunk's AEAD tag failed to verify - retrying won't help since
+    /// the channel's nonce counter has already moved on.
+    AuthenticationFailed,
 }
 
 #[cfg(test)]
@@ -564,4 +1598,46 @@ mod tests {
         assert!(sender.file_size() > 0);
         assert!(!sender.checksum().is_empty());
     }
+
+    #[test]
+    fn new_sender_starts_in_connecting_state() {
+        let mut temp = NamedTempFile::new().unwrap();
+        temp.write_all(b"Test content for sender").unwrap();
+        temp.flush().unwrap();
+
+        let sender = TcpFileSender::new(temp.path().to_str().unwrap()).unwrap();
+        assert_eq!(sender.state(), TransferState::Connecting);
+    }
+
+    #[test]
+    fn only_connection_and_timeout_failures_are_retryable() {
+        assert!(TcpFileSender::is_retryable(&TransferResult::ConnectionFailed(
+            "refused".into()
+        )));
+        assert!(TcpFileSender::is_retryable(&TransferResult::IoError(
+            "reset".into()
+        )));
+        assert!(TcpFileSender::is_retryable(&TransferResult::Timeout));
+
+        assert!(!TcpFileSender::is_retryable(&TransferResult::Rejected));
+        assert!(!TcpFileSender::is_retryable(
+            &TransferResult::AuthenticationFailed
+        ));
+        assert!(!TcpFileSender::is_retryable(
+            &TransferResult::DecryptionFailed
+        ));
+        assert!(!TcpFileSender::is_retryable(&TransferResult::Cancelled));
+        assert!(!TcpFileSender::is_retryable(&TransferResult::Success));
+    }
+
+    #[test]
+    fn resolve_range_is_relative_to_the_sender_file_size() {
+        let mut temp = NamedTempFile::new().unwrap();
+        temp.write_all(&vec![0u8; 100]).unwrap();
+        temp.flush().unwrap();
+
+        let sender = TcpFileSender::new(temp.path().to_str().unwrap()).unwrap();
+        assert_eq!(sender.resolve_range(ByteRange::Suffix(10)).unwrap(), (90, 10));
+        assert!(sender.resolve_range(ByteRange::From(1000)).is_err());
+    }
 }
@@ -8,9 +8,53 @@ use std::path::Path;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 
+use thiserror::Error;
+
+use crate::checksum::{self, HashMethod};
+use crate::dedup::ContentDefinedChunker;
+use crate::merkle::MerkleAccumulator;
+use crate::protocol::AeadParams;
+use crate::security::chunk_aead::{self, ChunkAead};
+use crate::security::crypto::CryptoError;
+use crate::security::validator::SecurePinValidator;
+
 /// Default chunk size: 1MB
 pub const DEFAULT_CHUNK_SIZE: usize = 1024 * 1024;
 
+/// Whether `data` (an unsealed chunk straight from `FileSender::read_chunk`,
+/// before any AEAD sealing) is all zero bytes. A caller driving the sender
+/// uses this to coalesce consecutive zero chunks into a single
+/// `protocol::ZeroRun` instead of sending each one's data - see
+/// `FileReceiver::write_zero_run`.
+pub fn is_all_zero(data: &[u8]) -> bool {
+    data.iter().all(|&b| b == 0)
+}
+
+/// Errors from writing a received chunk - split out from a plain
+/// `io::Error` so a caller can tell a corrupt chunk (recoverable: re-ask
+/// the sender for just that index via `protocol::NackChunk`) apart from an
+/// actual disk failure (not recoverable by retransmission).
+#[derive(Error, Debug)]
+pub enum ChunkError {
+    #[error("chunk {index} failed its checksum")]
+    ChecksumMismatch { index: u64 },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// How `FileSender` slices the file into chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkingMethod {
+    /// Fixed-size slices of `FileMetadata::chunk_size` bytes.
+    FixedSize,
+    /// Variable-sized, content-defined slices - see
+    /// `dedup::ContentDefinedChunker`. `chunk_size` holds the configured
+    /// average chunk size; `total_chunks` is an estimate (`size /
+    /// chunk_size`), since the real count isn't known until the file has
+    /// been fully scanned.
+    ContentDefined { min_size: usize, max_size: usize },
+}
+
 /// Transfer state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TransferState {
@@ -22,6 +66,24 @@ pub enum TransferState {
     Cancelled,
 }
 
+impl TransferState {
+    /// How a `protocol::PacketType` control packet changes this state when
+    /// it arrives interleaved with data on a `protocol::FrameReader`
+    /// stream, instead of only being noticed at a connection boundary.
+    /// `Data`/`Nack` leave the state untouched - a nack triggers a
+    /// retransmit of one chunk, not a transfer-wide state change - and
+    /// `Resume` only takes effect out of `Paused`.
+    pub fn apply_packet(self, packet_type: crate::protocol::PacketType) -> Self {
+        use crate::protocol::PacketType;
+        match packet_type {
+            PacketType::Pause => TransferState::Paused,
+            PacketType::Cancel => TransferState::Cancelled,
+            PacketType::Resume if self == TransferState::Paused => TransferState::Transferring,
+            PacketType::Data | PacketType::Nack | PacketType::Resume => self,
+        }
+    }
+}
+
 /// File transfer metadata
 #[derive(Debug, Clone)]
 pub struct FileMetadata {
@@ -29,6 +91,7 @@ pub struct FileMetadata {
     pub size: u64,
     pub chunk_size: usize,
     pub total_chunks: u64,
+    pub chunking: ChunkingMethod,
 }
 
 impl FileMetadata {
@@ -47,6 +110,30 @@ impl FileMetadata {
             size,
             chunk_size,
             total_chunks,
+            chunking: ChunkingMethod::FixedSize,
+        })
+    }
+
+    fn content_defined(
+        path: &Path,
+        min_size: usize,
+        avg_size: usize,
+        max_size: usize,
+    ) -> std::io::Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "unknown".to_string());
+        let size = metadata.len();
+        let total_chunks = size.div_ceil(avg_size as u64).max(1);
+
+        Ok(FileMetadata {
+            name,
+            size,
+            chunk_size: avg_size,
+            total_chunks,
+            chunking: ChunkingMethod::ContentDefined { min_size, max_size },
         })
     }
 }
@@ -71,13 +158,23 @@ impl TransferProgress {
     }
 }
 
+/// Where `FileSender::read_chunk` pulls its next chunk from, depending on
+/// `FileMetadata::chunking`.
+enum Chunker {
+    FixedSize { file: File, current_chunk: u64 },
+    ContentDefined(ContentDefinedChunker<File>),
+}
+
 /// File sender - reads file in chunks
 pub struct FileSender {
-    file: File,
+    chunker: Chunker,
     metadata: FileMetadata,
-    current_chunk: u64,
+    chunks_sent: u64,
     bytes_sent: Arc<AtomicU64>,
     cancelled: Arc<AtomicBool>,
+    aead: Option<ChunkAead>,
+    hash_method: HashMethod,
+    merkle: MerkleAccumulator,
 }
 
 impl FileSender {
@@ -86,18 +183,86 @@ impl FileSender {
         let metadata = FileMetadata::from_path(path)?;
 
         Ok(FileSender {
-            file,
+            chunker: Chunker::FixedSize {
+                file,
+                current_chunk: 0,
+            },
+            metadata,
+            chunks_sent: 0,
+            bytes_sent: Arc::new(AtomicU64::new(0)),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            aead: None,
+            hash_method: HashMethod::Md5,
+            merkle: MerkleAccumulator::new(HashMethod::Md5),
+        })
+    }
+
+    /// Build a sender that slices the file into content-defined chunks
+    /// (`dedup::ContentDefinedChunker`) instead of fixed-size blocks, so a
+    /// small edit only reshuffles the chunk(s) around it.
+    pub fn new_content_defined(
+        path: &Path,
+        min_size: usize,
+        avg_size: usize,
+        max_size: usize,
+    ) -> std::io::Result<Self> {
+        let file = File::open(path)?;
+        let metadata = FileMetadata::content_defined(path, min_size, avg_size, max_size)?;
+
+        Ok(FileSender {
+            chunker: Chunker::ContentDefined(ContentDefinedChunker::with_sizes(
+                file, min_size, avg_size, max_size,
+            )),
             metadata,
-            current_chunk: 0,
+            chunks_sent: 0,
             bytes_sent: Arc::new(AtomicU64::new(0)),
             cancelled: Arc::new(AtomicBool::new(false)),
+            aead: None,
+            hash_method: HashMethod::Md5,
+            merkle: MerkleAccumulator::new(HashMethod::Md5),
         })
     }
 
+    /// Seal every future `read_chunk` payload with
+    /// `security::chunk_aead::ChunkAead`, keyed from `passphrase`. Returns
+    /// the random `AeadParams` the caller must get to the receiver (e.g.
+    /// via `protocol::HandshakeRequest::with_aead_params`) so it can derive
+    /// the same key and nonce base. Rejects `passphrase` via
+    /// `SecurePinValidator::for_passphrase` before it ever reaches key
+    /// derivation - this is the only thing standing between the chunk
+    /// plaintext and an observer, so a weak passphrase here is rejected
+    /// rather than silently accepted.
+    pub fn with_passphrase(mut self, passphrase: &str) -> Result<(Self, AeadParams), CryptoError> {
+        SecurePinValidator::for_passphrase().validate(passphrase)?;
+        let salt = chunk_aead::random_salt();
+        let nonce_base = chunk_aead::random_nonce_base();
+        let key = chunk_aead::derive_key(passphrase, &salt);
+        self.aead = Some(ChunkAead::new(key, nonce_base));
+        Ok((self, AeadParams { salt, nonce_base }))
+    }
+
+    /// Negotiate a `HashMethod` other than the `Md5` default for this
+    /// sender's checksums and Merkle tree (see `merkle_root`).
+    pub fn with_hash_method(mut self, hash_method: HashMethod) -> Self {
+        self.hash_method = hash_method;
+        self.merkle = MerkleAccumulator::new(hash_method);
+        self
+    }
+
     pub fn metadata(&self) -> &FileMetadata {
         &self.metadata
     }
 
+    /// The `merkle::MerkleAccumulator::root` over every plaintext chunk
+    /// `read_chunk` has produced so far, hashed with this sender's
+    /// `hash_method` - send it to the receiver up front via
+    /// `protocol::HandshakeRequest::with_merkle_root` once the whole file
+    /// has been read (or read it once ahead of time to know it before
+    /// sending the handshake).
+    pub fn merkle_root(&self) -> Option<Vec<u8>> {
+        self.merkle.root()
+    }
+
     pub fn cancel(&self) {
         self.cancelled.store(true, Ordering::SeqCst);
     }
@@ -106,33 +271,53 @@ impl FileSender {
         self.cancelled.load(Ordering::SeqCst)
     }
 
-    /// Read the next chunk
-    pub fn read_chunk(&mut self) -> std::io::Result<Option<(u64, Vec<u8>)>> {
+    /// Read the next chunk as `(offset, len, data)`. `offset` is always the
+    /// chunk's position in the plaintext file; when this sender was built
+    /// `with_passphrase`, `len`/`data` describe the sealed
+    /// ciphertext-plus-tag actually handed back, which runs 16 bytes longer
+    /// than the plaintext chunk it came from.
+    pub fn read_chunk(&mut self) -> std::io::Result<Option<(u64, usize, Vec<u8>)>> {
         if self.is_cancelled() {
             return Ok(None);
         }
 
-        if self.current_chunk >= self.metadata.total_chunks {
-            return Ok(None);
-        }
-
-        let offset = self.current_chunk * self.metadata.chunk_size as u64;
-        self.file.seek(SeekFrom::Start(offset))?;
-
-        let mut buffer = vec![0u8; self.metadata.chunk_size];
-        let bytes_read = self.file.read(&mut buffer)?;
+        let next = match &mut self.chunker {
+            Chunker::FixedSize { file, current_chunk } => {
+                if *current_chunk >= self.metadata.total_chunks {
+                    return Ok(None);
+                }
+
+                let offset = *current_chunk * self.metadata.chunk_size as u64;
+                file.seek(SeekFrom::Start(offset))?;
+
+                let mut buffer = vec![0u8; self.metadata.chunk_size];
+                let bytes_read = file.read(&mut buffer)?;
+                if bytes_read == 0 {
+                    return Ok(None);
+                }
+                buffer.truncate(bytes_read);
+                *current_chunk += 1;
+                (offset, buffer)
+            }
+            Chunker::ContentDefined(chunker) => match chunker.read_chunk()? {
+                Some(next) => next,
+                None => return Ok(None),
+            },
+        };
 
-        if bytes_read == 0 {
-            return Ok(None);
-        }
+        let (offset, buffer) = next;
+        let chunk_index = self.chunks_sent;
+        self.chunks_sent += 1;
+        self.bytes_sent.fetch_add(buffer.len() as u64, Ordering::SeqCst);
+        self.merkle
+            .push_leaf(checksum::hash_bytes(self.hash_method, &buffer));
 
-        buffer.truncate(bytes_read);
-        let chunk_index = self.current_chunk;
-        self.current_chunk += 1;
-        self.bytes_sent
-            .fetch_add(bytes_read as u64, Ordering::SeqCst);
+        let buffer = match &self.aead {
+            Some(aead) => aead.seal(chunk_index, &buffer),
+            None => buffer,
+        };
 
-        Ok(Some((chunk_index, buffer)))
+        Ok(Some((offset, buffer.len(), buffer)))
     }
 
     pub fn get_progress(&self) -> TransferProgress {
@@ -140,7 +325,7 @@ impl FileSender {
         TransferProgress {
             bytes_transferred: bytes,
             total_bytes: self.metadata.size,
-            chunks_completed: self.current_chunk,
+            chunks_completed: self.chunks_sent,
             total_chunks: self.metadata.total_chunks,
             speed_bytes_per_sec: 0, // Calculated externally
             state: if self.is_cancelled() {
@@ -160,6 +345,17 @@ pub struct FileReceiver {
     metadata: FileMetadata,
     bytes_received: Arc<AtomicU64>,
     chunks_received: u64,
+    aead: Option<ChunkAead>,
+    hash_method: HashMethod,
+    /// Folds each plaintext chunk's hash in as `write_chunk` is called, in
+    /// call order - see `merkle_root`'s doc comment for why this only
+    /// proves anything when chunks are written in the same order the
+    /// sender read them.
+    merkle: MerkleAccumulator,
+    /// Whether `write_zero_run` may skip over a run of zero chunks
+    /// (relying on `finalize`'s `set_len` to leave a real filesystem hole)
+    /// instead of writing the zeros out explicitly - see `with_sparse`.
+    sparse: bool,
 }
 
 impl FileReceiver {
@@ -171,17 +367,128 @@ impl FileReceiver {
             metadata,
             bytes_received: Arc::new(AtomicU64::new(0)),
             chunks_received: 0,
+            aead: None,
+            hash_method: HashMethod::Md5,
+            merkle: MerkleAccumulator::new(HashMethod::Md5),
+            sparse: true,
         })
     }
 
-    /// Write a chunk at the specified index
-    pub fn write_chunk(&mut self, chunk_index: u64, data: &[u8]) -> std::io::Result<()> {
-        let offset = chunk_index * self.metadata.chunk_size as u64;
+    /// Whether `write_zero_run` should actually skip writing (`true`, the
+    /// default) or fall back to writing the zeros out explicitly
+    /// (`false`) - not every filesystem supports holes, and a caller
+    /// writing to e.g. a FAT-formatted removable drive should disable
+    /// this rather than end up with a file full of unwritten garbage.
+    pub fn with_sparse(mut self, sparse: bool) -> Self {
+        self.sparse = sparse;
+        self
+    }
+
+    /// Decrypt every future `write_chunk` payload with
+    /// `security::chunk_aead::ChunkAead`, keyed from `passphrase` and the
+    /// `aead_params` the sender sent (see
+    /// `protocol::HandshakeRequest::aead_params`). Rejects `passphrase` via
+    /// `SecurePinValidator::for_passphrase`, matching `FileSender::with_passphrase`
+    /// - a receiver accepting a weak passphrase would otherwise happily
+    /// decrypt chunks sealed under one.
+    pub fn with_passphrase(
+        mut self,
+        passphrase: &str,
+        aead_params: AeadParams,
+    ) -> Result<Self, CryptoError> {
+        SecurePinValidator::for_passphrase().validate(passphrase)?;
+        let key = chunk_aead::derive_key(passphrase, &aead_params.salt);
+        self.aead = Some(ChunkAead::new(key, aead_params.nonce_base));
+        Ok(self)
+    }
+
+    /// Negotiate the `HashMethod` the sender used for this transfer - see
+    /// `protocol::HandshakeRequest::hash_method`. Must match what the
+    /// sender folded its `merkle_root` with, or `merkle_root` here will
+    /// never agree with the sender's even once every chunk lands.
+    pub fn with_hash_method(mut self, hash_method: HashMethod) -> Self {
+        self.hash_method = hash_method;
+        self.merkle = MerkleAccumulator::new(hash_method);
+        self
+    }
+
+    /// Write a chunk at the specified index and byte offset, rejecting it
+    /// before seeking/writing if it fails integrity - its per-chunk
+    /// checksum (see `protocol::ChunkFrame`), or, when this receiver was
+    /// built `with_passphrase`, its AEAD tag - rather than persisting
+    /// corrupt bytes. `checksum` is ignored in the AEAD case: the tag
+    /// already authenticates the chunk, and a failed tag check surfaces as
+    /// the same `ChunkError::ChecksumMismatch` so a caller's retransmit
+    /// path doesn't need a separate branch for it.
+    ///
+    /// `offset` is taken from the wire (`protocol::ChunkFrame::offset`)
+    /// rather than recomputed as `chunk_index * chunk_size`: that only
+    /// holds for `ChunkingMethod::FixedSize` senders, and would silently
+    /// misplace every chunk from a `ChunkingMethod::ContentDefined` one,
+    /// whose chunks aren't uniform size. `chunk_index` is still what seeds
+    /// the AEAD nonce, so it's kept as its own parameter rather than
+    /// derived back out of `offset`.
+    pub fn write_chunk(
+        &mut self,
+        chunk_index: u64,
+        offset: u64,
+        data: &[u8],
+        checksum: [u8; 16],
+    ) -> Result<(), ChunkError> {
+        let plaintext = match &self.aead {
+            Some(aead) => aead
+                .open(chunk_index, data)
+                .ok_or(ChunkError::ChecksumMismatch { index: chunk_index })?,
+            None => {
+                if checksum::calculate_chunk_checksum_raw(data) != checksum {
+                    return Err(ChunkError::ChecksumMismatch { index: chunk_index });
+                }
+                data.to_vec()
+            }
+        };
+
         self.file.seek(SeekFrom::Start(offset))?;
-        self.file.write_all(data)?;
+        self.file.write_all(&plaintext)?;
         self.bytes_received
-            .fetch_add(data.len() as u64, Ordering::SeqCst);
+            .fetch_add(plaintext.len() as u64, Ordering::SeqCst);
         self.chunks_received += 1;
+        self.merkle
+            .push_leaf(checksum::hash_bytes(self.hash_method, &plaintext));
+        Ok(())
+    }
+
+    /// Apply a `protocol::ZeroRun`: `count` consecutive full
+    /// `FileMetadata::chunk_size` chunks starting at `start_chunk`, all
+    /// zero bytes, sent with no data at all. When `sparse` is enabled
+    /// (the default), this only `seek`s past the range - no bytes
+    /// actually touch disk, and `finalize`'s `set_len` later turns the gap
+    /// into a real filesystem hole. When disabled, the zeros are written
+    /// out explicitly instead.
+    ///
+    /// `get_progress` and `merkle_root` still account for these chunks as
+    /// if they'd arrived as ordinary all-zero `write_chunk` calls, so
+    /// progress percentage and the Merkle root stay correct either way.
+    pub fn write_zero_run(&mut self, start_chunk: u64, count: u64) -> std::io::Result<()> {
+        let zero_chunk = vec![0u8; self.metadata.chunk_size];
+        let zero_hash = checksum::hash_bytes(self.hash_method, &zero_chunk);
+
+        let run_bytes = count * self.metadata.chunk_size as u64;
+        if self.sparse {
+            let end_offset = (start_chunk * self.metadata.chunk_size as u64) + run_bytes;
+            self.file.seek(SeekFrom::Start(end_offset))?;
+        } else {
+            self.file
+                .seek(SeekFrom::Start(start_chunk * self.metadata.chunk_size as u64))?;
+            for _ in 0..count {
+                self.file.write_all(&zero_chunk)?;
+            }
+        }
+
+        self.bytes_received.fetch_add(run_bytes, Ordering::SeqCst);
+        self.chunks_received += count;
+        for _ in 0..count {
+            self.merkle.push_leaf(zero_hash.clone());
+        }
         Ok(())
     }
 
@@ -192,6 +499,19 @@ impl FileReceiver {
         Ok(())
     }
 
+    /// The `merkle::MerkleAccumulator::root` over every chunk `write_chunk`
+    /// has folded in so far, hashed with this receiver's `hash_method`.
+    /// The instant this equals the sender's `protocol::HandshakeRequest`
+    /// root, the file is known intact without re-reading it back off disk
+    /// the way `checksum::verify_file_checksum` would. Only meaningful if
+    /// chunks were written in the same order the sender read them - an
+    /// out-of-order resume (see `io_utils::missing_chunks_from_bitmap`)
+    /// still needs `checksum::verify_file_checksum_with_method` once
+    /// `finalize` has run instead.
+    pub fn merkle_root(&self) -> Option<Vec<u8>> {
+        self.merkle.root()
+    }
+
     pub fn get_progress(&self) -> TransferProgress {
         let bytes = self.bytes_received.load(Ordering::SeqCst);
         TransferProgress {
@@ -225,8 +545,294 @@ mod tests {
         let mut sender = FileSender::new(temp.path()).unwrap();
         assert_eq!(sender.metadata().total_chunks, 3);
 
-        let (idx, chunk) = sender.read_chunk().unwrap().unwrap();
-        assert_eq!(idx, 0);
+        let (offset, len, chunk) = sender.read_chunk().unwrap().unwrap();
+        assert_eq!(offset, 0);
+        assert_eq!(len, DEFAULT_CHUNK_SIZE);
         assert_eq!(chunk.len(), DEFAULT_CHUNK_SIZE);
     }
+
+    #[test]
+    fn test_file_sender_content_defined() {
+        let mut temp = NamedTempFile::new().unwrap();
+        let mut data = vec![0u8; 3 * 256 * 1024];
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = (i % 251) as u8;
+        }
+        temp.write_all(&data).unwrap();
+        temp.flush().unwrap();
+
+        let mut sender =
+            FileSender::new_content_defined(temp.path(), 16 * 1024, 64 * 1024, 128 * 1024)
+                .unwrap();
+        assert_eq!(
+            sender.metadata().chunking,
+            ChunkingMethod::ContentDefined {
+                min_size: 16 * 1024,
+                max_size: 128 * 1024,
+            }
+        );
+
+        let mut total = 0u64;
+        while let Some((offset, len, chunk)) = sender.read_chunk().unwrap() {
+            assert_eq!(offset, total);
+            assert_eq!(len, chunk.len());
+            assert!(len <= 128 * 1024);
+            total += len as u64;
+        }
+        assert_eq!(total, data.len() as u64);
+    }
+
+    #[test]
+    fn write_chunk_rejects_a_bad_checksum_without_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.bin");
+        let metadata = FileMetadata {
+            name: "out.bin".to_string(),
+            size: DEFAULT_CHUNK_SIZE as u64,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            total_chunks: 1,
+            chunking: ChunkingMethod::FixedSize,
+        };
+        let mut receiver = FileReceiver::new(&path, metadata).unwrap();
+
+        let data = vec![0x7Au8; 128];
+        let wrong_checksum = checksum::calculate_chunk_checksum_raw(b"not the data");
+        let err = receiver.write_chunk(0, 0, &data, wrong_checksum).unwrap_err();
+        assert!(matches!(err, ChunkError::ChecksumMismatch { index: 0 }));
+        assert_eq!(receiver.get_progress().bytes_transferred, 0);
+
+        let good_checksum = checksum::calculate_chunk_checksum_raw(&data);
+        receiver.write_chunk(0, 0, &data, good_checksum).unwrap();
+        assert_eq!(receiver.get_progress().bytes_transferred, data.len() as u64);
+    }
+
+    #[test]
+    fn sender_and_receiver_with_matching_passphrases_round_trip_chunks() {
+        let mut temp = NamedTempFile::new().unwrap();
+        let data = vec![0x99u8; DEFAULT_CHUNK_SIZE + 100];
+        temp.write_all(&data).unwrap();
+        temp.flush().unwrap();
+
+        let sender = FileSender::new(temp.path()).unwrap();
+        let (mut sender, aead_params) = sender
+            .with_passphrase("correct horse battery staple")
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let out_path = dir.path().join("out.bin");
+        let receiver = FileReceiver::new(&out_path, sender.metadata().clone()).unwrap();
+        let mut receiver = receiver
+            .with_passphrase("correct horse battery staple", aead_params)
+            .unwrap();
+
+        while let Some((offset, _len, sealed)) = sender.read_chunk().unwrap() {
+            let chunk_index = offset / DEFAULT_CHUNK_SIZE as u64;
+            // The checksum argument is ignored once AEAD is configured; the
+            // tag is what actually authenticates the chunk.
+            receiver
+                .write_chunk(chunk_index, offset, &sealed, [0u8; 16])
+                .unwrap();
+        }
+
+        assert_eq!(receiver.get_progress().bytes_transferred, data.len() as u64);
+        assert_eq!(std::fs::read(&out_path).unwrap(), data);
+    }
+
+    #[test]
+    fn with_passphrase_rejects_a_weak_passphrase() {
+        let mut temp = NamedTempFile::new().unwrap();
+        temp.write_all(&[0x22u8; 64]).unwrap();
+        temp.flush().unwrap();
+
+        let sender = FileSender::new(temp.path()).unwrap();
+        assert!(matches!(
+            sender.with_passphrase("passphrase"),
+            Err(CryptoError::WeakSecret)
+        ));
+    }
+
+    #[test]
+    fn a_tampered_sealed_chunk_is_rejected_as_a_checksum_mismatch() {
+        let mut temp = NamedTempFile::new().unwrap();
+        temp.write_all(&[0x11u8; 64]).unwrap();
+        temp.flush().unwrap();
+
+        let sender = FileSender::new(temp.path()).unwrap();
+        let (mut sender, aead_params) = sender
+            .with_passphrase("correct horse battery staple")
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let out_path = dir.path().join("out.bin");
+        let receiver = FileReceiver::new(&out_path, sender.metadata().clone()).unwrap();
+        let mut receiver = receiver
+            .with_passphrase("correct horse battery staple", aead_params)
+            .unwrap();
+
+        let (offset, _len, mut sealed) = sender.read_chunk().unwrap().unwrap();
+        let last = sealed.len() - 1;
+        sealed[last] ^= 0xFF;
+
+        let err = receiver.write_chunk(0, offset, &sealed, [0u8; 16]).unwrap_err();
+        assert!(matches!(err, ChunkError::ChecksumMismatch { index: 0 }));
+    }
+
+    #[test]
+    fn sender_and_receiver_merkle_roots_agree_once_every_chunk_lands() {
+        let mut temp = NamedTempFile::new().unwrap();
+        let data = vec![0x5Eu8; 2 * DEFAULT_CHUNK_SIZE + 100];
+        temp.write_all(&data).unwrap();
+        temp.flush().unwrap();
+
+        let mut sender =
+            FileSender::new(temp.path()).unwrap().with_hash_method(HashMethod::Sha256);
+        assert_eq!(sender.merkle_root(), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let out_path = dir.path().join("out.bin");
+        let mut receiver = FileReceiver::new(&out_path, sender.metadata().clone())
+            .unwrap()
+            .with_hash_method(HashMethod::Sha256);
+
+        while let Some((offset, _len, chunk)) = sender.read_chunk().unwrap() {
+            let chunk_index = offset / DEFAULT_CHUNK_SIZE as u64;
+            let checksum = checksum::calculate_chunk_checksum_raw(&chunk);
+            receiver.write_chunk(chunk_index, offset, &chunk, checksum).unwrap();
+        }
+
+        let sender_root = sender.merkle_root().unwrap();
+        let receiver_root = receiver.merkle_root().unwrap();
+        assert_eq!(sender_root, receiver_root);
+    }
+
+    #[test]
+    fn is_all_zero_distinguishes_zero_and_non_zero_chunks() {
+        assert!(is_all_zero(&[0u8; 4096]));
+        assert!(is_all_zero(&[]));
+        let mut mostly_zero = vec![0u8; 4096];
+        mostly_zero[4095] = 1;
+        assert!(!is_all_zero(&mostly_zero));
+    }
+
+    #[test]
+    fn write_zero_run_produces_a_sparse_hole_and_keeps_progress_accurate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.bin");
+        let metadata = FileMetadata {
+            name: "out.bin".to_string(),
+            size: 4 * DEFAULT_CHUNK_SIZE as u64,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            total_chunks: 4,
+            chunking: ChunkingMethod::FixedSize,
+        };
+        let mut receiver = FileReceiver::new(&path, metadata).unwrap();
+
+        receiver.write_zero_run(0, 3).unwrap();
+        let data = vec![0x22u8; DEFAULT_CHUNK_SIZE];
+        let checksum = checksum::calculate_chunk_checksum_raw(&data);
+        receiver
+            .write_chunk(3, 3 * DEFAULT_CHUNK_SIZE as u64, &data, checksum)
+            .unwrap();
+        receiver.finalize().unwrap();
+
+        assert_eq!(
+            receiver.get_progress().bytes_transferred,
+            4 * DEFAULT_CHUNK_SIZE as u64
+        );
+        assert_eq!(receiver.get_progress().chunks_completed, 4);
+
+        let written = std::fs::read(&path).unwrap();
+        assert_eq!(written.len(), 4 * DEFAULT_CHUNK_SIZE);
+        assert!(written[..3 * DEFAULT_CHUNK_SIZE].iter().all(|&b| b == 0));
+        assert_eq!(&written[3 * DEFAULT_CHUNK_SIZE..], data.as_slice());
+    }
+
+    #[test]
+    fn write_zero_run_with_sparse_disabled_writes_zeros_explicitly() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.bin");
+        let metadata = FileMetadata {
+            name: "out.bin".to_string(),
+            size: 2 * DEFAULT_CHUNK_SIZE as u64,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            total_chunks: 2,
+            chunking: ChunkingMethod::FixedSize,
+        };
+        let mut receiver = FileReceiver::new(&path, metadata).unwrap().with_sparse(false);
+
+        receiver.write_zero_run(0, 2).unwrap();
+        receiver.finalize().unwrap();
+
+        let written = std::fs::read(&path).unwrap();
+        assert_eq!(written.len(), 2 * DEFAULT_CHUNK_SIZE);
+        assert!(written.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn zero_run_and_equivalent_written_zeros_produce_the_same_merkle_root() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let path_a = dir.path().join("a.bin");
+        let metadata_a = FileMetadata {
+            name: "a.bin".to_string(),
+            size: 2 * DEFAULT_CHUNK_SIZE as u64,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            total_chunks: 2,
+            chunking: ChunkingMethod::FixedSize,
+        };
+        let mut via_zero_run = FileReceiver::new(&path_a, metadata_a).unwrap();
+        via_zero_run.write_zero_run(0, 2).unwrap();
+
+        let path_b = dir.path().join("b.bin");
+        let metadata_b = FileMetadata {
+            name: "b.bin".to_string(),
+            size: 2 * DEFAULT_CHUNK_SIZE as u64,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            total_chunks: 2,
+            chunking: ChunkingMethod::FixedSize,
+        };
+        let mut via_write_chunk = FileReceiver::new(&path_b, metadata_b).unwrap();
+        let zero_chunk = vec![0u8; DEFAULT_CHUNK_SIZE];
+        let checksum = checksum::calculate_chunk_checksum_raw(&zero_chunk);
+        via_write_chunk
+            .write_chunk(0, 0, &zero_chunk, checksum)
+            .unwrap();
+        via_write_chunk
+            .write_chunk(1, DEFAULT_CHUNK_SIZE as u64, &zero_chunk, checksum)
+            .unwrap();
+
+        assert_eq!(via_zero_run.merkle_root(), via_write_chunk.merkle_root());
+    }
+
+    #[test]
+    fn transfer_state_reacts_to_control_packets() {
+        use crate::protocol::PacketType;
+
+        assert_eq!(
+            TransferState::Transferring.apply_packet(PacketType::Pause),
+            TransferState::Paused
+        );
+        assert_eq!(
+            TransferState::Paused.apply_packet(PacketType::Resume),
+            TransferState::Transferring
+        );
+        // Resume only does anything out of Paused.
+        assert_eq!(
+            TransferState::Transferring.apply_packet(PacketType::Resume),
+            TransferState::Transferring
+        );
+        assert_eq!(
+            TransferState::Transferring.apply_packet(PacketType::Cancel),
+            TransferState::Cancelled
+        );
+        // Data/Nack never change the transfer-wide state.
+        assert_eq!(
+            TransferState::Transferring.apply_packet(PacketType::Data),
+            TransferState::Transferring
+        );
+        assert_eq!(
+            TransferState::Transferring.apply_packet(PacketType::Nack),
+            TransferState::Transferring
+        );
+    }
 }
@@ -1,14 +1,82 @@
-use std::io::{self, Read, Write};
+//! Blocking Ping/Pong keep-alive transport: a `TcpListener::accept` loop
+//! handing connections to a bounded worker pool, each running a blocking
+//! read/write loop over [`Packet`]s. Kept around unconditionally for the
+//! FFI and Android consumers that call [`TransportServer::bind_default`]
+//! directly - see [`crate::transport_async`] for a tokio-native
+//! alternative for callers that already run inside a runtime.
+
+use std::io::{self, Cursor, Read, Write};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
 use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
 use std::time::Duration;
 
+#[cfg(unix)]
+use std::os::unix::io::{FromRawFd, RawFd};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+use crossbeam_channel::{bounded, TrySendError};
 use socket2::{Domain, Protocol, Socket, Type};
 
 pub const MAGIC: u32 = 0xDEADBEEF;
-const HEADER_LEN: usize = 9;
-const MAX_PAYLOAD_LEN: u32 = 64 * 1024 * 1024;
+pub(crate) const HEADER_LEN: usize = 13;
+pub(crate) const MAX_PAYLOAD_LEN: u32 = 64 * 1024 * 1024;
+
+/// Reads the wire primitives `PacketHeader` is made of off anything
+/// readable - in practice a `Cursor` over the header bytes - so
+/// `read_packet` decodes by calling these instead of hand-slicing byte
+/// offsets.
+trait ProtoRead {
+    fn read_u8(&mut self) -> io::Result<u8>;
+    fn read_u32(&mut self) -> io::Result<u32>;
+}
+
+impl<R: Read> ProtoRead for R {
+    fn read_u8(&mut self) -> io::Result<u8> {
+        let mut buf = [0u8; 1];
+        self.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let mut buf = [0u8; 4];
+        self.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+}
+
+/// Writes the same primitives `ProtoRead` reads, plus a length-prefixed
+/// `Vec<u8>` for payloads. `Packet::encode` writes into a `Cursor` over a
+/// fresh `Vec<u8>` through this trait rather than pushing/extending bytes
+/// by hand.
+trait ProtoWrite {
+    fn write_u8(&mut self, value: u8) -> io::Result<()>;
+    fn write_u32(&mut self, value: u32) -> io::Result<()>;
+    fn write_vec(&mut self, value: &[u8]) -> io::Result<()>;
+}
+
+impl<W: Write> ProtoWrite for W {
+    fn write_u8(&mut self, value: u8) -> io::Result<()> {
+        self.write_all(&[value])
+    }
+
+    fn write_u32(&mut self, value: u32) -> io::Result<()> {
+        self.write_all(&value.to_le_bytes())
+    }
+
+    fn write_vec(&mut self, value: &[u8]) -> io::Result<()> {
+        self.write_u32(value.len() as u32)?;
+        self.write_all(value)
+    }
+}
+
+/// Default number of long-lived workers pulling accepted connections off
+/// the bounded queue in [`TransportServer::bind_default`].
+pub const DEFAULT_WORKER_POOL_SIZE: usize = 8;
+/// How many accepted-but-not-yet-serviced connections the accept loop will
+/// queue before it starts rejecting new ones outright.
+const CONNECTION_QUEUE_CAPACITY: usize = 64;
 
 // General packet timeout (for Ping/Pong and data packets)
 pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
@@ -48,6 +116,10 @@ impl PacketType {
 pub struct PacketHeader {
     pub packet_type: PacketType,
     pub payload_len: u32,
+    /// CRC32 of `payload`, checked by `read_packet` before any handler
+    /// sees the payload - catches wire corruption or truncation that
+    /// still carries a `payload_len` matching what was actually sent.
+    pub crc32: u32,
 }
 
 #[derive(Clone, Debug)]
@@ -57,47 +129,85 @@ pub struct Packet {
 }
 
 impl Packet {
+    /// Build a packet, deriving `payload_len` and `crc32` from `payload`
+    /// so callers never have to compute them by hand.
+    pub fn new(packet_type: PacketType, payload: Vec<u8>) -> Self {
+        let crc32 = crc32fast::hash(&payload);
+        Packet {
+            header: PacketHeader {
+                packet_type,
+                payload_len: payload.len() as u32,
+                crc32,
+            },
+            payload,
+        }
+    }
+
     pub fn encode(&self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(HEADER_LEN + self.payload.len());
-        buf.extend_from_slice(&MAGIC.to_le_bytes());
-        buf.push(self.header.packet_type as u8);
-        buf.extend_from_slice(&self.header.payload_len.to_le_bytes());
+        const MSG: &str = "writing a packet header to an in-memory buffer cannot fail";
+        let mut cursor = Cursor::new(Vec::with_capacity(HEADER_LEN + self.payload.len()));
+        cursor.write_u32(MAGIC).expect(MSG);
+        cursor.write_u8(self.header.packet_type as u8).expect(MSG);
+        cursor.write_u32(self.header.payload_len).expect(MSG);
+        cursor.write_u32(self.header.crc32).expect(MSG);
+        let mut buf = cursor.into_inner();
         buf.extend_from_slice(&self.payload);
         buf
     }
 }
 
-fn read_packet(stream: &mut TcpStream) -> io::Result<Packet> {
-    let mut header_buf = [0u8; HEADER_LEN];
-    stream.read_exact(&mut header_buf)?;
-    let magic = u32::from_le_bytes([header_buf[0], header_buf[1], header_buf[2], header_buf[3]]);
+/// Decode a raw `HEADER_LEN`-byte header into `(packet_type, payload_len,
+/// expected_crc32)`, checking the magic number and payload-length cap but
+/// not the CRC - the caller hasn't read the payload yet. Shared by the
+/// blocking `read_packet` below and [`crate::transport_async`]'s async
+/// counterpart, so the two codecs can't drift apart.
+pub(crate) fn decode_header(header_buf: &[u8; HEADER_LEN]) -> io::Result<(PacketType, u32, u32)> {
+    let mut cursor = Cursor::new(&header_buf[..]);
+
+    let magic = cursor.read_u32()?;
     if magic != MAGIC {
         return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid magic"));
     }
-    let packet_type = PacketType::from_u8(header_buf[4])
+    let packet_type = PacketType::from_u8(cursor.read_u8()?)
         .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid packet type"))?;
-    let payload_len =
-        u32::from_le_bytes([header_buf[5], header_buf[6], header_buf[7], header_buf[8]]);
+    let payload_len = cursor.read_u32()?;
+    let expected_crc32 = cursor.read_u32()?;
     if payload_len > MAX_PAYLOAD_LEN {
         return Err(io::Error::new(
             io::ErrorKind::InvalidData,
             "payload too large",
         ));
     }
+    Ok((packet_type, payload_len, expected_crc32))
+}
+
+fn read_packet<S: Read>(stream: &mut S) -> io::Result<Packet> {
+    let mut header_buf = [0u8; HEADER_LEN];
+    stream.read_exact(&mut header_buf)?;
+    let (packet_type, payload_len, expected_crc32) = decode_header(&header_buf)?;
+
     let mut payload = vec![0u8; payload_len as usize];
     if payload_len > 0 {
         stream.read_exact(&mut payload)?;
     }
+    let actual_crc32 = crc32fast::hash(&payload);
+    if actual_crc32 != expected_crc32 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "checksum mismatch",
+        ));
+    }
     Ok(Packet {
         header: PacketHeader {
             packet_type,
             payload_len,
+            crc32: actual_crc32,
         },
         payload,
     })
 }
 
-fn write_packet(stream: &mut TcpStream, packet: &Packet) -> io::Result<()> {
+fn write_packet<S: Write>(stream: &mut S, packet: &Packet) -> io::Result<()> {
     let buf = packet.encode();
     stream.write_all(&buf)?;
     stream.flush()
@@ -118,27 +228,74 @@ fn bind_with_reuse(addr: SocketAddr) -> io::Result<TcpListener> {
 pub struct TransportServer {
     connections: Arc<Mutex<Vec<SocketAddr>>>,
     _accept_thread: JoinHandle<()>,
+    _workers: Vec<JoinHandle<()>>,
 }
 
 impl TransportServer {
-    pub fn bind(addr: SocketAddr) -> io::Result<Self> {
+    /// Bind `addr` and service connections with a fixed pool of `pool_size`
+    /// workers fed by a bounded queue, rather than a thread per connection -
+    /// see [`Self::from_listener`] for why.
+    pub fn bind(addr: SocketAddr, pool_size: usize) -> io::Result<Self> {
         let listener = bind_with_reuse(addr)?;
+        Self::from_listener(listener, pool_size)
+    }
+
+    /// Adopt an already-bound, already-listening TCP socket passed in by a
+    /// service manager (systemd socket activation, see
+    /// [`systemd_activation_listener`]) instead of binding a fresh one.
+    /// Ownership of `fd` transfers to the returned server.
+    #[cfg(unix)]
+    pub fn from_raw_fd(fd: RawFd, pool_size: usize) -> io::Result<Self> {
+        let listener = unsafe { TcpListener::from_raw_fd(fd) };
+        Self::from_listener(listener, pool_size)
+    }
+
+    /// Accept connections on `listener` and hand them to a fixed-size pool
+    /// of `pool_size` long-lived workers (at least one) via a bounded
+    /// channel, instead of the old thread-per-connection model - which let
+    /// a burst of connections, accidental or a deliberate flood, spawn an
+    /// unbounded number of OS threads. Once the queue is full the accept
+    /// loop replies with a `Reject` packet and drops the connection rather
+    /// than blocking or queuing without limit.
+    fn from_listener(listener: TcpListener, pool_size: usize) -> io::Result<Self> {
         let connections = Arc::new(Mutex::new(Vec::new()));
+        let (job_tx, job_rx) = bounded::<(TcpStream, SocketAddr)>(CONNECTION_QUEUE_CAPACITY);
+
+        let workers = (0..pool_size.max(1))
+            .map(|_| {
+                let job_rx = job_rx.clone();
+                let connections = connections.clone();
+                thread::spawn(move || {
+                    for (mut stream, peer) in job_rx {
+                        handle_connection(&mut stream, peer, connections.clone());
+                    }
+                })
+            })
+            .collect();
+
         let accept_connections = connections.clone();
         let _accept_thread = thread::spawn(move || loop {
             match listener.accept() {
                 Ok((mut stream, peer)) => {
-                    let mut list = accept_connections.lock().unwrap();
-                    if !list.contains(&peer) {
-                        list.push(peer);
-                    }
-                    drop(list);
-                    let conn_list = accept_connections.clone();
                     let _ = stream.set_read_timeout(Some(DEFAULT_TIMEOUT));
                     let _ = stream.set_write_timeout(Some(DEFAULT_TIMEOUT));
-                    thread::spawn(move || {
-                        handle_connection(&mut stream, peer, conn_list);
-                    });
+                    match job_tx.try_send((stream, peer)) {
+                        Ok(()) => {
+                            let mut list = accept_connections.lock().unwrap();
+                            if !list.contains(&peer) {
+                                list.push(peer);
+                            }
+                        }
+                        Err(TrySendError::Full((mut stream, peer))) => {
+                            tracing::warn!(
+                                "Worker pool saturated, rejecting connection from {}",
+                                peer
+                            );
+                            let reject = Packet::new(PacketType::Reject, Vec::new());
+                            let _ = write_packet(&mut stream, &reject);
+                        }
+                        Err(TrySendError::Disconnected(_)) => break,
+                    }
                 }
                 Err(_) => {
                     thread::sleep(Duration::from_millis(50));
@@ -148,6 +305,7 @@ impl TransportServer {
         Ok(TransportServer {
             connections,
             _accept_thread,
+            _workers: workers,
         })
     }
 
@@ -155,10 +313,50 @@ impl TransportServer {
         self.connections.lock().unwrap().clone()
     }
 
-    pub fn bind_default(port: u16) -> io::Result<Self> {
+    pub fn bind_default(port: u16, pool_size: usize) -> io::Result<Self> {
         let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port);
-        Self::bind(addr)
+        Self::bind(addr, pool_size)
+    }
+
+    /// Like `bind_default`, but adopts a systemd-activated listening socket
+    /// (see [`systemd_activation_listener`]) when one is available instead
+    /// of binding a fresh port - letting a systemd unit hand its listening
+    /// socket off across restarts for a zero-downtime redeploy.
+    pub fn bind_default_or_activated(port: u16, pool_size: usize) -> io::Result<Self> {
+        if let Some(listener) = systemd_activation_listener() {
+            tracing::info!("Adopting systemd-activated listening socket (fd 3)");
+            return Self::from_listener(listener, pool_size);
+        }
+        Self::bind_default(port, pool_size)
+    }
+}
+
+/// Checks whether this process was started via systemd socket activation
+/// (`LISTEN_PID`/`LISTEN_FDS`, see `sd_listen_fds(3)`) and, if so, returns
+/// the first passed-in listening socket. Only fd 3 - the lowest fd systemd
+/// ever hands over, right after stdin/stdout/stderr - is used, since this
+/// server only ever needs one listening socket; `LISTEN_FDNAMES` isn't
+/// consulted for the same reason. Returns `None` (not an error) when the
+/// process wasn't socket-activated, so callers fall back to a normal bind.
+#[cfg(unix)]
+pub fn systemd_activation_listener() -> Option<TcpListener> {
+    const SD_LISTEN_FDS_START: RawFd = 3;
+
+    let listen_pid: u32 = std::env::var("LISTEN_PID").ok()?.parse().ok()?;
+    if listen_pid != std::process::id() {
+        return None;
+    }
+    let listen_fds: i32 = std::env::var("LISTEN_FDS").ok()?.parse().ok()?;
+    if listen_fds < 1 {
+        return None;
     }
+
+    Some(unsafe { TcpListener::from_raw_fd(SD_LISTEN_FDS_START) })
+}
+
+#[cfg(not(unix))]
+pub fn systemd_activation_listener() -> Option<TcpListener> {
+    None
 }
 
 fn handle_connection(
@@ -171,13 +369,7 @@ fn handle_connection(
     while let Ok(packet) = read_packet(stream) {
         if packet.header.packet_type == PacketType::Ping {
             tracing::trace!("Received Ping from {}, sending Pong", peer);
-            let pong = Packet {
-                header: PacketHeader {
-                    packet_type: PacketType::Pong,
-                    payload_len: 0,
-                },
-                payload: Vec::new(),
-            };
+            let pong = Packet::new(PacketType::Pong, Vec::new());
             let _ = write_packet(stream, &pong);
         } else {
             // Note: This transport layer is for Ping/Pong keep-alive only.
@@ -196,6 +388,64 @@ fn handle_connection(
     list.retain(|addr| *addr != peer);
 }
 
+/// Unix-domain-socket counterpart to `TransportServer`, for fast,
+/// permission-gated transfers between apps on the same machine: no
+/// network stack involved, and access is controlled by filesystem
+/// permissions on the socket path rather than a port number. Only carries
+/// the Ping/Pong keep-alive, same as `TransportServer`.
+#[cfg(unix)]
+pub struct UnixTransportServer {
+    _accept_thread: JoinHandle<()>,
+}
+
+#[cfg(unix)]
+impl UnixTransportServer {
+    /// Bind a Unix domain socket at `path`. Removes any stale socket file
+    /// left behind by a previous, uncleanly-stopped run first, since
+    /// `UnixListener::bind` refuses to bind over an existing path.
+    pub fn bind(path: &std::path::Path) -> io::Result<Self> {
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        let _accept_thread = thread::spawn(move || loop {
+            match listener.accept() {
+                Ok((mut stream, _peer)) => {
+                    let _ = stream.set_read_timeout(Some(DEFAULT_TIMEOUT));
+                    let _ = stream.set_write_timeout(Some(DEFAULT_TIMEOUT));
+                    thread::spawn(move || {
+                        handle_unix_connection(&mut stream);
+                    });
+                }
+                Err(_) => {
+                    thread::sleep(Duration::from_millis(50));
+                }
+            }
+        });
+        Ok(UnixTransportServer { _accept_thread })
+    }
+}
+
+#[cfg(unix)]
+fn handle_unix_connection(stream: &mut UnixStream) {
+    tracing::debug!("Unix transport connection handler started");
+
+    while let Ok(packet) = read_packet(stream) {
+        if packet.header.packet_type == PacketType::Ping {
+            tracing::trace!("Received Ping on unix transport socket, sending Pong");
+            let pong = Packet::new(PacketType::Pong, Vec::new());
+            let _ = write_packet(stream, &pong);
+        } else {
+            tracing::trace!(
+                "Received non-Ping packet type {:?} on unix transport socket, ignoring",
+                packet.header.packet_type
+            );
+        }
+    }
+
+    tracing::debug!("Unix transport connection closed");
+}
+
 pub struct TransportClient {
     stream: TcpStream,
 }
@@ -209,13 +459,7 @@ impl TransportClient {
     }
 
     pub fn ping(&mut self) -> io::Result<bool> {
-        let packet = Packet {
-            header: PacketHeader {
-                packet_type: PacketType::Ping,
-                payload_len: 0,
-            },
-            payload: Vec::new(),
-        };
+        let packet = Packet::new(PacketType::Ping, Vec::new());
         write_packet(&mut self.stream, &packet)?;
         let response = read_packet(&mut self.stream)?;
         Ok(response.header.packet_type == PacketType::Pong)
@@ -0,0 +1,213 @@
+//! Connection-ID pools for connection migration.
+//!
+//! `get_or_create_connection` keys connections purely on the remote
+//! `SocketAddr`, and a `Connection` has historically had exactly one ID for
+//! its whole lifetime - so when a client's address changes (NAT rebind,
+//! Wi-Fi to cellular) the old code treated it as a brand new connection and
+//! the session broke. `ConnectionIdPool` gives each side a small reserve of
+//! additional IDs it has minted and advertised to the peer (so the peer may
+//! address it by any of them) and a reserve of IDs the peer has advertised
+//! in return (so it may pick a fresh one when migrating), following the
+//! sequence-numbered `NEW_CONNECTION_ID` scheme QUIC uses for the same
+//! purpose.
+//!
+//! Picking a never-before-used ID from the pool isn't what makes a
+//! migration safe, though - an address is only trusted once it's been
+//! through the path-validation round trip in
+//! [`super::manager::TransportManager`], which reuses
+//! [`super::addr_valid::RetryTokenValidator`] for that purpose.
+
+use std::collections::VecDeque;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::packet::NewConnectionId;
+
+/// How many spare IDs each side tries to keep minted and advertised to the
+/// peer at once.
+pub const CID_POOL_SIZE: usize = 4;
+
+/// One side's view of the connection-ID pool for a single `Connection`.
+#[derive(Debug, Clone)]
+pub struct ConnectionIdPool {
+    next_seq: u64,
+    /// IDs we've minted, in issue order: `(sequence, id)`.
+    local_ids: VecDeque<(u64, u64)>,
+    /// IDs the peer has told us about, available to migrate to.
+    peer_ids: VecDeque<(u64, u64)>,
+}
+
+impl ConnectionIdPool {
+    /// Seed the pool with `primary`, the ID the connection already has
+    /// from before any migration, as sequence 0.
+    pub fn new(primary: u64) -> Self {
+        let mut local_ids = VecDeque::new();
+        local_ids.push_back((0, primary));
+        ConnectionIdPool {
+            next_seq: 1,
+            local_ids,
+            peer_ids: VecDeque::new(),
+        }
+    }
+
+    /// Whether we should mint and advertise more local IDs.
+    pub fn needs_more_local(&self) -> bool {
+        self.local_ids.len() < CID_POOL_SIZE
+    }
+
+    /// Mint a fresh local ID and record it, returning the
+    /// [`NewConnectionId`] advertisement to send the peer.
+    pub fn mint_local(&mut self) -> NewConnectionId {
+        let sequence = self.next_seq;
+        self.next_seq += 1;
+        let connection_id = rand_conn_id();
+        self.local_ids.push_back((sequence, connection_id));
+        NewConnectionId {
+            sequence,
+            connection_id,
+            retire_prior_to: 0,
+        }
+    }
+
+    /// Whether `id` is one of ours, i.e. the peer may legitimately address
+    /// us with it.
+    pub fn owns_local(&self, id: u64) -> bool {
+        self.local_ids.iter().any(|&(_, local)| local == id)
+    }
+
+    /// Drop every local ID minted before `sequence`, e.g. once a migration
+    /// onto a later one is confirmed.
+    pub fn retire_local_prior_to(&mut self, sequence: u64) {
+        self.local_ids.retain(|&(seq, _)| seq >= sequence);
+    }
+
+    /// Every ID currently valid for the peer to address us with.
+    pub fn local_ids(&self) -> impl Iterator<Item = u64> + '_ {
+        self.local_ids.iter().map(|&(_, id)| id)
+    }
+
+    /// Record an ID the peer advertised, applying its `retire_prior_to` to
+    /// our record of the peer's pool and deduplicating by sequence number
+    /// in case the advertisement is seen twice.
+    pub fn learn_peer_id(&mut self, advertised: NewConnectionId) {
+        if advertised.retire_prior_to > 0 {
+            self.peer_ids
+                .retain(|&(seq, _)| seq >= advertised.retire_prior_to);
+        }
+        if !self
+            .peer_ids
+            .iter()
+            .any(|&(seq, _)| seq == advertised.sequence)
+        {
+            self.peer_ids
+                .push_back((advertised.sequence, advertised.connection_id));
+        }
+    }
+
+    /// Take an unused peer-advertised ID to migrate to, if one is
+    /// available.
+    pub fn next_peer_id(&mut self) -> Option<u64> {
+        self.peer_ids.pop_front().map(|(_, id)| id)
+    }
+
+    /// Once a migration is confirmed, the IDs that were valid on the old
+    /// path aren't needed any more: retire every local ID except the most
+    /// recently minted one and mint a replacement to refill the pool,
+    /// returning the advertisement (with `retire_prior_to` set) to send
+    /// the peer.
+    pub fn retire_all_but_newest_and_refill(&mut self) -> NewConnectionId {
+        let keep_from = self.local_ids.back().map_or(0, |&(seq, _)| seq);
+        self.retire_local_prior_to(keep_from);
+        let mut advertisement = self.mint_local();
+        advertisement.retire_prior_to = keep_from;
+        advertisement
+    }
+}
+
+/// Mint a random 64-bit connection ID. Not cryptographically unguessable -
+/// connection IDs are a routing aid, not a secret; unforgeable security
+/// comes from the handshake and AEAD traffic keys layered on top.
+pub fn rand_conn_id() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    (nanos as u64) ^ (process::id() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_starts_with_only_the_primary_id() {
+        let pool = ConnectionIdPool::new(42);
+        assert!(pool.owns_local(42));
+        assert_eq!(pool.local_ids().collect::<Vec<_>>(), vec![42]);
+        assert!(pool.needs_more_local());
+    }
+
+    #[test]
+    fn minting_fills_the_pool_and_is_recognized_as_ours() {
+        let mut pool = ConnectionIdPool::new(1);
+        while pool.needs_more_local() {
+            let advertisement = pool.mint_local();
+            assert!(pool.owns_local(advertisement.connection_id));
+        }
+        assert_eq!(pool.local_ids().count(), CID_POOL_SIZE);
+    }
+
+    #[test]
+    fn retiring_drops_only_ids_before_the_given_sequence() {
+        let mut pool = ConnectionIdPool::new(1); // sequence 0
+        let second = pool.mint_local(); // sequence 1
+        let third = pool.mint_local(); // sequence 2
+
+        pool.retire_local_prior_to(second.sequence);
+        assert!(!pool.owns_local(1), "sequence 0 should be retired");
+        assert!(pool.owns_local(second.connection_id));
+        assert!(pool.owns_local(third.connection_id));
+    }
+
+    #[test]
+    fn learning_a_peer_id_makes_it_available_to_migrate_to() {
+        let mut pool = ConnectionIdPool::new(1);
+        assert_eq!(pool.next_peer_id(), None);
+
+        pool.learn_peer_id(NewConnectionId {
+            sequence: 0,
+            connection_id: 99,
+            retire_prior_to: 0,
+        });
+        assert_eq!(pool.next_peer_id(), Some(99));
+        assert_eq!(pool.next_peer_id(), None, "each ID is handed out once");
+    }
+
+    #[test]
+    fn retire_all_but_newest_and_refill_keeps_only_the_newest_and_one_fresh_id() {
+        let mut pool = ConnectionIdPool::new(1); // sequence 0
+        let second = pool.mint_local(); // sequence 1
+
+        let advertisement = pool.retire_all_but_newest_and_refill();
+        assert_eq!(advertisement.retire_prior_to, second.sequence);
+        assert!(!pool.owns_local(1), "pre-migration id is retired");
+        assert!(pool.owns_local(second.connection_id), "newest pre-migration id survives");
+        assert!(pool.owns_local(advertisement.connection_id));
+        assert_eq!(pool.local_ids().count(), 2);
+    }
+
+    #[test]
+    fn duplicate_peer_advertisement_is_not_double_counted() {
+        let mut pool = ConnectionIdPool::new(1);
+        let advertisement = NewConnectionId {
+            sequence: 0,
+            connection_id: 99,
+            retire_prior_to: 0,
+        };
+        pool.learn_peer_id(advertisement);
+        pool.learn_peer_id(advertisement);
+
+        assert_eq!(pool.next_peer_id(), Some(99));
+        assert_eq!(pool.next_peer_id(), None);
+    }
+}
@@ -0,0 +1,443 @@
+//! Pluggable congestion control.
+//!
+//! [`Connection::acknowledge`](super::connection::Connection::acknowledge)
+//! tracked `rtt_ms` via EWMA but had no notion of a congestion window, so
+//! [`super::manager::TransportManager::send_data`] would blast packets onto
+//! a congested path unthrottled. [`CongestionController`] is the extension
+//! point: a `Connection` owns one behind a trait object and consults
+//! `can_send` before actually putting bytes on the wire, matching how
+//! `rtt_ms`/`pending_acks` are already threaded through `Connection` rather
+//! than the manager.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Typical UDP-safe maximum segment size, used as the unit for window/
+/// threshold math below (mirrors how TCP congestion control is usually
+/// expressed).
+pub const MSS: usize = 1400;
+
+/// A pluggable congestion-control algorithm. Byte-counted (not packet-number
+/// keyed) to match how `Connection` already accounts `pending_acks` by size.
+pub trait CongestionController: std::fmt::Debug + Send {
+    /// A packet of `bytes` was just sent.
+    fn on_packet_sent(&mut self, bytes: usize);
+    /// An ACK covered `bytes`; `rtt_sample` is the RTT of the packet it
+    /// acknowledged, when attributable to one.
+    fn on_ack(&mut self, bytes: usize, rtt_sample: Option<Duration>);
+    /// A packet of `bytes` was declared lost (e.g. a retransmit).
+    fn on_loss(&mut self, bytes: usize);
+    /// A one-way-delay sample (microseconds, sender's send time to
+    /// receiver's reported receive time) became available for an acked
+    /// packet. Loss-based controllers have no use for this and ignore it;
+    /// [`Ledbat`] is the only implementation that acts on it.
+    fn on_delay_sample(&mut self, _one_way_delay_us: i64) {}
+    /// How many more bytes may be sent right now, given `bytes_in_flight`.
+    fn can_send(&self, bytes_in_flight: usize) -> usize;
+    /// Current congestion window, for diagnostics/tests.
+    fn cwnd(&self) -> usize;
+}
+
+/// Which [`CongestionController`] a [`super::manager::TransportConfig`]
+/// should build for new connections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CongestionAlgorithm {
+    /// Simple, well-understood slow-start + AIMD. Default: a safe choice
+    /// when the path's characteristics (and thus whether CUBIC's more
+    /// aggressive growth would help) aren't known ahead of time.
+    NewReno,
+    /// Cubic-growth window, better suited to high-bandwidth-delay-product
+    /// links where NewReno's linear congestion-avoidance growth is slow to
+    /// recover after a loss.
+    Cubic,
+    /// Delay-based, not loss-based: backs off as soon as it sees its own
+    /// queuing delay grow, well before a loss-based flow sharing the same
+    /// bottleneck would react. Intended for bulk/background transfers that
+    /// should yield to interactive traffic rather than compete with it.
+    Ledbat,
+}
+
+impl Default for CongestionAlgorithm {
+    fn default() -> Self {
+        CongestionAlgorithm::NewReno
+    }
+}
+
+impl CongestionAlgorithm {
+    pub fn build(self) -> Box<dyn CongestionController> {
+        match self {
+            CongestionAlgorithm::NewReno => Box::new(NewReno::new()),
+            CongestionAlgorithm::Cubic => Box::new(Cubic::new()),
+            CongestionAlgorithm::Ledbat => Box::new(Ledbat::new()),
+        }
+    }
+}
+
+/// NewReno: slow-start doubling (approximated as +1 byte of window per
+/// byte ACKed) until `ssthresh`, then linear congestion avoidance; a loss
+/// halves the window and collapses to one round of recovery.
+#[derive(Debug)]
+pub struct NewReno {
+    cwnd: usize,
+    ssthresh: usize,
+    /// Monotonic count of bytes sent so far. A loss only triggers once per
+    /// "round" - further losses reported before `sent_bytes` has advanced
+    /// past `recovery_point` are assumed to be the same loss event,
+    /// mirroring real NewReno's recovery window keyed on the highest
+    /// packet number outstanding when loss was first detected.
+    sent_bytes: u64,
+    recovery_point: u64,
+}
+
+impl NewReno {
+    pub fn new() -> Self {
+        NewReno {
+            cwnd: 10 * MSS,
+            ssthresh: usize::MAX,
+            sent_bytes: 0,
+            recovery_point: 0,
+        }
+    }
+}
+
+impl Default for NewReno {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CongestionController for NewReno {
+    fn on_packet_sent(&mut self, bytes: usize) {
+        self.sent_bytes += bytes as u64;
+    }
+
+    fn on_ack(&mut self, bytes: usize, _rtt_sample: Option<Duration>) {
+        if self.cwnd < self.ssthresh {
+            // Slow start.
+            self.cwnd += bytes;
+        } else {
+            // Congestion avoidance: ~+1 MSS per RTT's worth of ACKs.
+            self.cwnd += (MSS * bytes) / self.cwnd.max(1);
+        }
+    }
+
+    fn on_loss(&mut self, _bytes: usize) {
+        if self.sent_bytes <= self.recovery_point {
+            return;
+        }
+        self.ssthresh = (self.cwnd / 2).max(2 * MSS);
+        self.cwnd = self.ssthresh;
+        self.recovery_point = self.sent_bytes;
+    }
+
+    fn can_send(&self, bytes_in_flight: usize) -> usize {
+        self.cwnd.saturating_sub(bytes_in_flight)
+    }
+
+    fn cwnd(&self) -> usize {
+        self.cwnd
+    }
+}
+
+const CUBIC_C: f64 = 0.4;
+const CUBIC_BETA: f64 = 0.7;
+
+/// CUBIC: plain slow start (like NewReno) until the first loss, then a
+/// cubic (not linear) growth function of time-since-that-loss, falling
+/// back to a TCP-friendly estimate early in the epoch so it doesn't lose
+/// out to NewReno-like flows sharing the same bottleneck.
+#[derive(Debug)]
+pub struct Cubic {
+    cwnd: f64,
+    w_max: f64,
+    /// `None` before the first congestion event - the cubic growth
+    /// function only applies once there's a `w_max` to grow back towards;
+    /// until then this behaves like plain slow start.
+    epoch_start: Option<Instant>,
+    last_rtt: Duration,
+}
+
+impl Cubic {
+    pub fn new() -> Self {
+        let initial = (10 * MSS) as f64;
+        Cubic {
+            cwnd: initial,
+            w_max: initial,
+            epoch_start: None,
+            last_rtt: Duration::from_millis(100),
+        }
+    }
+
+    /// `W_cubic(t) = C*(t - K)^3 + w_max`, `K = cbrt(w_max*(1-beta)/C)`.
+    fn w_cubic(&self, t: f64) -> f64 {
+        let k = (self.w_max * (1.0 - CUBIC_BETA) / CUBIC_C).cbrt();
+        CUBIC_C * (t - k).powi(3) + self.w_max
+    }
+
+    /// Standard CUBIC TCP-friendly region, so CUBIC doesn't starve a
+    /// NewReno flow sharing the same bottleneck early in an epoch.
+    fn tcp_friendly_estimate(&self, t: f64) -> f64 {
+        let rtt = self.last_rtt.as_secs_f64().max(0.001);
+        self.w_max * CUBIC_BETA + 3.0 * (1.0 - CUBIC_BETA) / (1.0 + CUBIC_BETA) * (t / rtt)
+    }
+}
+
+impl Default for Cubic {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CongestionController for Cubic {
+    fn on_packet_sent(&mut self, _bytes: usize) {}
+
+    fn on_ack(&mut self, bytes: usize, rtt_sample: Option<Duration>) {
+        if let Some(rtt) = rtt_sample {
+            self.last_rtt = rtt;
+        }
+        match self.epoch_start {
+            None => {
+                // No congestion event yet: plain slow start.
+                self.cwnd += bytes as f64;
+            }
+            Some(epoch) => {
+                let t = epoch.elapsed().as_secs_f64() + self.last_rtt.as_secs_f64();
+                let w_cubic = self.w_cubic(t);
+                let w_est = self.tcp_friendly_estimate(t);
+                self.cwnd = w_cubic.max(w_est).max(MSS as f64);
+            }
+        }
+    }
+
+    fn on_loss(&mut self, _bytes: usize) {
+        self.w_max = self.cwnd;
+        self.cwnd = (self.cwnd * CUBIC_BETA).max(2.0 * MSS as f64);
+        // The epoch - and thus `t` in `w_cubic` - starts counting from the
+        // moment of this loss, at the now-reduced window.
+        self.epoch_start = Some(Instant::now());
+    }
+
+    fn can_send(&self, bytes_in_flight: usize) -> usize {
+        (self.cwnd as usize).saturating_sub(bytes_in_flight)
+    }
+
+    fn cwnd(&self) -> usize {
+        self.cwnd as usize
+    }
+}
+
+/// LEDBAT's target queuing delay: the amount of self-induced queue it
+/// tolerates before backing off. 100ms, the standard BEP-29 value.
+const LEDBAT_TARGET_US: f64 = 100_000.0;
+const LEDBAT_GAIN: f64 = 1.0;
+const LEDBAT_MIN_CWND: usize = 2 * MSS;
+/// How long a `base_delay` bucket covers, and how many of them are kept.
+/// `base_delay` is the minimum over the last `LEDBAT_BASE_DELAY_WINDOWS`
+/// buckets rather than an all-time minimum, so a path change that genuinely
+/// lowers the floor (e.g. a route change) is eventually recognized instead
+/// of being masked forever by an earlier, now-stale minimum.
+const LEDBAT_BASE_DELAY_WINDOW: Duration = Duration::from_secs(60);
+const LEDBAT_BASE_DELAY_WINDOWS: usize = 2;
+
+/// LEDBAT (BEP-29-style low-extra-delay background transport): tracks a
+/// rolling `base_delay` (the path's minimum one-way delay) against the
+/// current delay, and shrinks or grows the window to hold queuing delay at
+/// [`LEDBAT_TARGET_US`] rather than waiting for a loss. See
+/// [`super::packet::AckFrame::receiver_timestamp_us`] for how the delay
+/// samples this relies on reach the sender.
+#[derive(Debug)]
+pub struct Ledbat {
+    cwnd: f64,
+    base_delay_windows: VecDeque<(Instant, i64)>,
+    current_delay_us: i64,
+}
+
+impl Ledbat {
+    pub fn new() -> Self {
+        Ledbat {
+            cwnd: (10 * MSS) as f64,
+            base_delay_windows: VecDeque::new(),
+            current_delay_us: 0,
+        }
+    }
+
+    fn base_delay_us(&self) -> i64 {
+        self.base_delay_windows
+            .iter()
+            .map(|&(_, delay)| delay)
+            .min()
+            .unwrap_or(self.current_delay_us)
+    }
+}
+
+impl Default for Ledbat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CongestionController for Ledbat {
+    fn on_packet_sent(&mut self, _bytes: usize) {}
+
+    fn on_delay_sample(&mut self, one_way_delay_us: i64) {
+        self.current_delay_us = one_way_delay_us;
+
+        let now = Instant::now();
+        match self.base_delay_windows.back_mut() {
+            Some((window_start, min)) if now.duration_since(*window_start) < LEDBAT_BASE_DELAY_WINDOW => {
+                *min = (*min).min(one_way_delay_us);
+            }
+            _ => {
+                self.base_delay_windows.push_back((now, one_way_delay_us));
+                while self.base_delay_windows.len() > LEDBAT_BASE_DELAY_WINDOWS {
+                    self.base_delay_windows.pop_front();
+                }
+            }
+        }
+    }
+
+    fn on_ack(&mut self, bytes: usize, _rtt_sample: Option<Duration>) {
+        let queuing_delay = (self.current_delay_us - self.base_delay_us()) as f64;
+        let off_target = (LEDBAT_TARGET_US - queuing_delay) / LEDBAT_TARGET_US;
+        self.cwnd += LEDBAT_GAIN * off_target * bytes as f64 * MSS as f64 / self.cwnd;
+        self.cwnd = self.cwnd.max(LEDBAT_MIN_CWND as f64);
+    }
+
+    fn on_loss(&mut self, _bytes: usize) {
+        // Delay-based, not loss-based: LEDBAT has already backed off from
+        // queue growth well before a loss-based controller sharing the
+        // bottleneck would react, so also halving the window on loss here
+        // would make it yield even more aggressively than intended.
+    }
+
+    fn can_send(&self, bytes_in_flight: usize) -> usize {
+        (self.cwnd as usize).saturating_sub(bytes_in_flight)
+    }
+
+    fn cwnd(&self) -> usize {
+        self.cwnd as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_reno_starts_in_slow_start_and_grows_per_ack() {
+        let mut reno = NewReno::new();
+        let initial = reno.cwnd();
+        reno.on_ack(MSS, None);
+        assert_eq!(reno.cwnd(), initial + MSS);
+    }
+
+    #[test]
+    fn new_reno_loss_halves_window_and_sets_ssthresh() {
+        let mut reno = NewReno::new();
+        reno.on_packet_sent(5 * MSS);
+        let before = reno.cwnd();
+        reno.on_loss(MSS);
+        assert_eq!(reno.cwnd(), (before / 2).max(2 * MSS));
+        assert_eq!(reno.ssthresh, reno.cwnd());
+    }
+
+    #[test]
+    fn new_reno_single_burst_loss_only_halves_once() {
+        let mut reno = NewReno::new();
+        reno.on_packet_sent(5 * MSS);
+        reno.on_loss(MSS);
+        let after_first = reno.cwnd();
+        // More losses reported from the same burst (no further sends in
+        // between) shouldn't halve the window again.
+        reno.on_loss(MSS);
+        reno.on_loss(MSS);
+        assert_eq!(reno.cwnd(), after_first);
+    }
+
+    #[test]
+    fn new_reno_can_send_respects_window() {
+        let reno = NewReno::new();
+        let cwnd = reno.cwnd();
+        assert_eq!(reno.can_send(0), cwnd);
+        assert_eq!(reno.can_send(cwnd), 0);
+        assert_eq!(reno.can_send(cwnd + MSS), 0);
+    }
+
+    #[test]
+    fn cubic_grows_window_on_repeated_acks() {
+        let mut cubic = Cubic::new();
+        let initial = cubic.cwnd();
+        for _ in 0..20 {
+            cubic.on_ack(MSS, Some(Duration::from_millis(50)));
+        }
+        assert!(cubic.cwnd() >= initial);
+    }
+
+    #[test]
+    fn cubic_loss_sets_w_max_and_shrinks_by_beta() {
+        let mut cubic = Cubic::new();
+        cubic.on_ack(MSS, Some(Duration::from_millis(50)));
+        let before = cubic.cwnd();
+        cubic.on_loss(MSS);
+        assert_eq!(cubic.w_max, before as f64);
+        assert!(cubic.cwnd() <= before);
+    }
+
+    #[test]
+    fn algorithm_default_is_new_reno() {
+        assert_eq!(CongestionAlgorithm::default(), CongestionAlgorithm::NewReno);
+    }
+
+    #[test]
+    fn ledbat_grows_window_while_under_target_delay() {
+        let mut ledbat = Ledbat::new();
+        let initial = ledbat.cwnd();
+        // Every sample reports the same delay, so base_delay tracks it
+        // exactly and queuing_delay is 0 - well under target, window grows.
+        for _ in 0..10 {
+            ledbat.on_delay_sample(20_000);
+            ledbat.on_ack(MSS, None);
+        }
+        assert!(ledbat.cwnd() > initial);
+    }
+
+    #[test]
+    fn ledbat_shrinks_window_once_queuing_delay_exceeds_target() {
+        let mut ledbat = Ledbat::new();
+        // Establish a low base_delay first...
+        ledbat.on_delay_sample(10_000);
+        ledbat.on_ack(MSS, None);
+        let before = ledbat.cwnd();
+
+        // ...then a self-induced queue builds: current delay climbs well
+        // past base_delay + LEDBAT_TARGET_US.
+        ledbat.on_delay_sample(10_000 + 200_000);
+        ledbat.on_ack(MSS, None);
+        assert!(ledbat.cwnd() < before, "window should shrink once queuing delay exceeds target");
+    }
+
+    #[test]
+    fn ledbat_does_not_shrink_on_loss() {
+        let mut ledbat = Ledbat::new();
+        let before = ledbat.cwnd();
+        ledbat.on_loss(MSS);
+        assert_eq!(ledbat.cwnd(), before, "LEDBAT is delay-based, not loss-based");
+    }
+
+    #[test]
+    fn ledbat_base_delay_tracks_the_minimum_across_samples() {
+        let mut ledbat = Ledbat::new();
+        ledbat.on_delay_sample(50_000);
+        ledbat.on_delay_sample(10_000);
+        ledbat.on_delay_sample(30_000);
+        assert_eq!(ledbat.base_delay_us(), 10_000);
+    }
+
+    #[test]
+    fn loss_based_controllers_ignore_delay_samples_by_default() {
+        let mut reno = NewReno::new();
+        let before = reno.cwnd();
+        reno.on_delay_sample(1_000_000);
+        assert_eq!(reno.cwnd(), before);
+    }
+}
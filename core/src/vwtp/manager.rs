@@ -0,0 +1,1301 @@
+//! Transport Manager
+//!
+//! Coordinates UDP I/O, connection management, and packet reliability.
+
+use bytes::{Bytes, BytesMut};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::{mpsc, RwLock};
+
+use super::addr_valid::RetryTokenValidator;
+use super::cid;
+use super::congestion::CongestionAlgorithm;
+use super::connection::{Connection, ConnectionState};
+use super::crypto::HandshakeMessage;
+use super::packet::{AckFrame, Header, NewConnectionId, Packet, PacketError, PacketType};
+use super::udp::{UdpTransport, UdpTransportConfig};
+use crate::security::crypto::DeviceIdentity;
+use crate::security::spake2::Role;
+use std::time::{Duration, Instant};
+
+/// Events emitted by the TransportManager
+#[derive(Debug)]
+pub enum TransportEvent {
+    /// New connection established
+    Connected { conn_id: u64, remote: SocketAddr },
+    /// Data received on a connection
+    Data { conn_id: u64, payload: Bytes },
+    /// Connection closed
+    Disconnected { conn_id: u64 },
+    /// A connection went idle-timed-out and `TransportManager` is
+    /// transparently re-running the handshake to the same remote instead
+    /// of tearing it down; `conn_id` is unchanged, so callers don't need
+    /// to do anything but wait unless they want to surface the transient
+    /// state to a user. `attempt` is the 0-based count of reconnect
+    /// attempts made so far for this outage.
+    Reconnecting { conn_id: u64, attempt: u32 },
+    /// Error occurred
+    Error { conn_id: Option<u64>, error: String },
+}
+
+/// Backoff between a timed-out connection's reconnect attempts; see
+/// [`TransportConfig::reconnect`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReconnectStrategy {
+    /// Wait the same amount of time before every attempt.
+    Fixed(Duration),
+    /// Double the wait after every attempt, capped at `max`.
+    Exponential { initial: Duration, max: Duration },
+}
+
+impl ReconnectStrategy {
+    fn delay(&self, attempt: u32) -> Duration {
+        match *self {
+            ReconnectStrategy::Fixed(delay) => delay,
+            ReconnectStrategy::Exponential { initial, max } => {
+                let factor = 1u32.checked_shl(attempt.min(16)).unwrap_or(u32::MAX);
+                initial.checked_mul(factor).unwrap_or(max).min(max)
+            }
+        }
+    }
+}
+
+/// Configuration for the TransportManager
+#[derive(Debug, Clone)]
+pub struct TransportConfig {
+    pub max_retries: u32,
+    pub max_connections: usize,
+    /// Socket-level tuning for the underlying `UdpTransport`. Defaults to
+    /// [`UdpTransportConfig::bulk_throughput`] since this manager carries
+    /// bulk transfer data, not latency-sensitive control traffic.
+    pub udp: UdpTransportConfig,
+    /// Congestion-control algorithm new connections are created with.
+    pub congestion: CongestionAlgorithm,
+    /// How `run_timer_pass` reacts to an idle-timed-out connection.
+    /// `Some` transparently re-handshakes to the same remote instead of
+    /// disconnecting (see [`TransportEvent::Reconnecting`]); `None`
+    /// restores the old hard-disconnect behavior.
+    pub reconnect: Option<ReconnectStrategy>,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        TransportConfig {
+            max_retries: 5,
+            max_connections: 100,
+            udp: UdpTransportConfig::bulk_throughput(),
+            congestion: CongestionAlgorithm::default(),
+            reconnect: Some(ReconnectStrategy::Exponential {
+                initial: Duration::from_millis(500),
+                max: Duration::from_secs(30),
+            }),
+        }
+    }
+}
+
+/// How long `send_data` waits for congestion-window room to free up
+/// before giving up; bounded so a permanently-closed window (e.g. a dead
+/// peer that never ACKs) fails loudly instead of hanging forever.
+const SEND_WINDOW_WAIT_TOTAL: Duration = Duration::from_secs(2);
+const SEND_WINDOW_WAIT_STEP: Duration = Duration::from_millis(10);
+
+/// Bounds on `run_timers`'s tick interval, which is otherwise derived from
+/// the smallest RTT across all connections.
+const MIN_TIMER_TICK: Duration = Duration::from_millis(10);
+const MAX_TIMER_TICK: Duration = Duration::from_millis(500);
+const DEFAULT_TIMER_TICK: Duration = Duration::from_millis(100);
+
+/// How many times `connect` is willing to answer a `Retry` with a fresh
+/// `Initial` before giving up on address validation and handshaking
+/// anyway; bounds a peer that keeps churning tokens out from hanging a
+/// connection attempt forever.
+const INITIAL_RETRY_ATTEMPTS: u32 = 2;
+/// How long `connect` waits for a `Retry` reply to an `Initial` before
+/// concluding the token it just sent (or the absence of one, against a
+/// peer that isn't gating on one yet) was accepted and moving on to the
+/// handshake.
+const INITIAL_RETRY_WAIT: Duration = Duration::from_millis(200);
+
+/// `Connection::is_timed_out` fires at 30s of inactivity; a `KeepAlive` is
+/// sent at half that so a healthy-but-idle connection never gets close.
+const KEEPALIVE_IDLE_THRESHOLD: Duration = Duration::from_secs(15);
+
+/// Main transport controller
+pub struct TransportManager {
+    transport: UdpTransport,
+    connections: Arc<RwLock<HashMap<u64, Connection>>>,
+    #[allow(dead_code)]
+    config: TransportConfig,
+    event_tx: mpsc::Sender<TransportEvent>,
+    /// Gates connection creation from inbound `Initial` packets behind a
+    /// stateless retry-token round trip; see [`super::addr_valid`].
+    retry_validator: RwLock<RetryTokenValidator>,
+    /// This device's signing identity, used to authenticate the `Handshake`
+    /// packet pair that establishes each connection's [`super::crypto::TrafficKeys`].
+    identity: Arc<DeviceIdentity>,
+    /// Maps a connection's additional migration-ready IDs (see
+    /// [`super::cid`]) back to the primary ID it's keyed under in
+    /// `connections`, so `handle_packet` can route a packet addressed with
+    /// any of them to the right `Connection`.
+    alt_cids: RwLock<HashMap<u64, u64>>,
+}
+
+impl TransportManager {
+    /// Create a new TransportManager bound to the given port
+    pub async fn bind(
+        port: u16,
+        config: TransportConfig,
+        identity: Arc<DeviceIdentity>,
+    ) -> std::io::Result<(Self, mpsc::Receiver<TransportEvent>)> {
+        let transport = UdpTransport::bind_dual_stack_with_config(port, &config.udp).await?;
+        let (event_tx, event_rx) = mpsc::channel(256);
+
+        let manager = TransportManager {
+            transport,
+            connections: Arc::new(RwLock::new(HashMap::new())),
+            config,
+            event_tx,
+            retry_validator: RwLock::new(RetryTokenValidator::new()),
+            identity,
+            alt_cids: RwLock::new(HashMap::new()),
+        };
+
+        Ok((manager, event_rx))
+    }
+
+    /// The local address this manager's socket is bound to.
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.transport.local_addr()
+    }
+
+    /// Initiate a connection to `remote`: allocate (or reuse) its
+    /// `Connection`, perform the `Initial`/`Retry` address-validation round
+    /// trip (see [`super::addr_valid`]), begin our half of the handshake,
+    /// and send the first `Handshake` packet. Does not wait for the peer's
+    /// reply to the handshake itself; traffic keys land once
+    /// `handle_packet` processes it.
+    ///
+    /// Reads directly off the socket while validating, so must be awaited
+    /// to completion before `recv_loop` is spawned for this manager -
+    /// otherwise the two would race for the same inbound `Retry` packet.
+    pub async fn connect(&self, remote: SocketAddr) -> Result<u64, PacketError> {
+        let conn_id = self.get_or_create_connection(remote).await;
+        self.send_validated_initial(conn_id, remote).await?;
+        let message = {
+            let mut conns = self.connections.write().await;
+            let conn = conns.get_mut(&conn_id).ok_or(PacketError::Incomplete)?;
+            conn.begin_handshake(&self.identity, Role::Initiator)
+        };
+        self.send_handshake(conn_id, &message, remote).await?;
+        Ok(conn_id)
+    }
+
+    /// Performs the client side of address validation: send an `Initial`
+    /// (echoing back whatever token a prior `Retry` handed us, or empty on
+    /// the first try), and if the peer answers with a `Retry` rather than
+    /// silently accepting it, resend with its token. Gives up validating
+    /// after [`INITIAL_RETRY_ATTEMPTS`] and proceeds to the handshake
+    /// regardless, since a peer that isn't gating on retry tokens at all
+    /// will never reply any other way.
+    async fn send_validated_initial(&self, conn_id: u64, remote: SocketAddr) -> Result<(), PacketError> {
+        let mut token: Vec<u8> = Vec::new();
+        for _ in 0..INITIAL_RETRY_ATTEMPTS {
+            self.send_initial(conn_id, &token, remote).await?;
+            match self.recv_retry_token(remote).await {
+                Some(next_token) => token = next_token,
+                None => return Ok(()),
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_initial(&self, conn_id: u64, token: &[u8], remote: SocketAddr) -> Result<(), PacketError> {
+        let header = Header {
+            packet_type: PacketType::Initial,
+            key_phase: false,
+            connection_id: conn_id,
+            packet_number: 0,
+        };
+        let packet = Packet {
+            header,
+            payload: Bytes::copy_from_slice(token),
+        };
+        let mut buf = BytesMut::new();
+        packet.encode(&mut buf);
+
+        self.transport
+            .send(&buf.freeze(), remote)
+            .await
+            .map_err(|_| PacketError::Incomplete)?;
+        Ok(())
+    }
+
+    /// Waits up to [`INITIAL_RETRY_WAIT`] for a `Retry` from `remote`,
+    /// returning its token. `None` means nothing arrived in the window,
+    /// which is read as the `Initial` just sent having been accepted.
+    async fn recv_retry_token(&self, remote: SocketAddr) -> Option<Vec<u8>> {
+        let mut buf = [0u8; 65535];
+        loop {
+            let (len, from) =
+                match tokio::time::timeout(INITIAL_RETRY_WAIT, self.transport.recv(&mut buf)).await
+                {
+                    Ok(Ok(pair)) => pair,
+                    _ => return None,
+                };
+            if from != remote {
+                continue;
+            }
+            let Ok(packet) = Packet::decode(Bytes::copy_from_slice(&buf[..len])) else {
+                continue;
+            };
+            if packet.header.packet_type == PacketType::Retry {
+                return Some(packet.payload.to_vec());
+            }
+        }
+    }
+
+    /// Get or create a connection for the given remote address
+    pub async fn get_or_create_connection(&self, remote: SocketAddr) -> u64 {
+        let mut conns = self.connections.write().await;
+
+        // Check if existing connection
+        for (id, conn) in conns.iter() {
+            if conn.remote_addr == remote {
+                return *id;
+            }
+        }
+
+        // Create new connection
+        let conn_id = cid::rand_conn_id();
+        let conn = Connection::new(conn_id, remote, self.config.congestion);
+        conns.insert(conn_id, conn);
+        conn_id
+    }
+
+    /// Map an incoming packet's `connection_id` to the primary key used in
+    /// `self.connections`: itself if it's already a primary ID, or
+    /// whichever primary ID it was minted under if it's one of a
+    /// connection's additional migration IDs (see [`super::cid`]).
+    async fn resolve_conn_id(&self, wire_conn_id: u64) -> u64 {
+        if self.connections.read().await.contains_key(&wire_conn_id) {
+            return wire_conn_id;
+        }
+        self.alt_cids
+            .read()
+            .await
+            .get(&wire_conn_id)
+            .copied()
+            .unwrap_or(wire_conn_id)
+    }
+
+    /// Send a data packet to a connection, waiting for the congestion
+    /// window to have room rather than sending past it.
+    pub async fn send_data(&self, conn_id: u64, payload: Bytes) -> Result<(), PacketError> {
+        self.wait_for_send_window(conn_id, payload.len()).await?;
+
+        let mut conns = self.connections.write().await;
+        let conn = conns.get_mut(&conn_id).ok_or(PacketError::Incomplete)?;
+        if !conn.can_send(payload.len()) {
+            return Err(PacketError::CongestionWindowFull);
+        }
+
+        let pkt_num = conn.alloc_pkt_num();
+        let (sealed, key_phase) = conn.seal_data(pkt_num, &payload)?;
+        let header = Header {
+            packet_type: PacketType::Data,
+            key_phase,
+            connection_id: conn_id,
+            packet_number: pkt_num,
+        };
+
+        let packet = Packet {
+            header,
+            payload: Bytes::from(sealed),
+        };
+        let mut buf = BytesMut::new();
+        packet.encode(&mut buf);
+
+        // Keep the plaintext, not the sealed wire bytes: a later
+        // retransmission (possibly after a reconnect rotates in brand new
+        // traffic keys) re-seals from this rather than resending stale
+        // ciphertext the peer can no longer open.
+        conn.record_sent(pkt_num, payload.to_vec());
+
+        self.transport
+            .send(&buf.freeze(), conn.remote_addr)
+            .await
+            .map_err(|_| PacketError::Incomplete)?;
+        Ok(())
+    }
+
+    /// Poll `conn_id`'s congestion window until it has room for `bytes`,
+    /// giving up after [`SEND_WINDOW_WAIT_TOTAL`].
+    async fn wait_for_send_window(&self, conn_id: u64, bytes: usize) -> Result<(), PacketError> {
+        let mut waited = Duration::ZERO;
+        loop {
+            {
+                let conns = self.connections.read().await;
+                let conn = conns.get(&conn_id).ok_or(PacketError::Incomplete)?;
+                if conn.can_send(bytes) {
+                    return Ok(());
+                }
+            }
+            if waited >= SEND_WINDOW_WAIT_TOTAL {
+                return Err(PacketError::CongestionWindowFull);
+            }
+            tokio::time::sleep(SEND_WINDOW_WAIT_STEP).await;
+            waited += SEND_WINDOW_WAIT_STEP;
+        }
+    }
+
+    /// Process incoming packets (should be run in a loop)
+    pub async fn recv_loop(&self) {
+        let mut buf = [0u8; 65535];
+
+        loop {
+            match self.transport.recv(&mut buf).await {
+                Ok((len, remote)) => {
+                    let data = Bytes::copy_from_slice(&buf[..len]);
+                    if let Err(e) = self.handle_packet(data, remote).await {
+                        tracing::warn!("Packet handling error: {:?}", e);
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Recv error: {:?}", e);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Background timer loop (should be run in a loop alongside
+    /// `recv_loop`): resends overdue packets, keeps idle connections alive,
+    /// and reaps timed-out ones. Ticks at a rate derived from the smallest
+    /// RTT across all connections, so a fast LAN connection doesn't wait as
+    /// long to notice a loss as a slow one would.
+    pub async fn run_timers(&self) {
+        loop {
+            tokio::time::sleep(self.timer_tick_interval().await).await;
+            self.run_timer_pass().await;
+        }
+    }
+
+    async fn timer_tick_interval(&self) -> Duration {
+        let conns = self.connections.read().await;
+        let Some(min_rtt_ms) = conns.values().map(|c| c.rtt_ms).min() else {
+            return DEFAULT_TIMER_TICK;
+        };
+        Duration::from_millis(min_rtt_ms / 2).clamp(MIN_TIMER_TICK, MAX_TIMER_TICK)
+    }
+
+    async fn run_timer_pass(&self) {
+        let mut retransmits: Vec<(SocketAddr, Vec<u8>)> = Vec::new();
+        let mut keepalives: Vec<(u64, SocketAddr)> = Vec::new();
+        let mut retries_exhausted: Vec<u64> = Vec::new();
+        let mut timed_out: Vec<u64> = Vec::new();
+        let mut reconnects: Vec<(u64, SocketAddr, u32, HandshakeMessage)> = Vec::new();
+
+        {
+            let mut conns = self.connections.write().await;
+            let now = Instant::now();
+            for (&conn_id, conn) in conns.iter_mut() {
+                if conn.is_timed_out() {
+                    match self.config.reconnect {
+                        None => timed_out.push(conn_id),
+                        Some(strategy) => {
+                            if conn.reconnect_due(now) {
+                                let attempt = conn.next_reconnect_attempt();
+                                conn.begin_reconnect(attempt, now + strategy.delay(attempt));
+                                let message = conn.begin_handshake(&self.identity, Role::Initiator);
+                                reconnects.push((conn_id, conn.remote_addr, attempt, message));
+                            }
+                        }
+                    }
+                    continue;
+                }
+
+                for pkt_num in conn.get_retransmit_candidates() {
+                    if conn.retry_count(pkt_num) >= self.config.max_retries {
+                        retries_exhausted.push(conn_id);
+                        break;
+                    }
+                    if let Some(plaintext) = conn.mark_retransmit(pkt_num) {
+                        if let Ok((sealed, key_phase)) = conn.seal_data(pkt_num, &plaintext) {
+                            let header = Header {
+                                packet_type: PacketType::Data,
+                                key_phase,
+                                connection_id: conn_id,
+                                packet_number: pkt_num,
+                            };
+                            let packet = Packet {
+                                header,
+                                payload: Bytes::from(sealed),
+                            };
+                            let mut buf = BytesMut::new();
+                            packet.encode(&mut buf);
+                            retransmits.push((conn.remote_addr, buf.freeze().to_vec()));
+                        }
+                    }
+                }
+
+                if conn.last_activity.elapsed() > KEEPALIVE_IDLE_THRESHOLD {
+                    keepalives.push((conn_id, conn.remote_addr));
+                }
+            }
+
+            for conn_id in &timed_out {
+                conns.remove(conn_id);
+            }
+            for conn_id in &retries_exhausted {
+                if let Some(conn) = conns.get_mut(conn_id) {
+                    conn.state = ConnectionState::Closed;
+                }
+            }
+        }
+
+        for (remote, data) in retransmits {
+            let _ = self.transport.send(&data, remote).await;
+        }
+        for (conn_id, remote) in keepalives {
+            let _ = self.send_keepalive(conn_id, remote).await;
+        }
+        for (conn_id, remote, attempt, message) in reconnects {
+            let _ = self
+                .event_tx
+                .send(TransportEvent::Reconnecting { conn_id, attempt })
+                .await;
+            let _ = self.send_handshake(conn_id, &message, remote).await;
+        }
+        for conn_id in retries_exhausted {
+            let _ = self
+                .event_tx
+                .send(TransportEvent::Error {
+                    conn_id: Some(conn_id),
+                    error: format!("exceeded max_retries ({})", self.config.max_retries),
+                })
+                .await;
+            let _ = self
+                .event_tx
+                .send(TransportEvent::Disconnected { conn_id })
+                .await;
+        }
+        for conn_id in timed_out {
+            let _ = self
+                .event_tx
+                .send(TransportEvent::Disconnected { conn_id })
+                .await;
+        }
+    }
+
+    async fn send_keepalive(&self, conn_id: u64, remote: SocketAddr) -> Result<(), PacketError> {
+        let header = Header {
+            packet_type: PacketType::KeepAlive,
+            key_phase: false,
+            connection_id: conn_id,
+            packet_number: 0,
+        };
+        let packet = Packet {
+            header,
+            payload: Bytes::new(),
+        };
+        let mut buf = BytesMut::new();
+        packet.encode(&mut buf);
+
+        self.transport
+            .send(&buf.freeze(), remote)
+            .await
+            .map_err(|_| PacketError::Incomplete)?;
+        Ok(())
+    }
+
+    async fn handle_packet(&self, data: Bytes, remote: SocketAddr) -> Result<(), PacketError> {
+        let packet = Packet::decode(data)?;
+        // `Initial` governs allocating a brand new connection, so it always
+        // uses the raw wire ID; every other packet type may be addressed to
+        // one of a connection's additional migration IDs (see
+        // `super::cid`) and needs mapping back to the primary one.
+        let conn_id = match packet.header.packet_type {
+            PacketType::Initial => packet.header.connection_id,
+            _ => self.resolve_conn_id(packet.header.connection_id).await,
+        };
+
+        match packet.header.packet_type {
+            PacketType::Initial => {
+                self.handle_initial(conn_id, packet.payload, remote).await?;
+            }
+            PacketType::Data => {
+                // Open the payload and ack the whole range set we've seen
+                // so far, not just this one packet number.
+                let (opened, migration_candidate) = {
+                    let mut conns = self.connections.write().await;
+                    match conns.get_mut(&conn_id) {
+                        Some(conn) => {
+                            let candidate = conn.note_possible_migration(remote);
+                            let opened = match conn.open_data(
+                                packet.header.packet_number,
+                                packet.header.key_phase,
+                                &packet.payload,
+                            ) {
+                                Ok(plaintext) => {
+                                    conn.record_received(packet.header.packet_number);
+                                    Some((conn.build_ack_frame(), plaintext))
+                                }
+                                Err(e) => {
+                                    tracing::warn!("dropping undecryptable Data packet: {:?}", e);
+                                    None
+                                }
+                            };
+                            (opened, candidate)
+                        }
+                        None => (None, None),
+                    }
+                };
+                if let Some(candidate) = migration_candidate {
+                    self.send_path_challenge(conn_id, candidate).await?;
+                }
+                if let Some((frame, plaintext)) = opened {
+                    self.send_ack(conn_id, &frame, remote).await?;
+                    let _ = self
+                        .event_tx
+                        .send(TransportEvent::Data {
+                            conn_id,
+                            payload: Bytes::from(plaintext),
+                        })
+                        .await;
+                }
+            }
+            PacketType::Handshake => {
+                self.handle_handshake(conn_id, packet.payload, remote)
+                    .await?;
+            }
+            PacketType::Ack => {
+                // Process ACK frame: retires every packet number it covers.
+                let mut buf = packet.payload;
+                let frame = AckFrame::decode(&mut buf)?;
+                let mut conns = self.connections.write().await;
+                if let Some(conn) = conns.get_mut(&conn_id) {
+                    conn.acknowledge_frame(&frame);
+                }
+            }
+            PacketType::KeepAlive => {
+                let migration_candidate = {
+                    let mut conns = self.connections.write().await;
+                    conns.get_mut(&conn_id).and_then(|conn| {
+                        conn.last_activity = Instant::now();
+                        conn.note_possible_migration(remote)
+                    })
+                };
+                if let Some(candidate) = migration_candidate {
+                    self.send_path_challenge(conn_id, candidate).await?;
+                }
+            }
+            PacketType::NewConnectionId => {
+                let mut buf = packet.payload;
+                let advertisement = NewConnectionId::decode(&mut buf)?;
+                let mut conns = self.connections.write().await;
+                if let Some(conn) = conns.get_mut(&conn_id) {
+                    conn.cids.learn_peer_id(advertisement);
+                }
+            }
+            PacketType::PathChallenge => {
+                // Echo the token back so the challenger can validate this
+                // address; we don't alter any connection state ourselves.
+                self.send_path_response(conn_id, packet.payload, remote)
+                    .await?;
+            }
+            PacketType::PathResponse => {
+                self.handle_path_response(conn_id, &packet.payload, remote)
+                    .await?;
+            }
+            PacketType::Close => {
+                let mut conns = self.connections.write().await;
+                if let Some(conn) = conns.get_mut(&conn_id) {
+                    conn.state = ConnectionState::Closed;
+                }
+                let _ = self
+                    .event_tx
+                    .send(TransportEvent::Disconnected { conn_id })
+                    .await;
+            }
+            _ => {
+                tracing::debug!("Unhandled packet type: {:?}", packet.header.packet_type);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle an inbound `Initial`: an empty or invalid payload gets a
+    /// fresh `Retry` token and no connection state; only a payload that
+    /// echoes back a still-fresh, correctly-signed token results in
+    /// `get_or_create_connection` being called.
+    async fn handle_initial(
+        &self,
+        conn_id: u64,
+        payload: Bytes,
+        remote: SocketAddr,
+    ) -> Result<(), PacketError> {
+        let validated = {
+            let validator = self.retry_validator.read().await;
+            validator.validate(remote, &payload).is_ok()
+        };
+
+        if !validated {
+            self.send_retry(conn_id, remote).await?;
+            return Ok(());
+        }
+
+        self.get_or_create_connection(remote).await;
+        Ok(())
+    }
+
+    /// Issue a fresh retry token for `remote` and send it back as a
+    /// `Retry` packet, rotating the signing secret first if it's due.
+    async fn send_retry(&self, conn_id: u64, remote: SocketAddr) -> Result<(), PacketError> {
+        let token = {
+            let mut validator = self.retry_validator.write().await;
+            validator.maybe_rotate();
+            validator.issue(remote)
+        };
+
+        let header = Header {
+            packet_type: PacketType::Retry,
+            key_phase: false,
+            connection_id: conn_id,
+            packet_number: 0,
+        };
+        let packet = Packet {
+            header,
+            payload: Bytes::from(token),
+        };
+        let mut buf = BytesMut::new();
+        packet.encode(&mut buf);
+
+        self.transport
+            .send(&buf.freeze(), remote)
+            .await
+            .map_err(|_| PacketError::Incomplete)?;
+        Ok(())
+    }
+
+    /// Handle an inbound `Handshake` packet: if we initiated, this is the
+    /// peer's reply and completes our side; if the peer initiated, this is
+    /// their opening message, so we begin our own half, complete using
+    /// theirs, and reply with our own `Handshake` packet. Always processed,
+    /// even if the connection already has traffic keys, since that's
+    /// exactly what an incoming reconnect handshake after a timeout looks
+    /// like - `Handshake` packets aren't retransmitted, so there's no
+    /// "duplicate of one we already handled" case to guard against here.
+    async fn handle_handshake(
+        &self,
+        conn_id: u64,
+        payload: Bytes,
+        remote: SocketAddr,
+    ) -> Result<(), PacketError> {
+        let peer_message = HandshakeMessage::decode(&payload)?;
+
+        let (our_reply, new_local_cids) = {
+            let mut conns = self.connections.write().await;
+            let conn = match conns.get_mut(&conn_id) {
+                Some(conn) => conn,
+                None => return Ok(()), // no Connection yet to handshake onto
+            };
+
+            let our_reply = if conn.has_pending_handshake() {
+                conn.complete_handshake(&peer_message)?;
+                None
+            } else {
+                let our_message = conn.begin_handshake(&self.identity, Role::Responder);
+                conn.complete_handshake(&peer_message)?;
+                Some(our_message)
+            };
+
+            // Fill out the migration-ready ID pool now that there's a
+            // connection worth migrating; see `super::cid`.
+            let mut new_local_cids = Vec::new();
+            while conn.cids.needs_more_local() {
+                new_local_cids.push(conn.cids.mint_local());
+            }
+            (our_reply, new_local_cids)
+        };
+
+        if !new_local_cids.is_empty() {
+            let mut alt_cids = self.alt_cids.write().await;
+            for advertisement in &new_local_cids {
+                alt_cids.insert(advertisement.connection_id, conn_id);
+            }
+        }
+
+        if let Some(message) = our_reply {
+            self.send_handshake(conn_id, &message, remote).await?;
+        }
+        for advertisement in &new_local_cids {
+            self.send_new_connection_id(conn_id, advertisement, remote)
+                .await?;
+        }
+
+        // Traffic keys are live on both the initiator's "peer replied" path
+        // and the responder's "this was the opening message" path, so a
+        // caller waiting on `TransportEvent::Connected` to know it's safe
+        // to `send_data` gets it either way.
+        let _ = self
+            .event_tx
+            .send(TransportEvent::Connected { conn_id, remote })
+            .await;
+        Ok(())
+    }
+
+    /// Validate a `PathResponse`'s echoed token against `remote` and, if it
+    /// matches the candidate address noted for `conn_id`, commit the
+    /// migration: `remote_addr` updates, the pre-migration IDs retire, and
+    /// a replacement is advertised to refill the pool.
+    async fn handle_path_response(
+        &self,
+        conn_id: u64,
+        token: &[u8],
+        remote: SocketAddr,
+    ) -> Result<(), PacketError> {
+        let validated = {
+            let validator = self.retry_validator.read().await;
+            validator.validate(remote, token).is_ok()
+        };
+        if !validated {
+            tracing::warn!("PathResponse token did not validate for {:?}", remote);
+            return Ok(());
+        }
+
+        let refill = {
+            let mut conns = self.connections.write().await;
+            match conns.get_mut(&conn_id) {
+                Some(conn) if conn.confirm_migration(remote) => {
+                    Some(conn.cids.retire_all_but_newest_and_refill())
+                }
+                _ => None,
+            }
+        };
+
+        if let Some(advertisement) = refill {
+            {
+                let mut alt_cids = self.alt_cids.write().await;
+                alt_cids.insert(advertisement.connection_id, conn_id);
+            }
+            self.send_new_connection_id(conn_id, &advertisement, remote)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Challenge `candidate`, a newly-observed source address for
+    /// `conn_id`, to prove it can receive traffic there before it's
+    /// trusted; reuses [`RetryTokenValidator`] purely for its stateless
+    /// issue/validate mechanics, unrelated to `Initial`-gating.
+    async fn send_path_challenge(
+        &self,
+        conn_id: u64,
+        candidate: SocketAddr,
+    ) -> Result<(), PacketError> {
+        let token = {
+            let mut validator = self.retry_validator.write().await;
+            validator.maybe_rotate();
+            validator.issue(candidate)
+        };
+
+        let header = Header {
+            packet_type: PacketType::PathChallenge,
+            key_phase: false,
+            connection_id: conn_id,
+            packet_number: 0,
+        };
+        let packet = Packet {
+            header,
+            payload: Bytes::from(token),
+        };
+        let mut buf = BytesMut::new();
+        packet.encode(&mut buf);
+
+        self.transport
+            .send(&buf.freeze(), candidate)
+            .await
+            .map_err(|_| PacketError::Incomplete)?;
+        Ok(())
+    }
+
+    /// Echo a `PathChallenge`'s token back verbatim as a `PathResponse`.
+    async fn send_path_response(
+        &self,
+        conn_id: u64,
+        token: Bytes,
+        remote: SocketAddr,
+    ) -> Result<(), PacketError> {
+        let header = Header {
+            packet_type: PacketType::PathResponse,
+            key_phase: false,
+            connection_id: conn_id,
+            packet_number: 0,
+        };
+        let packet = Packet {
+            header,
+            payload: token,
+        };
+        let mut buf = BytesMut::new();
+        packet.encode(&mut buf);
+
+        self.transport
+            .send(&buf.freeze(), remote)
+            .await
+            .map_err(|_| PacketError::Incomplete)?;
+        Ok(())
+    }
+
+    /// Advertise one additional connection ID the peer may address this
+    /// side with.
+    async fn send_new_connection_id(
+        &self,
+        conn_id: u64,
+        advertisement: &NewConnectionId,
+        remote: SocketAddr,
+    ) -> Result<(), PacketError> {
+        let header = Header {
+            packet_type: PacketType::NewConnectionId,
+            key_phase: false,
+            connection_id: conn_id,
+            packet_number: 0,
+        };
+        let mut payload = BytesMut::new();
+        advertisement.encode(&mut payload);
+        let packet = Packet {
+            header,
+            payload: payload.freeze(),
+        };
+        let mut buf = BytesMut::new();
+        packet.encode(&mut buf);
+
+        self.transport
+            .send(&buf.freeze(), remote)
+            .await
+            .map_err(|_| PacketError::Incomplete)?;
+        Ok(())
+    }
+
+    async fn send_handshake(
+        &self,
+        conn_id: u64,
+        message: &HandshakeMessage,
+        remote: SocketAddr,
+    ) -> Result<(), PacketError> {
+        let header = Header {
+            packet_type: PacketType::Handshake,
+            key_phase: false,
+            connection_id: conn_id,
+            packet_number: 0,
+        };
+        let packet = Packet {
+            header,
+            payload: Bytes::from(message.encode()),
+        };
+        let mut buf = BytesMut::new();
+        packet.encode(&mut buf);
+
+        self.transport
+            .send(&buf.freeze(), remote)
+            .await
+            .map_err(|_| PacketError::Incomplete)?;
+        Ok(())
+    }
+
+    async fn send_ack(
+        &self,
+        conn_id: u64,
+        frame: &AckFrame,
+        remote: SocketAddr,
+    ) -> Result<(), PacketError> {
+        let header = Header {
+            packet_type: PacketType::Ack,
+            key_phase: false,
+            connection_id: conn_id,
+            packet_number: frame.largest_acked,
+        };
+
+        let mut payload = BytesMut::new();
+        frame.encode(&mut payload);
+        let packet = Packet {
+            header,
+            payload: payload.freeze(),
+        };
+        let mut buf = BytesMut::new();
+        packet.encode(&mut buf);
+
+        self.transport
+            .send(&buf.freeze(), remote)
+            .await
+            .map_err(|_| PacketError::Incomplete)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_manager_creation() {
+        let identity = Arc::new(DeviceIdentity::generate("test device"));
+        let (manager, _rx) = TransportManager::bind(0, TransportConfig::default(), identity)
+            .await
+            .expect("Failed to create manager");
+
+        let addr = manager.transport.local_addr().unwrap();
+        assert!(addr.port() > 0);
+    }
+
+    #[tokio::test]
+    async fn connect_validates_the_address_then_sends_a_handshake_packet() {
+        let identity = Arc::new(DeviceIdentity::generate("alice"));
+        let (manager, _rx) = TransportManager::bind(0, TransportConfig::default(), identity)
+            .await
+            .unwrap();
+
+        // Bind a bare socket to stand in for the peer, just to receive
+        // whatever connect() puts on the wire. It never answers the
+        // Initial with a Retry, so connect() should fall through to the
+        // handshake once INITIAL_RETRY_WAIT elapses.
+        let peer = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let peer_addr = peer.local_addr().unwrap();
+
+        let conn_id = manager.connect(peer_addr).await.unwrap();
+
+        let mut buf = [0u8; 65535];
+        let (len, _from) = peer.recv_from(&mut buf).await.unwrap();
+        let initial = Packet::decode(Bytes::copy_from_slice(&buf[..len])).unwrap();
+        assert_eq!(initial.header.packet_type, PacketType::Initial);
+        assert_eq!(initial.header.connection_id, conn_id);
+        assert!(initial.payload.is_empty(), "no retry token on the first try");
+
+        let (len, _from) = peer.recv_from(&mut buf).await.unwrap();
+        let packet = Packet::decode(Bytes::copy_from_slice(&buf[..len])).unwrap();
+        assert_eq!(packet.header.packet_type, PacketType::Handshake);
+        assert_eq!(packet.header.connection_id, conn_id);
+        HandshakeMessage::decode(&packet.payload).expect("valid handshake message");
+
+        let conns = manager.connections.read().await;
+        let conn = conns.get(&conn_id).unwrap();
+        assert!(conn.has_pending_handshake());
+        assert!(!conn.has_traffic_keys());
+    }
+
+    #[tokio::test]
+    async fn connect_retries_the_initial_once_a_retry_token_comes_back() {
+        let identity = Arc::new(DeviceIdentity::generate("alice"));
+        let (manager, _rx) = TransportManager::bind(0, TransportConfig::default(), identity)
+            .await
+            .unwrap();
+        let manager = Arc::new(manager);
+
+        let peer = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let peer_addr = peer.local_addr().unwrap();
+        let my_addr = manager.transport.local_addr().unwrap();
+
+        let connect_task = tokio::spawn({
+            let manager = manager.clone();
+            async move { manager.connect(peer_addr).await }
+        });
+
+        let mut buf = [0u8; 65535];
+        let (len, _from) = peer.recv_from(&mut buf).await.unwrap();
+        let initial = Packet::decode(Bytes::copy_from_slice(&buf[..len])).unwrap();
+        assert_eq!(initial.header.packet_type, PacketType::Initial);
+        assert!(initial.payload.is_empty());
+
+        let validator = RetryTokenValidator::new();
+        let token = validator.issue(my_addr);
+        let retry = Packet {
+            header: Header {
+                packet_type: PacketType::Retry,
+                key_phase: false,
+                connection_id: initial.header.connection_id,
+                packet_number: 0,
+            },
+            payload: Bytes::from(token.clone()),
+        };
+        let mut retry_buf = BytesMut::new();
+        retry.encode(&mut retry_buf);
+        peer.send_to(&retry_buf, my_addr).await.unwrap();
+
+        let (len, _from) = peer.recv_from(&mut buf).await.unwrap();
+        let retried_initial = Packet::decode(Bytes::copy_from_slice(&buf[..len])).unwrap();
+        assert_eq!(retried_initial.header.packet_type, PacketType::Initial);
+        assert_eq!(retried_initial.payload.as_ref(), token.as_slice());
+
+        let (len, _from) = peer.recv_from(&mut buf).await.unwrap();
+        let handshake = Packet::decode(Bytes::copy_from_slice(&buf[..len])).unwrap();
+        assert_eq!(handshake.header.packet_type, PacketType::Handshake);
+
+        connect_task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn timer_pass_retransmits_overdue_packets_and_closes_after_max_retries() {
+        let identity = Arc::new(DeviceIdentity::generate("alice"));
+        let mut config = TransportConfig::default();
+        config.max_retries = 1;
+        let (manager, _rx) = TransportManager::bind(0, config, identity.clone())
+            .await
+            .unwrap();
+
+        let peer = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let peer_addr = peer.local_addr().unwrap();
+        let conn_id = manager.get_or_create_connection(peer_addr).await;
+
+        // Complete a real handshake so retransmission has traffic keys to
+        // re-seal with; `peer_conn` is a local stand-in used only to open
+        // the retransmitted packet below, since the other end here is a
+        // bare socket rather than a second TransportManager.
+        let peer_identity = DeviceIdentity::generate("bob");
+        let mut peer_conn = Connection::new(conn_id, peer_addr, CongestionAlgorithm::NewReno);
+        let peer_msg = peer_conn.begin_handshake(&peer_identity, Role::Responder);
+        {
+            let mut conns = manager.connections.write().await;
+            let conn = conns.get_mut(&conn_id).unwrap();
+            let our_msg = conn.begin_handshake(&identity, Role::Initiator);
+            peer_conn.complete_handshake(&our_msg).unwrap();
+            conn.complete_handshake(&peer_msg).unwrap();
+
+            conn.rtt_ms = 1;
+            conn.record_sent(0, vec![1, 2, 3]);
+            conn.pending_acks.get_mut(&0).unwrap().sent_at =
+                Instant::now() - Duration::from_secs(1);
+        }
+
+        // First pass: one retry left, so it retransmits.
+        manager.run_timer_pass().await;
+        let mut buf = [0u8; 65535];
+        let (len, _) = peer.recv_from(&mut buf).await.unwrap();
+        let packet = Packet::decode(Bytes::copy_from_slice(&buf[..len])).unwrap();
+        let opened = peer_conn
+            .open_data(
+                packet.header.packet_number,
+                packet.header.key_phase,
+                &packet.payload,
+            )
+            .unwrap();
+        assert_eq!(opened, vec![1, 2, 3]);
+
+        {
+            let mut conns = manager.connections.write().await;
+            let conn = conns.get_mut(&conn_id).unwrap();
+            conn.pending_acks.get_mut(&0).unwrap().sent_at =
+                Instant::now() - Duration::from_secs(1);
+        }
+
+        // Second pass: retry budget exhausted, connection closes instead.
+        manager.run_timer_pass().await;
+        let conns = manager.connections.read().await;
+        assert_eq!(conns.get(&conn_id).unwrap().state, ConnectionState::Closed);
+    }
+
+    #[tokio::test]
+    async fn timer_pass_reconnects_instead_of_disconnecting_on_timeout() {
+        let identity = Arc::new(DeviceIdentity::generate("alice"));
+        let (manager, mut rx) = TransportManager::bind(0, TransportConfig::default(), identity)
+            .await
+            .unwrap();
+
+        let peer = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let peer_addr = peer.local_addr().unwrap();
+        let conn_id = manager.get_or_create_connection(peer_addr).await;
+
+        {
+            let mut conns = manager.connections.write().await;
+            let conn = conns.get_mut(&conn_id).unwrap();
+            conn.last_activity = Instant::now() - Duration::from_secs(31);
+        }
+
+        manager.run_timer_pass().await;
+
+        match rx.recv().await.expect("an event should have been sent") {
+            TransportEvent::Reconnecting {
+                conn_id: reported_id,
+                attempt,
+            } => {
+                assert_eq!(reported_id, conn_id);
+                assert_eq!(attempt, 0);
+            }
+            other => panic!("expected Reconnecting, got {other:?}"),
+        }
+
+        // The connection itself is preserved under its original id, not
+        // torn down, and a fresh Handshake went out to the same remote.
+        let conns = manager.connections.read().await;
+        let conn = conns.get(&conn_id).expect("connection survives a reconnect");
+        assert!(conn.has_pending_handshake());
+
+        let mut buf = [0u8; 65535];
+        let (len, _) = peer.recv_from(&mut buf).await.unwrap();
+        let packet = Packet::decode(Bytes::copy_from_slice(&buf[..len])).unwrap();
+        assert_eq!(packet.header.packet_type, PacketType::Handshake);
+        assert_eq!(packet.header.connection_id, conn_id);
+    }
+
+    #[tokio::test]
+    async fn reconnect_disabled_falls_back_to_hard_disconnect() {
+        let identity = Arc::new(DeviceIdentity::generate("alice"));
+        let mut config = TransportConfig::default();
+        config.reconnect = None;
+        let (manager, mut rx) = TransportManager::bind(0, config, identity).await.unwrap();
+
+        let peer_addr: SocketAddr = "127.0.0.1:9".parse().unwrap();
+        let conn_id = manager.get_or_create_connection(peer_addr).await;
+        {
+            let mut conns = manager.connections.write().await;
+            let conn = conns.get_mut(&conn_id).unwrap();
+            conn.last_activity = Instant::now() - Duration::from_secs(31);
+        }
+
+        manager.run_timer_pass().await;
+
+        match rx.recv().await.expect("an event should have been sent") {
+            TransportEvent::Disconnected {
+                conn_id: reported_id,
+            } => assert_eq!(reported_id, conn_id),
+            other => panic!("expected Disconnected, got {other:?}"),
+        }
+        assert!(!manager.connections.read().await.contains_key(&conn_id));
+    }
+
+    #[tokio::test]
+    async fn handshake_completion_primes_the_connection_id_pool() {
+        let identity = Arc::new(DeviceIdentity::generate("alice"));
+        let (manager, _rx) = TransportManager::bind(0, TransportConfig::default(), identity)
+            .await
+            .unwrap();
+
+        let peer = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let peer_addr = peer.local_addr().unwrap();
+        let conn_id = manager.get_or_create_connection(peer_addr).await;
+
+        let peer_identity = DeviceIdentity::generate("bob");
+        let mut peer_conn = Connection::new(conn_id, peer_addr, CongestionAlgorithm::NewReno);
+        let peer_msg = peer_conn.begin_handshake(&peer_identity, Role::Initiator);
+
+        let header = Header {
+            packet_type: PacketType::Handshake,
+            key_phase: false,
+            connection_id: conn_id,
+            packet_number: 0,
+        };
+        let mut buf = BytesMut::new();
+        Packet {
+            header,
+            payload: Bytes::from(peer_msg.encode()),
+        }
+        .encode(&mut buf);
+        manager.handle_packet(buf.freeze(), peer_addr).await.unwrap();
+
+        // First datagram back is our Handshake reply...
+        let mut recv_buf = [0u8; 65535];
+        let (len, _) = peer.recv_from(&mut recv_buf).await.unwrap();
+        let reply = Packet::decode(Bytes::copy_from_slice(&recv_buf[..len])).unwrap();
+        assert_eq!(reply.header.packet_type, PacketType::Handshake);
+
+        // ...followed by enough NewConnectionId advertisements to fill the
+        // pool out beyond the primary ID it started with.
+        for _ in 1..cid::CID_POOL_SIZE {
+            let (len, _) = peer.recv_from(&mut recv_buf).await.unwrap();
+            let advertisement = Packet::decode(Bytes::copy_from_slice(&recv_buf[..len])).unwrap();
+            assert_eq!(advertisement.header.packet_type, PacketType::NewConnectionId);
+        }
+
+        let conns = manager.connections.read().await;
+        assert_eq!(
+            conns.get(&conn_id).unwrap().cids.local_ids().count(),
+            cid::CID_POOL_SIZE
+        );
+    }
+
+    #[tokio::test]
+    async fn migration_requires_path_validation_before_committing_new_address() {
+        let identity = Arc::new(DeviceIdentity::generate("alice"));
+        let (manager, _rx) = TransportManager::bind(0, TransportConfig::default(), identity.clone())
+            .await
+            .unwrap();
+
+        let old_sock = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let old_addr = old_sock.local_addr().unwrap();
+        let new_sock = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let new_addr = new_sock.local_addr().unwrap();
+
+        let conn_id = manager.get_or_create_connection(old_addr).await;
+
+        // Complete a real handshake so the migrated Data packet below can
+        // actually be opened; `peer_conn` stands in for "bob", the other
+        // end of the connection.
+        let peer_identity = DeviceIdentity::generate("bob");
+        let mut peer_conn = Connection::new(conn_id, old_addr, CongestionAlgorithm::NewReno);
+        let peer_msg = peer_conn.begin_handshake(&peer_identity, Role::Responder);
+        {
+            let mut conns = manager.connections.write().await;
+            let conn = conns.get_mut(&conn_id).unwrap();
+            let our_msg = conn.begin_handshake(&identity, Role::Initiator);
+            peer_conn.complete_handshake(&our_msg).unwrap();
+            conn.complete_handshake(&peer_msg).unwrap();
+        }
+
+        // "bob" sends Data from `new_addr` instead of `old_addr`, as if a
+        // NAT rebind moved the path out from under the connection.
+        let (sealed, key_phase) = peer_conn.seal_data(0, b"hi from the new path").unwrap();
+        let data_header = Header {
+            packet_type: PacketType::Data,
+            key_phase,
+            connection_id: conn_id,
+            packet_number: 0,
+        };
+        let mut data_buf = BytesMut::new();
+        Packet {
+            header: data_header,
+            payload: Bytes::from(sealed),
+        }
+        .encode(&mut data_buf);
+        manager
+            .handle_packet(data_buf.freeze(), new_addr)
+            .await
+            .unwrap();
+
+        // Not committed yet - the payload is accepted, but remote_addr is
+        // unchanged until the new address proves it's reachable.
+        {
+            let conns = manager.connections.read().await;
+            assert_eq!(conns.get(&conn_id).unwrap().remote_addr, old_addr);
+        }
+
+        let mut recv_buf = [0u8; 65535];
+        let (len, _) = new_sock.recv_from(&mut recv_buf).await.unwrap();
+        let challenge = Packet::decode(Bytes::copy_from_slice(&recv_buf[..len])).unwrap();
+        assert_eq!(challenge.header.packet_type, PacketType::PathChallenge);
+
+        // "bob" echoes the challenge token back from the new address.
+        let response_header = Header {
+            packet_type: PacketType::PathResponse,
+            key_phase: false,
+            connection_id: conn_id,
+            packet_number: 0,
+        };
+        let mut response_buf = BytesMut::new();
+        Packet {
+            header: response_header,
+            payload: challenge.payload,
+        }
+        .encode(&mut response_buf);
+        manager
+            .handle_packet(response_buf.freeze(), new_addr)
+            .await
+            .unwrap();
+
+        let conns = manager.connections.read().await;
+        assert_eq!(
+            conns.get(&conn_id).unwrap().remote_addr,
+            new_addr,
+            "migration commits once the new address is validated"
+        );
+    }
+}
@@ -0,0 +1,28 @@
+//! VoidWarp Transport Protocol (VWTP)
+//!
+//! QUIC-style reliable UDP transport for VoidWarp: connection IDs with
+//! migration support (see [`cid`]), retry-token address validation (see
+//! [`addr_valid`]), AEAD-protected packets with key-phase rotation (see
+//! [`crypto`]), and pluggable congestion control (see [`congestion`]).
+//! Named `vwtp` rather than `transport` to avoid colliding with the
+//! flat `crate::transport` module, which remains the transport real
+//! callers use today.
+
+pub mod addr_valid;
+pub mod cid;
+pub mod congestion;
+pub mod connection;
+pub mod crypto;
+pub mod manager;
+pub mod packet;
+pub mod udp;
+
+// Re-exports for convenience
+pub use addr_valid::{RetryTokenError, RetryTokenValidator};
+pub use cid::ConnectionIdPool;
+pub use congestion::{CongestionAlgorithm, CongestionController};
+pub use crypto::{generate_keypair, public_key_from_private_key, HandshakeMessage, TrafficKeys};
+pub use connection::{Connection, ConnectionState};
+pub use manager::{TransportConfig, TransportEvent, TransportManager};
+pub use packet::{Header, NewConnectionId, Packet, PacketError, PacketType};
+pub use udp::UdpTransport;
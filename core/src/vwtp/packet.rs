@@ -0,0 +1,397 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::collections::BTreeSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Microseconds since `UNIX_EPOCH`. Used to stamp [`AckFrame::receiver_timestamp_us`]
+/// and the send time recorded alongside each `PendingPacket`, so one side's
+/// samples are comparable to the other's despite being taken on different
+/// processes - LEDBAT-style delay-based congestion control only ever looks
+/// at the difference between two samples, never the absolute value, so a
+/// constant clock offset between peers washes out.
+pub fn wall_clock_us() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as u64
+}
+
+/// Packet Type Definitions (4 bits)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PacketType {
+    Initial = 0x00,
+    Handshake = 0x01,
+    Data = 0x02,
+    Ack = 0x03,
+    KeepAlive = 0x04,
+    Close = 0x05,
+    /// Sent in reply to an `Initial` that didn't carry a valid address-
+    /// validation token yet; see [`super::addr_valid`]. Carries the token
+    /// the client must echo back in its next `Initial`.
+    Retry = 0x06,
+    /// Advertises one additional connection ID the peer may address this
+    /// side with; see [`super::cid`]. Payload is a [`NewConnectionId`].
+    NewConnectionId = 0x07,
+    /// Sent to a newly-observed source address for an established
+    /// connection, asking it to prove it can receive traffic there before
+    /// that address is trusted; see [`super::cid`]. Payload is an opaque
+    /// token, same encoding as a `Retry` token.
+    PathChallenge = 0x08,
+    /// Echoes a `PathChallenge`'s token back verbatim, confirming the new
+    /// address.
+    PathResponse = 0x09,
+    Unknown = 0xFF,
+}
+
+impl From<u8> for PacketType {
+    fn from(byte: u8) -> Self {
+        match byte & 0x0F {
+            0x00 => PacketType::Initial,
+            0x01 => PacketType::Handshake,
+            0x02 => PacketType::Data,
+            0x03 => PacketType::Ack,
+            0x04 => PacketType::KeepAlive,
+            0x05 => PacketType::Close,
+            0x06 => PacketType::Retry,
+            0x07 => PacketType::NewConnectionId,
+            0x08 => PacketType::PathChallenge,
+            0x09 => PacketType::PathResponse,
+            _ => PacketType::Unknown,
+        }
+    }
+}
+
+/// VWTP Packet Header
+/// Flags (1) | Connection ID (8) | Packet Number (8)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub packet_type: PacketType,
+    pub key_phase: bool,
+    pub connection_id: u64,
+    pub packet_number: u64,
+}
+
+impl Header {
+    pub const SIZE: usize = 1 + 8 + 8;
+
+    pub fn encode(&self, buf: &mut BytesMut) {
+        let mut flags = self.packet_type as u8;
+        if self.key_phase {
+            flags |= 0x10;
+        }
+        buf.put_u8(flags);
+        buf.put_u64_le(self.connection_id);
+        buf.put_u64_le(self.packet_number);
+    }
+
+    pub fn decode(buf: &mut Bytes) -> Result<Self, PacketError> {
+        if buf.remaining() < Self::SIZE {
+            return Err(PacketError::Incomplete);
+        }
+
+        let flags = buf.get_u8();
+        let packet_type = PacketType::from(flags);
+        if packet_type == PacketType::Unknown {
+            return Err(PacketError::InvalidType(flags));
+        }
+
+        let key_phase = (flags & 0x10) != 0;
+        let connection_id = buf.get_u64_le();
+        let packet_number = buf.get_u64_le();
+
+        Ok(Header {
+            packet_type,
+            key_phase,
+            connection_id,
+            packet_number,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    pub header: Header,
+    pub payload: Bytes,
+}
+
+impl Packet {
+    pub fn encode(&self, buf: &mut BytesMut) {
+        self.header.encode(buf);
+        buf.put(self.payload.clone());
+    }
+
+    pub fn decode(mut buf: Bytes) -> Result<Self, PacketError> {
+        let header = Header::decode(&mut buf)?;
+        // Remaining bytes are payload
+        let payload = buf;
+        Ok(Packet { header, payload })
+    }
+}
+
+/// One contiguous run of acknowledged packet numbers below the previous
+/// run (or below `largest_acked`, for the first range), QUIC-style.
+///
+/// `gap` is the count of *unacknowledged* packet numbers between this
+/// run's top and the bottom of the run above it; `range_length` is the
+/// count of additional acknowledged packet numbers below this run's top
+/// (so the run covers `range_length + 1` packets in total).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AckRange {
+    pub gap: u64,
+    pub range_length: u64,
+}
+
+/// Payload of a [`PacketType::Ack`] packet: a variable-length set of
+/// acknowledged-packet-number ranges instead of one packet number per ACK,
+/// so a single ACK can retire many pending packets and a dropped ACK
+/// doesn't erase all knowledge of what the peer has received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AckFrame {
+    pub largest_acked: u64,
+    /// Microseconds between receiving the largest-acked packet and sending
+    /// this frame. Always 0 today since acks are sent immediately on
+    /// receipt; reserved for a future delayed-ack optimization.
+    pub ack_delay_us: u64,
+    /// Wall-clock microseconds (since `UNIX_EPOCH`, via
+    /// [`super::connection::wall_clock_us`]) at which this frame was built,
+    /// i.e. the receive time of `largest_acked`. Echoed back so the sender
+    /// can compute one-way delay for LEDBAT-style delay-based congestion
+    /// control; see [`super::congestion::Ledbat`]. A constant clock offset
+    /// between sender and receiver doesn't matter here since only the
+    /// difference between samples is ever used, never the absolute value.
+    pub receiver_timestamp_us: u64,
+    pub ranges: Vec<AckRange>,
+}
+
+impl AckFrame {
+    /// Collapse a set of received packet numbers into ranges, newest-first.
+    /// `receiver_timestamp_us` is this side's wall clock at the moment of
+    /// building the frame, stamped into `receiver_timestamp_us` above.
+    pub fn from_received(received: &BTreeSet<u64>, receiver_timestamp_us: u64) -> Self {
+        let mut descending = received.iter().rev();
+        let Some(&largest_acked) = descending.next() else {
+            return AckFrame {
+                largest_acked: 0,
+                ack_delay_us: 0,
+                receiver_timestamp_us,
+                ranges: Vec::new(),
+            };
+        };
+
+        let mut ranges = Vec::new();
+        let mut cursor = largest_acked + 1;
+        let mut run_high = largest_acked;
+        let mut run_low = largest_acked;
+        for &pn in descending {
+            if pn == run_low - 1 {
+                run_low = pn;
+                continue;
+            }
+            ranges.push(AckRange {
+                gap: cursor - run_high - 1,
+                range_length: run_high - run_low,
+            });
+            cursor = run_low;
+            run_high = pn;
+            run_low = pn;
+        }
+        ranges.push(AckRange {
+            gap: cursor - run_high - 1,
+            range_length: run_high - run_low,
+        });
+
+        AckFrame {
+            largest_acked,
+            ack_delay_us: 0,
+            receiver_timestamp_us,
+            ranges,
+        }
+    }
+
+    /// Expand this frame back into the individual acknowledged packet
+    /// numbers it covers.
+    pub fn acked_packet_numbers(&self) -> Vec<u64> {
+        let mut out = Vec::new();
+        let mut cursor = self.largest_acked + 1;
+        for range in &self.ranges {
+            let run_high = cursor - range.gap - 1;
+            let run_low = run_high - range.range_length;
+            out.extend((run_low..=run_high).rev());
+            cursor = run_low;
+        }
+        out
+    }
+
+    pub fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u64_le(self.largest_acked);
+        buf.put_u64_le(self.ack_delay_us);
+        buf.put_u64_le(self.receiver_timestamp_us);
+        buf.put_u16_le(self.ranges.len() as u16);
+        for range in &self.ranges {
+            buf.put_u64_le(range.gap);
+            buf.put_u64_le(range.range_length);
+        }
+    }
+
+    pub fn decode(buf: &mut Bytes) -> Result<Self, PacketError> {
+        if buf.remaining() < 8 + 8 + 8 + 2 {
+            return Err(PacketError::Incomplete);
+        }
+        let largest_acked = buf.get_u64_le();
+        let ack_delay_us = buf.get_u64_le();
+        let receiver_timestamp_us = buf.get_u64_le();
+        let count = buf.get_u16_le();
+
+        let mut ranges = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            if buf.remaining() < 16 {
+                return Err(PacketError::Incomplete);
+            }
+            ranges.push(AckRange {
+                gap: buf.get_u64_le(),
+                range_length: buf.get_u64_le(),
+            });
+        }
+
+        Ok(AckFrame {
+            largest_acked,
+            ack_delay_us,
+            receiver_timestamp_us,
+            ranges,
+        })
+    }
+}
+
+/// Payload of a [`PacketType::NewConnectionId`] packet, mirroring QUIC's
+/// `NEW_CONNECTION_ID` frame: `sequence` identifies this ID among the ones
+/// this side has minted so the peer can deduplicate a retransmitted
+/// advertisement, `connection_id` is the ID itself, and a nonzero
+/// `retire_prior_to` asks the peer to stop using any ID minted before
+/// that sequence number (e.g. once a migration has been confirmed and the
+/// pre-migration IDs are no longer needed).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NewConnectionId {
+    pub sequence: u64,
+    pub connection_id: u64,
+    pub retire_prior_to: u64,
+}
+
+impl NewConnectionId {
+    pub const SIZE: usize = 8 + 8 + 8;
+
+    pub fn encode(&self, buf: &mut BytesMut) {
+        buf.put_u64_le(self.sequence);
+        buf.put_u64_le(self.connection_id);
+        buf.put_u64_le(self.retire_prior_to);
+    }
+
+    pub fn decode(buf: &mut Bytes) -> Result<Self, PacketError> {
+        if buf.remaining() < Self::SIZE {
+            return Err(PacketError::Incomplete);
+        }
+        Ok(NewConnectionId {
+            sequence: buf.get_u64_le(),
+            connection_id: buf.get_u64_le(),
+            retire_prior_to: buf.get_u64_le(),
+        })
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum PacketError {
+    #[error("Packet data incomplete")]
+    Incomplete,
+    #[error("Invalid packet type: {0:#x}")]
+    InvalidType(u8),
+    #[error("Congestion window has no room for this send")]
+    CongestionWindowFull,
+    #[error("Payload failed to decrypt: auth tag mismatch or bad handshake signature")]
+    Decrypt,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_data() {
+        let header = Header {
+            packet_type: PacketType::Data,
+            key_phase: true,
+            connection_id: 0x1234567890ABCDEF,
+            packet_number: 1,
+        };
+        let payload = Bytes::from_static(b"Hello VoidWarp");
+        let packet = Packet {
+            header: header.clone(),
+            payload: payload.clone(),
+        };
+
+        let mut buf = BytesMut::new();
+        packet.encode(&mut buf);
+
+        assert_eq!(buf.len(), Header::SIZE + payload.len());
+
+        let decoded = Packet::decode(buf.freeze()).expect("Decode failed");
+        assert_eq!(decoded, packet);
+        assert_eq!(decoded.header.packet_type, PacketType::Data);
+        assert!(decoded.header.key_phase);
+    }
+
+    #[test]
+    fn ack_frame_roundtrips_contiguous_range() {
+        let received: BTreeSet<u64> = (0..=5).collect();
+        let frame = AckFrame::from_received(&received, 123_456);
+        assert_eq!(frame.largest_acked, 5);
+        assert_eq!(frame.ranges.len(), 1);
+
+        let mut buf = BytesMut::new();
+        frame.encode(&mut buf);
+        let decoded = AckFrame::decode(&mut buf.freeze()).expect("decode failed");
+        assert_eq!(decoded, frame);
+        assert_eq!(decoded.receiver_timestamp_us, 123_456);
+
+        let mut acked = decoded.acked_packet_numbers();
+        acked.sort_unstable();
+        assert_eq!(acked, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn ack_frame_encodes_gaps_for_lost_packets() {
+        // Packets 7, 6, 5 and 2, 1 were received; 4 and 3 were lost.
+        let received: BTreeSet<u64> = [1, 2, 5, 6, 7].into_iter().collect();
+        let frame = AckFrame::from_received(&received, 0);
+        assert_eq!(frame.largest_acked, 7);
+        assert_eq!(frame.ranges.len(), 2);
+
+        let mut acked = frame.acked_packet_numbers();
+        acked.sort_unstable();
+        assert_eq!(acked, vec![1, 2, 5, 6, 7]);
+        assert!(!acked.contains(&3));
+        assert!(!acked.contains(&4));
+    }
+
+    #[test]
+    fn ack_frame_handles_single_packet() {
+        let received: BTreeSet<u64> = [42].into_iter().collect();
+        let frame = AckFrame::from_received(&received, 0);
+        assert_eq!(frame.largest_acked, 42);
+        assert_eq!(frame.acked_packet_numbers(), vec![42]);
+    }
+
+    #[test]
+    fn new_connection_id_roundtrips() {
+        let message = NewConnectionId {
+            sequence: 3,
+            connection_id: 0xDEADBEEF,
+            retire_prior_to: 1,
+        };
+        let mut buf = BytesMut::new();
+        message.encode(&mut buf);
+        assert_eq!(buf.len(), NewConnectionId::SIZE);
+
+        let decoded = NewConnectionId::decode(&mut buf.freeze()).expect("decode failed");
+        assert_eq!(decoded, message);
+    }
+}
@@ -0,0 +1,741 @@
+//! Single-file transfer over [`crate::vwtp`]'s `TransportManager`.
+//!
+//! `vwtp` itself only speaks connections and reliably-delivered opaque
+//! payloads; this module is what turns that into an actual file transfer,
+//! the same role [`crate::quic`] plays on top of `quinn`. It reuses
+//! `crate::transfer::{FileSender, FileReceiver}` for chunk I/O and
+//! `crate::protocol::{HandshakeRequest, ChunkFrame, ZeroRun}` for framing,
+//! so the bytes on the wire look like any other chunked transfer in this
+//! crate - `vwtp` just carries them instead of a QUIC stream or a raw TCP
+//! socket.
+//!
+//! `TransportManager` is async (`tokio`), but every other transfer path in
+//! this crate is synchronous, so `VwtpFileSender`/`VwtpFileReceiverServer`
+//! follow `crate::quic`'s convention of owning a dedicated `tokio` runtime
+//! and blocking on it internally.
+
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::checksum::{calculate_chunk_checksum_raw, calculate_file_checksum, HashMethod};
+use crate::protocol::{ChunkFrame, HandshakeRequest, TransferType, ZeroRun};
+use crate::security::crypto::{CryptoError, DeviceIdentity};
+use crate::security::validator::SecurePinValidator;
+use crate::sender::{TransferResult, DEFAULT_CHUNK_SIZE};
+use crate::transfer::{ChunkError, ChunkingMethod, FileMetadata, FileReceiver, FileSender};
+use crate::vwtp::{CongestionAlgorithm, TransportConfig, TransportEvent, TransportManager};
+
+/// How long a handshake, or the final completion acknowledgement, is
+/// allowed to take - mirrors `crate::sender::HANDSHAKE_TIMEOUT`.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// First byte of a chunk-phase payload: which of the three frame kinds
+/// follows. Needed because `ChunkFrame`, `ZeroRun`, and the completion
+/// marker all travel as ordinary `TransportEvent::Data` payloads rather
+/// than distinct `vwtp::PacketType`s.
+const DATA_KIND_CHUNK: u8 = 0;
+const DATA_KIND_ZERO_RUN: u8 = 1;
+/// Sent by the receiver once `FileReceiver::finalize` succeeds, since
+/// `vwtp` has no stream-level "end of data" signal the way a QUIC stream's
+/// FIN does - the sender otherwise has no way to know the last chunk it
+/// sent actually landed and verified.
+const DATA_KIND_DONE: u8 = 2;
+
+/// File sender over a `vwtp` connection.
+pub struct VwtpFileSender {
+    file_path: String,
+    file_size: u64,
+    file_checksum: String,
+    chunk_size: usize,
+    congestion: CongestionAlgorithm,
+    hash_method: HashMethod,
+    passphrase: Option<String>,
+    bytes_sent: Arc<AtomicU64>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl VwtpFileSender {
+    /// Create a sender for a single file, using `TransportConfig`'s default
+    /// congestion controller (NewReno) and `HashMethod::Md5`.
+    pub fn new(path_str: &str) -> io::Result<Self> {
+        Self::new_with_congestion(path_str, CongestionAlgorithm::default())
+    }
+
+    /// Create a sender that drives the connection with `congestion` instead
+    /// of the default - e.g. [`CongestionAlgorithm::Ledbat`] for a transfer
+    /// that should back off in favor of the peer's other traffic rather than
+    /// compete with it for bandwidth.
+    pub fn new_with_congestion(
+        path_str: &str,
+        congestion: CongestionAlgorithm,
+    ) -> io::Result<Self> {
+        let path = Path::new(path_str);
+        let metadata = path.metadata()?;
+        let file_checksum = calculate_file_checksum(path)?;
+
+        Ok(VwtpFileSender {
+            file_path: path_str.to_string(),
+            file_size: metadata.len(),
+            file_checksum,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            congestion,
+            hash_method: HashMethod::Md5,
+            passphrase: None,
+            bytes_sent: Arc::new(AtomicU64::new(0)),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Negotiate a `HashMethod` other than the `Md5` default for this
+    /// transfer's checksums and Merkle tree - see
+    /// `transfer::FileSender::with_hash_method`.
+    pub fn with_hash_method(mut self, hash_method: HashMethod) -> Self {
+        self.hash_method = hash_method;
+        self
+    }
+
+    /// Seal every chunk with `security::chunk_aead::ChunkAead`, keyed from
+    /// `passphrase` - see `transfer::FileSender::with_passphrase`. Validated
+    /// eagerly with `SecurePinValidator::for_passphrase` here rather than
+    /// when the real `FileSender` is built mid-transfer, so a weak
+    /// passphrase fails at setup instead of after the handshake is already
+    /// underway.
+    pub fn with_passphrase(mut self, passphrase: &str) -> Result<Self, CryptoError> {
+        SecurePinValidator::for_passphrase().validate(passphrase)?;
+        self.passphrase = Some(passphrase.to_string());
+        Ok(self)
+    }
+
+    pub fn file_size(&self) -> u64 {
+        self.file_size
+    }
+
+    pub fn checksum(&self) -> &str {
+        &self.file_checksum
+    }
+
+    pub fn file_name(&self) -> String {
+        Path::new(&self.file_path)
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent.load(Ordering::SeqCst)
+    }
+
+    pub fn progress(&self) -> f32 {
+        if self.file_size == 0 {
+            return 100.0;
+        }
+        (self.bytes_sent() as f32 / self.file_size as f32) * 100.0
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Send the file to `peer_addr`, authenticating with `identity`.
+    pub fn send_to(
+        &self,
+        peer_addr: SocketAddr,
+        sender_name: &str,
+        identity: Arc<DeviceIdentity>,
+    ) -> TransferResult {
+        let runtime = match tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(rt) => rt,
+            Err(e) => return TransferResult::IoError(format!("failed to start runtime: {}", e)),
+        };
+
+        runtime.block_on(self.send_to_async(peer_addr, sender_name, identity))
+    }
+
+    async fn send_to_async(
+        &self,
+        peer_addr: SocketAddr,
+        sender_name: &str,
+        identity: Arc<DeviceIdentity>,
+    ) -> TransferResult {
+        let config = TransportConfig {
+            congestion: self.congestion,
+            ..TransportConfig::default()
+        };
+        let (manager, mut events) = match TransportManager::bind(0, config, identity).await {
+            Ok(pair) => pair,
+            Err(e) => return TransferResult::IoError(format!("bind failed: {}", e)),
+        };
+
+        // connect() reads directly off the socket while validating the
+        // address, so it has to finish before recv_loop starts competing
+        // with it for the same inbound packets.
+        let conn_id = match manager.connect(peer_addr).await {
+            Ok(id) => id,
+            Err(e) => return TransferResult::ConnectionFailed(format!("{}", e)),
+        };
+
+        // recv_loop is also where every chunk's selective ACK gets built and
+        // sent back (TransportManager::send_ack / AckFrame), so a dropped
+        // chunk is retried from the peer's own pending_acks bookkeeping -
+        // this path doesn't need a NackChunk-style scheme of its own the way
+        // crate::quic's per-chunk acking does.
+        let manager = Arc::new(manager);
+        let recv_handle = tokio::spawn({
+            let manager = manager.clone();
+            async move { manager.recv_loop().await }
+        });
+        // run_timers is what actually retransmits a chunk the ack path above
+        // never confirmed (ticking at a rate derived from the connection's
+        // own RTT) and keeps the connection alive with a keepalive during the
+        // gap between the last chunk going out and wait_for_completion
+        // hearing back - without it a transfer over a slow or idle-prone
+        // link would silently stall instead of recovering.
+        let timers_handle = tokio::spawn({
+            let manager = manager.clone();
+            async move { manager.run_timers().await }
+        });
+
+        let result = self
+            .run_transfer(&manager, &mut events, conn_id, sender_name)
+            .await;
+
+        recv_handle.abort();
+        timers_handle.abort();
+        result
+    }
+
+    async fn run_transfer(
+        &self,
+        manager: &TransportManager,
+        events: &mut mpsc::Receiver<TransportEvent>,
+        conn_id: u64,
+        sender_name: &str,
+    ) -> TransferResult {
+        if let Err(result) = wait_for_connected(events, conn_id).await {
+            return result;
+        }
+
+        // The Merkle root has to be in the handshake itself (the receiver
+        // folds chunks into its own accumulator as they arrive, so it needs
+        // the root before the first one lands), which means reading the
+        // whole file once up front just to build it - see
+        // `transfer::FileSender::merkle_root`'s doc comment on the two ways
+        // to get a root to the receiver. The actual send below re-reads the
+        // file a second time through a fresh `FileSender` so it can stream
+        // chunks out as they're read rather than holding the whole file in
+        // memory from this pass.
+        let merkle_root = match self.compute_merkle_root() {
+            Ok(root) => root,
+            Err(e) => return TransferResult::IoError(e.to_string()),
+        };
+
+        let mut sender = match FileSender::new(Path::new(&self.file_path)) {
+            Ok(s) => s.with_hash_method(self.hash_method),
+            Err(e) => return TransferResult::IoError(e.to_string()),
+        };
+        let aead_params = match &self.passphrase {
+            Some(passphrase) => match sender.with_passphrase(passphrase) {
+                Ok((sealed, params)) => {
+                    sender = sealed;
+                    Some(params)
+                }
+                Err(e) => return TransferResult::IoError(e.to_string()),
+            },
+            None => None,
+        };
+
+        let mut handshake = HandshakeRequest::new(
+            sender_name,
+            &self.file_name(),
+            self.file_size,
+            self.chunk_size as u32,
+            &self.file_checksum,
+            TransferType::SingleFile,
+            false,
+        )
+        .with_hash_method(self.hash_method);
+        if let Some(root) = merkle_root {
+            handshake = handshake.with_merkle_root(root);
+        }
+        if let Some(params) = aead_params {
+            handshake = handshake.with_aead_params(params);
+        }
+        let mut handshake_payload = Vec::new();
+        if let Err(e) = handshake.write_to(&mut handshake_payload) {
+            return TransferResult::IoError(format!("handshake encode failed: {}", e));
+        }
+        if let Err(e) = manager.send_data(conn_id, Bytes::from(handshake_payload)).await {
+            return TransferResult::IoError(format!("handshake send failed: {}", e));
+        }
+
+        loop {
+            if self.cancelled.load(Ordering::SeqCst) {
+                return TransferResult::Cancelled;
+            }
+
+            let (offset, len, data) = match sender.read_chunk() {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break,
+                Err(e) => return TransferResult::IoError(e.to_string()),
+            };
+            let chunk_index = offset / self.chunk_size as u64;
+
+            let mut frame_payload = Vec::with_capacity(data.len() + 32);
+            if crate::transfer::is_all_zero(&data) {
+                frame_payload.push(DATA_KIND_ZERO_RUN);
+                if let Err(e) = (ZeroRun {
+                    start_chunk: chunk_index,
+                    count: 1,
+                })
+                .write_to(&mut frame_payload)
+                {
+                    return TransferResult::IoError(e.to_string());
+                }
+            } else {
+                let checksum = calculate_chunk_checksum_raw(&data);
+                frame_payload.push(DATA_KIND_CHUNK);
+                if let Err(e) = (ChunkFrame {
+                    chunk_index,
+                    offset,
+                    checksum,
+                    data,
+                })
+                .write_to(&mut frame_payload)
+                {
+                    return TransferResult::IoError(e.to_string());
+                }
+            }
+
+            if let Err(e) = manager.send_data(conn_id, Bytes::from(frame_payload)).await {
+                return TransferResult::IoError(format!("chunk send failed: {}", e));
+            }
+            self.bytes_sent.fetch_add(len as u64, Ordering::SeqCst);
+        }
+
+        wait_for_completion(events, conn_id).await
+    }
+
+    /// Read the whole file once through a throwaway `FileSender`, purely to
+    /// fold every chunk's hash into a `merkle::MerkleAccumulator` and come
+    /// back with its root - see the comment at this method's call site.
+    fn compute_merkle_root(&self) -> io::Result<Option<Vec<u8>>> {
+        let mut sender =
+            FileSender::new(Path::new(&self.file_path))?.with_hash_method(self.hash_method);
+        while sender.read_chunk()?.is_some() {}
+        Ok(sender.merkle_root())
+    }
+}
+
+/// Blocks until `conn_id`'s handshake completes (`TransportEvent::Connected`),
+/// fails with the right `TransferResult` otherwise.
+async fn wait_for_connected(
+    events: &mut mpsc::Receiver<TransportEvent>,
+    conn_id: u64,
+) -> Result<(), TransferResult> {
+    let wait = async {
+        loop {
+            match events.recv().await {
+                Some(TransportEvent::Connected { conn_id: id, .. }) if id == conn_id => {
+                    return Ok(())
+                }
+                Some(TransportEvent::Error {
+                    conn_id: Some(id),
+                    error,
+                }) if id == conn_id => return Err(TransferResult::ConnectionFailed(error)),
+                Some(_) => continue,
+                None => {
+                    return Err(TransferResult::ConnectionFailed(
+                        "connection closed before handshake completed".to_string(),
+                    ))
+                }
+            }
+        }
+    };
+    match tokio::time::timeout(HANDSHAKE_TIMEOUT, wait).await {
+        Ok(result) => result,
+        Err(_) => Err(TransferResult::Timeout),
+    }
+}
+
+/// Blocks until the receiver's [`DATA_KIND_DONE`] completion marker arrives
+/// on `conn_id`.
+async fn wait_for_completion(
+    events: &mut mpsc::Receiver<TransportEvent>,
+    conn_id: u64,
+) -> TransferResult {
+    let wait = async {
+        loop {
+            match events.recv().await {
+                Some(TransportEvent::Data {
+                    conn_id: id,
+                    payload,
+                }) if id == conn_id && payload.first() == Some(&DATA_KIND_DONE) => {
+                    return TransferResult::Success
+                }
+                // An idle-timed-out connection reconnects under the same
+                // conn_id with no action needed here - surface it so a stall
+                // waiting on the completion marker reads as "reconnecting"
+                // rather than as a hang.
+                Some(TransportEvent::Reconnecting { conn_id: id, attempt }) if id == conn_id => {
+                    tracing::info!(attempt, "vwtp transfer reconnecting, resuming once handshake completes");
+                    continue;
+                }
+                Some(_) => continue,
+                None => {
+                    return TransferResult::ConnectionFailed(
+                        "connection closed before completion was acknowledged".to_string(),
+                    )
+                }
+            }
+        }
+    };
+    match tokio::time::timeout(HANDSHAKE_TIMEOUT, wait).await {
+        Ok(result) => result,
+        Err(_) => TransferResult::Timeout,
+    }
+}
+
+/// A transfer offer received by [`VwtpFileReceiverServer`], awaiting
+/// accept/reject - mirrors [`crate::quic::QuicIncomingTransfer`].
+#[derive(Debug, Clone)]
+pub struct VwtpIncomingTransfer {
+    pub sender_name: String,
+    pub sender_addr: SocketAddr,
+    pub file_name: String,
+    pub file_size: u64,
+    pub chunk_size: u32,
+    pub file_checksum: String,
+    /// Whether the sender negotiated `security::chunk_aead::ChunkAead` - if
+    /// so, the offer can only be taken with
+    /// `VwtpFileReceiverServer::accept_transfer_with_passphrase`.
+    pub requires_passphrase: bool,
+}
+
+/// File receiver over a `vwtp` connection. Binds eagerly and starts
+/// listening immediately; surfaces at most one pending transfer at a time,
+/// the same single-shot shape as [`crate::quic::QuicFileReceiverServer`].
+pub struct VwtpFileReceiverServer {
+    port: u16,
+    pending_transfer: Arc<Mutex<Option<VwtpIncomingTransfer>>>,
+    accept_tx: Mutex<Option<oneshot::Sender<AcceptParams>>>,
+    result_rx: Mutex<Option<std::sync::mpsc::Receiver<TransferResult>>>,
+}
+
+/// What `accept_transfer`/`accept_transfer_with_passphrase` hands to
+/// `VwtpFileReceiverServer::drive` once the caller has decided to take the
+/// pending transfer.
+struct AcceptParams {
+    dest_dir: PathBuf,
+    /// `Some` when the sender negotiated `security::chunk_aead::ChunkAead`
+    /// (see `VwtpFileSender::with_passphrase`) and the caller supplied the
+    /// matching passphrase via `accept_transfer_with_passphrase`.
+    passphrase: Option<String>,
+}
+
+impl VwtpFileReceiverServer {
+    /// Bind a `TransportManager` on a random UDP port and start driving it
+    /// from a dedicated background thread, so the public API here stays
+    /// synchronous like its TCP and QUIC counterparts.
+    pub fn new(identity: Arc<DeviceIdentity>) -> io::Result<Self> {
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel::<io::Result<u16>>();
+        let (accept_tx, accept_rx) = oneshot::channel::<AcceptParams>();
+        let (result_tx, result_rx) = std::sync::mpsc::channel::<TransferResult>();
+        let pending_transfer = Arc::new(Mutex::new(None));
+
+        let pending_transfer_for_thread = pending_transfer.clone();
+        std::thread::spawn(move || {
+            let runtime = match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(rt) => rt,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+
+            runtime.block_on(async move {
+                let bind_result =
+                    TransportManager::bind(0, TransportConfig::default(), identity).await;
+                let (manager, events) = match bind_result {
+                    Ok(pair) => pair,
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                };
+                let port = match manager.local_addr() {
+                    Ok(addr) => addr.port(),
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                };
+                let manager = Arc::new(manager);
+                let _ = ready_tx.send(Ok(port));
+
+                tokio::spawn({
+                    let manager = manager.clone();
+                    async move { manager.recv_loop().await }
+                });
+                tokio::spawn({
+                    let manager = manager.clone();
+                    async move { manager.run_timers().await }
+                });
+
+                Self::drive(
+                    manager,
+                    events,
+                    pending_transfer_for_thread,
+                    accept_rx,
+                    result_tx,
+                )
+                .await;
+            });
+        });
+
+        let port = ready_rx
+            .recv()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "receiver thread exited early"))??;
+
+        Ok(VwtpFileReceiverServer {
+            port,
+            pending_transfer,
+            accept_tx: Mutex::new(Some(accept_tx)),
+            result_rx: Mutex::new(Some(result_rx)),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn pending_transfer(&self) -> Option<VwtpIncomingTransfer> {
+        self.pending_transfer.lock().unwrap().clone()
+    }
+
+    /// Accept the pending transfer and receive it into `dest_dir`. Fails
+    /// with `TransferResult::IoError` if the sender negotiated
+    /// `security::chunk_aead::ChunkAead` - use
+    /// `accept_transfer_with_passphrase` for those.
+    pub fn accept_transfer(&self, dest_dir: &str) -> TransferResult {
+        self.accept_transfer_inner(AcceptParams {
+            dest_dir: PathBuf::from(dest_dir),
+            passphrase: None,
+        })
+    }
+
+    /// Like `accept_transfer`, but supplying the passphrase the sender's
+    /// `VwtpFileSender::with_passphrase` was built with, so a
+    /// `ChunkAead`-sealed transfer can be decrypted on write.
+    pub fn accept_transfer_with_passphrase(
+        &self,
+        dest_dir: &str,
+        passphrase: &str,
+    ) -> TransferResult {
+        self.accept_transfer_inner(AcceptParams {
+            dest_dir: PathBuf::from(dest_dir),
+            passphrase: Some(passphrase.to_string()),
+        })
+    }
+
+    fn accept_transfer_inner(&self, params: AcceptParams) -> TransferResult {
+        let Some(accept_tx) = self.accept_tx.lock().unwrap().take() else {
+            return TransferResult::IoError(
+                "no pending transfer to accept, or already accepted".to_string(),
+            );
+        };
+        if accept_tx.send(params).is_err() {
+            return TransferResult::IoError("receiver task exited before accept".to_string());
+        }
+
+        let Some(result_rx) = self.result_rx.lock().unwrap().take() else {
+            return TransferResult::IoError("accept_transfer already called".to_string());
+        };
+        result_rx.recv().unwrap_or_else(|_| {
+            TransferResult::IoError("receiver task exited without a result".to_string())
+        })
+    }
+
+    /// Drives one connection end to end: wait for its handshake, surface
+    /// the offer, wait for `accept_transfer` to supply a destination, then
+    /// apply chunk frames until the whole file has arrived.
+    async fn drive(
+        manager: Arc<TransportManager>,
+        mut events: mpsc::Receiver<TransportEvent>,
+        pending_transfer: Arc<Mutex<Option<VwtpIncomingTransfer>>>,
+        accept_rx: oneshot::Receiver<PathBuf>,
+        result_tx: std::sync::mpsc::Sender<TransferResult>,
+    ) {
+        // If the sender's address changes mid-transfer (e.g. it roams
+        // networks), TransportManager validates and migrates to the new path
+        // entirely inside recv_loop/handle_packet - there's no event for it
+        // and nothing here needs to react, `conn_id` stays the same and
+        // chunks keep arriving. `remote` is only a snapshot for the offer
+        // shown to the user before accept.
+        let (conn_id, remote) = loop {
+            match events.recv().await {
+                Some(TransportEvent::Connected { conn_id, remote }) => break (conn_id, remote),
+                Some(_) => continue,
+                None => return,
+            }
+        };
+
+        let handshake_bytes = loop {
+            match events.recv().await {
+                Some(TransportEvent::Data {
+                    conn_id: id,
+                    payload,
+                }) if id == conn_id => break payload,
+                Some(_) => continue,
+                None => return,
+            }
+        };
+        let handshake = match HandshakeRequest::read_from(&mut &handshake_bytes[..]) {
+            Ok(h) => h,
+            Err(e) => {
+                let _ = result_tx.send(TransferResult::IoError(format!(
+                    "malformed handshake: {}",
+                    e
+                )));
+                return;
+            }
+        };
+
+        *pending_transfer.lock().unwrap() = Some(VwtpIncomingTransfer {
+            sender_name: handshake.sender_name.clone(),
+            sender_addr: remote,
+            file_name: handshake.file_name.clone(),
+            file_size: handshake.file_size,
+            chunk_size: handshake.chunk_size,
+            file_checksum: handshake.file_checksum.clone(),
+            requires_passphrase: handshake.aead_params.is_some(),
+        });
+
+        let Ok(AcceptParams {
+            dest_dir,
+            passphrase,
+        }) = accept_rx.await
+        else {
+            return;
+        };
+
+        let dest_path = dest_dir.join(&handshake.file_name);
+        let total_chunks = handshake.file_size.div_ceil(handshake.chunk_size as u64);
+        let metadata = FileMetadata {
+            name: handshake.file_name.clone(),
+            size: handshake.file_size,
+            chunk_size: handshake.chunk_size as usize,
+            total_chunks,
+            chunking: ChunkingMethod::FixedSize,
+        };
+        let mut receiver = match FileReceiver::new(&dest_path, metadata) {
+            Ok(r) => r.with_hash_method(handshake.hash_method),
+            Err(e) => {
+                let _ = result_tx.send(TransferResult::IoError(e.to_string()));
+                return;
+            }
+        };
+        if let Some(aead_params) = handshake.aead_params.clone() {
+            let Some(passphrase) = passphrase else {
+                let _ = result_tx.send(TransferResult::IoError(
+                    "sender negotiated encrypted chunks but no passphrase was supplied"
+                        .to_string(),
+                ));
+                return;
+            };
+            receiver = match receiver.with_passphrase(&passphrase, aead_params) {
+                Ok(r) => r,
+                Err(e) => {
+                    let _ = result_tx.send(TransferResult::IoError(e.to_string()));
+                    return;
+                }
+            };
+        }
+
+        while receiver.get_progress().bytes_transferred < handshake.file_size {
+            let payload = match events.recv().await {
+                Some(TransportEvent::Data {
+                    conn_id: id,
+                    payload,
+                }) if id == conn_id => payload,
+                Some(TransportEvent::Reconnecting { conn_id: id, attempt }) if id == conn_id => {
+                    tracing::info!(attempt, "vwtp transfer reconnecting, resuming once handshake completes");
+                    continue;
+                }
+                Some(_) => continue,
+                None => {
+                    let _ = result_tx.send(TransferResult::ConnectionFailed(
+                        "connection closed mid-transfer".to_string(),
+                    ));
+                    return;
+                }
+            };
+            let Some((&kind, body)) = payload.split_first() else {
+                continue;
+            };
+
+            if kind == DATA_KIND_ZERO_RUN {
+                match ZeroRun::read_from(&mut &body[..]) {
+                    Ok(zero_run) => {
+                        if let Err(e) =
+                            receiver.write_zero_run(zero_run.start_chunk, zero_run.count)
+                        {
+                            let _ = result_tx.send(TransferResult::IoError(e.to_string()));
+                            return;
+                        }
+                    }
+                    Err(_) => continue,
+                }
+                continue;
+            }
+
+            let frame = match ChunkFrame::read_from(&mut &body[..]) {
+                Ok(f) => f,
+                Err(_) => continue,
+            };
+            match receiver.write_chunk(frame.chunk_index, frame.offset, &frame.data, frame.checksum) {
+                Ok(()) => {}
+                Err(ChunkError::ChecksumMismatch { .. }) => {
+                    let _ = result_tx.send(TransferResult::ChecksumMismatch);
+                    return;
+                }
+                Err(ChunkError::Io(e)) => {
+                    let _ = result_tx.send(TransferResult::IoError(e.to_string()));
+                    return;
+                }
+            }
+        }
+
+        // Every chunk already passed its own per-chunk checksum/AEAD check in
+        // write_chunk, but the Merkle root catches anything that check can't
+        // - e.g. a whole chunk landing in the wrong place - without having
+        // to re-read the finished file back off disk the way
+        // `checksum::verify_file_checksum` would.
+        if handshake.merkle_root.is_some() && handshake.merkle_root != receiver.merkle_root() {
+            let _ = result_tx.send(TransferResult::ChecksumMismatch);
+            return;
+        }
+
+        if let Err(e) = receiver.finalize() {
+            let _ = result_tx.send(TransferResult::IoError(e.to_string()));
+            return;
+        }
+
+        let _ = manager
+            .send_data(conn_id, Bytes::copy_from_slice(&[DATA_KIND_DONE]))
+            .await;
+        let _ = result_tx.send(TransferResult::Success);
+    }
+}
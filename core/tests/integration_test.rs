@@ -4,6 +4,7 @@ use std::thread;
 use std::time::Duration;
 use tempfile::NamedTempFile;
 use voidwarp_core::receiver::{FileReceiverServer, ReceiverState};
+use voidwarp_core::security::crypto::DeviceIdentity;
 use voidwarp_core::sender::{TcpFileSender, TransferResult};
 use voidwarp_core::checksum::calculate_file_checksum;
 
@@ -22,11 +23,15 @@ fn test_sender_receiver_integration() {
     
     let src_path = temp_src.path().to_str().unwrap();
     let sender = TcpFileSender::new(src_path).expect("Failed to create sender");
-    
+
+    let sender_identity = DeviceIdentity::generate("sender device");
+    let receiver_identity = DeviceIdentity::generate("receiver device");
+    let pairing_code = "123456";
+
     // 3. Start Transfer in separate thread (Sender blocks)
     let sender_handle = thread::spawn(move || {
         let addr = format!("127.0.0.1:{}", port).parse::<SocketAddr>().unwrap();
-        sender.send_to(addr, "TestSender")
+        sender.send_to(addr, "TestSender", &sender_identity, pairing_code)
     });
     
     // 4. Receiver Logic
@@ -50,7 +55,9 @@ fn test_sender_receiver_integration() {
     // Checksum verification is part of protocol now, receiver has it in pending (if we added it to struct, which we did)
     // assert!(!pending.file_checksum.is_empty()); // Field was added in our refactor
     
-    receiver.accept_transfer(&save_path).expect("Failed to accept transfer");
+    receiver
+        .accept_transfer(&save_path, &receiver_identity, pairing_code)
+        .expect("Failed to accept transfer");
     
     // Wait for completion
     tries = 0;